@@ -1,7 +1,10 @@
-use anyhow::{bail, Result};
+use anyhow::{anyhow, Result};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
 
 use crate::assets;
+use crate::config::AgentDefinition;
 use crate::model::Model;
 use crate::util::{today_date, write_text};
 
@@ -9,21 +12,93 @@ use crate::util::{today_date, write_text};
 pub enum AgentKind {
     Code,
     Writer,
+    Custom(&'static CustomAgent),
 }
 
-impl AgentKind {
-    pub fn from_str(value: &str) -> Result<Self> {
-        match value {
-            "code" => Ok(Self::Code),
-            "writer" => Ok(Self::Writer),
-            _ => bail!("Unknown agent: {value}"),
-        }
-    }
+/// Pipeline declared under `[[agents]]` in the repo config. Registered once per
+/// process and leaked so `AgentKind` stays `Copy` with `'static` stage lists.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CustomAgent {
+    pub name: &'static str,
+    /// Declared stages followed by `completed`.
+    pub stages: &'static [&'static str],
+    pub queue_stages: &'static [&'static str],
+    pub initial_stage: &'static str,
+    /// Absolute prompt path per stage, under `.agents/<name>/`.
+    pub prompts: Vec<(&'static str, PathBuf)>,
+}
+
+static CUSTOM_AGENTS: OnceLock<Vec<&'static CustomAgent>> = OnceLock::new();
+
+fn leak(value: &str) -> &'static str {
+    Box::leak(value.to_string().into_boxed_str())
+}
+
+fn leak_list(values: &[String]) -> &'static [&'static str] {
+    let list: Vec<&'static str> = values.iter().map(|value| leak(value)).collect();
+    Box::leak(list.into_boxed_slice())
+}
+
+/// Makes `[[agents]]` definitions available to `AgentKind::from_str`. Only the first
+/// call in a process takes effect.
+pub fn register_custom_agents(repo_root: &Path, definitions: &[AgentDefinition]) {
+    let agents = definitions
+        .iter()
+        .map(|definition| {
+            let mut stages = definition.stages.clone();
+            stages.push("completed".to_string());
+            let queue_stages = if definition.queue_stages.is_empty() {
+                &definition.stages
+            } else {
+                &definition.queue_stages
+            };
+            let agent_dir = repo_root.join(".agents").join(&definition.name);
+            let prompts = definition
+                .stages
+                .iter()
+                .map(|stage| {
+                    let file = definition
+                        .prompts
+                        .get(stage)
+                        .cloned()
+                        .unwrap_or_else(|| format!("prompts/{stage}.md"));
+                    (leak(stage), agent_dir.join(file))
+                })
+                .collect();
+            let agent: &'static CustomAgent = Box::leak(Box::new(CustomAgent {
+                name: leak(&definition.name),
+                stages: leak_list(&stages),
+                queue_stages: leak_list(queue_stages),
+                initial_stage: leak(definition.initial_stage()),
+                prompts,
+            }));
+            agent
+        })
+        .collect();
+    CUSTOM_AGENTS.set(agents).ok();
+}
 
+fn title_case(stage: &str) -> String {
+    stage
+        .split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl AgentKind {
     pub fn name(&self) -> &'static str {
         match self {
             Self::Code => "code",
             Self::Writer => "writer",
+            Self::Custom(agent) => agent.name,
         }
     }
 
@@ -39,6 +114,7 @@ impl AgentKind {
                 "completed",
             ],
             Self::Writer => &["init", "plan", "write", "edit", "completed"],
+            Self::Custom(agent) => agent.stages,
         }
     }
 
@@ -47,13 +123,14 @@ impl AgentKind {
         match self {
             Self::Code => &["spec", "planning"],
             Self::Writer => &["init", "plan", "write", "edit"],
+            Self::Custom(_) => &[],
         }
     }
 
     pub fn handoff_stage(&self) -> Option<&'static str> {
         match self {
             Self::Code => Some("build"),
-            Self::Writer => None,
+            Self::Writer | Self::Custom(_) => None,
         }
     }
 
@@ -62,6 +139,7 @@ impl AgentKind {
         match self {
             Self::Code => &["spec-review-issues", "build", "review"],
             Self::Writer => &["write", "edit"],
+            Self::Custom(agent) => agent.queue_stages,
         }
     }
 
@@ -69,6 +147,7 @@ impl AgentKind {
         match self {
             Self::Code => "spec",
             Self::Writer => "init",
+            Self::Custom(agent) => agent.initial_stage,
         }
     }
 
@@ -91,6 +170,10 @@ impl AgentKind {
                 "edit" => Some("completed"),
                 _ => None,
             },
+            Self::Custom(agent) => {
+                let index = agent.stages.iter().position(|name| *name == stage)?;
+                agent.stages.get(index + 1).copied()
+            }
         }
     }
 
@@ -106,6 +189,7 @@ impl AgentKind {
                 "task",
             ],
             Self::Writer => &["init", "plan", "write", "edit"],
+            Self::Custom(agent) => &agent.stages[..agent.stages.len() - 1],
         }
     }
 
@@ -129,6 +213,7 @@ impl AgentKind {
                 "completed" => "Completed",
                 _ => stage,
             },
+            Self::Custom(_) => return title_case(stage),
         }
         .to_string()
     }
@@ -157,6 +242,11 @@ impl AgentKind {
                 "edit" => Some(PathBuf::from("EDITOR_PROMPT.md")),
                 _ => None,
             },
+            Self::Custom(agent) => agent
+                .prompts
+                .iter()
+                .find(|(name, _)| *name == stage)
+                .map(|(_, path)| path.clone()),
         }
     }
 
@@ -164,7 +254,7 @@ impl AgentKind {
     pub fn review_prompt_name(&self) -> Option<&'static str> {
         match self {
             Self::Code => Some("REVIEW_PROMPT.md"),
-            Self::Writer => None,
+            Self::Writer | Self::Custom(_) => None,
         }
     }
 
@@ -172,7 +262,7 @@ impl AgentKind {
     pub fn spec_review_prompt_name(&self) -> Option<&'static str> {
         match self {
             Self::Code => Some("SPEC_REVIEW_PROMPT.md"),
-            Self::Writer => None,
+            Self::Writer | Self::Custom(_) => None,
         }
     }
 
@@ -184,7 +274,7 @@ impl AgentKind {
                 }
                 _ => None,
             },
-            Self::Writer => None,
+            Self::Writer | Self::Custom(_) => None,
         }
     }
 
@@ -208,6 +298,10 @@ impl AgentKind {
                 "RESEARCH_PROMPT.md" => Some(assets::CODE_RESEARCH_PROMPT),
                 "how/commit.md" => Some(assets::CODE_HOW_COMMIT),
                 "how/plan-update.md" => Some(assets::CODE_HOW_PLAN_UPDATE),
+                "issue-templates/bug.md" => Some(assets::CODE_ISSUE_TEMPLATE_BUG),
+                "issue-templates/test.md" => Some(assets::CODE_ISSUE_TEMPLATE_TEST),
+                "issue-templates/perf.md" => Some(assets::CODE_ISSUE_TEMPLATE_PERF),
+                "issue-templates/spec.md" => Some(assets::CODE_ISSUE_TEMPLATE_SPEC),
                 _ => None,
             },
             Self::Writer => match file_name {
@@ -217,6 +311,7 @@ impl AgentKind {
                 "EDITOR_PROMPT.md" => Some(assets::WRITER_EDITOR_PROMPT),
                 _ => None,
             },
+            Self::Custom(_) => None,
         }
     }
 
@@ -245,6 +340,10 @@ impl AgentKind {
                 ("RESEARCH_PROMPT.md", assets::CODE_RESEARCH_PROMPT),
                 ("how/commit.md", assets::CODE_HOW_COMMIT),
                 ("how/plan-update.md", assets::CODE_HOW_PLAN_UPDATE),
+                ("issue-templates/bug.md", assets::CODE_ISSUE_TEMPLATE_BUG),
+                ("issue-templates/test.md", assets::CODE_ISSUE_TEMPLATE_TEST),
+                ("issue-templates/perf.md", assets::CODE_ISSUE_TEMPLATE_PERF),
+                ("issue-templates/spec.md", assets::CODE_ISSUE_TEMPLATE_SPEC),
             ],
             Self::Writer => vec![
                 ("INIT_PROMPT.md", assets::WRITER_INIT_PROMPT),
@@ -252,13 +351,14 @@ impl AgentKind {
                 ("PROMPT.md", assets::WRITER_PROMPT),
                 ("EDITOR_PROMPT.md", assets::WRITER_EDITOR_PROMPT),
             ],
+            Self::Custom(_) => Vec::new(),
         }
     }
 
     pub fn how_topics(&self) -> Vec<&'static str> {
         match self {
             Self::Code => vec!["commit", "plan-update"],
-            Self::Writer => Vec::new(),
+            Self::Writer | Self::Custom(_) => Vec::new(),
         }
     }
 
@@ -278,6 +378,7 @@ impl AgentKind {
                 ("PLANNING_PROMPT.md", "writer-plan"),
                 ("PROMPT.md", "writer"),
             ],
+            Self::Custom(_) => Vec::new(),
         }
     }
 
@@ -292,6 +393,7 @@ impl AgentKind {
                 ),
             ],
             Self::Writer => vec![("AGENTS.md", assets::WRITER_TEMPLATE_AGENTS)],
+            Self::Custom(_) => Vec::new(),
         }
     }
 
@@ -328,7 +430,24 @@ impl AgentKind {
                 );
                 write_text(&task_dir.join("editorial_plan.md"), &editorial)?;
             }
+            Self::Custom(_) => std::fs::create_dir_all(task_dir)?,
         }
         Ok(())
     }
 }
+
+impl FromStr for AgentKind {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "code" => Ok(Self::Code),
+            "writer" => Ok(Self::Writer),
+            _ => CUSTOM_AGENTS
+                .get()
+                .and_then(|agents| agents.iter().find(|agent| agent.name == value))
+                .map(|agent| Self::Custom(agent))
+                .ok_or_else(|| anyhow!("Unknown agent: {value}")),
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use anyhow::{bail, Result};
+use owo_colors::OwoColorize;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+use crate::commands::{open_issues, sync_task_status_for_issues, CommandContext};
+use crate::issues::{
+    advisory_expired, append_escalation, append_resolution, resolved_before, Issue, IssuePriority,
+    IssueStatus,
+};
+use crate::state::TaskState;
+use crate::util::{json_output, now_iso, print_json};
+
+/// Moves resolved issues older than `days` (or the configured retention) into
+/// `issues/archive/`. Skipped without a retention setting or in read-only mode.
+pub fn archive_resolved_issues(ctx: &CommandContext, days: Option<u64>) -> Result<usize> {
+    let Some(days) = days.or(ctx.config.issues.archive_after_days) else {
+        return Ok(0);
+    };
+    if crate::state::is_read_only() {
+        return Ok(0);
+    }
+    let now = chrono::Utc::now();
+    let mut archived = 0;
+    for issue in ctx.store.list_issues()? {
+        if resolved_before(&issue, days, now) {
+            ctx.store.archive_issue(&issue.id)?;
+            archived += 1;
+        }
+    }
+    Ok(archived)
+}
+
+/// Resolves open advisory issues (P3, type `other`) older than `[issues]
+/// expire_advisory_after_days` and re-syncs their tasks' status.
+pub fn expire_advisory_issues(ctx: &CommandContext) -> Result<usize> {
+    let Some(days) = ctx.config.issues.expire_advisory_after_days else {
+        return Ok(0);
+    };
+    if crate::state::is_read_only() {
+        return Ok(0);
+    }
+    let now = chrono::Utc::now();
+    let mut tasks = BTreeSet::new();
+    let mut expired = 0;
+    for mut issue in ctx.store.list_issues()? {
+        if !advisory_expired(&issue, days, now) {
+            continue;
+        }
+        issue.status = IssueStatus::Resolved;
+        issue.updated_at = now_iso();
+        issue.body = Some(append_resolution(
+            issue.body.take(),
+            &format!("Expired: advisory finding left open for {} days", days),
+        ));
+        ctx.store.save_issue(&issue)?;
+        tasks.extend(issue.task);
+        expired += 1;
+    }
+    for task in tasks {
+        if ctx.store.task_exists(&task) {
+            sync_task_status_for_issues(ctx.store.as_ref(), &task)?;
+        }
+    }
+    Ok(expired)
+}
+
+/// Raises the priority of open issues matching `[[issues.escalate]]`, noting each
+/// bump in the issue body. Returns the issues as escalated, with their old
+/// priority. `dry_run` (and read-only mode) saves nothing.
+pub fn escalate_issue_priorities(
+    ctx: &CommandContext,
+    dry_run: bool,
+) -> Result<Vec<(Issue, IssuePriority)>> {
+    let rules = ctx
+        .config
+        .issues
+        .escalate
+        .iter()
+        .map(|rule| {
+            Ok((
+                IssuePriority::from_str(&rule.from)?,
+                IssuePriority::from_str(&rule.to)?,
+                rule.after_days,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if rules.is_empty() || (crate::state::is_read_only() && !dry_run) {
+        return Ok(Vec::new());
+    }
+    let now = chrono::Utc::now();
+    let mut escalated = Vec::new();
+    for mut issue in ctx.store.list_issues()? {
+        if issue.status != IssueStatus::Open {
+            continue;
+        }
+        let Ok(created) = chrono::DateTime::parse_from_rfc3339(&issue.created_at) else {
+            continue;
+        };
+        let age_days = now.signed_duration_since(created).num_days();
+        let old = issue.priority.clone();
+        // Each rule raises the priority, so following them always ends.
+        while let Some((_, to, _)) = rules
+            .iter()
+            .find(|(from, _, days)| *from == issue.priority && age_days >= *days as i64)
+        {
+            issue.priority = to.clone();
+        }
+        if issue.priority == old {
+            continue;
+        }
+        let at = now_iso();
+        issue.body = Some(append_escalation(
+            issue.body.take(),
+            &old,
+            &issue.priority,
+            &at,
+            &format!("Open for {} days.", age_days),
+        ));
+        issue.updated_at = at;
+        if !dry_run {
+            ctx.store.save_issue(&issue)?;
+        }
+        escalated.push((issue, old));
+    }
+    Ok(escalated)
+}
+
+pub fn report_escalations(escalated: &[(Issue, IssuePriority)]) {
+    for (issue, old) in escalated {
+        eprintln!(
+            "Escalated issue {} from {} to {}: {}",
+            issue.id, old, issue.priority, issue.title
+        );
+    }
+}
+
+pub fn cmd_issue_escalate(ctx: &CommandContext, dry_run: bool) -> Result<()> {
+    if ctx.config.issues.escalate.is_empty() {
+        bail!("Set [[issues.escalate]] rules in .agents/mung.toml");
+    }
+    let escalated = escalate_issue_priorities(ctx, dry_run)?;
+    if escalated.is_empty() {
+        println!("{}", "No issues due for escalation".dimmed());
+        return Ok(());
+    }
+    for (issue, old) in &escalated {
+        println!(
+            "  {} {} -> {}: {}",
+            issue.id, old, issue.priority, issue.title
+        );
+    }
+    if dry_run {
+        println!("Would escalate {} issue(s)", escalated.len());
+    } else {
+        println!("Escalated {} issue(s)", escalated.len());
+    }
+    Ok(())
+}
+
+/// Days since `issue` was last updated, or `None` if `updated_at` doesn't parse.
+fn days_since_update(issue: &Issue, now: chrono::DateTime<chrono::Utc>) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(&issue.updated_at)
+        .ok()
+        .map(|updated| now.signed_duration_since(updated).num_days())
+}
+
+/// `mung issue stale`: open issues untouched for `days`, grouped by task. `ping`
+/// queues each task's stale issues for its next review prompt.
+pub fn cmd_issue_stale(ctx: &CommandContext, days: u64, ping: bool) -> Result<()> {
+    let now = chrono::Utc::now();
+    let mut by_task: BTreeMap<String, Vec<(Issue, i64)>> = BTreeMap::new();
+    for issue in open_issues(ctx)? {
+        let Some(age) = days_since_update(&issue, now).filter(|age| *age >= days as i64) else {
+            continue;
+        };
+        let task = issue
+            .task
+            .clone()
+            .unwrap_or_else(|| "unassigned".to_string());
+        by_task.entry(task).or_default().push((issue, age));
+    }
+    for issues in by_task.values_mut() {
+        issues.sort_by(|(a, a_age), (b, b_age)| {
+            a.priority
+                .weight()
+                .cmp(&b.priority.weight())
+                .then_with(|| b_age.cmp(a_age))
+        });
+    }
+
+    let mut pinged = BTreeMap::new();
+    if ping && !crate::state::is_read_only() {
+        for (task, issues) in &by_task {
+            if !issues.iter().any(|(issue, _)| issue.task.is_some()) || !ctx.store.task_exists(task)
+            {
+                continue;
+            }
+            ctx.store.update_task(task, |task_state| {
+                for (issue, _) in issues {
+                    if !task_state.pinged_issues.contains(&issue.id) {
+                        task_state.pinged_issues.push(issue.id.clone());
+                    }
+                }
+                Ok(())
+            })?;
+            pinged.insert(task.clone(), issues.len());
+        }
+    }
+
+    if json_output() {
+        let tasks: BTreeMap<&String, Vec<serde_json::Value>> = by_task
+            .iter()
+            .map(|(task, issues)| {
+                let issues = issues
+                    .iter()
+                    .map(|(issue, age)| {
+                        json!({
+                            "id": issue.id,
+                            "title": issue.title,
+                            "priority": issue.priority,
+                            "updated_at": issue.updated_at,
+                            "days_since_update": age,
+                        })
+                    })
+                    .collect();
+                (task, issues)
+            })
+            .collect();
+        return print_json(&json!({ "days": days, "tasks": tasks, "pinged": pinged }));
+    }
+    if by_task.is_empty() {
+        println!(
+            "{}",
+            format!("No open issues untouched for {} days", days).dimmed()
+        );
+        return Ok(());
+    }
+    println!("Open issues not updated in {} days:", days);
+    for (task, issues) in &by_task {
+        println!("  {} ({}):", task, issues.len());
+        for (issue, age) in issues {
+            println!(
+                "    {} [{}] {} ({} days)",
+                issue.id, issue.priority, issue.title, age
+            );
+        }
+    }
+    for (task, count) in &pinged {
+        println!(
+            "Flagged {} issue(s) for the next review of '{}'",
+            count, task
+        );
+    }
+    Ok(())
+}
+
+/// Section for the review prompt listing the task's pinged issues that are still open.
+pub fn pinged_issues_section(ctx: &CommandContext, task: &TaskState) -> String {
+    let issues: Vec<Issue> = task
+        .pinged_issues
+        .iter()
+        .filter_map(|id| ctx.store.load_issue(id).ok())
+        .filter(|issue| issue.status == IssueStatus::Open)
+        .collect();
+    if issues.is_empty() {
+        return String::new();
+    }
+    let mut section = String::from(
+        "## STALE ISSUES\n\nThese open issues have not been touched in a while. For each one, fix it, resolve it with a resolution saying why it no longer applies, or comment with what is blocking it:\n",
+    );
+    for issue in issues {
+        section.push_str(&format!(
+            "- {} [{}] {}\n",
+            issue.id, issue.priority, issue.title
+        ));
+    }
+    section
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::process::{Command, Stdio};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::commands::{block_task, heartbeat_interval, refresh_claim, CommandContext, INTERRUPTED};
+
+/// Runs the `[approval] notify` command for a task that reached a gated stage.
+pub fn notify_approval_needed(ctx: &CommandContext, task: &str, stage: &str) {
+    let Some(command) = ctx.config.approval.notify.as_deref() else {
+        return;
+    };
+    let result = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(&ctx.repo_root)
+        .env("MUNG_APPROVAL_TASK", task)
+        .env("MUNG_APPROVAL_STAGE", stage)
+        .stdin(Stdio::null())
+        .status();
+    match result {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("Warning: approval notify command exited with {}", status),
+        Err(err) => eprintln!("Warning: failed to run approval notify command: {}", err),
+    }
+}
+
+/// Waits until `task` is approved. Returns false when it was interrupted or the
+/// configured timeout elapsed, in which case the task is marked blocked. The
+/// task's claim keeps its heartbeat while waiting, so no other worker takes it.
+pub fn wait_for_approval(ctx: &CommandContext, task: &str) -> Result<bool> {
+    let timeout = chrono::Duration::minutes(ctx.config.approval.timeout_minutes as i64);
+    let mut last_heartbeat = Instant::now();
+    loop {
+        let task_state = ctx.store.load_task(task)?;
+        let Some(pending) = task_state.awaiting_approval else {
+            return Ok(true);
+        };
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return Ok(false);
+        }
+        let requested = DateTime::parse_from_rfc3339(&pending.requested_at)
+            .map(|time| time.with_timezone(&Utc))
+            .unwrap_or_else(|_| Utc::now());
+        if Utc::now().signed_duration_since(requested) >= timeout {
+            let reason = format!(
+                "no approval to enter '{}' after {}m",
+                pending.stage, ctx.config.approval.timeout_minutes
+            );
+            block_task(ctx, task, &reason)?;
+            println!("Blocked '{}': {}", task, reason);
+            return Ok(false);
+        }
+        if last_heartbeat.elapsed() >= heartbeat_interval(ctx) {
+            refresh_claim(ctx, task);
+            last_heartbeat = Instant::now();
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+}
@@ -18,6 +18,10 @@ pub const CODE_SPEC_REVIEW_ISSUES_PROMPT: &str =
 pub const CODE_RESEARCH_PROMPT: &str = include_str!("../code/prompts/RESEARCH_PROMPT.md");
 pub const CODE_HOW_COMMIT: &str = include_str!("../code/how/commit.md");
 pub const CODE_HOW_PLAN_UPDATE: &str = include_str!("../code/how/plan-update.md");
+pub const CODE_ISSUE_TEMPLATE_BUG: &str = include_str!("../code/issue-templates/bug.md");
+pub const CODE_ISSUE_TEMPLATE_TEST: &str = include_str!("../code/issue-templates/test.md");
+pub const CODE_ISSUE_TEMPLATE_PERF: &str = include_str!("../code/issue-templates/perf.md");
+pub const CODE_ISSUE_TEMPLATE_SPEC: &str = include_str!("../code/issue-templates/spec.md");
 
 pub const WRITER_INIT_PROMPT: &str = include_str!("../writer/prompts/INIT_PROMPT.md");
 pub const WRITER_PLANNING_PROMPT: &str = include_str!("../writer/prompts/PLANNING_PROMPT.md");
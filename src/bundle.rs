@@ -0,0 +1,447 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use crate::commands::CommandContext;
+use crate::config::StateBackend;
+use crate::events::EVENTS_FILE;
+use crate::git::head_commit;
+use crate::issues::{self, render_issue};
+use crate::state::{self, TaskStatus};
+use crate::util::{copy_dir_all, now_iso, read_text, today_date, write_text};
+
+const MANIFEST_FILE: &str = "bundle.json";
+const BUNDLE_FORMAT: u32 = 1;
+
+/// `bundle.json` at the root of an export: what it holds and where it came from.
+#[derive(Serialize, Deserialize, Debug)]
+struct BundleManifest {
+    format: u32,
+    agent: String,
+    exported_at: String,
+    #[serde(default)]
+    commit: Option<String>,
+    tasks: Vec<String>,
+    issues: usize,
+    #[serde(default)]
+    sessions: bool,
+}
+
+/// `mung export`: packs tasks (spec, plan, task.json), issues, and the event log
+/// into a gzipped tar for another clone. Sessions are left out unless
+/// `include_sessions`; claims never travel.
+pub fn cmd_export(
+    ctx: &CommandContext,
+    output: Option<PathBuf>,
+    include_sessions: bool,
+) -> Result<()> {
+    let output = absolute(output.unwrap_or_else(|| {
+        PathBuf::from(format!("mung-{}-{}.tar.gz", ctx.agent.name(), today_date()))
+    }))?;
+    let mut entries = Vec::new();
+    for entry in ["tasks", "issues", EVENTS_FILE] {
+        if ctx.agent_root.join(entry).exists() {
+            entries.push(entry);
+        }
+    }
+    if include_sessions && ctx.agent_root.join("sessions").is_dir() {
+        entries.push("sessions");
+    }
+    let mut tasks: Vec<String> = ctx
+        .store
+        .list_tasks()
+        .into_iter()
+        .map(|task| task.task)
+        .collect();
+    tasks.sort();
+    let issues = ctx.store.list_issues()?.len() + ctx.store.list_archived_issues()?.len();
+    let manifest = BundleManifest {
+        format: BUNDLE_FORMAT,
+        agent: ctx.agent.name().to_string(),
+        exported_at: now_iso(),
+        commit: head_commit(&ctx.repo_root),
+        tasks,
+        issues,
+        sessions: entries.contains(&"sessions"),
+    };
+
+    let staging = staging_dir("export")?;
+    let result = (|| {
+        write_text(
+            &staging.join(MANIFEST_FILE),
+            &serde_json::to_string_pretty(&manifest)?,
+        )?;
+        let mut tar = Command::new("tar");
+        tar.arg("-czf")
+            .arg(&output)
+            .arg("-C")
+            .arg(&staging)
+            .arg(MANIFEST_FILE);
+        if ctx.config.state.backend == StateBackend::Files {
+            tar.arg("-C").arg(&ctx.agent_root).args(&entries);
+        } else {
+            tar.args(stage_state(ctx, &staging, &entries)?);
+        }
+        run_tar(tar)
+    })();
+    fs::remove_dir_all(&staging).ok();
+    result?;
+
+    println!(
+        "Exported {} task(s) and {} issue(s) to {}",
+        manifest.tasks.len(),
+        manifest.issues,
+        output.display()
+    );
+    Ok(())
+}
+
+/// `mung import`: unpacks a bundle from `mung export` into this repo. Tasks or
+/// issues that already exist stop the import unless `force` overwrites them;
+/// imported tasks that were mid-run come back pending.
+pub fn cmd_import(ctx: &CommandContext, bundle: &Path, force: bool) -> Result<()> {
+    let bundle = absolute(bundle.to_path_buf())?;
+    validate_entries(&bundle)?;
+    let staging = staging_dir("import")?;
+    let result = import_from(ctx, &bundle, &staging, force);
+    fs::remove_dir_all(&staging).ok();
+    result
+}
+
+fn import_from(ctx: &CommandContext, bundle: &Path, staging: &Path, force: bool) -> Result<()> {
+    let mut tar = Command::new("tar");
+    tar.arg("-xzf").arg(bundle).arg("-C").arg(staging);
+    run_tar(tar)?;
+    reject_symlinks(staging)?;
+    let manifest_path = staging.join(MANIFEST_FILE);
+    if !manifest_path.exists() {
+        bail!(
+            "{} is not a mung bundle (no {})",
+            bundle.display(),
+            MANIFEST_FILE
+        );
+    }
+    let manifest: BundleManifest = serde_json::from_str(&read_text(&manifest_path)?)
+        .with_context(|| format!("Failed to parse {}", MANIFEST_FILE))?;
+    if manifest.format != BUNDLE_FORMAT {
+        bail!(
+            "Bundle format {} is not supported (expected {})",
+            manifest.format,
+            BUNDLE_FORMAT
+        );
+    }
+    if manifest.agent != ctx.agent.name() {
+        bail!(
+            "Bundle holds '{}' agent state; rerun with --agent {}",
+            manifest.agent,
+            manifest.agent
+        );
+    }
+
+    let files_backend = ctx.config.state.backend == StateBackend::Files;
+    let tasks = dir_names(&staging.join("tasks"));
+    let issues = issue_files(&staging.join("issues"), Path::new(""));
+    let mut conflicts: Vec<String> = tasks
+        .iter()
+        .filter(|task| ctx.agent_root.join("tasks").join(task).exists())
+        .map(|task| format!("task {task}"))
+        .collect();
+    let archived: HashSet<String> = if files_backend {
+        HashSet::new()
+    } else {
+        ctx.store
+            .list_archived_issues()?
+            .into_iter()
+            .map(|issue| issue.id)
+            .collect()
+    };
+    let issue_present = |file: &Path| {
+        if files_backend {
+            return ctx.agent_root.join("issues").join(file).exists();
+        }
+        let id = file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        ctx.store.issue_exists(&id) || archived.contains(&id)
+    };
+    conflicts.extend(
+        issues
+            .iter()
+            .filter(|file| issue_present(file))
+            .map(|file| format!("issue {}", file.display())),
+    );
+    if !conflicts.is_empty() && !force {
+        bail!(
+            "Already present: {}. Re-run with --force to overwrite.",
+            conflicts.join(", ")
+        );
+    }
+
+    for task in &tasks {
+        let dest = ctx.agent_root.join("tasks").join(task);
+        if dest.exists() {
+            fs::remove_dir_all(&dest)?;
+        }
+        copy_dir_all(&staging.join("tasks").join(task), &dest)?;
+        if !files_backend {
+            adopt_file(&dest.join("task.json"), |path| {
+                let mut task_state = state::load_task(path)?;
+                task_state.record_history();
+                ctx.store.save_task(&task_state)
+            })?;
+        }
+        if ctx.store.task_exists(task) {
+            ctx.store.update_task(task, |task_state| {
+                if task_state.status == TaskStatus::Running {
+                    task_state.status = TaskStatus::Pending;
+                }
+                Ok(())
+            })?;
+        }
+    }
+    if files_backend && staging.join("issues").is_dir() {
+        copy_dir_all(&staging.join("issues"), &ctx.agent_root.join("issues"))?;
+    } else if !files_backend {
+        for file in &issues {
+            let issue = issues::load_issue(&staging.join("issues").join(file))?;
+            if file.starts_with("archive") {
+                ctx.store.save_archived_issue(&issue)?;
+            } else {
+                ctx.store.save_issue(&issue)?;
+            }
+        }
+    }
+    let mut sessions = 0;
+    for session in dir_names(&staging.join("sessions")) {
+        let dest = ctx.agent_root.join("sessions").join(&session);
+        if !dest.exists() {
+            copy_dir_all(&staging.join("sessions").join(&session), &dest)?;
+            if !files_backend {
+                adopt_file(&dest.join("session.json"), |path| {
+                    ctx.store.save_session(&state::load_session(path)?)
+                })?;
+            }
+            sessions += 1;
+        }
+    }
+    let events = merge_events(
+        &staging.join(EVENTS_FILE),
+        &ctx.agent_root.join(EVENTS_FILE),
+    )?;
+
+    println!(
+        "Imported {} task(s) and {} issue(s) from {}",
+        tasks.len(),
+        issues.len(),
+        bundle.display()
+    );
+    if sessions > 0 {
+        println!("  Sessions: {}", sessions);
+    }
+    if events > 0 {
+        println!("  Events: {}", events);
+    }
+    if let Some(commit) = manifest.commit.as_deref() {
+        println!("  Exported at commit {}", commit);
+    }
+    Ok(())
+}
+
+/// Bundles always carry state as files. With another backend, `entries` are copied
+/// into `staging` and the store's records written out the way the files backend
+/// keeps them; returns the entries that ended up there.
+fn stage_state(ctx: &CommandContext, staging: &Path, entries: &[&str]) -> Result<Vec<String>> {
+    for entry in entries {
+        let source = ctx.agent_root.join(entry);
+        if source.is_dir() {
+            copy_dir_all(&source, &staging.join(entry))?;
+        } else {
+            fs::copy(&source, staging.join(entry))?;
+        }
+    }
+    for task in ctx.store.list_tasks() {
+        let path = staging.join("tasks").join(&task.task).join("task.json");
+        write_text(&path, &serde_json::to_string_pretty(&task)?)?;
+    }
+    let issues_dir = staging.join("issues");
+    for issue in ctx.store.list_issues()? {
+        write_text(
+            &issues_dir.join(format!("{}.md", issue.id)),
+            &render_issue(&issue),
+        )?;
+    }
+    for issue in ctx.store.list_archived_issues()? {
+        write_text(
+            &issues_dir.join("archive").join(format!("{}.md", issue.id)),
+            &render_issue(&issue),
+        )?;
+    }
+    if entries.contains(&"sessions") {
+        for session in ctx.store.list_sessions() {
+            let path = staging
+                .join("sessions")
+                .join(&session.session_id)
+                .join("session.json");
+            write_text(&path, &serde_json::to_string_pretty(&session)?)?;
+        }
+    }
+    Ok(["tasks", "issues", "sessions", EVENTS_FILE]
+        .into_iter()
+        .filter(|entry| staging.join(entry).exists())
+        .map(String::from)
+        .collect())
+}
+
+/// Hands a state file from a bundle to the store, then removes it.
+fn adopt_file(path: &Path, save: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    save(path)?;
+    fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))
+}
+
+/// Rejects bundles with absolute paths, `..`, or anything outside the exported
+/// directories before extracting.
+fn validate_entries(bundle: &Path) -> Result<()> {
+    let output = Command::new("tar")
+        .arg("-tzf")
+        .arg(bundle)
+        .output()
+        .context("Failed to run tar")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to read {}: {}",
+            bundle.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    for entry in String::from_utf8_lossy(&output.stdout).lines() {
+        let path = Path::new(entry.trim_start_matches("./"));
+        let safe = path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        let top = path
+            .components()
+            .next()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .unwrap_or_default();
+        let known = matches!(
+            top.as_str(),
+            MANIFEST_FILE | "tasks" | "issues" | "sessions" | EVENTS_FILE
+        );
+        if !safe || !known {
+            bail!("Refusing to import unexpected entry '{}'", entry);
+        }
+    }
+    Ok(())
+}
+
+/// Appends bundle events not already in the local log; returns how many.
+fn merge_events(source: &Path, dest: &Path) -> Result<usize> {
+    if !source.exists() {
+        return Ok(0);
+    }
+    let existing: HashSet<String> = if dest.exists() {
+        read_text(dest)?.lines().map(str::to_string).collect()
+    } else {
+        HashSet::new()
+    };
+    let incoming = read_text(source)?;
+    let new_lines: Vec<&str> = incoming
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !existing.contains(*line))
+        .collect();
+    if new_lines.is_empty() {
+        return Ok(0);
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dest)
+        .with_context(|| format!("Failed to open {}", dest.display()))?;
+    for line in &new_lines {
+        writeln!(file, "{line}")?;
+    }
+    Ok(new_lines.len())
+}
+
+/// A bundle from someone else must not pull local files in through links.
+fn reject_symlinks(dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            bail!(
+                "Refusing to import symlink '{}'",
+                entry.file_name().to_string_lossy()
+            );
+        }
+        if file_type.is_dir() {
+            reject_symlinks(&entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+fn dir_names(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Issue files under `dir` (archived ones included), relative to the issues root.
+fn issue_files(dir: &Path, prefix: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let relative = prefix.join(entry.file_name());
+        if entry.path().is_dir() {
+            files.extend(issue_files(&entry.path(), &relative));
+        } else if relative.extension().and_then(|ext| ext.to_str()) == Some("md") {
+            files.push(relative);
+        }
+    }
+    files
+}
+
+fn absolute(path: PathBuf) -> Result<PathBuf> {
+    if path.is_absolute() {
+        return Ok(path);
+    }
+    Ok(env::current_dir()?.join(path))
+}
+
+fn staging_dir(kind: &str) -> Result<PathBuf> {
+    let dir = env::temp_dir().join(format!("mung-{}-{}", kind, std::process::id()));
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+fn run_tar(mut tar: Command) -> Result<()> {
+    let output = tar.output().context("Failed to run tar")?;
+    if !output.status.success() {
+        bail!(
+            "tar failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
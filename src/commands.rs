@@ -1,36 +1,83 @@
 use anyhow::{bail, Context, Result};
 use clap::Subcommand;
 use owo_colors::OwoColorize;
-use std::collections::{HashMap, HashSet};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::env;
 use std::fs;
-use std::io::Read;
+use std::hash::{Hash, Hasher};
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
 use crate::agent::AgentKind;
+use crate::aging::{
+    archive_resolved_issues, cmd_issue_escalate, cmd_issue_stale, escalate_issue_priorities,
+    expire_advisory_issues, pinged_issues_section, report_escalations,
+};
+use crate::approval::{notify_approval_needed, wait_for_approval};
+use crate::config::{load_config, DedupeMode, InstallConfig, NotifyEvent, QueuePolicy, RepoConfig};
+use crate::events::{Actor, Event};
+use crate::git::{
+    add_worktree, change_summary, commit_all, commit_staged, current_branch, delete_branch,
+    diff_dirs, diff_patch, head_commit, linked_worktrees, local_branches, merge_branch,
+    merged_branches, rebase_onto, remove_worktree, short_commit, staged_diff, task_changed_files,
+    tracked_ignored_files, uncommitted_changes,
+};
+use crate::gitfiles::{stale_git_files, sync_git_files};
+use crate::history::build_task_history;
+use crate::install::{
+    command_dirs, installed_prompts, update_prompt, InstalledPrompt, PromptState, PromptUpdate,
+};
 use crate::issues::{
-    append_resolution, count_open_issues, filter_issues, issue_path, list_issues, new_issue,
-    save_issue, sort_issues, IssueFilter, IssuePriority, IssueSource, IssueStatus,
-    IssueStatusFilter, IssueType,
+    append_comment, append_resolution, count_open_issues, filter_issues, issue_attachments_dir,
+    new_issue, normalize_labels, parse_issue, parse_step_id, render_issue, sort_issues, Issue,
+    IssueCounts, IssueFilter, IssuePriority, IssueSource, IssueStatus, IssueStatusFilter,
+    IssueType,
 };
+use crate::messages::msg;
 use crate::model::Model;
-use crate::prompt::{issues_text, parallelism_text, render_prompt, PromptContext};
+use crate::notify::notify;
+use crate::process::{pid_alive, terminate_tree};
+use crate::prompt::{
+    finish_marker_text, issues_text, parallelism_text, render_prompt, PromptContext, PromptRecord,
+    PROMPT_RECORD_FILE, PROMPT_TEXT_FILE,
+};
+use crate::queue::{next_eligible_task, task_priority, unmet_dependencies};
+use crate::similar::{
+    duplicate_issue_pairs, duplicate_pairs, similar_documents, similar_issues, Document,
+    DUPLICATE_THRESHOLD, ISSUE_DUPLICATE_THRESHOLD,
+};
+use crate::sla::{breached_tasks, check_issue_slas, find_breaches, SlaBreach};
+use crate::spec::{
+    changed_spec_files, issue_spec_file, snapshot_spec, spec_hashes, spec_progress, spec_snapshots,
+    SpecFileProgress, SPEC_HISTORY_DIR,
+};
 use crate::state::{
-    claim_task, create_session, create_task_state, has_active_claim, has_active_session,
-    list_tasks, load_session, load_task, save_session, update_session, update_task, SessionState,
-    SessionStatus, TaskState, TaskStatus,
+    create_session, create_task_state, has_active_session, load_task, update_task, ClaimGuard,
+    PendingApproval, SessionState, SessionStatus, TaskState, TaskStatus,
 };
+use crate::store::{open_store, StateStore};
+use crate::transcript::{self, FinishMarker, MarkerScanner, Transcript, TRANSCRIPT_FILE};
+#[cfg(target_os = "macos")]
+use crate::util::env_var_os;
 use crate::util::{
-    confirm, env_var, env_var_os, get_agent_root, home_dir, now_iso, read_text, task_dir,
-    task_state_path, validate_task_name, write_text, TerminalGuard,
+    archive_dir, claim_path, confirm, copy_dir_all, env_var, get_agent_root, get_repo_root,
+    home_dir, is_executable, json_output, new_uuid, now_iso, print_json, prompt_line, read_text,
+    session_dir, session_state_path, task_dir, today_date, validate_task_name, write_text,
+    TerminalGuard,
 };
+use crate::workers::passed_over;
 
 pub static INTERRUPTED: AtomicBool = AtomicBool::new(false);
-const PROMPT_HOME_DIR: &str = ".mung";
+pub const PROMPT_HOME_DIR: &str = ".mung";
 const LEGACY_PROMPT_HOME_DIR: &str = ".metagent";
 
 #[cfg(unix)]
@@ -57,6 +104,83 @@ pub struct ModelChoice {
     pub model: Model,
     pub explicit: bool,
     pub force_model: bool,
+    /// `--model-args` then each `--model-arg`, appended after any `[model_args]`
+    /// for the model.
+    pub model_args: Vec<String>,
+    /// `--headless` or `MUNG_HEADLESS`.
+    pub headless: bool,
+}
+
+#[derive(Subcommand)]
+pub enum PromptsCommands {
+    Diff {
+        #[arg(help = "Prompts to compare, e.g. code/BUILD_PROMPT.md (default: all)")]
+        files: Vec<String>,
+    },
+    Update {
+        #[arg(help = "Prompts to update, e.g. code/BUILD_PROMPT.md (default: all)")]
+        files: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SessionCommands {
+    Last {
+        task: String,
+    },
+    List {
+        #[arg(long)]
+        task: Option<String>,
+        #[arg(long, help = "running, finished, or failed")]
+        status: Option<String>,
+    },
+    Show {
+        session: String,
+    },
+    Clean {
+        #[arg(
+            long,
+            help = "Delete finished and failed sessions older than this many days"
+        )]
+        older_than: u64,
+        #[arg(
+            long,
+            help = "Newest sessions to keep per task (default: [sessions] keep_per_task or 1)"
+        )]
+        keep: Option<usize>,
+        #[arg(long, help = "List the sessions that would be deleted")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PlanCommands {
+    /// File an issue for each open canonical step
+    #[command(name = "to-issues")]
+    ToIssues {
+        task: String,
+        #[arg(long, help = "List the issues that would be filed")]
+        dry_run: bool,
+    },
+    /// Mark canonical steps done (e.g. `mung plan check my-task T17`)
+    Check {
+        task: String,
+        #[arg(required = true)]
+        steps: Vec<String>,
+    },
+    /// Mark canonical steps not done
+    Uncheck {
+        task: String,
+        #[arg(required = true)]
+        steps: Vec<String>,
+    },
+    /// Check plan steps against the canonical grammar; exits non-zero on problems
+    Lint { task: String },
+}
+
+#[derive(Subcommand)]
+pub enum ClaimCommands {
+    List,
 }
 
 #[derive(Subcommand)]
@@ -74,6 +198,17 @@ pub enum IssueCommands {
         issue_type: Option<String>,
         #[arg(long)]
         source: Option<String>,
+        #[arg(long = "label", help = "Only issues with this label (repeatable)")]
+        labels: Vec<String>,
+        #[arg(long, help = "Also list issues moved to issues/archive/")]
+        include_archived: bool,
+    },
+    Archive {
+        #[arg(
+            long,
+            help = "Archive resolved issues untouched for this many days (default: [issues] archive_after_days)"
+        )]
+        older_than_days: Option<u64>,
     },
     Add {
         #[arg(long)]
@@ -94,12 +229,24 @@ pub enum IssueCommands {
         body: Option<String>,
         #[arg(long)]
         stdin_body: bool,
+        #[arg(long = "label", help = "Free-form label, e.g. security (repeatable)")]
+        labels: Vec<String>,
+        #[arg(
+            long = "attach",
+            help = "Copy a file such as a log into the issue's attachments (repeatable)"
+        )]
+        attachments: Vec<PathBuf>,
     },
     Resolve {
         #[arg(help = "Issue ID (use `mung issues` to list IDs)")]
         id: String,
         #[arg(long)]
         resolution: Option<String>,
+        #[arg(
+            long = "step",
+            help = "Plan step the fix touched (e.g. T17); repeatable"
+        )]
+        steps: Vec<String>,
     },
     Assign {
         #[arg(help = "Issue ID (use `mung issues` to list IDs)")]
@@ -113,6 +260,59 @@ pub enum IssueCommands {
         #[arg(help = "Issue ID (use `mung issues` to list IDs)")]
         id: String,
     },
+    Dedupe {
+        #[arg(long, default_value_t = ISSUE_DUPLICATE_THRESHOLD)]
+        threshold: f64,
+        #[arg(long, help = "Resolve the newer issue of each pair as a duplicate")]
+        link: bool,
+    },
+    Comment {
+        #[arg(help = "Issue ID (use `mung issues` to list IDs)")]
+        id: String,
+        #[arg(long)]
+        body: Option<String>,
+        #[arg(long)]
+        stdin_body: bool,
+        #[arg(long, help = "Comment author (default: MUNG_USER, then git user)")]
+        by: Option<String>,
+    },
+    /// List open issues not updated in a while, grouped by task
+    Stale {
+        #[arg(long, default_value_t = 14)]
+        days: u64,
+        #[arg(long, help = "Also list them in each task's next review prompt")]
+        ping: bool,
+    },
+    /// Bring in issues from another tracker's export
+    Import {
+        #[arg(long, help = "github (gh/REST JSON array) or jsonl")]
+        from: String,
+        #[arg(long)]
+        file: PathBuf,
+        #[arg(long, help = "Assign every imported issue to this task")]
+        task: Option<String>,
+        #[arg(long, help = "List what would be imported without saving")]
+        dry_run: bool,
+    },
+    /// Dump issues with every field as JSON or CSV
+    Export {
+        #[arg(long, default_value = "json", help = "json or csv")]
+        format: String,
+        #[arg(long, help = "open (default), resolved, or all")]
+        status: Option<String>,
+        #[arg(long, help = "Also export issues moved to issues/archive/")]
+        include_archived: bool,
+    },
+    /// Raise the priority of aged open issues per [[issues.escalate]]
+    Escalate {
+        #[arg(long, help = "List what would be escalated without saving")]
+        dry_run: bool,
+    },
+    /// Print the body template `issue add` uses for an issue type
+    Template {
+        #[arg(help = "bug, test, perf, or spec")]
+        issue_type: String,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -124,6 +324,10 @@ pub struct CommandContext {
     pub prompt_root: PathBuf,
     pub legacy_prompt_root: PathBuf,
     pub host: String,
+    pub config: RepoConfig,
+    pub store: Arc<dyn StateStore>,
+    /// Render stage prompts and print them instead of starting a model.
+    pub dry_run: bool,
 }
 
 impl CommandContext {
@@ -136,6 +340,8 @@ impl CommandContext {
             .unwrap_or_default()
             .to_string_lossy()
             .to_string();
+        let config = load_config(&repo_root)?;
+        let store = open_store(&agent_root, &config.state)?;
         Ok(Self {
             agent,
             model_choice,
@@ -144,8 +350,16 @@ impl CommandContext {
             prompt_root,
             legacy_prompt_root,
             host,
+            config,
+            store,
+            dry_run: false,
         })
     }
+
+    /// Identity recorded on events; `user` overrides the detected one.
+    pub fn actor(&self, user: Option<&str>) -> Actor {
+        Actor::current(&self.repo_root, &self.host, user)
+    }
 }
 
 #[cfg(target_os = "macos")]
@@ -275,18 +489,15 @@ pub fn cmd_install() -> Result<()> {
     }
 
     let prompt_home = home.join(PROMPT_HOME_DIR);
-    for agent in [AgentKind::Code, AgentKind::Writer] {
-        let agent_dir = prompt_home.join(agent.name());
-        fs::create_dir_all(&agent_dir)?;
-        for (file, content) in agent.install_prompts() {
-            write_text(&agent_dir.join(file), content)?;
-        }
+    for prompt in installed_prompts(&prompt_home) {
+        report_prompt_update(&prompt, update_prompt(&prompt)?, false);
     }
 
-    let claude_commands = home.join(".claude/commands");
-    let codex_commands = home.join(".codex/prompts");
-    for dir in [&claude_commands, &codex_commands] {
-        fs::create_dir_all(dir)?;
+    let command_dirs = command_dirs(&home, &install_config());
+    let mut linked = vec![0usize; command_dirs.len()];
+    for dir in &command_dirs {
+        fs::create_dir_all(&dir.path)
+            .with_context(|| format!("Failed to create {}", dir.path.display()))?;
     }
     for agent in [AgentKind::Code, AgentKind::Writer] {
         let prompt_dir = prompt_home.join(agent.name());
@@ -295,13 +506,32 @@ pub fn cmd_install() -> Result<()> {
             if !target.exists() {
                 continue;
             }
-            for commands_dir in [&claude_commands, &codex_commands] {
-                let link = commands_dir.join(format!("{command_name}.md"));
+            for (index, dir) in command_dirs.iter().enumerate() {
+                let link = dir.path.join(format!("{command_name}.md"));
                 link_prompt(&target, &link)?;
+                linked[index] += 1;
             }
         }
     }
 
+    let method = if cfg!(unix) { "symlinks" } else { "copies" };
+    let tool_width = command_dirs
+        .iter()
+        .map(|dir| dir.tool.len())
+        .max()
+        .unwrap_or(0)
+        .max("Tool".len());
+    println!("{:<tool_width$}  Commands  Directory", "Tool");
+    for (dir, count) in command_dirs.iter().zip(&linked) {
+        println!(
+            "{:<tool_width$}  {:>8}  {} ({})",
+            dir.tool,
+            count,
+            dir.path.display(),
+            method
+        );
+    }
+
     if let Ok(path) = env::var("PATH") {
         let bin_str = bin_dir.display().to_string();
         if !path.split(':').any(|entry| entry == bin_str) {
@@ -315,33 +545,172 @@ pub fn cmd_install() -> Result<()> {
     Ok(())
 }
 
+/// `[install]` from the current repo's config, when run inside one.
+pub fn install_config() -> InstallConfig {
+    get_repo_root(None)
+        .ok()
+        .and_then(|repo_root| load_config(&repo_root).ok())
+        .map(|config| config.install)
+        .unwrap_or_default()
+}
+
+/// `mung prompts`: compares installed prompts with this binary's and updates
+/// them without dropping local edits.
+pub fn cmd_prompts(command: PromptsCommands) -> Result<()> {
+    let prompt_home = home_dir()?.join(PROMPT_HOME_DIR);
+    match command {
+        PromptsCommands::Diff { files } => {
+            let prompts = select_prompts(installed_prompts(&prompt_home), &files)?;
+            let mut differ = 0;
+            for prompt in prompts
+                .iter()
+                .filter(|prompt| prompt.state != PromptState::Current)
+            {
+                differ += 1;
+                println!(
+                    "{}",
+                    format!("{} ({})", prompt.name, prompt.state.as_str()).bold()
+                );
+                if prompt.state != PromptState::Missing {
+                    print_prompt_diff(prompt)?;
+                }
+            }
+            if differ == 0 {
+                println!("Installed prompts match this version of mung.");
+            }
+            Ok(())
+        }
+        PromptsCommands::Update { files } => {
+            let prompts = select_prompts(installed_prompts(&prompt_home), &files)?;
+            let mut changed = 0;
+            for prompt in &prompts {
+                if report_prompt_update(prompt, update_prompt(prompt)?, true) {
+                    changed += 1;
+                }
+            }
+            println!("Updated {} of {} prompt(s).", changed, prompts.len());
+            Ok(())
+        }
+    }
+}
+
+/// Keeps prompts named in `files`, as `code/BUILD_PROMPT.md` or just
+/// `BUILD_PROMPT.md` for every agent that has one.
+fn select_prompts(prompts: Vec<InstalledPrompt>, files: &[String]) -> Result<Vec<InstalledPrompt>> {
+    if files.is_empty() {
+        return Ok(prompts);
+    }
+    for file in files {
+        let known = prompts
+            .iter()
+            .any(|prompt| prompt_matches(&prompt.name, file));
+        if !known {
+            bail!("Unknown prompt '{}' (see 'mung prompts diff')", file);
+        }
+    }
+    Ok(prompts
+        .into_iter()
+        .filter(|prompt| files.iter().any(|file| prompt_matches(&prompt.name, file)))
+        .collect())
+}
+
+fn prompt_matches(name: &str, file: &str) -> bool {
+    name == file
+        || name
+            .split_once('/')
+            .is_some_and(|(_, relative)| relative == file)
+}
+
+/// Unified diff from the installed prompt to the one in this binary.
+fn print_prompt_diff(prompt: &InstalledPrompt) -> Result<()> {
+    let mut child = Command::new("diff")
+        .arg("-u")
+        .arg("-L")
+        .arg(format!("installed/{}", prompt.name))
+        .arg("-L")
+        .arg(format!("mung/{}", prompt.name))
+        .arg(&prompt.path)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("Failed to run diff")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(prompt.embedded.as_bytes())?;
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// Prints what happened to a prompt; routine installs only when `verbose`.
+/// Returns whether the file changed.
+fn report_prompt_update(prompt: &InstalledPrompt, update: PromptUpdate, verbose: bool) -> bool {
+    match update {
+        PromptUpdate::Unchanged => false,
+        PromptUpdate::Installed | PromptUpdate::Updated => {
+            if verbose {
+                println!("Updated {}", prompt.name);
+            }
+            true
+        }
+        PromptUpdate::Merged => {
+            println!("Merged upstream changes into edited {}", prompt.name);
+            true
+        }
+        PromptUpdate::KeptEdits => {
+            if verbose {
+                println!("Kept local edits to {}", prompt.name);
+            }
+            false
+        }
+        PromptUpdate::BackedUp(backup) => {
+            println!(
+                "Replaced edited {}; local copy saved to {}",
+                prompt.name,
+                backup.display()
+            );
+            true
+        }
+    }
+}
+
 pub fn cmd_uninstall() -> Result<()> {
     let home = home_dir()?;
     let bin_dir = home.join(".local/bin/mung");
     let prompt_home = home.join(PROMPT_HOME_DIR);
     let legacy_prompt_home = home.join(LEGACY_PROMPT_HOME_DIR);
-    let claude_commands = home.join(".claude/commands");
-    let codex_commands = home.join(".codex/prompts");
+    // Without symlinks, install copies prompts; recognize those copies by name.
+    let command_files: HashSet<String> = [AgentKind::Code, AgentKind::Writer]
+        .iter()
+        .flat_map(|agent| agent.slash_commands())
+        .map(|(_, command_name)| format!("{command_name}.md"))
+        .collect();
 
     if bin_dir.exists() {
         fs::remove_file(&bin_dir)?;
         println!("Removed {}", bin_dir.display());
     }
 
-    for dir in [&claude_commands, &codex_commands] {
-        if !dir.exists() {
+    for dir in command_dirs(&home, &install_config()) {
+        if !dir.path.exists() {
             continue;
         }
-        for entry in fs::read_dir(dir)? {
+        for entry in fs::read_dir(&dir.path)? {
             let entry = entry?;
             let path = entry.path();
             if !path.is_file() {
                 continue;
             }
-            if let Ok(target) = fs::read_link(&path) {
-                if target.starts_with(&prompt_home) || target.starts_with(&legacy_prompt_home) {
-                    fs::remove_file(&path)?;
+            let installed = match fs::read_link(&path) {
+                Ok(target) => {
+                    target.starts_with(&prompt_home) || target.starts_with(&legacy_prompt_home)
+                }
+                Err(_) => {
+                    cfg!(not(unix))
+                        && command_files.contains(&entry.file_name().to_string_lossy().to_string())
                 }
+            };
+            if installed {
+                fs::remove_file(&path)?;
             }
         }
     }
@@ -372,7 +741,7 @@ pub fn cmd_init(
     if !target.join(".git").is_dir() {
         let proceed = confirm("Warning: Target is not a git repository. Continue? (y/N) ")?;
         if !proceed {
-            println!("Aborted.");
+            println!("{}", msg("aborted", &[]));
             return Ok(());
         }
     }
@@ -385,7 +754,7 @@ pub fn cmd_init(
             agent.name()
         ))?;
         if !overwrite {
-            println!("Aborted.");
+            println!("{}", msg("aborted", &[]));
             return Ok(());
         }
     }
@@ -401,6 +770,12 @@ pub fn cmd_init(
         }
         write_text(&dest, content)?;
     }
+    let config = load_config(&target)?;
+    if config.gitignore.manage {
+        for path in sync_git_files(&target.join(".agents"), &config.gitignore)? {
+            println!("Updated {}", path.display());
+        }
+    }
 
     println!("Initialized {} agent in {}", agent.name(), target.display());
 
@@ -414,49 +789,175 @@ pub fn cmd_init(
     Ok(())
 }
 
+pub fn cmd_gitignore(ctx: &CommandContext, check: bool) -> Result<()> {
+    let config = &ctx.config.gitignore;
+    if !config.manage {
+        bail!("Git file management is disabled ([gitignore] manage = false in .agents/mung.toml)");
+    }
+    let agents_dir = ctx.repo_root.join(".agents");
+    if check {
+        let stale = stale_git_files(&agents_dir, config);
+        for path in &stale {
+            println!("Out of date: {}", path.display());
+        }
+        report_tracked_ignored(ctx);
+        if !stale.is_empty() {
+            bail!("Managed git files are out of date; run 'mung gitignore'");
+        }
+        println!("Managed git files are up to date");
+        return Ok(());
+    }
+    let changed = sync_git_files(&agents_dir, config)?;
+    if changed.is_empty() {
+        println!("Managed git files are up to date");
+    }
+    for path in changed {
+        println!("Updated {}", path.display());
+    }
+    report_tracked_ignored(ctx);
+    Ok(())
+}
+
+fn report_tracked_ignored(ctx: &CommandContext) {
+    let tracked = tracked_ignored_files(&ctx.repo_root, ".agents");
+    if tracked.is_empty() {
+        return;
+    }
+    println!("Tracked files that are now ignored (untrack with 'git rm --cached <path>'):");
+    for path in tracked {
+        println!("  {}", path);
+    }
+}
+
 fn prompt_task_stage(agent: AgentKind) -> &'static str {
     match agent {
         AgentKind::Code => "build",
         AgentKind::Writer => "write",
+        AgentKind::Custom(agent) => agent.initial_stage,
     }
 }
 
-pub fn cmd_task(
-    ctx: &CommandContext,
-    task: &str,
-    hold: bool,
-    description: Option<String>,
-    prompt: Option<String>,
-) -> Result<()> {
+/// What `mung task` records on a task. Apart from `hold`, which only applies when
+/// the task is created, an existing task keeps whatever is left unset here.
+#[derive(Debug, Clone, Default)]
+pub struct TaskOptions {
+    pub hold: bool,
+    pub description: Option<String>,
+    pub prompt: Option<String>,
+    pub depends_on: Vec<String>,
+    pub owner: Option<String>,
+    pub labels: Vec<String>,
+    pub tags: Vec<String>,
+    /// `YYYY-MM-DD`, or `none` to clear it.
+    pub due: Option<String>,
+    /// `S`/`M`/`L` or hours such as `6h`, or `none` to clear it.
+    pub estimate: Option<String>,
+    pub priority: Option<String>,
+}
+
+pub fn cmd_task(ctx: &CommandContext, task: &str, options: TaskOptions) -> Result<()> {
+    let TaskOptions {
+        hold,
+        description,
+        prompt,
+        depends_on,
+        owner,
+        labels,
+        tags,
+        due,
+        estimate,
+        priority,
+    } = options;
+    let (depends_on, labels, tags) = (&depends_on[..], &labels[..], &tags[..]);
     validate_task_name(task)?;
     let prompt = prompt.map(|value| value.trim().to_string());
     if matches!(prompt.as_deref(), Some("")) {
         bail!("Prompt cannot be empty");
     }
-    let task_path = task_state_path(&ctx.agent_root, task);
+    let due = due.as_deref().map(parse_due_date).transpose()?;
+    let estimate = estimate.as_deref().map(parse_estimate).transpose()?;
+    let priority = parse_priority(priority.as_deref())?;
+    validate_dependencies(ctx, task, depends_on)?;
     let task_dir_path = task_dir(&ctx.agent_root, task);
 
-    if task_path.exists() {
-        if description.is_some() || prompt.is_some() {
-            update_task(&task_path, |task_state| {
+    if ctx.store.task_exists(task) {
+        if description.is_some()
+            || prompt.is_some()
+            || !depends_on.is_empty()
+            || owner.is_some()
+            || !labels.is_empty()
+            || !tags.is_empty()
+            || due.is_some()
+            || estimate.is_some()
+            || priority.is_some()
+        {
+            ctx.store.update_task(task, |task_state| {
                 if let Some(description) = description.as_ref() {
                     task_state.description = Some(description.clone());
                 }
                 if let Some(prompt) = prompt.as_ref() {
                     task_state.prompt = Some(prompt.clone());
                 }
+                if let Some(owner) = owner.as_ref() {
+                    task_state.owner = Some(owner.clone());
+                }
+                add_labels(task_state, labels);
+                add_tags(task_state, tags);
+                add_dependencies(task_state, depends_on);
+                if let Some(due) = due.as_ref() {
+                    task_state.due_at = due.clone();
+                }
+                if let Some(estimate) = estimate.as_ref() {
+                    task_state.estimate = estimate.clone();
+                }
+                if let Some(priority) = priority.as_ref() {
+                    task_state.priority = Some(priority.to_string());
+                }
                 task_state.updated_at = now_iso();
                 Ok(())
             })?;
         }
-        let task_state = load_task(&task_path)?;
-        println!("Task '{}' already exists", task);
+        let task_state = ctx.store.load_task(task)?;
+        if json_output() {
+            return print_task_json(ctx, &task_state, false);
+        }
+        println!("{}", msg("task_exists", &[("task", &task)]));
         println!("  Stage: {}", task_state.stage);
         if task_state.held {
-            println!("  Status: held (backlog)");
+            match task_state.held_until.as_deref() {
+                Some(until) => println!("  Status: held until {}", snooze_label(until)),
+                None => println!("  Status: held (backlog)"),
+            }
+            if let Some(reason) = task_state.held_reason.as_ref() {
+                println!("  Held: {}", reason);
+            }
+        }
+        if let Some(reason) = task_state.blocked_reason.as_ref() {
+            println!("  Blocked: {}", reason);
+        }
+        if !task_state.depends_on.is_empty() {
+            println!("  Depends on: {}", task_state.depends_on.join(", "));
+        }
+        if let Some(owner) = task_state.owner.as_ref() {
+            println!("  Owner: {}", owner);
+        }
+        if !task_state.labels.is_empty() {
+            println!("  Labels: {}", task_state.labels.join(", "));
+        }
+        if !task_state.tags.is_empty() {
+            println!("  Tags: {}", task_state.tags.join(", "));
+        }
+        if let Some(due) = task_state.due_at.as_ref() {
+            println!("  Due: {}", due);
+        }
+        if let Some(estimate) = task_state.estimate.as_ref() {
+            println!("  Estimate: {}", estimate);
+        }
+        if let Some(priority) = task_state.priority.as_ref() {
+            println!("  Priority: {}", priority);
         }
         if let Some(description) = task_state.description.as_ref() {
-            println!("  Description: {}", description);
+            println!("  Description: {}", description_summary(description));
         } else {
             println!("  Description: (none)");
         }
@@ -465,7 +966,10 @@ pub fn cmd_task(
         } else {
             println!("  Prompt: (none)");
         }
-        let history = build_task_history(&ctx.agent_root, task)?;
+        if !task_state.escalated_stages.is_empty() {
+            println!("  Escalated: {}", task_state.escalated_stages.join(", "));
+        }
+        let history = build_task_history(&task_state);
         if history.is_empty() {
             println!("  History: (none yet)");
         } else {
@@ -483,7 +987,7 @@ pub fn cmd_task(
         ctx.agent.initial_stage()
     };
     create_task_state(
-        &ctx.agent_root,
+        ctx.store.as_ref(),
         ctx.agent.name(),
         task,
         initial_stage,
@@ -492,1333 +996,5646 @@ pub fn cmd_task(
         description.clone(),
         prompt.clone(),
     )?;
+    let owner = owner.unwrap_or_else(|| ctx.actor(None).user);
+    ctx.store.update_task(task, |task_state| {
+        task_state.owner = Some(owner.clone());
+        add_labels(task_state, labels);
+        add_tags(task_state, tags);
+        add_dependencies(task_state, depends_on);
+        task_state.due_at = due.clone().flatten();
+        task_state.estimate = estimate.clone().flatten();
+        task_state.priority = priority.as_ref().map(ToString::to_string);
+        Ok(())
+    })?;
 
-    println!("Created task: {}", task);
+    if json_output() {
+        print_task_json(ctx, &ctx.store.load_task(task)?, true)?;
+        warn_possible_duplicates(ctx, task);
+        return Ok(());
+    }
+    println!("{}", msg("task_created", &[("task", &task)]));
     println!("  Directory: {}", task_dir_path.display());
     println!("  Stage: {}", initial_stage);
     if hold {
         println!("  Status: held (backlog)");
     }
+    if !depends_on.is_empty() {
+        println!("  Depends on: {}", depends_on.join(", "));
+    }
+    println!("  Owner: {}", owner);
+    if !labels.is_empty() {
+        println!("  Labels: {}", labels.join(", "));
+    }
+    if !tags.is_empty() {
+        println!("  Tags: {}", tags.join(", "));
+    }
+    if let Some(due) = due.flatten() {
+        println!("  Due: {}", due);
+    }
+    if let Some(estimate) = estimate.flatten() {
+        println!("  Estimate: {}", estimate);
+    }
+    if let Some(priority) = priority {
+        println!("  Priority: {}", priority);
+    }
     if let Some(description) = description {
-        println!("  Description: {}", description);
+        println!("  Description: {}", description_summary(&description));
     }
     if prompt.is_some() {
         println!("  Prompt: (custom)");
     }
+    warn_possible_duplicates(ctx, task);
     Ok(())
 }
 
-pub fn cmd_hold(ctx: &CommandContext, task: &str) -> Result<()> {
-    validate_task_name(task)?;
-    let task_path = task_state_path(&ctx.agent_root, task);
-    if !task_path.exists() {
-        bail!("Task '{}' not found", task);
-    }
-    update_task(&task_path, |task_state| {
-        if task_state.status == TaskStatus::Running {
-            bail!("Task '{}' is running. Finish it before holding.", task);
+/// Full description text from `task --description-file` or `--stdin-description`.
+pub fn read_description_input(file: Option<&Path>, stdin: bool) -> Result<Option<String>> {
+    let text = match (file, stdin) {
+        (Some(_), true) => bail!("Use --description-file or --stdin-description, not both"),
+        (Some(path), false) => read_text(path)?,
+        (None, true) => {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input)?;
+            input
         }
-        task_state.held = true;
-        task_state.updated_at = now_iso();
-        Ok(())
-    })?;
-    println!("Held '{}'", task);
-    Ok(())
+        (None, false) => return Ok(None),
+    };
+    if text.trim().is_empty() {
+        bail!("Description cannot be empty");
+    }
+    Ok(Some(text.trim().to_string()))
 }
 
-pub fn cmd_activate(ctx: &CommandContext, task: &str) -> Result<()> {
-    validate_task_name(task)?;
-    let task_path = task_state_path(&ctx.agent_root, task);
-    if !task_path.exists() {
-        bail!("Task '{}' not found", task);
+/// Copies a long task description into `spec/overview.md` as the spec's starting
+/// point, as long as the overview is still the empty stub `task` writes.
+pub fn seed_overview(ctx: &CommandContext, task: &str, description: &str) -> Result<()> {
+    let path = task_dir(&ctx.agent_root, task).join("spec/overview.md");
+    let Ok(current) = read_text(&path) else {
+        return Ok(());
+    };
+    if current.trim() != "# Overview" {
+        eprintln!(
+            "Warning: {} already has content; left as is",
+            path.display()
+        );
+        return Ok(());
+    }
+    write_text(&path, &format!("# Overview\n\n{}\n", description))?;
+    if !json_output() {
+        println!("  Overview: {}", path.display());
     }
-    update_task(&task_path, |task_state| {
-        task_state.held = false;
-        task_state.updated_at = now_iso();
-        Ok(())
-    })?;
-    sync_task_status_for_issues(&ctx.agent_root, task)?;
-    println!("Activated '{}'", task);
     Ok(())
 }
 
-pub fn cmd_queue(ctx: &CommandContext, task: Option<&str>) -> Result<()> {
-    if let Some(task) = task {
-        validate_task_name(task)?;
-        let task_path = task_state_path(&ctx.agent_root, task);
-        if task_path.exists() {
-            let task_state = load_task(&task_path)?;
-            println!("Task '{}' already exists", task);
-            println!("  Stage: {}", task_state.stage);
-            if task_state.held {
-                println!("  Status: held (backlog)");
-            }
-            return Ok(());
+/// A description for one-line output: its first line, noting any more.
+fn description_summary(description: &str) -> String {
+    let mut lines = description.lines();
+    let first = lines.next().unwrap_or_default();
+    match lines.count() {
+        0 => first.to_string(),
+        more => format!("{} (+{} lines)", first, more),
+    }
+}
+
+fn add_labels(task_state: &mut TaskState, labels: &[String]) {
+    for label in labels {
+        let label = label.trim();
+        if !label.is_empty() && !task_state.labels.iter().any(|known| known == label) {
+            task_state.labels.push(label.to_string());
         }
+    }
+}
 
-        let dir = task_dir(&ctx.agent_root, task);
-        if !dir.exists() {
-            bail!(
-                "Task '{}' not found. Create it with 'mung task {}'",
-                task,
-                task
-            );
+fn add_tags(task_state: &mut TaskState, tags: &[String]) {
+    for tag in tags {
+        let tag = tag.trim();
+        if !tag.is_empty() && !task_state.tags.iter().any(|known| known == tag) {
+            task_state.tags.push(tag.to_string());
         }
+    }
+}
 
-        let timestamp = now_iso();
-        create_task_state(
-            &ctx.agent_root,
-            ctx.agent.name(),
-            task,
-            ctx.agent.initial_stage(),
-            &timestamp,
-            false,
-            None,
-            None,
-        )?;
-        println!("Queued '{}' (stage: {})", task, ctx.agent.initial_stage());
-        return Ok(());
+/// `task --due`: a `YYYY-MM-DD` date, or `none` to clear it.
+fn parse_due_date(value: &str) -> Result<Option<String>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("none") {
+        return Ok(None);
     }
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .with_context(|| format!("Invalid due date: {} (use YYYY-MM-DD or none)", value))?;
+    Ok(Some(date.format("%Y-%m-%d").to_string()))
+}
 
-    let tasks = list_tasks(&ctx.agent_root);
-    if tasks.is_empty() {
-        println!("{}", "No tasks".dimmed());
-        return Ok(());
+/// `task --estimate`: `S`, `M`, `L`, or whole hours (`6h`, `6`), or `none` to clear it.
+fn parse_estimate(value: &str) -> Result<Option<String>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+    let upper = value.to_uppercase();
+    if matches!(upper.as_str(), "S" | "M" | "L") {
+        return Ok(Some(upper));
+    }
+    match value.trim_end_matches(['h', 'H']).parse::<u32>() {
+        Ok(hours) if hours > 0 => Ok(Some(format!("{hours}h"))),
+        _ => bail!(
+            "Invalid estimate: {} (use S, M, L, hours like 6h, or none)",
+            value
+        ),
     }
+}
 
-    let issue_counts = match list_issues(&ctx.agent_root) {
-        Ok(issues) => count_open_issues(&issues),
-        Err(err) => {
-            eprintln!("Warning: failed to load issues: {}", err);
-            Default::default()
+/// Hours an estimate stands for. Sizes count as 2h, a day (8h), and three days (24h).
+fn estimate_hours(estimate: &str) -> Option<u32> {
+    match estimate {
+        "S" => Some(2),
+        "M" => Some(8),
+        "L" => Some(24),
+        hours => hours.strip_suffix('h')?.parse().ok(),
+    }
+}
+
+/// Past its due date (in local time) and not completed yet.
+fn is_overdue(task: &TaskState) -> bool {
+    task.stage != "completed"
+        && task
+            .due_at
+            .as_deref()
+            .and_then(|due| chrono::NaiveDate::parse_from_str(due, "%Y-%m-%d").ok())
+            .is_some_and(|due| due < chrono::Local::now().date_naive())
+}
+
+fn has_tags(task: &TaskState, tags: &[String]) -> bool {
+    tags.iter()
+        .all(|tag| task.tags.iter().any(|known| known == tag.trim()))
+}
+
+/// Tasks outside `--tag` are held for this pick. They stay in the list so a
+/// finished dependency still counts.
+fn with_tags(mut tasks: Vec<TaskState>, tags: &[String]) -> Vec<TaskState> {
+    for task in tasks.iter_mut() {
+        if !has_tags(task, tags) {
+            task.held = true;
         }
-    };
-    if issue_counts.unassigned > 0 {
-        println!(
-            "Unassigned issues: {} (run 'mung issues --unassigned')",
-            issue_counts.unassigned
-        );
     }
+    tasks
+}
 
-    let mut backlog: Vec<&TaskState> = tasks.iter().filter(|t| t.held).collect();
-    println!("{}", "Tasks:".bold());
-    for stage in ctx.agent.stages() {
-        if *stage == "completed" {
-            continue;
+/// Dependencies must be existing tasks of this agent and must not form a cycle.
+fn validate_dependencies(ctx: &CommandContext, task: &str, depends_on: &[String]) -> Result<()> {
+    for dependency in depends_on {
+        validate_task_name(dependency)?;
+        if dependency == task {
+            bail!("Task '{}' cannot depend on itself", task);
         }
-        let mut stage_tasks: Vec<&TaskState> = tasks
-            .iter()
-            .filter(|t| !t.held && t.stage == *stage)
-            .collect();
-        if stage_tasks.is_empty() {
-            continue;
+        if !ctx.store.task_exists(dependency) {
+            bail!("{}", msg("dependency_not_found", &[("task", &dependency)]));
         }
-        if *stage == "build" {
-            stage_tasks.sort_by(|a, b| {
-                let ar = a.queue_rank.unwrap_or(i64::MAX);
-                let br = b.queue_rank.unwrap_or(i64::MAX);
-                ar.cmp(&br).then_with(|| a.added_at.cmp(&b.added_at))
-            });
-        } else {
-            stage_tasks.sort_by(|a, b| a.added_at.cmp(&b.added_at));
-        }
-        println!("{}:", ctx.agent.stage_label(stage));
-        for task in stage_tasks {
-            let issue_count = issue_counts.per_task.get(&task.task).copied().unwrap_or(0);
-            if issue_count > 0 {
-                println!(
-                    "  {} {} [issues: {}]",
-                    task.status.styled(),
-                    task.task,
-                    issue_count
-                );
-            } else {
-                println!("  {} {}", task.status.styled(), task.task);
-            }
+        if depends_transitively(ctx.store.as_ref(), dependency, task) {
+            bail!(
+                "Task '{}' already depends on '{}'; adding this dependency would create a cycle",
+                dependency,
+                task
+            );
         }
-        println!();
     }
+    Ok(())
+}
 
-    let mut completed: Vec<&TaskState> = tasks
-        .iter()
-        .filter(|t| !t.held && t.stage == "completed")
-        .collect();
-    if !completed.is_empty() {
-        completed.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
-        let total_completed = completed.len();
-        println!("{}:", ctx.agent.stage_label("completed").dimmed());
-        for task in completed.into_iter().take(10) {
-            let issue_count = issue_counts.per_task.get(&task.task).copied().unwrap_or(0);
-            if issue_count > 0 {
-                println!(
-                    "  {} {} [issues: {}]",
-                    task.status.styled(),
-                    task.task.dimmed(),
-                    issue_count
-                );
-            } else {
-                println!("  {} {}", task.status.styled(), task.task.dimmed());
-            }
+fn depends_transitively(store: &dyn StateStore, from: &str, target: &str) -> bool {
+    let mut pending = vec![from.to_string()];
+    let mut seen = HashSet::new();
+    while let Some(name) = pending.pop() {
+        if !seen.insert(name.clone()) {
+            continue;
         }
-        if total_completed > 10 {
-            println!("  ... and {} more", total_completed - 10);
+        let Ok(task_state) = store.load_task(&name) else {
+            continue;
+        };
+        for dependency in task_state.depends_on {
+            if dependency == target {
+                return true;
+            }
+            pending.push(dependency);
         }
     }
+    false
+}
 
-    if !backlog.is_empty() {
-        backlog.sort_by(|a, b| a.added_at.cmp(&b.added_at));
-        println!("\nBacklog:");
-        for task in backlog {
-            let issue_count = issue_counts.per_task.get(&task.task).copied().unwrap_or(0);
-            if issue_count > 0 {
-                println!(
-                    "  {} {} [issues: {}] (stage: {})",
-                    task.status.styled(),
-                    task.task,
-                    issue_count,
-                    ctx.agent.stage_label(&task.stage)
-                );
-            } else {
-                println!(
-                    "  {} {} (stage: {})",
-                    task.status.styled(),
-                    task.task,
-                    ctx.agent.stage_label(&task.stage)
-                );
-            }
+fn add_dependencies(task_state: &mut TaskState, depends_on: &[String]) {
+    for dependency in depends_on {
+        if !task_state.depends_on.contains(dependency) {
+            task_state.depends_on.push(dependency.clone());
         }
     }
+}
 
-    Ok(())
+/// `--json` view of a task: its `task.json` plus directory, the one-line stage
+/// history, and whether this call created it.
+fn print_task_json(ctx: &CommandContext, task_state: &TaskState, created: bool) -> Result<()> {
+    let mut value = serde_json::to_value(task_state)?;
+    value["created"] = json!(created);
+    value["directory"] = json!(task_dir(&ctx.agent_root, &task_state.task));
+    value["history"] = json!(build_task_history(task_state));
+    print_json(&value)
 }
 
-pub fn cmd_plan(ctx: &CommandContext, task: &str) -> Result<()> {
-    validate_task_name(task)?;
-    let file_name = if ctx.agent == AgentKind::Code {
-        "plan.md"
-    } else {
-        "editorial_plan.md"
+fn open_task_documents(ctx: &CommandContext) -> Vec<Document> {
+    ctx.store
+        .list_tasks()
+        .iter()
+        .filter(|task| task.stage != "completed")
+        .map(|task| Document::from_task(&ctx.agent_root, task))
+        .collect()
+}
+
+fn warn_possible_duplicates(ctx: &CommandContext, task: &str) {
+    let Ok(task_state) = ctx.store.load_task(task) else {
+        return;
     };
-    let plan_path = task_dir(&ctx.agent_root, task).join(file_name);
-    if !plan_path.exists() {
-        bail!(
-            "{} not found for task '{}': {}",
-            file_name,
+    let candidate = Document::from_task(&ctx.agent_root, &task_state);
+    let others = open_task_documents(ctx);
+    for (other, score) in similar_documents(&candidate, &others, DUPLICATE_THRESHOLD) {
+        eprintln!(
+            "{} '{}' looks similar to open task '{}' (similarity {:.2}); if it is a duplicate, combine them with 'mung merge-tasks {} {} --into {}'",
+            "Warning:".yellow(),
             task,
-            plan_path.display()
+            other,
+            score,
+            other,
+            task,
+            other
         );
     }
+}
 
-    let content = read_text(&plan_path)?;
-    let mut canonical_steps = Vec::new();
-    let mut checklist_steps = Vec::new();
-    let mut id_lines: HashMap<u32, Vec<usize>> = HashMap::new();
-
-    for (index, line) in content.lines().enumerate() {
-        let line_number = index + 1;
-        if let Some(step) = parse_canonical_plan_step(line, line_number) {
-            id_lines.entry(step.id).or_default().push(line_number);
-            canonical_steps.push(step);
-            continue;
-        }
-        if let Some(step) = parse_checklist_step(line, line_number) {
-            checklist_steps.push(step);
-        }
+pub fn cmd_dupes(ctx: &CommandContext, threshold: f64) -> Result<()> {
+    if !(0.0..=1.0).contains(&threshold) {
+        bail!("Threshold must be between 0 and 1");
     }
-
-    if canonical_steps.is_empty() && checklist_steps.is_empty() {
-        println!(
-            "{}",
-            format!("No checklist steps found in {}", plan_path.display()).dimmed()
-        );
+    let pairs = duplicate_pairs(&open_task_documents(ctx), threshold);
+    if pairs.is_empty() {
+        println!("{}", "No likely duplicates".dimmed());
         return Ok(());
     }
+    println!("{}", "Likely duplicates:".bold());
+    for (a, b, score) in pairs {
+        println!("  {:.2} {} <-> {}", score, a, b);
+    }
+    Ok(())
+}
 
-    println!("Plan '{}': {}", task, plan_path.display());
-    let mut open = 0usize;
-    let mut done = 0usize;
-
-    if !canonical_steps.is_empty() {
-        println!("Canonical steps:");
-        for step in &canonical_steps {
-            let marker = if step.done { "x" } else { " " };
-            if step.done {
-                done += 1;
-            } else {
-                open += 1;
-            }
-            println!(
-                "  L{} - [{}] [{}][{}][T{}] {}",
-                step.line, marker, step.priority, step.complexity, step.id, step.title
-            );
+/// `mung hold <task>`: keeps the task out of the queue. With `until` (a date) or
+/// `for_` (`3d`, `12h`, `2w`) it is snoozed: the next queue or run that sees the time
+/// has passed activates it again. `reason` is kept until the task is activated.
+pub fn cmd_hold(
+    ctx: &CommandContext,
+    task: &str,
+    until: Option<&str>,
+    for_: Option<&str>,
+    reason: Option<&str>,
+) -> Result<()> {
+    validate_task_name(task)?;
+    let held_until = parse_snooze(until, for_)?;
+    let reason = reason
+        .map(|reason| reason.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|reason| !reason.is_empty());
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    ctx.store.update_task(task, |task_state| {
+        if task_state.status == TaskStatus::Running {
+            bail!("Task '{}' is running. Finish it before holding.", task);
         }
+        task_state.held = true;
+        task_state.held_until = held_until.clone();
+        task_state.held_reason = reason.clone();
+        task_state.updated_at = now_iso();
+        Ok(())
+    })?;
+    match held_until.as_deref() {
+        Some(until) => println!(
+            "{}",
+            msg(
+                "task_held_until",
+                &[("task", &task), ("until", &snooze_label(until))]
+            )
+        ),
+        None => println!("{}", msg("task_held", &[("task", &task)])),
     }
+    Ok(())
+}
 
-    if !checklist_steps.is_empty() {
-        println!("Other checklist lines:");
-        for step in &checklist_steps {
-            let marker = if step.done { "x" } else { " " };
-            if step.done {
-                done += 1;
-            } else {
-                open += 1;
-            }
-            println!("  L{} - [{}] {}", step.line, marker, step.title);
+/// `hold --until <YYYY-MM-DD>` (local midnight) or `--for <N>[hdw]`, as an RFC 3339
+/// time in UTC.
+fn parse_snooze(until: Option<&str>, for_: Option<&str>) -> Result<Option<String>> {
+    let now = chrono::Utc::now();
+    let at = match (until, for_) {
+        (None, None) => return Ok(None),
+        (Some(_), Some(_)) => bail!("Pass --until or --for, not both"),
+        (Some(until), None) => {
+            let until = until.trim();
+            let date = chrono::NaiveDate::parse_from_str(until, "%Y-%m-%d")
+                .with_context(|| format!("Invalid date: {} (use YYYY-MM-DD)", until))?;
+            date.and_hms_opt(0, 0, 0)
+                .and_then(|midnight| midnight.and_local_timezone(chrono::Local).earliest())
+                .with_context(|| format!("Invalid date: {}", until))?
+                .with_timezone(&chrono::Utc)
         }
+        (None, Some(duration)) => {
+            let duration = duration.trim();
+            let invalid = || format!("Invalid duration: {} (use e.g. 12h, 7d, or 2w)", duration);
+            let unit = duration.chars().last().with_context(invalid)?;
+            let count: i64 = duration[..duration.len() - unit.len_utf8()]
+                .parse()
+                .ok()
+                .filter(|count| *count > 0)
+                .with_context(invalid)?;
+            let delta = match unit {
+                'h' => chrono::TimeDelta::try_hours(count),
+                'd' => chrono::TimeDelta::try_days(count),
+                'w' => chrono::TimeDelta::try_weeks(count),
+                _ => bail!(invalid()),
+            };
+            delta
+                .and_then(|delta| now.checked_add_signed(delta))
+                .with_context(|| format!("Cannot hold for {}: that is too far ahead", duration))?
+        }
+    };
+    if at <= now {
+        bail!(
+            "Cannot hold until {}: that is already past",
+            snooze_label(&at.to_rfc3339())
+        );
     }
+    Ok(Some(at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)))
+}
 
-    let total = open + done;
-    println!();
-    println!("Summary: {} total ({} open, {} done)", total, open, done);
+/// A `held_until` time for display, in local time.
+fn snooze_label(until: &str) -> String {
+    match chrono::DateTime::parse_from_rfc3339(until) {
+        Ok(at) => at
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        Err(_) => until.to_string(),
+    }
+}
 
-    let mut duplicates: Vec<(u32, Vec<usize>)> = id_lines
-        .into_iter()
-        .filter_map(|(id, mut lines)| {
-            if lines.len() <= 1 {
-                return None;
-            }
-            lines.sort_unstable();
-            Some((id, lines))
-        })
-        .collect();
-    duplicates.sort_by_key(|(id, _)| *id);
-    if !duplicates.is_empty() {
-        println!();
-        println!("Warnings:");
-        for (id, lines) in duplicates {
-            let joined = lines
-                .iter()
-                .map(|line| line.to_string())
-                .collect::<Vec<_>>()
-                .join(", ");
-            println!("  duplicate T{} at lines {}", id, joined);
+/// Activates held tasks whose `held_until` has passed. Runs wherever the queue is
+/// listed or picked from, except in read-only mode.
+fn wake_snoozed_tasks(ctx: &CommandContext) -> Result<()> {
+    if crate::state::is_read_only() {
+        return Ok(());
+    }
+    let now = chrono::Utc::now();
+    for task in ctx.store.list_tasks() {
+        let Some(until) = task.held_until.as_deref().filter(|_| task.held) else {
+            continue;
+        };
+        if chrono::DateTime::parse_from_rfc3339(until).is_ok_and(|until| until > now) {
+            continue;
         }
+        release_hold(ctx, &task.task)?;
+        sync_task_status_for_issues(ctx.store.as_ref(), &task.task)?;
+        eprintln!(
+            "{}",
+            msg(
+                "task_reactivated",
+                &[("task", &task.task), ("until", &snooze_label(until))]
+            )
+        );
     }
-
     Ok(())
 }
 
-pub fn cmd_issues(
-    ctx: &CommandContext,
-    task: Option<String>,
-    unassigned: bool,
-    status: Option<String>,
-    priority: Option<String>,
-    issue_type: Option<String>,
-    source: Option<String>,
-) -> Result<()> {
-    ensure_code_agent(ctx)?;
-    if unassigned && task.is_some() {
-        bail!("Use --task or --unassigned, not both");
-    }
-    if let Some(task) = task.as_deref() {
-        validate_task_name(task)?;
+/// `mung prioritize <task> <P0..P3>`: the queue runs higher-priority tasks first.
+pub fn cmd_prioritize(ctx: &CommandContext, task: &str, priority: &str) -> Result<()> {
+    validate_task_name(task)?;
+    let priority = IssuePriority::from_str(priority)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
     }
-    let status_filter = parse_status_filter(status.as_deref())?;
-    let priority = parse_priority(priority.as_deref())?;
-    let issue_type = parse_issue_type(issue_type.as_deref())?;
-    let source = parse_issue_source(source.as_deref())?;
+    ctx.store.update_task(task, |task_state| {
+        task_state.priority = Some(priority.to_string());
+        task_state.updated_at = now_iso();
+        Ok(())
+    })?;
+    println!(
+        "{}",
+        msg(
+            "task_prioritized",
+            &[("task", &task), ("priority", &priority)]
+        )
+    );
+    Ok(())
+}
 
-    let filter = IssueFilter {
-        status: status_filter,
-        task,
-        unassigned,
-        issue_type,
-        priority,
-        source,
-    };
+pub fn cmd_activate(ctx: &CommandContext, task: &str) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    release_hold(ctx, task)?;
+    sync_task_status_for_issues(ctx.store.as_ref(), task)?;
+    println!("{}", msg("task_activated", &[("task", &task)]));
+    Ok(())
+}
 
-    let issues = list_issues(&ctx.agent_root)?;
-    let mut issues = filter_issues(issues, &filter);
-    sort_issues(&mut issues);
+/// Takes `task` off hold, dropping its snooze time and hold reason with it.
+fn release_hold(ctx: &CommandContext, task: &str) -> Result<()> {
+    ctx.store.update_task(task, |task_state| {
+        task_state.held = false;
+        task_state.held_until = None;
+        task_state.held_reason = None;
+        task_state.updated_at = now_iso();
+        Ok(())
+    })
+}
 
-    if issues.is_empty() {
-        println!("{}", "No issues".dimmed());
-        return Ok(());
+pub fn cmd_block(ctx: &CommandContext, task: &str, reason: &str) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
     }
-
-    let heading = match status_filter {
-        IssueStatusFilter::Open => "Open issues",
-        IssueStatusFilter::Resolved => "Resolved issues",
-        IssueStatusFilter::All => "Issues",
-    };
-    println!("{}:", heading);
-    for (index, issue) in issues.iter().enumerate() {
-        let task_label = issue.task.as_deref().unwrap_or("unassigned");
-        println!("  id: {}", issue.id);
-        println!("  [{}] {}: {}", issue.priority, task_label, issue.title);
-        if status_filter == IssueStatusFilter::All {
-            println!("      status: {}", issue.status);
-        }
-        if index + 1 < issues.len() {
-            println!();
-        }
+    let reason = reason.trim();
+    if reason.is_empty() {
+        bail!("A reason is required to block a task");
     }
+    block_task(ctx, task, reason)?;
+    println!(
+        "{}",
+        msg("task_blocked", &[("task", &task), ("reason", &reason)])
+    );
     Ok(())
 }
 
-pub fn cmd_issue(ctx: &CommandContext, command: IssueCommands) -> Result<()> {
-    ensure_code_agent(ctx)?;
-    match command {
-        IssueCommands::List {
-            task,
-            unassigned,
-            status,
-            priority,
-            issue_type,
-            source,
-        } => cmd_issues(ctx, task, unassigned, status, priority, issue_type, source),
-        IssueCommands::Add {
-            title,
-            task,
-            priority,
-            issue_type,
-            source,
-            file,
-            stage,
-            body,
-            stdin_body,
-        } => cmd_issue_add(
-            ctx, title, task, priority, issue_type, source, file, stage, body, stdin_body,
-        ),
-        IssueCommands::Resolve { id, resolution } => cmd_issue_resolve(ctx, &id, resolution),
-        IssueCommands::Assign { id, task, stage } => cmd_issue_assign(ctx, &id, &task, stage),
-        IssueCommands::Show { id } => cmd_issue_show(ctx, &id),
-    }
+/// Marks a task as unable to proceed. Unlike `held` ("not now"), a blocked task
+/// stays out of the queue until someone runs `mung unblock`.
+pub fn block_task(ctx: &CommandContext, task: &str, reason: &str) -> Result<()> {
+    ctx.store.update_task(task, |task_state| {
+        if task_state.status == TaskStatus::Running {
+            bail!("Task '{}' is running. Finish it before blocking.", task);
+        }
+        task_state.status = TaskStatus::Blocked;
+        task_state.blocked_reason = Some(reason.to_string());
+        task_state.updated_at = now_iso();
+        Ok(())
+    })?;
+    let mut event = Event::new(task, "blocked", ctx.actor(None));
+    event.note = Some(reason.to_string());
+    ctx.store.append_event(&event)
 }
 
-pub fn cmd_delete(ctx: &CommandContext, task: &str, force: bool) -> Result<()> {
+pub fn cmd_unblock(ctx: &CommandContext, task: &str) -> Result<()> {
     validate_task_name(task)?;
-    let dir = task_dir(&ctx.agent_root, task);
-    if !dir.exists() {
-        println!("Task '{}' not found", task);
-        return Ok(());
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
     }
+    if ctx.store.load_task(task)?.status != TaskStatus::Blocked {
+        bail!("Task '{}' is not blocked", task);
+    }
+    ctx.store.update_task(task, |task_state| {
+        // A failed update writes nothing, should the task change in between.
+        if task_state.status != TaskStatus::Blocked {
+            bail!("Task '{}' is not blocked", task);
+        }
+        task_state.status = TaskStatus::Pending;
+        task_state.blocked_reason = None;
+        task_state.updated_at = now_iso();
+        Ok(())
+    })?;
+    sync_task_status_for_issues(ctx.store.as_ref(), task)?;
+    ctx.store
+        .append_event(&Event::new(task, "unblocked", ctx.actor(None)))?;
+    println!("{}", msg("task_unblocked", &[("task", &task)]));
+    Ok(())
+}
 
-    let issues = list_issues(&ctx.agent_root)?;
-    let open_issue_ids: Vec<_> = issues
-        .iter()
-        .filter(|issue| issue.status == IssueStatus::Open && issue.task.as_deref() == Some(task))
-        .map(|issue| issue.id.clone())
-        .collect();
-
-    if !open_issue_ids.is_empty() && !force {
+fn ensure_not_blocked(task_state: &TaskState) -> Result<()> {
+    if task_state.status == TaskStatus::Blocked {
         bail!(
-            "Task '{}' has open issues ({}). Re-run with --force to delete and unassign them.",
-            task,
-            open_issue_ids.len()
+            "Task '{}' is blocked: {}. Run 'mung unblock {}' first.",
+            task_state.task,
+            task_state
+                .blocked_reason
+                .as_deref()
+                .unwrap_or("no reason given"),
+            task_state.task
         );
     }
+    Ok(())
+}
 
-    if force && !open_issue_ids.is_empty() {
-        for mut issue in issues {
-            if issue.status == IssueStatus::Open && issue.task.as_deref() == Some(task) {
-                issue.task = None;
-                issue.updated_at = now_iso();
-                let path = issue_path(&ctx.agent_root, &issue.id);
-                save_issue(&path, &issue)?;
+pub fn cmd_queue(
+    ctx: &CommandContext,
+    task: Option<&str>,
+    tags: &[String],
+    overdue: bool,
+    sort: Option<&str>,
+    format: QueueFormat,
+) -> Result<()> {
+    let sort = sort.map(QueueSort::from_str).transpose()?;
+    if let Some(task) = task {
+        validate_task_name(task)?;
+        if ctx.store.task_exists(task) {
+            let task_state = ctx.store.load_task(task)?;
+            if json_output() {
+                return print_task_json(ctx, &task_state, false);
+            }
+            println!("{}", msg("task_exists", &[("task", &task)]));
+            println!("  Stage: {}", task_state.stage);
+            if task_state.held {
+                println!("  Status: held (backlog)");
             }
+            return Ok(());
+        }
+
+        let dir = task_dir(&ctx.agent_root, task);
+        if !dir.exists() {
+            bail!(
+                "Task '{}' not found. Create it with 'mung task {}'",
+                task,
+                task
+            );
+        }
+
+        let timestamp = now_iso();
+        create_task_state(
+            ctx.store.as_ref(),
+            ctx.agent.name(),
+            task,
+            ctx.agent.initial_stage(),
+            &timestamp,
+            false,
+            None,
+            None,
+        )?;
+        if json_output() {
+            return print_task_json(ctx, &ctx.store.load_task(task)?, true);
         }
+        println!("Queued '{}' (stage: {})", task, ctx.agent.initial_stage());
+        return Ok(());
+    }
+
+    wake_snoozed_tasks(ctx)?;
+    let all_tasks = ctx.store.list_tasks();
+    let tasks: Vec<TaskState> = all_tasks
+        .iter()
+        .filter(|task| has_tags(task, tags) && (!overdue || is_overdue(task)))
+        .cloned()
+        .collect();
+    if tasks.is_empty() && !json_output() {
+        println!("{}", msg("no_tasks", &[]).dimmed());
+        return Ok(());
     }
 
-    fs::remove_dir_all(&dir)?;
-    println!("Removed '{}'", task);
+    let issue_counts = match ctx.store.list_issues() {
+        Ok(issues) => count_open_issues(&issues),
+        Err(err) => {
+            eprintln!("Warning: failed to load issues: {}", err);
+            Default::default()
+        }
+    };
+    report_escalations(&escalate_issue_priorities(ctx, false)?);
+    let breaches = check_issue_slas(ctx)?;
+    if json_output() {
+        let sla_breaches = sla_breaches_by_task(&breaches);
+        let mut sorted: Vec<&TaskState> = tasks.iter().collect();
+        sort.unwrap_or(QueueSort::Added)
+            .sort(&mut sorted, &issue_counts);
+        let mut entries = Vec::new();
+        for task in sorted {
+            let mut value = serde_json::to_value(task)?;
+            value["open_issues"] =
+                json!(issue_counts.per_task.get(&task.task).copied().unwrap_or(0));
+            value["sla"] = json!(sla_breaches.get(task.task.as_str()).map(|breach| json!({
+                "issue": breach.issue.id,
+                "priority": breach.issue.priority,
+                "age_hours": breach.age_hours,
+                "max_age_hours": breach.max_age_hours,
+            })));
+            entries.push(value);
+        }
+        let next = next_eligible_task(
+            ctx.agent,
+            &ctx.config.queue,
+            &with_tags(all_tasks, tags),
+            &breached_tasks(&breaches),
+        );
+        let mut estimate_hours_by_stage: BTreeMap<&str, u32> = BTreeMap::new();
+        for task in tasks.iter().filter(|task| !task.held) {
+            if let Some(hours) = task.estimate.as_deref().and_then(estimate_hours) {
+                *estimate_hours_by_stage.entry(&task.stage).or_default() += hours;
+            }
+        }
+        return print_json(&json!({
+            "tasks": entries,
+            "estimate_hours": estimate_hours_by_stage,
+            "next": next.map(|task| task.task),
+            "policy": ctx.config.queue.policy.as_str(),
+            "unassigned_issues": issue_counts.unassigned,
+            "sla_breaches": breaches.len(),
+        }));
+    }
+    for line in queue_lines(ctx, &tasks, &issue_counts, &breaches, sort, format) {
+        println!("{}", line);
+    }
     Ok(())
 }
 
-pub fn cmd_reorder(ctx: &CommandContext, task: &str, position: usize) -> Result<()> {
-    validate_task_name(task)?;
-    if position == 0 {
-        bail!("Position must be 1 or greater");
+/// The first listed breach for each task.
+fn sla_breaches_by_task(breaches: &[SlaBreach]) -> HashMap<&str, &SlaBreach> {
+    let mut sla_breaches: HashMap<&str, &SlaBreach> = HashMap::new();
+    for breach in breaches {
+        if let Some(task) = breach.issue.task.as_deref() {
+            sla_breaches.entry(task).or_insert(breach);
+        }
     }
-    let task_path = task_state_path(&ctx.agent_root, task);
-    if !task_path.exists() {
-        bail!("Task '{}' not found", task);
+    sla_breaches
+}
+
+/// `queue --sort`: lists the active tasks as one list in this order instead of by
+/// stage.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueSort {
+    /// Most recently updated first.
+    Updated,
+    /// Oldest first.
+    Added,
+    /// `P0` first, then oldest.
+    Priority,
+    /// Most open issues first, then oldest.
+    Issues,
+}
+
+impl FromStr for QueueSort {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "updated" => Ok(Self::Updated),
+            "added" => Ok(Self::Added),
+            "priority" => Ok(Self::Priority),
+            "issues" => Ok(Self::Issues),
+            other => bail!(
+                "Invalid sort: {} (use updated, added, priority, or issues)",
+                other
+            ),
+        }
     }
-    let task_state = load_task(&task_path)?;
-    if task_state.stage != "build" {
-        bail!("Reorder is only supported for build stage tasks");
+}
+
+impl QueueSort {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Updated => "updated",
+            Self::Added => "added",
+            Self::Priority => "priority",
+            Self::Issues => "issues",
+        }
     }
-    if task_state.held {
-        bail!("Task '{}' is held. Activate it before reordering.", task);
+
+    fn sort(self, tasks: &mut [&TaskState], issue_counts: &IssueCounts) {
+        let issues = |task: &TaskState| issue_counts.per_task.get(&task.task).copied().unwrap_or(0);
+        tasks.sort_by(|a, b| match self {
+            Self::Updated => b.updated_at.cmp(&a.updated_at),
+            Self::Added => a.added_at.cmp(&b.added_at),
+            Self::Priority => task_priority(a)
+                .cmp(&task_priority(b))
+                .then_with(|| a.added_at.cmp(&b.added_at)),
+            Self::Issues => issues(b)
+                .cmp(&issues(a))
+                .then_with(|| a.added_at.cmp(&b.added_at)),
+        });
     }
+}
+
+/// How `mung queue` prints tasks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QueueFormat {
+    /// Tasks under their stage, with flags.
+    #[default]
+    Board,
+    /// `--oneline`: one line per task with status, stage, open issues, and age.
+    Oneline,
+    /// `--verbose`: the board plus each task's description, last session, and
+    /// plan progress.
+    Verbose,
+}
 
-    let mut stage_tasks: Vec<TaskState> = list_tasks(&ctx.agent_root)
+/// The printed queue in `format`, one entry per line.
+fn queue_lines(
+    ctx: &CommandContext,
+    tasks: &[TaskState],
+    issue_counts: &IssueCounts,
+    breaches: &[SlaBreach],
+    sort: Option<QueueSort>,
+    format: QueueFormat,
+) -> Vec<String> {
+    if format != QueueFormat::Oneline {
+        let verbose = format == QueueFormat::Verbose;
+        return queue_board(ctx, tasks, issue_counts, breaches, sort, verbose);
+    }
+    let stages = ctx.agent.stages();
+    let mut sorted: Vec<&TaskState> = tasks.iter().collect();
+    match sort {
+        Some(sort) => sort.sort(&mut sorted, issue_counts),
+        None => sorted.sort_by_key(|task| {
+            (
+                stages.iter().position(|stage| *stage == task.stage),
+                task.held,
+                task_priority(task),
+                task.added_at.clone(),
+            )
+        }),
+    }
+    let name_width = sorted.iter().map(|task| task.task.len()).max().unwrap_or(0);
+    let stage_width = sorted
+        .iter()
+        .map(|task| ctx.agent.stage_label(&task.stage).len())
+        .max()
+        .unwrap_or(0);
+    let now = chrono::Utc::now();
+    sorted
         .into_iter()
-        .filter(|t| !t.held && t.stage == "build")
+        .map(|task| {
+            let issues = issue_counts.per_task.get(&task.task).copied().unwrap_or(0);
+            let age = chrono::DateTime::parse_from_rfc3339(&task.added_at)
+                .map(|added| format_age(now.signed_duration_since(added).num_seconds()))
+                .unwrap_or_default();
+            let mut line = format!(
+                "{} {:<name_width$}  {:<stage_width$}  {:>2} issue(s)  added {}",
+                task.status.styled(),
+                task.task,
+                ctx.agent.stage_label(&task.stage),
+                issues,
+                age
+            );
+            if task.held {
+                line.push_str(&format!("  {}", "held".dimmed()));
+            }
+            line
+        })
+        .collect()
+}
+
+/// `--verbose` detail lines under a task on the board.
+fn task_detail_lines(ctx: &CommandContext, task: &TaskState) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(description) = task.description.as_deref() {
+        lines.push(format!("      {}", description_summary(description)));
+    }
+    if let Some(session) = task
+        .last_session
+        .as_deref()
+        .and_then(|id| ctx.store.load_session(id).ok())
+    {
+        let age = session_age_seconds(&session, chrono::Utc::now())
+            .map(format_age)
+            .unwrap_or_default();
+        lines.push(format!(
+            "      Last session: {} {} ({}, {})",
+            session.session_id, session.stage, session.status, age
+        ));
+    }
+    if let Some((done, total)) = plan_progress(ctx, &task.task) {
+        lines.push(format!("      Plan: {}/{} steps done", done, total));
+    }
+    lines
+        .into_iter()
+        .map(|line| line.dimmed().to_string())
+        .collect()
+}
+
+/// Checked and total steps in the task's plan, if it has any.
+fn plan_progress(ctx: &CommandContext, task: &str) -> Option<(usize, usize)> {
+    let plan = read_text(&task_dir(&ctx.agent_root, task).join(plan_file_name(ctx.agent))).ok()?;
+    let steps: Vec<ChecklistStep> = plan
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| parse_checklist_step(line, index + 1))
         .collect();
-    if stage_tasks.is_empty() {
-        bail!("No build tasks to reorder");
+    if steps.is_empty() {
+        return None;
     }
+    Some((steps.iter().filter(|step| step.done).count(), steps.len()))
+}
 
-    stage_tasks.sort_by(|a, b| {
-        let ar = a.queue_rank.unwrap_or(i64::MAX);
-        let br = b.queue_rank.unwrap_or(i64::MAX);
-        ar.cmp(&br).then_with(|| a.added_at.cmp(&b.added_at))
-    });
+/// The `mung queue` board, one entry per printed line.
+fn queue_board(
+    ctx: &CommandContext,
+    tasks: &[TaskState],
+    issue_counts: &IssueCounts,
+    breaches: &[SlaBreach],
+    sort: Option<QueueSort>,
+    verbose: bool,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    let sla_breaches = sla_breaches_by_task(breaches);
+    if !breaches.is_empty() {
+        lines.push(
+            format!(
+                "SLA breaches: {} open issue(s) past their limit",
+                breaches.len()
+            )
+            .red()
+            .to_string(),
+        );
+    }
+    if issue_counts.unassigned > 0 {
+        lines.push(format!(
+            "Unassigned issues: {} (run 'mung issues --unassigned')",
+            issue_counts.unassigned
+        ));
+    }
 
-    let current_index = stage_tasks
-        .iter()
-        .position(|t| t.task == task)
-        .ok_or_else(|| anyhow::anyhow!("Task '{}' is not in the build queue", task))?;
+    if ctx.config.queue.policy != QueuePolicy::default() {
+        lines.push(format!(
+            "Queue policy: {}",
+            ctx.config.queue.policy.as_str()
+        ));
+    }
 
-    let mut ordered = Vec::with_capacity(stage_tasks.len());
-    for (idx, item) in stage_tasks.into_iter().enumerate() {
-        if idx != current_index {
-            ordered.push(item);
+    let mut backlog: Vec<&TaskState> = tasks.iter().filter(|t| t.held).collect();
+    let mut blocked: Vec<&TaskState> = tasks
+        .iter()
+        .filter(|t| !t.held && t.status == TaskStatus::Blocked)
+        .collect();
+    // A sorted board lists the active tasks once instead of under each stage.
+    let stages = if sort.is_some() {
+        &[]
+    } else {
+        ctx.agent.stages()
+    };
+    if let Some(sort) = sort {
+        let mut active: Vec<&TaskState> = tasks
+            .iter()
+            .filter(|t| !t.held && t.status != TaskStatus::Blocked && t.stage != "completed")
+            .collect();
+        sort.sort(&mut active, issue_counts);
+        lines.push(format!("Tasks (by {}):", sort.as_str()).bold().to_string());
+        for task in active {
+            let stage = format!("(stage: {})", ctx.agent.stage_label(&task.stage));
+            lines.push(format!(
+                "{} {}",
+                board_task_line(task, tasks, issue_counts, &sla_breaches),
+                stage.dimmed()
+            ));
+            if verbose {
+                lines.extend(task_detail_lines(ctx, task));
+            }
         }
+        lines.push(String::new());
+    } else {
+        lines.push("Tasks:".bold().to_string());
     }
-    let insert_index = std::cmp::min(position - 1, ordered.len());
-    ordered.insert(insert_index, task_state);
-
-    for (idx, item) in ordered.iter().enumerate() {
-        let new_rank = (idx + 1) as i64;
-        if item.queue_rank == Some(new_rank) {
+    for stage in stages {
+        if *stage == "completed" {
             continue;
         }
-        let path = task_state_path(&ctx.agent_root, &item.task);
-        update_task(&path, |task_state| {
-            task_state.queue_rank = Some(new_rank);
-            task_state.updated_at = now_iso();
-            Ok(())
-        })?;
+        let mut stage_tasks: Vec<&TaskState> = tasks
+            .iter()
+            .filter(|t| !t.held && t.status != TaskStatus::Blocked && t.stage == *stage)
+            .collect();
+        if stage_tasks.is_empty() {
+            continue;
+        }
+        if *stage == "build" {
+            stage_tasks.sort_by(|a, b| {
+                let ar = a.queue_rank.unwrap_or(i64::MAX);
+                let br = b.queue_rank.unwrap_or(i64::MAX);
+                ar.cmp(&br).then_with(|| a.added_at.cmp(&b.added_at))
+            });
+        } else {
+            stage_tasks.sort_by(|a, b| a.added_at.cmp(&b.added_at));
+        }
+        stage_tasks.sort_by_key(|task| task_priority(task));
+        let estimated: Vec<u32> = stage_tasks
+            .iter()
+            .filter_map(|task| task.estimate.as_deref().and_then(estimate_hours))
+            .collect();
+        if estimated.is_empty() {
+            lines.push(format!("{}:", ctx.agent.stage_label(stage)));
+        } else {
+            let unestimated = stage_tasks.len() - estimated.len();
+            lines.push(format!(
+                "{}: ~{}h estimated{}",
+                ctx.agent.stage_label(stage),
+                estimated.iter().sum::<u32>(),
+                if unestimated > 0 {
+                    format!(" ({} unestimated)", unestimated)
+                } else {
+                    String::new()
+                }
+            ));
+        }
+        for task in stage_tasks {
+            lines.push(board_task_line(task, tasks, issue_counts, &sla_breaches));
+            if verbose {
+                lines.extend(task_detail_lines(ctx, task));
+            }
+        }
+        lines.push(String::new());
     }
 
-    println!(
-        "Reordered '{}' to position {} in build queue.",
-        task,
-        insert_index + 1
-    );
-    let mut build_tasks: Vec<TaskState> = list_tasks(&ctx.agent_root)
-        .into_iter()
-        .filter(|t| !t.held && t.stage == "build")
+    let mut completed: Vec<&TaskState> = tasks
+        .iter()
+        .filter(|t| !t.held && t.stage == "completed")
         .collect();
-    build_tasks.sort_by(|a, b| {
-        let ar = a.queue_rank.unwrap_or(i64::MAX);
-        let br = b.queue_rank.unwrap_or(i64::MAX);
-        ar.cmp(&br).then_with(|| a.added_at.cmp(&b.added_at))
-    });
-    let issue_counts = match list_issues(&ctx.agent_root) {
-        Ok(issues) => count_open_issues(&issues),
-        Err(err) => {
-            eprintln!("Warning: failed to load issues: {}", err);
-            Default::default()
+    if !completed.is_empty() {
+        completed.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        let total_completed = completed.len();
+        lines.push(format!("{}:", ctx.agent.stage_label("completed").dimmed()));
+        for task in completed.into_iter().take(10) {
+            let issue_count = issue_counts.per_task.get(&task.task).copied().unwrap_or(0);
+            if issue_count > 0 {
+                lines.push(format!(
+                    "  {} {} [issues: {}]",
+                    task.status.styled(),
+                    task.task.dimmed(),
+                    issue_count
+                ));
+            } else {
+                lines.push(format!("  {} {}", task.status.styled(), task.task.dimmed()));
+            }
         }
-    };
-    println!("{}:", ctx.agent.stage_label("build"));
-    for task in build_tasks {
-        let issue_count = issue_counts.per_task.get(&task.task).copied().unwrap_or(0);
-        if issue_count > 0 {
-            println!(
-                "  {} {} [issues: {}]",
+        if total_completed > 10 {
+            lines.push(format!("  ... and {} more", total_completed - 10));
+        }
+    }
+
+    if !blocked.is_empty() {
+        blocked.sort_by(|a, b| a.added_at.cmp(&b.added_at));
+        lines.push(String::new());
+        lines.push("Blocked:".red().to_string());
+        for task in blocked {
+            lines.push(format!(
+                "  {} {} (stage: {}) {}",
                 task.status.styled(),
                 task.task,
-                issue_count
-            );
-        } else {
-            println!("  {} {}", task.status.styled(), task.task);
+                ctx.agent.stage_label(&task.stage),
+                task.blocked_reason.as_deref().unwrap_or("no reason given")
+            ));
         }
     }
-    Ok(())
+
+    if !backlog.is_empty() {
+        backlog.sort_by(|a, b| a.added_at.cmp(&b.added_at));
+        lines.push(String::new());
+        lines.push("Backlog:".to_string());
+        for task in backlog {
+            let issue_count = issue_counts.per_task.get(&task.task).copied().unwrap_or(0);
+            let issues = if issue_count > 0 {
+                format!(" [issues: {}]", issue_count)
+            } else {
+                String::new()
+            };
+            let snooze = task
+                .held_until
+                .as_deref()
+                .map(|until| {
+                    format!(" [held until {}]", snooze_label(until))
+                        .dimmed()
+                        .to_string()
+                })
+                .unwrap_or_default();
+            let reason = task
+                .held_reason
+                .as_deref()
+                .map(|reason| format!(" {}", reason))
+                .unwrap_or_default();
+            lines.push(format!(
+                "  {} {}{} (stage: {}){}{}",
+                task.status.styled(),
+                task.task,
+                issues,
+                ctx.agent.stage_label(&task.stage),
+                reason,
+                snooze
+            ));
+        }
+    }
+
+    lines
 }
 
-pub fn cmd_start(ctx: &CommandContext) -> Result<()> {
-    let mut task_name: Option<String> = None;
-    let mut stage = ctx.agent.initial_stage().to_string();
-    let handoff_stage = ctx.agent.handoff_stage();
+/// One task's line on the queue board: status, name, and its flags.
+fn board_task_line(
+    task: &TaskState,
+    tasks: &[TaskState],
+    issue_counts: &IssueCounts,
+    sla_breaches: &HashMap<&str, &SlaBreach>,
+) -> String {
+    let issue_count = issue_counts.per_task.get(&task.task).copied().unwrap_or(0);
+    let sla_flag = sla_breaches
+        .get(task.task.as_str())
+        .map(|breach| {
+            let flag = format!(
+                "[SLA: {} open {}h]",
+                breach.issue.priority, breach.age_hours
+            );
+            format!(" {}", flag.red())
+        })
+        .unwrap_or_default();
+    let approval_flag = task
+        .awaiting_approval
+        .as_ref()
+        .map(|pending| {
+            let flag = format!("[awaiting approval: {}]", pending.stage);
+            format!(" {}", flag.yellow())
+        })
+        .unwrap_or_default();
+    let unmet = unmet_dependencies(task, tasks);
+    let dependency_flag = if unmet.is_empty() {
+        String::new()
+    } else {
+        let flag = format!("[waiting on: {}]", unmet.join(", "));
+        format!(" {}", flag.dimmed())
+    };
+    let overdue = is_overdue(task);
+    let due_flag = match task.due_at.as_deref() {
+        Some(due) if overdue => format!(" {}", format!("[overdue: due {}]", due).red()),
+        Some(due) => format!(" {}", format!("[due {}]", due).dimmed()),
+        None => String::new(),
+    };
+    let priority_flag = task
+        .priority
+        .as_deref()
+        .map(|priority| format!(" [{}]", priority))
+        .unwrap_or_default();
+    let estimate_flag = task
+        .estimate
+        .as_deref()
+        .map(|estimate| format!(" {}", format!("[est {}]", estimate).dimmed()))
+        .unwrap_or_default();
+    let name = if overdue {
+        task.task.red().to_string()
+    } else {
+        task.task.clone()
+    };
+    let issues_flag = if issue_count > 0 {
+        format!(" [issues: {}]", issue_count)
+    } else {
+        String::new()
+    };
+    format!(
+        "  {} {}{}{}{}{}{}{}{}",
+        task.status.styled(),
+        name,
+        priority_flag,
+        issues_flag,
+        estimate_flag,
+        sla_flag,
+        due_flag,
+        approval_flag,
+        dependency_flag
+    )
+}
 
-    loop {
-        if let Some(task) = task_name.as_ref() {
-            let task_path = task_state_path(&ctx.agent_root, task);
-            if task_path.exists() {
-                update_task(&task_path, |task_state| {
-                    // Preserve Issues status so issue injection works in run_stage
-                    if task_state.status != TaskStatus::Issues {
-                        task_state.status = TaskStatus::Running;
-                    }
-                    task_state.updated_at = now_iso();
-                    Ok(())
-                })?;
+/// `mung queue --watch`: redraws the board in place whenever a file under tasks/ or
+/// issues/ changes, and every `interval` seconds so SLA ages stay current. Lines
+/// that changed since the previous draw are marked. Runs until Ctrl-C.
+pub fn cmd_queue_watch(
+    ctx: &CommandContext,
+    interval: u64,
+    tags: &[String],
+    overdue: bool,
+    sort: Option<&str>,
+    format: QueueFormat,
+) -> Result<()> {
+    if json_output() {
+        bail!("--watch cannot be combined with --json");
+    }
+    let sort = sort.map(QueueSort::from_str).transpose()?;
+    let interval = Duration::from_secs(interval.max(1));
+    let mut previous: Option<Vec<String>> = None;
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x1b[2J")?;
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        wake_snoozed_tasks(ctx)?;
+        let fingerprint = queue_fingerprint(ctx);
+        let mut tasks = ctx.store.list_tasks();
+        tasks.retain(|task| has_tags(task, tags) && (!overdue || is_overdue(task)));
+        let board = if tasks.is_empty() {
+            vec![msg("no_tasks", &[]).dimmed().to_string()]
+        } else {
+            let issue_counts = ctx
+                .store
+                .list_issues()
+                .map(|issues| count_open_issues(&issues))
+                .unwrap_or_default();
+            report_escalations(&escalate_issue_priorities(ctx, false)?);
+            let breaches = check_issue_slas(ctx)?;
+            queue_lines(ctx, &tasks, &issue_counts, &breaches, sort, format)
+        };
+
+        // Home the cursor and overwrite line by line instead of clearing, so the
+        // board doesn't flicker.
+        let header = format!(
+            "Every {}s, on change: {}  (Ctrl-C to quit)",
+            interval.as_secs(),
+            chrono::Local::now().format("%H:%M:%S")
+        );
+        let mut frame = format!("\x1b[H{}\x1b[K\n\x1b[K\n", header.dimmed());
+        for line in &board {
+            let changed = previous
+                .as_ref()
+                .is_some_and(|previous| !previous.contains(line));
+            let marker = if changed {
+                "*".yellow().bold().to_string()
+            } else {
+                " ".to_string()
+            };
+            frame.push_str(&format!("{} {}\x1b[K\n", marker, line));
+        }
+        frame.push_str("\x1b[J");
+        stdout.write_all(frame.as_bytes())?;
+        stdout.flush()?;
+        previous = Some(board);
+
+        let deadline = std::time::Instant::now() + interval;
+        while std::time::Instant::now() < deadline && !INTERRUPTED.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(250));
+            if queue_fingerprint(ctx) != fingerprint {
+                break;
             }
         }
+    }
+    println!();
+    Ok(())
+}
 
-        let result = run_stage(
-            ctx,
-            task_name.as_deref(),
-            &stage,
-            None,
-            ReviewFinishMode::Queue,
-        )?;
-        match result {
-            StageResult::Finished(session) => {
-                if task_name.is_none() {
-                    if let Some(task) = session.task.clone() {
-                        task_name = Some(task);
-                    }
-                }
-                let next_stage = session
-                    .next_stage
-                    .clone()
-                    .or_else(|| ctx.agent.next_stage(&stage).map(|s| s.to_string()));
-                if let Some(next_stage) = next_stage {
-                    if let Some(handoff) = handoff_stage {
-                        if next_stage == handoff {
-                            if let Some(task) = task_name.as_ref() {
-                                println!("Task '{}' is ready.", task);
-                                println!("Run 'mung run {}' or 'mung run-queue' to start.", task);
-                            }
-                            return Ok(());
-                        }
-                    }
-                    if next_stage == "completed" {
-                        println!("Task completed.");
-                        return Ok(());
-                    }
-                    stage = next_stage;
-                    continue;
-                }
-
-                bail!("No next stage provided.");
-            }
-            StageResult::Interrupted => {
-                if let Some(task) = task_name.as_ref() {
-                    let task_path = task_state_path(&ctx.agent_root, task);
-                    if task_path.exists() {
-                        update_task(&task_path, |task_state| {
-                            task_state.status = TaskStatus::Incomplete;
-                            task_state.updated_at = now_iso();
-                            Ok(())
-                        })?;
-                    }
-                }
-                return Ok(());
+/// Changes whenever a file under tasks/ or issues/, or the state database, is
+/// added, removed, or rewritten.
+fn queue_fingerprint(ctx: &CommandContext) -> u64 {
+    fn walk(dir: &Path, hasher: &mut DefaultHasher) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        let mut entries: Vec<fs::DirEntry> = entries.flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            entry.file_name().hash(hasher);
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                walk(&entry.path(), hasher);
+            } else {
+                meta.len().hash(hasher);
+                meta.modified().ok().hash(hasher);
             }
-            StageResult::NoFinish => {
-                if let Some(task) = task_name.as_ref() {
-                    let task_path = task_state_path(&ctx.agent_root, task);
-                    if task_path.exists() {
-                        update_task(&task_path, |task_state| {
-                            task_state.status = TaskStatus::Failed;
-                            task_state.updated_at = now_iso();
-                            Ok(())
-                        })?;
-                    }
-                    bail!("Task '{}' exited without completing stage {}", task, stage);
-                } else {
-                    bail!("Interview ended without creating a task");
-                }
+        }
+    }
+    let mut hasher = DefaultHasher::new();
+    walk(&ctx.agent_root.join("tasks"), &mut hasher);
+    walk(&ctx.agent_root.join("issues"), &mut hasher);
+    if let Some(database) = ctx.config.state.database(&ctx.agent_root) {
+        // Writes land in the write-ahead log before the database file.
+        for path in [database.clone(), database.with_extension("db-wal")] {
+            if let Ok(meta) = fs::metadata(&path) {
+                meta.len().hash(&mut hasher);
+                meta.modified().ok().hash(&mut hasher);
             }
         }
     }
+    hasher.finish()
+}
+
+fn plan_file_name(agent: AgentKind) -> &'static str {
+    if agent == AgentKind::Code {
+        "plan.md"
+    } else {
+        "editorial_plan.md"
+    }
+}
+
+/// Opens a task directory, its spec or plan, or an issue file in `$VISUAL` /
+/// `$EDITOR`, falling back to the OS file opener.
+pub fn cmd_open(
+    ctx: &CommandContext,
+    task: Option<&str>,
+    spec: bool,
+    plan: bool,
+    issue: Option<&str>,
+    print: bool,
+) -> Result<()> {
+    let path = if let Some(id) = issue {
+        let Some(path) = ctx.store.issue_file(id) else {
+            bail!(
+                "Issues are kept in the state database; use 'mung issue show {}'",
+                id
+            );
+        };
+        path
+    } else {
+        let task = task.ok_or_else(|| anyhow::anyhow!("Pass a task or --issue <id>"))?;
+        validate_task_name(task)?;
+        if !ctx.store.task_exists(task) {
+            bail!("{}", msg("task_not_found", &[("task", &task)]));
+        }
+        let dir = task_dir(&ctx.agent_root, task);
+        if spec {
+            dir.join("spec")
+        } else if plan {
+            dir.join(plan_file_name(ctx.agent))
+        } else {
+            dir
+        }
+    };
+    if !path.exists() {
+        bail!("{} not found", path.display());
+    }
+    if print {
+        println!("{}", path.display());
+        return Ok(());
+    }
+
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.trim().is_empty());
+    let status = match editor {
+        // Through the shell so editors configured with arguments (`code -w`) work.
+        Some(editor) => Command::new("sh")
+            .arg("-c")
+            .arg(format!("{editor} \"$1\""))
+            .arg("sh")
+            .arg(&path)
+            .status()
+            .with_context(|| format!("Failed to start editor '{}'", editor))?,
+        None => Command::new(file_opener())
+            .arg(&path)
+            .status()
+            .with_context(|| format!("Failed to run {}", file_opener()))?,
+    };
+    if !status.success() {
+        bail!("Opening {} exited with {}", path.display(), status);
+    }
+    Ok(())
+}
+
+fn file_opener() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PlanProblem {
+    line: usize,
+    problem: String,
 }
 
-pub fn cmd_run(ctx: &CommandContext, task: &str) -> Result<()> {
+/// `mung plan lint <task>`: every checklist line must be a canonical
+/// `- [ ] [P1][M][T17] <description>` step with a unique id, and no step may be
+/// checked off while an issue linked to it is still open. Fails when anything is off.
+pub fn cmd_plan_lint(ctx: &CommandContext, task: &str) -> Result<()> {
     validate_task_name(task)?;
-    let task_path = task_state_path(&ctx.agent_root, task);
-    if !task_path.exists() {
+    let file_name = plan_file_name(ctx.agent);
+    let plan_path = task_dir(&ctx.agent_root, task).join(file_name);
+    if !plan_path.exists() {
         bail!(
-            "Task '{}' not found. Run 'mung queue {}' to add it first.",
+            "{} not found for task '{}': {}",
+            file_name,
             task,
-            task
+            plan_path.display()
         );
     }
-    reconcile_running_tasks(&ctx.agent_root)?;
-    let claim = claim_task(&ctx.agent_root, task, 3600, &ctx.host)?;
-    let Some(_guard) = claim else {
-        bail!("Task '{}' is already claimed.", task);
-    };
+    let content = read_text(&plan_path)?;
+    let (steps, problems) = lint_plan(ctx, task, &content);
 
-    loop {
-        let task_state = load_task(&task_path)?;
-        if task_state.stage == "completed" {
-            println!("Task '{}' completed.", task);
-            return Ok(());
+    if json_output() {
+        print_json(&json!({ "task": task, "path": plan_path, "problems": problems }))?;
+    } else if problems.is_empty() {
+        println!("{}: {} steps, no problems", file_name, steps.len());
+    } else {
+        for problem in &problems {
+            println!("{}:{}: {}", file_name, problem.line, problem.problem);
         }
+    }
+    if !problems.is_empty() {
+        bail!("{} has {} problem(s)", file_name, problems.len());
+    }
+    Ok(())
+}
 
-        if task_state.held {
-            update_task(&task_path, |task_state| {
-                task_state.held = false;
-                task_state.updated_at = now_iso();
-                Ok(())
-            })?;
-            println!("Activating held task '{}'", task);
+/// The canonical steps of a plan and what `plan lint` finds wrong with it, by line.
+fn lint_plan(
+    ctx: &CommandContext,
+    task: &str,
+    content: &str,
+) -> (Vec<CanonicalPlanStep>, Vec<PlanProblem>) {
+    let mut problems = Vec::new();
+    let mut steps = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        if let Some(step) = parse_canonical_plan_step(line, line_number) {
+            steps.push(step);
+        } else if let Some(problem) = plan_line_problem(line) {
+            problems.push(PlanProblem {
+                line: line_number,
+                problem,
+            });
         }
-
-        update_task(&task_path, |task_state| {
-            // Preserve Issues status so issue injection works in run_stage
-            if task_state.status != TaskStatus::Issues {
-                task_state.status = TaskStatus::Running;
-            }
-            task_state.updated_at = now_iso();
-            Ok(())
-        })?;
-
-        let result = run_stage(
-            ctx,
-            Some(task),
-            &task_state.stage,
-            None,
-            ReviewFinishMode::Queue,
-        )?;
-        match result {
-            StageResult::Finished(_) => continue,
-            StageResult::Interrupted => {
-                update_task(&task_path, |task_state| {
-                    task_state.status = TaskStatus::Incomplete;
-                    task_state.updated_at = now_iso();
-                    Ok(())
-                })?;
-                return Ok(());
-            }
-            StageResult::NoFinish => {
-                update_task(&task_path, |task_state| {
-                    task_state.status = TaskStatus::Incomplete;
-                    task_state.updated_at = now_iso();
-                    Ok(())
-                })?;
-                println!("Session ended. Run 'mung run {}' to continue.", task);
-                return Ok(());
+    }
+    let mut seen: HashMap<u32, usize> = HashMap::new();
+    for step in &steps {
+        if let Some(first) = seen.insert(step.id, step.line) {
+            problems.push(PlanProblem {
+                line: step.line,
+                problem: format!("duplicate id T{} (also on line {})", step.id, first),
+            });
+            seen.insert(step.id, first);
+        }
+    }
+    if ctx.agent == AgentKind::Code {
+        let linked = linked_step_issues(ctx.store.as_ref(), task);
+        for step in steps.iter().filter(|step| step.done) {
+            let open = linked
+                .get(&step.id)
+                .into_iter()
+                .flatten()
+                .find(|issue| issue.status == IssueStatus::Open);
+            if let Some(issue) = open {
+                problems.push(PlanProblem {
+                    line: step.line,
+                    problem: format!(
+                        "T{} is checked off but issue {} is still open",
+                        step.id, issue.id
+                    ),
+                });
             }
         }
     }
+    problems.sort_by_key(|problem| problem.line);
+    (steps, problems)
 }
 
-pub fn cmd_run_queue(ctx: &CommandContext, loop_limit: usize) -> Result<()> {
-    let tasks = list_tasks(&ctx.agent_root);
-    if tasks.is_empty() {
-        println!("No tasks");
-        return Ok(());
+/// Why a checklist line isn't a canonical step, or `None` for other lines.
+fn plan_line_problem(line: &str) -> Option<String> {
+    let rest = line.trim_start().strip_prefix("- [")?;
+    let status = rest.chars().next()?;
+    // `- [text](url)` is a link, not a checkbox.
+    if !rest[status.len_utf8()..].starts_with(']') {
+        return None;
     }
-    reconcile_running_tasks(&ctx.agent_root)?;
-
-    let mut current_task: Option<String> = None;
-    let mut current_claim: Option<crate::state::ClaimGuard> = None;
-    let mut review_loops = 0usize;
-    let loop_limit = if loop_limit == 0 { 100 } else { loop_limit };
+    if status != ' ' && status != 'x' {
+        return Some(format!("checkbox must be [ ] or [x], found [{}]", status));
+    }
+    let Some((_, mut rest)) = parse_checklist_prefix(line) else {
+        return Some("checkbox must be followed by a space".to_string());
+    };
+    let mut tags = Vec::new();
+    while let Some((tag, after)) = parse_bracket_tag(rest) {
+        tags.push(tag);
+        rest = after;
+    }
+    let is_priority = |tag: &&str| matches!(*tag, "P0" | "P1" | "P2" | "P3");
+    let is_estimate = |tag: &&str| matches!(*tag, "S" | "M" | "L");
+    let is_id = |tag: &&str| {
+        tag.strip_prefix('T')
+            .is_some_and(|id| !id.is_empty() && id.chars().all(|c| c.is_ascii_digit()))
+    };
+    let problem = if !tags.iter().any(is_priority) {
+        "missing priority [P0]-[P3]".to_string()
+    } else if !tags.iter().any(is_estimate) {
+        "missing estimate [S], [M], or [L]".to_string()
+    } else if let Some(id) = tags.iter().find(|tag| is_id(tag)) {
+        if tags.len() != 3 || !is_priority(&tags[0]) || !is_estimate(&tags[1]) {
+            "tags must be exactly [P?][S/M/L][T#], in that order".to_string()
+        } else if id.len() > 2 && id.starts_with("T0") {
+            format!("step id {} has a leading zero", id)
+        } else if rest.trim().is_empty() {
+            "missing description".to_string()
+        } else {
+            "description must follow the tags after one space".to_string()
+        }
+    } else {
+        "missing step id [T<n>]".to_string()
+    };
+    Some(problem)
+}
 
-    loop {
-        if let Some(task_name) = current_task.clone() {
-            let task_path = task_state_path(&ctx.agent_root, &task_name);
-            if !task_path.exists() {
-                current_task = None;
-                current_claim = None;
-                continue;
-            }
-            let task_state = load_task(&task_path)?;
-            if task_state.held {
-                current_task = None;
-                current_claim = None;
-                continue;
-            }
-            if task_state.stage == "completed" {
-                current_task = None;
-                current_claim = None;
-                continue;
+/// `mung plan check`/`uncheck`: flips the checkbox of canonical steps `steps` in the
+/// task's plan, leaving the rest of each line and the file as written.
+pub fn cmd_plan_check(
+    ctx: &CommandContext,
+    task: &str,
+    steps: &[String],
+    done: bool,
+) -> Result<()> {
+    validate_task_name(task)?;
+    let file_name = plan_file_name(ctx.agent);
+    let plan_path = task_dir(&ctx.agent_root, task).join(file_name);
+    if !plan_path.exists() {
+        bail!(
+            "{} not found for task '{}': {}",
+            file_name,
+            task,
+            plan_path.display()
+        );
+    }
+    let ids = steps
+        .iter()
+        .map(|step| parse_step_id(step))
+        .collect::<Result<Vec<_>>>()?;
+    let content = read_text(&plan_path)?;
+    let mut lines: Vec<String> = content.split_inclusive('\n').map(str::to_string).collect();
+    let mut changed = false;
+    for id in ids {
+        let matches: Vec<usize> = lines
+            .iter()
+            .enumerate()
+            .filter(|(index, line)| {
+                parse_canonical_plan_step(line.trim_end(), index + 1)
+                    .is_some_and(|step| step.id == id)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        let index = match matches.as_slice() {
+            [] => bail!("No step T{} in {}", id, file_name),
+            [index] => *index,
+            _ => bail!(
+                "T{} appears on lines {}; give each step its own id first",
+                id,
+                matches
+                    .iter()
+                    .map(|index| (index + 1).to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let line = &mut lines[index];
+        let indent = line.len() - line.trim_start().len();
+        // `- [` then the box character, which parse_checklist_prefix limited to ' ' or 'x'.
+        let mark = indent + 3;
+        let was_done = &line[mark..mark + 1] == "x";
+        if was_done == done {
+            println!(
+                "T{} is already {}",
+                id,
+                if done { "checked" } else { "unchecked" }
+            );
+            continue;
+        }
+        line.replace_range(mark..mark + 1, if done { "x" } else { " " });
+        println!("{} T{}", if done { "Checked" } else { "Unchecked" }, id);
+        changed = true;
+    }
+    if changed {
+        write_text(&plan_path, &lines.concat())?;
+    }
+    Ok(())
+}
+
+/// `mung plan to-issues <task>`: files an issue for each open `[P?][S/M/L][T#]`
+/// step, linked to the step through its `steps` frontmatter and with the plan step
+/// as its `origin`. Refuses a plan `plan lint` rejects. Steps filed before (even if
+/// since resolved or archived) or with an open linked issue are skipped, so running
+/// it again files only new steps.
+pub fn cmd_plan_to_issues(ctx: &CommandContext, task: &str, dry_run: bool) -> Result<()> {
+    ensure_code_agent(ctx)?;
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    let plan_path = task_dir(&ctx.agent_root, task).join(plan_file_name(ctx.agent));
+    if !plan_path.exists() {
+        bail!(
+            "plan.md not found for task '{}': {}",
+            task,
+            plan_path.display()
+        );
+    }
+    let content = read_text(&plan_path)?;
+    let file_name = plan_file_name(ctx.agent);
+    let (steps, problems) = lint_plan(ctx, task, &content);
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("{}:{}: {}", file_name, problem.line, problem.problem);
+        }
+        bail!(
+            "{} has {} problem(s); fix them (see `mung plan lint {}`) before filing issues",
+            file_name,
+            problems.len(),
+            task
+        );
+    }
+    let linked = linked_step_issues(ctx.store.as_ref(), task);
+    let mut filed = 0;
+    for step in steps.iter().filter(|step| !step.done) {
+        let origin = plan_step_origin(ctx.agent, task, step.id);
+        let tracked = linked.get(&step.id).and_then(|issues| {
+            issues.iter().find(|issue| {
+                issue.status == IssueStatus::Open || issue.origin.as_deref() == Some(&origin)
+            })
+        });
+        if let Some(issue) = tracked {
+            println!("T{}: already tracked by {}", step.id, issue.id);
+            continue;
+        }
+        if dry_run {
+            println!(
+                "T{}: would file [{}] {}",
+                step.id, step.priority, step.title
+            );
+            continue;
+        }
+        let mut issue = new_issue(
+            step.title.clone(),
+            IssueStatus::Open,
+            IssuePriority::from_str(&step.priority)?,
+            Some(task.to_string()),
+            IssueType::Build,
+            IssueSource::Plan,
+            None,
+            Some(format!(
+                "From plan step T{} [{}] ({} line {}).",
+                step.id, step.complexity, file_name, step.line
+            )),
+        );
+        issue.steps = vec![step.id];
+        issue.origin = Some(origin);
+        ctx.store.save_issue(&issue)?;
+        println!("T{}: filed {} {}", step.id, issue.id, step.title);
+        filed += 1;
+    }
+    if filed > 0 {
+        let default_stage = issue_default_stage(ctx.agent, &IssueType::Build);
+        update_task_for_issue(ctx.store.as_ref(), task, None, default_stage.as_deref())?;
+    }
+    if filed == 0 && !dry_run {
+        println!("No new issues to file for '{}'", task);
+    }
+    Ok(())
+}
+
+/// `origin` of an issue filed from step `id` of a task's plan, e.g.
+/// `tasks/add-login/plan.md#T17`.
+fn plan_step_origin(agent: AgentKind, task: &str, id: u32) -> String {
+    format!("tasks/{}/{}#T{}", task, plan_file_name(agent), id)
+}
+
+pub fn cmd_plan(ctx: &CommandContext, task: &str) -> Result<()> {
+    validate_task_name(task)?;
+    let file_name = plan_file_name(ctx.agent);
+    let plan_path = task_dir(&ctx.agent_root, task).join(file_name);
+    if !plan_path.exists() {
+        bail!(
+            "{} not found for task '{}': {}",
+            file_name,
+            task,
+            plan_path.display()
+        );
+    }
+
+    let content = read_text(&plan_path)?;
+    let mut canonical_steps = Vec::new();
+    let mut checklist_steps = Vec::new();
+    let mut id_lines: HashMap<u32, Vec<usize>> = HashMap::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        if let Some(step) = parse_canonical_plan_step(line, line_number) {
+            id_lines.entry(step.id).or_default().push(line_number);
+            canonical_steps.push(step);
+            continue;
+        }
+        if let Some(step) = parse_checklist_step(line, line_number) {
+            checklist_steps.push(step);
+        }
+    }
+
+    let step_issues = if ctx.agent == AgentKind::Code {
+        linked_step_issues(ctx.store.as_ref(), task)
+    } else {
+        HashMap::new()
+    };
+    let mut duplicates: Vec<(u32, Vec<usize>)> = id_lines
+        .into_iter()
+        .filter_map(|(id, mut lines)| {
+            if lines.len() <= 1 {
+                return None;
             }
-            if !ctx
-                .agent
-                .queue_stages()
-                .contains(&task_state.stage.as_str())
-            {
-                println!(
-                    "Task '{}' moved to stage '{}' (not handled by run-queue).",
-                    task_state.task, task_state.stage
-                );
-                return Ok(());
+            lines.sort_unstable();
+            Some((id, lines))
+        })
+        .collect();
+    duplicates.sort_by_key(|(id, _)| *id);
+
+    if json_output() {
+        let done = canonical_steps.iter().filter(|step| step.done).count()
+            + checklist_steps.iter().filter(|step| step.done).count();
+        let total = canonical_steps.len() + checklist_steps.len();
+        let mut steps = Vec::new();
+        for step in &canonical_steps {
+            let mut value = serde_json::to_value(step)?;
+            value["issues"] = json!(step_issues.get(&step.id).cloned().unwrap_or_default());
+            steps.push(value);
+        }
+        let duplicates: Vec<serde_json::Value> = duplicates
+            .iter()
+            .map(|(id, lines)| json!({ "id": id, "lines": lines }))
+            .collect();
+        return print_json(&json!({
+            "task": task,
+            "path": plan_path,
+            "steps": steps,
+            "checklist": checklist_steps,
+            "summary": { "total": total, "open": total - done, "done": done },
+            "duplicates": duplicates,
+        }));
+    }
+
+    if canonical_steps.is_empty() && checklist_steps.is_empty() {
+        println!(
+            "{}",
+            format!("No checklist steps found in {}", plan_path.display()).dimmed()
+        );
+        return Ok(());
+    }
+
+    println!("Plan '{}': {}", task, plan_path.display());
+    let mut open = 0usize;
+    let mut done = 0usize;
+
+    if !canonical_steps.is_empty() {
+        println!("Canonical steps:");
+        for step in &canonical_steps {
+            let marker = if step.done { "x" } else { " " };
+            if step.done {
+                done += 1;
+            } else {
+                open += 1;
             }
-            if current_claim.is_none() {
-                let claim = claim_task(&ctx.agent_root, &task_state.task, 3600, &ctx.host)?;
-                let Some(guard) = claim else {
-                    println!("Task '{}' is already claimed.", task_state.task);
-                    return Ok(());
-                };
-                current_claim = Some(guard);
+            println!(
+                "  L{} - [{}] [{}][{}][T{}] {}",
+                step.line, marker, step.priority, step.complexity, step.id, step.title
+            );
+            if let Some(issues) = step_issues.get(&step.id) {
+                for issue in issues {
+                    println!(
+                        "        issue {} ({}): {}",
+                        issue.id, issue.status, issue.title
+                    );
+                }
             }
+        }
+    }
 
-            update_task(&task_path, |task_state| {
-                // Preserve Issues status so issue injection works in run_stage
-                if task_state.status != TaskStatus::Issues {
-                    task_state.status = TaskStatus::Running;
-                }
-                task_state.updated_at = now_iso();
-                Ok(())
-            })?;
+    if !checklist_steps.is_empty() {
+        println!("Other checklist lines:");
+        for step in &checklist_steps {
+            let marker = if step.done { "x" } else { " " };
+            if step.done {
+                done += 1;
+            } else {
+                open += 1;
+            }
+            println!("  L{} - [{}] {}", step.line, marker, step.title);
+        }
+    }
 
-            let stage_name = task_state.stage.clone();
-            let result = run_stage(
-                ctx,
-                Some(&task_state.task),
-                &task_state.stage,
-                None,
-                ReviewFinishMode::Queue,
-            )?;
-            match result {
-                StageResult::Finished(_) => {
-                    if stage_name == "review" {
-                        let task_state = load_task(&task_path)?;
-                        if task_state.stage == "build" {
-                            review_loops += 1;
-                            if review_loops >= loop_limit {
-                                update_task(&task_path, |task_state| {
-                                    task_state.held = true;
-                                    task_state.updated_at = now_iso();
-                                    Ok(())
-                                })?;
-                                println!(
-                                    "Task '{}' exceeded review/build loop limit ({}); moving to backlog.",
-                                    task_state.task, loop_limit
-                                );
-                                current_task = None;
-                                current_claim = None;
-                                review_loops = 0;
-                                continue;
-                            }
-                        }
-                    }
-                    continue;
+    let total = open + done;
+    println!();
+    println!("Summary: {} total ({} open, {} done)", total, open, done);
+
+    if !duplicates.is_empty() {
+        println!();
+        println!("Warnings:");
+        for (id, lines) in duplicates {
+            let joined = lines
+                .iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!("  duplicate T{} at lines {}", id, joined);
+        }
+    }
+
+    Ok(())
+}
+
+/// Trailing session summary that the build prompt asks agents to append to the plan:
+/// everything from the last heading that mentions a session.
+fn last_session_notes(plan: &str) -> Option<String> {
+    let lines: Vec<&str> = plan.lines().collect();
+    let start = lines.iter().rposition(|line| {
+        line.trim_start().starts_with('#') && line.to_lowercase().contains("session")
+    })?;
+    let notes = lines[start..].join("\n");
+    let notes = notes.trim();
+    if notes.is_empty() {
+        None
+    } else {
+        Some(notes.to_string())
+    }
+}
+
+/// One-shot task briefing for prompts: description, spec files, plan progress, open
+/// issues with bodies, the last session, and the current branch.
+pub fn cmd_context(ctx: &CommandContext, task: &str) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    let task_state = ctx.store.load_task(task)?;
+    let dir = task_dir(&ctx.agent_root, task);
+
+    println!("# Context: {}", task);
+    println!();
+    println!("- Agent: {}", ctx.agent.name());
+    println!("- Stage: {}", task_state.stage);
+    println!("- Status: {}", task_state.status);
+    if task_state.held {
+        println!("- Held: yes (backlog)");
+    }
+    if let Some(reason) = task_state.blocked_reason.as_ref() {
+        println!("- Blocked: {}", reason);
+    }
+    if !task_state.depends_on.is_empty() {
+        println!("- Depends on: {}", task_state.depends_on.join(", "));
+    }
+    println!(
+        "- Branch: {}",
+        current_branch(&ctx.repo_root).unwrap_or_else(|| "(not a git repo)".to_string())
+    );
+    println!("- Directory: {}", dir.display());
+
+    println!();
+    println!("## Description");
+    println!();
+    println!("{}", task_state.description.as_deref().unwrap_or("(none)"));
+    if let Some(prompt) = task_state.prompt.as_deref() {
+        println!();
+        println!("## Prompt");
+        println!();
+        println!("{}", prompt);
+    }
+
+    let spec_dir = dir.join("spec");
+    if spec_dir.is_dir() {
+        println!();
+        println!("## Spec files");
+        println!();
+        let mut files: Vec<PathBuf> = fs::read_dir(&spec_dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+        files.sort();
+        if files.is_empty() {
+            println!("(none)");
+        }
+        for file in files {
+            let display = file.strip_prefix(&ctx.repo_root).unwrap_or(&file);
+            println!("- {}", display.display());
+        }
+    }
+
+    let plan_path = dir.join(plan_file_name(ctx.agent));
+    let plan = if plan_path.exists() {
+        Some(read_text(&plan_path)?)
+    } else {
+        None
+    };
+    println!();
+    println!("## Plan progress");
+    println!();
+    match plan.as_deref() {
+        None => println!("(no {})", plan_file_name(ctx.agent)),
+        Some(plan) => {
+            let mut open_steps = Vec::new();
+            let mut done = 0usize;
+            for (index, line) in plan.lines().enumerate() {
+                let (is_done, title) =
+                    if let Some(step) = parse_canonical_plan_step(line, index + 1) {
+                        (step.done, format!("[T{}] {}", step.id, step.title))
+                    } else if let Some(step) = parse_checklist_step(line, index + 1) {
+                        (step.done, step.title)
+                    } else {
+                        continue;
+                    };
+                if is_done {
+                    done += 1;
+                } else {
+                    open_steps.push(title);
                 }
-                StageResult::Interrupted => {
-                    update_task(&task_path, |task_state| {
-                        task_state.status = TaskStatus::Incomplete;
+            }
+            println!(
+                "{} total ({} open, {} done)",
+                open_steps.len() + done,
+                open_steps.len(),
+                done
+            );
+            for title in open_steps {
+                println!("- [ ] {}", title);
+            }
+        }
+    }
+
+    if ctx.agent == AgentKind::Code {
+        let mut issues: Vec<Issue> = ctx
+            .store
+            .list_issues()?
+            .into_iter()
+            .filter(|issue| {
+                issue.status == IssueStatus::Open && issue.task.as_deref() == Some(task)
+            })
+            .collect();
+        sort_issues(&mut issues);
+        println!();
+        println!("## Open issues ({})", issues.len());
+        for issue in issues {
+            println!();
+            println!(
+                "### {} [{}][{}] {}",
+                issue.id, issue.priority, issue.issue_type, issue.title
+            );
+            if let Some(file) = issue.file.as_deref() {
+                println!("File: {}", file);
+            }
+            if let Some(body) = issue.body.as_deref() {
+                println!();
+                println!("{}", body.trim());
+            }
+        }
+    }
+
+    println!();
+    println!("## Last session");
+    println!();
+    match task_state.last_session.as_deref() {
+        None => println!("(none yet)"),
+        Some(session_id) => match ctx.store.load_session(session_id) {
+            Ok(session) => {
+                println!(
+                    "{} ({} stage, {}{})",
+                    session.session_id,
+                    session.stage,
+                    session.status,
+                    session
+                        .model
+                        .as_deref()
+                        .map(|model| format!(", {model}"))
+                        .unwrap_or_default()
+                );
+                if let Some(finished_at) = session.finished_at.as_deref() {
+                    println!("Finished: {}", finished_at);
+                }
+                if let Some(next_stage) = session.next_stage.as_deref() {
+                    println!("Next stage: {}", next_stage);
+                }
+            }
+            Err(_) => println!("{} (session state missing)", session_id),
+        },
+    }
+    if let Some(error) = task_state.last_error.as_deref() {
+        println!("Last error: {}", error);
+    }
+    if let Some(notes) = plan.as_deref().and_then(last_session_notes) {
+        println!();
+        println!("{}", notes);
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn cmd_issues(
+    ctx: &CommandContext,
+    task: Option<String>,
+    unassigned: bool,
+    status: Option<String>,
+    priority: Option<String>,
+    issue_type: Option<String>,
+    source: Option<String>,
+    labels: &[String],
+    include_archived: bool,
+) -> Result<()> {
+    ensure_code_agent(ctx)?;
+    if unassigned && task.is_some() {
+        bail!("Use --task or --unassigned, not both");
+    }
+    if let Some(task) = task.as_deref() {
+        validate_task_name(task)?;
+    }
+    let status_filter = parse_status_filter(status.as_deref())?;
+    let priority = parse_priority(priority.as_deref())?;
+    let issue_type = parse_issue_type(issue_type.as_deref())?;
+    let source = parse_issue_source(source.as_deref())?;
+
+    let filter = IssueFilter {
+        status: status_filter,
+        task,
+        unassigned,
+        issue_type,
+        priority,
+        source,
+        labels: normalize_labels(labels)?,
+    };
+
+    report_escalations(&escalate_issue_priorities(ctx, false)?);
+    expire_advisory_issues(ctx)?;
+    archive_resolved_issues(ctx, None)?;
+    let mut issues = ctx.store.list_issues()?;
+    if include_archived {
+        issues.extend(ctx.store.list_archived_issues()?);
+    }
+    let mut issues = filter_issues(issues, &filter);
+    sort_issues(&mut issues);
+
+    if json_output() {
+        return print_json(&issues);
+    }
+    if issues.is_empty() {
+        println!("{}", msg("no_issues", &[]).dimmed());
+        return Ok(());
+    }
+
+    let heading = match status_filter {
+        IssueStatusFilter::Open => "Open issues",
+        IssueStatusFilter::Resolved => "Resolved issues",
+        IssueStatusFilter::All => "Issues",
+    };
+    println!("{}:", heading);
+    for (index, issue) in issues.iter().enumerate() {
+        let task_label = issue.task.as_deref().unwrap_or("unassigned");
+        println!("  id: {}", issue.id);
+        println!("  [{}] {}: {}", issue.priority, task_label, issue.title);
+        if !issue.labels.is_empty() {
+            println!("      labels: {}", issue.labels.join(", "));
+        }
+        if status_filter == IssueStatusFilter::All {
+            println!("      status: {}", issue.status);
+        }
+        if index + 1 < issues.len() {
+            println!();
+        }
+    }
+    Ok(())
+}
+
+pub fn cmd_issue(ctx: &CommandContext, command: IssueCommands) -> Result<()> {
+    ensure_code_agent(ctx)?;
+    match command {
+        IssueCommands::List {
+            task,
+            unassigned,
+            status,
+            priority,
+            issue_type,
+            source,
+            labels,
+            include_archived,
+        } => cmd_issues(
+            ctx,
+            task,
+            unassigned,
+            status,
+            priority,
+            issue_type,
+            source,
+            &labels,
+            include_archived,
+        ),
+        IssueCommands::Archive { older_than_days } => {
+            let Some(days) = older_than_days.or(ctx.config.issues.archive_after_days) else {
+                bail!("Pass --older-than-days or set [issues] archive_after_days in .agents/mung.toml");
+            };
+            let archived = archive_resolved_issues(ctx, Some(days))?;
+            println!("Archived {} resolved issue(s)", archived);
+            Ok(())
+        }
+        IssueCommands::Add {
+            title,
+            task,
+            priority,
+            issue_type,
+            source,
+            file,
+            stage,
+            body,
+            stdin_body,
+            labels,
+            attachments,
+        } => cmd_issue_add(
+            ctx,
+            title,
+            task,
+            priority,
+            issue_type,
+            source,
+            file,
+            stage,
+            body,
+            stdin_body,
+            &labels,
+            &attachments,
+        ),
+        IssueCommands::Resolve {
+            id,
+            resolution,
+            steps,
+        } => cmd_issue_resolve(ctx, &id, resolution, steps),
+        IssueCommands::Assign { id, task, stage } => cmd_issue_assign(ctx, &id, &task, stage),
+        IssueCommands::Show { id } => cmd_issue_show(ctx, &id),
+        IssueCommands::Dedupe { threshold, link } => cmd_issue_dedupe(ctx, threshold, link),
+        IssueCommands::Comment {
+            id,
+            body,
+            stdin_body,
+            by,
+        } => cmd_issue_comment(ctx, &id, body, stdin_body, by.as_deref()),
+        IssueCommands::Stale { days, ping } => cmd_issue_stale(ctx, days, ping),
+        IssueCommands::Import {
+            from,
+            file,
+            task,
+            dry_run,
+        } => crate::import::cmd_issue_import(ctx, &from, &file, task.as_deref(), dry_run),
+        IssueCommands::Export {
+            format,
+            status,
+            include_archived,
+        } => cmd_issue_export(ctx, &format, status.as_deref(), include_archived),
+        IssueCommands::Escalate { dry_run } => cmd_issue_escalate(ctx, dry_run),
+        IssueCommands::Template { issue_type } => cmd_issue_template(ctx, &issue_type),
+    }
+}
+
+pub fn cmd_delete(ctx: &CommandContext, task: &str, force: bool) -> Result<()> {
+    validate_task_name(task)?;
+    let dir = task_dir(&ctx.agent_root, task);
+    if !dir.exists() && !ctx.store.task_exists(task) {
+        println!("{}", msg("task_not_found", &[("task", &task)]));
+        return Ok(());
+    }
+
+    let issues = ctx.store.list_issues()?;
+    let open_issue_ids: Vec<_> = issues
+        .iter()
+        .filter(|issue| issue.status == IssueStatus::Open && issue.task.as_deref() == Some(task))
+        .map(|issue| issue.id.clone())
+        .collect();
+
+    if !open_issue_ids.is_empty() && !force {
+        bail!(
+            "Task '{}' has open issues ({}). Re-run with --force to delete and unassign them.",
+            task,
+            open_issue_ids.len()
+        );
+    }
+
+    if force && !open_issue_ids.is_empty() {
+        for mut issue in issues {
+            if issue.status == IssueStatus::Open && issue.task.as_deref() == Some(task) {
+                issue.task = None;
+                issue.updated_at = now_iso();
+                ctx.store.save_issue(&issue)?;
+            }
+        }
+    }
+
+    ctx.store.delete_task(task)?;
+    if dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    }
+    println!("{}", msg("task_removed", &[("task", &task)]));
+    Ok(())
+}
+
+/// Renames a task everywhere its name is recorded: the task directory and
+/// task.json, issue `task:` fields (archived ones included), session records, and
+/// other tasks' dependencies. The event log is append-only, so a `renamed` event
+/// links the old name for `history` and `audit`.
+pub fn cmd_rename(ctx: &CommandContext, old: &str, new: &str) -> Result<()> {
+    validate_task_name(old)?;
+    validate_task_name(new)?;
+    if old == new {
+        bail!("Task is already named '{}'", new);
+    }
+    if !ctx.store.task_exists(old) {
+        bail!("{}", msg("task_not_found", &[("task", &old)]));
+    }
+    if task_dir(&ctx.agent_root, new).exists() || ctx.store.task_exists(new) {
+        bail!("{}", msg("task_exists", &[("task", &new)]));
+    }
+    let mut task_state = ctx.store.load_task(old)?;
+    if task_state.status == TaskStatus::Running || ctx.store.has_active_claim(old)? {
+        bail!("Task '{}' is running. Finish it before renaming.", old);
+    }
+
+    fs::rename(
+        task_dir(&ctx.agent_root, old),
+        task_dir(&ctx.agent_root, new),
+    )
+    .with_context(|| format!("Failed to move task '{}' to '{}'", old, new))?;
+    ctx.store.remove_stale_claim(old)?;
+    ctx.store.delete_task(old)?;
+    task_state.task = new.to_string();
+    task_state.updated_at = now_iso();
+    task_state.record_history();
+    ctx.store.save_task(&task_state)?;
+
+    let mut issues = 0usize;
+    for mut issue in ctx.store.list_issues()? {
+        if issue.task.as_deref() == Some(old) {
+            issue.task = Some(new.to_string());
+            ctx.store.save_issue(&issue)?;
+            issues += 1;
+        }
+    }
+    for mut issue in ctx.store.list_archived_issues()? {
+        if issue.task.as_deref() == Some(old) {
+            issue.task = Some(new.to_string());
+            ctx.store.save_archived_issue(&issue)?;
+            issues += 1;
+        }
+    }
+
+    let mut sessions = 0usize;
+    for session in ctx.store.list_sessions() {
+        if session.task.as_deref() == Some(old) {
+            ctx.store.update_session(&session.session_id, |session| {
+                session.task = Some(new.to_string());
+                Ok(())
+            })?;
+            sessions += 1;
+        }
+    }
+
+    let mut dependents = Vec::new();
+    for other in ctx.store.list_tasks() {
+        if other.depends_on.iter().any(|dependency| dependency == old) {
+            ctx.store.update_task(&other.task, |task_state| {
+                for dependency in task_state.depends_on.iter_mut() {
+                    if dependency == old {
+                        *dependency = new.to_string();
+                    }
+                }
+                task_state.updated_at = now_iso();
+                Ok(())
+            })?;
+            dependents.push(other.task);
+        }
+    }
+
+    let mut event = Event::new(new, "renamed", ctx.actor(None));
+    event.from = Some(old.to_string());
+    event.to = Some(new.to_string());
+    ctx.store.append_event(&event)?;
+
+    println!("{}", msg("task_renamed", &[("old", &old), ("new", &new)]));
+    println!("  Issues updated: {}", issues);
+    println!("  Sessions updated: {}", sessions);
+    if !dependents.is_empty() {
+        println!("  Dependencies updated: {}", dependents.join(", "));
+    }
+    if ctx.config.workdirs.tasks.contains_key(old) {
+        println!(
+            "Note: [workdirs.tasks] in .agents/mung.toml still names '{}'",
+            old
+        );
+    }
+    let branch = format!("{}{}", ctx.config.branches.prefix, old);
+    if local_branches(&ctx.repo_root).contains(&branch) {
+        println!(
+            "Note: branch {} keeps the old name (git branch -m {} {}{})",
+            branch, branch, ctx.config.branches.prefix, new
+        );
+    }
+    Ok(())
+}
+
+/// Starts a follow-up task from `source`: a copy of its spec, plan, description,
+/// and labels as a fresh pending task in the first stage.
+pub fn cmd_clone(ctx: &CommandContext, source: &str, new: &str) -> Result<()> {
+    validate_task_name(source)?;
+    validate_task_name(new)?;
+    if !ctx.store.task_exists(source) {
+        bail!("{}", msg("task_not_found", &[("task", &source)]));
+    }
+    let dest = task_dir(&ctx.agent_root, new);
+    if dest.exists() {
+        bail!("{}", msg("task_exists", &[("task", &new)]));
+    }
+    let source_state = ctx.store.load_task(source)?;
+    let source_dir = task_dir(&ctx.agent_root, source);
+
+    ctx.agent.create_task(&dest, new)?;
+    let spec_dir = source_dir.join("spec");
+    if spec_dir.exists() {
+        let dest_spec = dest.join("spec");
+        if dest_spec.exists() {
+            fs::remove_dir_all(&dest_spec)?;
+        }
+        copy_dir_all(&spec_dir, &dest_spec)?;
+    }
+    let plan_path = source_dir.join("plan.md");
+    if plan_path.exists() {
+        fs::copy(&plan_path, dest.join("plan.md"))?;
+    }
+    let stage = ctx.agent.initial_stage().to_string();
+    create_task_state(
+        ctx.store.as_ref(),
+        ctx.agent.name(),
+        new,
+        &stage,
+        &now_iso(),
+        false,
+        source_state.description.clone(),
+        None,
+    )?;
+    let owner = ctx.actor(None).user;
+    ctx.store.update_task(new, |task_state| {
+        task_state.owner = Some(owner);
+        task_state.labels = source_state.labels.clone();
+        Ok(())
+    })?;
+
+    let mut event = Event::new(new, "cloned", ctx.actor(None));
+    event.from = Some(source.to_string());
+    event.to = Some(new.to_string());
+    ctx.store.append_event(&event)?;
+
+    println!("Cloned '{}' into '{}'", source, new);
+    println!("  Directory: {}", dest.display());
+    println!("  Stage: {}", stage);
+    Ok(())
+}
+
+pub fn cmd_merge_tasks(ctx: &CommandContext, sources: &[String], into: &str) -> Result<()> {
+    if ctx.agent != AgentKind::Code {
+        bail!("merge-tasks is only supported for the code agent");
+    }
+    validate_task_name(into)?;
+    if sources.len() < 2 {
+        bail!("Provide at least two tasks to merge");
+    }
+    let mut seen = HashSet::new();
+    let mut source_states = Vec::new();
+    for source in sources {
+        validate_task_name(source)?;
+        if !seen.insert(source.as_str()) {
+            bail!("Task '{}' listed more than once", source);
+        }
+        if !ctx.store.task_exists(source) {
+            bail!("{}", msg("task_not_found", &[("task", &source)]));
+        }
+        let task_state = ctx.store.load_task(source)?;
+        if task_state.status == TaskStatus::Running || ctx.store.has_active_claim(source)? {
+            bail!("Task '{}' is running. Finish it before merging.", source);
+        }
+        if task_state.prompt.is_some() {
+            bail!(
+                "Task '{}' is a one-off prompt task and cannot be merged",
+                source
+            );
+        }
+        source_states.push(task_state);
+    }
+    let into_is_source = sources.iter().any(|source| source == into);
+    if !into_is_source && task_dir(&ctx.agent_root, into).exists() {
+        bail!("{}", msg("task_exists", &[("task", &into)]));
+    }
+
+    let merged_specs = merge_spec_files(&ctx.agent_root, sources)?;
+    let (merged_plan, step_maps, step_count) = merge_plans(&ctx.agent_root, sources, into)?;
+
+    let stages = ctx.agent.stages();
+    let stage = source_states
+        .iter()
+        .map(|state| state.stage.as_str())
+        .min_by_key(|stage| {
+            stages
+                .iter()
+                .position(|known| known == stage)
+                .unwrap_or(usize::MAX)
+        })
+        .unwrap_or(ctx.agent.initial_stage())
+        .to_string();
+    let descriptions: Vec<String> = source_states
+        .iter()
+        .filter_map(|state| state.description.clone())
+        .filter(|description| !description.trim().is_empty())
+        .collect();
+    let description = if descriptions.is_empty() {
+        None
+    } else {
+        Some(descriptions.join("; "))
+    };
+
+    let into_dir = task_dir(&ctx.agent_root, into);
+    if into_is_source {
+        let spec_dir = into_dir.join("spec");
+        if spec_dir.exists() {
+            fs::remove_dir_all(&spec_dir)?;
+        }
+        ctx.store.update_task(into, |task_state| {
+            task_state.stage = stage.clone();
+            task_state.description = description.clone();
+            task_state.updated_at = now_iso();
+            Ok(())
+        })?;
+    } else {
+        ctx.agent.create_task(&into_dir, into)?;
+        create_task_state(
+            ctx.store.as_ref(),
+            ctx.agent.name(),
+            into,
+            &stage,
+            &now_iso(),
+            false,
+            description.clone(),
+            None,
+        )?;
+    }
+    for (file, content) in &merged_specs {
+        write_text(&into_dir.join("spec").join(file), content)?;
+    }
+    write_text(&into_dir.join("plan.md"), &merged_plan)?;
+
+    let mut reassigned = 0usize;
+    for mut issue in ctx.store.list_issues()? {
+        let Some(source) = issue.task.clone() else {
+            continue;
+        };
+        let Some(step_map) = step_maps.get(&source) else {
+            continue;
+        };
+        issue.task = Some(into.to_string());
+        issue.steps = issue
+            .steps
+            .iter()
+            .map(|step| step_map.get(step).copied().unwrap_or(*step))
+            .collect();
+        issue.updated_at = now_iso();
+        ctx.store.save_issue(&issue)?;
+        reassigned += 1;
+    }
+    sync_task_status_for_issues(ctx.store.as_ref(), into)?;
+
+    println!("Merged {} into '{}'", sources.join(", "), into);
+    println!("  Stage: {}", stage);
+    println!("  Plan steps: {} (renumbered)", step_count);
+    println!("  Issues reassigned: {}", reassigned);
+    for source in sources.iter().filter(|source| source.as_str() != into) {
+        let archived = archive_task(ctx, source)?;
+        println!("  Archived '{}' -> {}", source, archived.display());
+    }
+    Ok(())
+}
+
+pub fn cmd_split(
+    ctx: &CommandContext,
+    task: &str,
+    into: Vec<String>,
+    map_file: Option<PathBuf>,
+) -> Result<()> {
+    if ctx.agent != AgentKind::Code {
+        bail!("split is only supported for the code agent");
+    }
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    let task_state = ctx.store.load_task(task)?;
+    if task_state.status == TaskStatus::Running || ctx.store.has_active_claim(task)? {
+        bail!("Task '{}' is running. Finish it before splitting.", task);
+    }
+
+    let source_dir = task_dir(&ctx.agent_root, task);
+    let plan_path = source_dir.join("plan.md");
+    let plan = if plan_path.exists() {
+        read_text(&plan_path)?
+    } else {
+        String::new()
+    };
+    let steps: Vec<CanonicalPlanStep> = plan
+        .lines()
+        .enumerate()
+        .filter_map(|(index, line)| parse_canonical_plan_step(line, index + 1))
+        .collect();
+    if steps.is_empty() {
+        bail!("Task '{}' has no canonical plan steps to split", task);
+    }
+
+    let assignments: HashMap<u32, String> = match map_file {
+        Some(path) => {
+            let mapping = parse_split_map(&read_text(&path)?)?;
+            if !into.is_empty() {
+                for target in mapping.values() {
+                    if !into.contains(target) {
+                        bail!("Mapping targets '{}' which is not listed in --into", target);
+                    }
+                }
+            }
+            mapping
+        }
+        None => prompt_split_assignments(&steps, &into)?,
+    };
+
+    let mut targets: Vec<String> = Vec::new();
+    for target in into.iter().chain(assignments.values()) {
+        if !targets.contains(target) {
+            targets.push(target.clone());
+        }
+    }
+    if targets.len() < 2 {
+        bail!("Split needs at least two target tasks");
+    }
+    for target in &targets {
+        validate_task_name(target)?;
+        if task_dir(&ctx.agent_root, target).exists() {
+            bail!("{}", msg("task_exists", &[("task", &target)]));
+        }
+    }
+    for step in &steps {
+        if !assignments.contains_key(&step.id) {
+            bail!("Step T{} is not assigned to a target task", step.id);
+        }
+    }
+
+    let mut plans: HashMap<&str, Vec<String>> = HashMap::new();
+    for (index, line) in plan.lines().enumerate() {
+        if let Some(step) = parse_canonical_plan_step(line, index + 1) {
+            let target = assignments[&step.id].as_str();
+            plans.entry(target).or_default().push(line.to_string());
+        } else if let Some(step) = parse_checklist_step(line, index + 1) {
+            if step.title.starts_with("(tasks will be added") {
+                continue;
+            }
+            plans
+                .entry(targets[0].as_str())
+                .or_default()
+                .push(line.to_string());
+        }
+    }
+
+    for target in &targets {
+        let dest = task_dir(&ctx.agent_root, target);
+        ctx.agent.create_task(&dest, target)?;
+        let spec_dir = source_dir.join("spec");
+        if spec_dir.exists() {
+            copy_dir_all(&spec_dir, &dest.join("spec"))?;
+        }
+        let lines = plans.get(target.as_str()).cloned().unwrap_or_default();
+        let content = format!(
+            "# Implementation Plan - {target}\n\n> Generated: {}\n> Status: READY\n> Split from: {task}\n\n{}\n",
+            today_date(),
+            lines.join("\n")
+        );
+        write_text(&dest.join("plan.md"), &content)?;
+        let description = match task_state.description.as_deref() {
+            Some(description) => format!("{description} (split from {task})"),
+            None => format!("Split from {task}"),
+        };
+        create_task_state(
+            ctx.store.as_ref(),
+            ctx.agent.name(),
+            target,
+            &task_state.stage,
+            &now_iso(),
+            task_state.held,
+            Some(description),
+            None,
+        )?;
+    }
+
+    let step_counts: HashMap<&str, usize> = targets
+        .iter()
+        .map(|target| {
+            let count = assignments
+                .values()
+                .filter(|assigned| *assigned == target)
+                .count();
+            (target.as_str(), count)
+        })
+        .collect();
+    let mut issue_counts: HashMap<String, usize> = HashMap::new();
+    let mut issues: Vec<Issue> = ctx
+        .store
+        .list_issues()?
+        .into_iter()
+        .filter(|issue| issue.task.as_deref() == Some(task))
+        .collect();
+    sort_issues(&mut issues);
+    for mut issue in issues {
+        let linked = issue
+            .steps
+            .iter()
+            .find_map(|step| assignments.get(step).cloned());
+        // Unlinked issues go wherever the issue/step ratio is currently lowest.
+        let target = linked.unwrap_or_else(|| {
+            targets
+                .iter()
+                .min_by(|a, b| {
+                    let ratio = |name: &String| {
+                        let issues = issue_counts.get(name).copied().unwrap_or(0) as f64;
+                        let steps = step_counts.get(name.as_str()).copied().unwrap_or(0) as f64;
+                        (issues + 1.0) / steps.max(1.0)
+                    };
+                    ratio(a)
+                        .partial_cmp(&ratio(b))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .cloned()
+                .unwrap_or_else(|| targets[0].clone())
+        });
+        *issue_counts.entry(target.clone()).or_insert(0) += 1;
+        issue.task = Some(target);
+        issue.updated_at = now_iso();
+        ctx.store.save_issue(&issue)?;
+    }
+
+    println!("Split '{}' into {}", task, targets.join(", "));
+    for target in &targets {
+        sync_task_status_for_issues(ctx.store.as_ref(), target)?;
+        println!(
+            "  {}: {} steps, {} issues",
+            target,
+            step_counts.get(target.as_str()).copied().unwrap_or(0),
+            issue_counts.get(target).copied().unwrap_or(0)
+        );
+    }
+    let archived = archive_task(ctx, task)?;
+    println!("  Archived '{}' -> {}", task, archived.display());
+    Ok(())
+}
+
+/// Mapping file lines look like `api-part: T1, T2` (blank lines and `#` comments ignored).
+fn parse_split_map(content: &str) -> Result<HashMap<u32, String>> {
+    let mut assignments = HashMap::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((target, steps)) = line.split_once(':') else {
+            bail!(
+                "Invalid mapping on line {}: expected '<task>: T1, T2'",
+                index + 1
+            );
+        };
+        let target = target.trim().to_string();
+        for step in steps.split(',').filter(|step| !step.trim().is_empty()) {
+            let id = parse_step_id(step)?;
+            if let Some(existing) = assignments.insert(id, target.clone()) {
+                bail!(
+                    "Step T{} is mapped to both '{}' and '{}'",
+                    id,
+                    existing,
+                    target
+                );
+            }
+        }
+    }
+    Ok(assignments)
+}
+
+fn prompt_split_assignments(
+    steps: &[CanonicalPlanStep],
+    into: &[String],
+) -> Result<HashMap<u32, String>> {
+    if into.len() < 2 {
+        bail!("Interactive split needs at least two --into tasks (or pass --map <file>)");
+    }
+    println!("Assign each step to a target task:");
+    for (index, target) in into.iter().enumerate() {
+        println!("  {}) {}", index + 1, target);
+    }
+    let mut assignments = HashMap::new();
+    for step in steps {
+        if assignments.contains_key(&step.id) {
+            continue;
+        }
+        loop {
+            let marker = if step.done { "x" } else { " " };
+            let reply = prompt_line(&format!(
+                "[{}] [{}][{}][T{}] {} -> ",
+                marker, step.priority, step.complexity, step.id, step.title
+            ))?;
+            let choice = reply
+                .parse::<usize>()
+                .ok()
+                .and_then(|choice| choice.checked_sub(1))
+                .and_then(|choice| into.get(choice))
+                .or_else(|| into.iter().find(|target| **target == reply));
+            if let Some(target) = choice {
+                assignments.insert(step.id, target.clone());
+                break;
+            }
+            println!("Enter 1-{} or a target task name", into.len());
+        }
+    }
+    Ok(assignments)
+}
+
+fn merge_spec_files(agent_root: &Path, sources: &[String]) -> Result<Vec<(String, String)>> {
+    let mut files = Vec::new();
+    for source in sources {
+        let spec_dir = task_dir(agent_root, source).join("spec");
+        let Ok(entries) = fs::read_dir(&spec_dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !files.contains(&name) {
+                files.push(name);
+            }
+        }
+    }
+    files.sort();
+
+    let mut merged = Vec::new();
+    for file in files {
+        let mut heading: Option<String> = None;
+        let mut sections = Vec::new();
+        for source in sources {
+            let path = task_dir(agent_root, source).join("spec").join(&file);
+            if !path.exists() {
+                continue;
+            }
+            let content = read_text(&path)?;
+            let (title, body) = match content.split_once('\n') {
+                Some((first, rest)) if first.starts_with("# ") => (Some(first), rest),
+                _ => (None, content.as_str()),
+            };
+            if heading.is_none() {
+                heading = title.map(|title| title.to_string());
+            }
+            if body.trim().is_empty() {
+                continue;
+            }
+            sections.push(format!("## From {}\n\n{}", source, body.trim()));
+        }
+        let heading = heading.unwrap_or_else(|| format!("# {}", file.trim_end_matches(".md")));
+        let mut content = format!("{heading}\n\n");
+        if !sections.is_empty() {
+            content.push_str(&sections.join("\n\n"));
+            content.push('\n');
+        }
+        merged.push((file, content));
+    }
+    Ok(merged)
+}
+
+type StepMaps = HashMap<String, HashMap<u32, u32>>;
+
+fn merge_plans(
+    agent_root: &Path,
+    sources: &[String],
+    into: &str,
+) -> Result<(String, StepMaps, usize)> {
+    let mut next_id = 1u32;
+    let mut step_count = 0usize;
+    let mut step_maps = HashMap::new();
+    let mut sections = Vec::new();
+    for source in sources {
+        let plan_path = task_dir(agent_root, source).join("plan.md");
+        let content = if plan_path.exists() {
+            read_text(&plan_path)?
+        } else {
+            String::new()
+        };
+        let mut step_map = HashMap::new();
+        let mut lines = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            if let Some(step) = parse_canonical_plan_step(line, index + 1) {
+                let new_id = *step_map.entry(step.id).or_insert_with(|| {
+                    let id = next_id;
+                    next_id += 1;
+                    id
+                });
+                lines.push(renumber_plan_step(line, step.id, new_id));
+                step_count += 1;
+            } else if let Some(step) = parse_checklist_step(line, index + 1) {
+                if step.title.starts_with("(tasks will be added") {
+                    continue;
+                }
+                lines.push(line.to_string());
+            }
+        }
+        step_maps.insert(source.clone(), step_map);
+        if !lines.is_empty() {
+            sections.push(format!("## From {}\n\n{}", source, lines.join("\n")));
+        }
+    }
+    let status = if step_count > 0 {
+        "READY"
+    } else {
+        "PENDING_SPEC"
+    };
+    let mut plan = format!(
+        "# Implementation Plan - {into}\n\n> Generated: {}\n> Status: {status}\n> Merged from: {}\n\n",
+        today_date(),
+        sources.join(", ")
+    );
+    if sections.is_empty() {
+        plan.push_str("- [ ] (tasks will be added during planning phase)\n");
+    } else {
+        plan.push_str(&sections.join("\n\n"));
+        plan.push('\n');
+    }
+    Ok((plan, step_maps, step_count))
+}
+
+fn renumber_plan_step(line: &str, old_id: u32, new_id: u32) -> String {
+    line.replacen(&format!("[T{old_id}]"), &format!("[T{new_id}]"), 1)
+}
+
+/// Moves a task directory under `archive/`, keeping prior archives of the same name.
+/// The archive gets a `task.json` whichever backend held the task's state.
+fn archive_task(ctx: &CommandContext, task: &str) -> Result<PathBuf> {
+    let agent_root = &ctx.agent_root;
+    let task_state = ctx.store.load_task(task)?;
+    let source = task_dir(agent_root, task);
+    let mut dest = archive_dir(agent_root, task);
+    if dest.exists() {
+        let stamp = now_iso().replace([':', '-'], "");
+        dest = archive_dir(agent_root, &format!("{task}-{stamp}"));
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(&source, &dest)
+        .with_context(|| format!("Failed to archive {}", source.display()))?;
+    ctx.store.delete_task(task)?;
+    let snapshot = dest.join("task.json");
+    if !snapshot.exists() {
+        write_text(&snapshot, &serde_json::to_string_pretty(&task_state)?)?;
+    }
+    Ok(dest)
+}
+
+/// Task names with live state under any agent in `.agents/`.
+fn live_task_names(ctx: &CommandContext) -> HashSet<String> {
+    let mut names = HashSet::new();
+    let Ok(agents) = fs::read_dir(ctx.repo_root.join(".agents")) else {
+        return names;
+    };
+    for agent in agents.flatten() {
+        if !agent.path().join("tasks").is_dir() {
+            continue;
+        }
+        let Ok(store) = open_store(&agent.path(), &ctx.config.state) else {
+            continue;
+        };
+        names.extend(store.list_tasks().into_iter().map(|task| task.task));
+    }
+    names
+}
+
+/// Removes worktrees and `[branches] prefix` branches whose task was archived or
+/// deleted, once they are merged into `[branches] merged_into`.
+pub fn cmd_prune(ctx: &CommandContext, dry_run: bool) -> Result<()> {
+    let branches = &ctx.config.branches;
+    let Some(merged) = merged_branches(&ctx.repo_root, &branches.merged_into) else {
+        bail!(
+            "Could not list branches merged into '{}'",
+            branches.merged_into
+        );
+    };
+    let live = live_task_names(ctx);
+    let current = current_branch(&ctx.repo_root);
+    let dead_task = |branch: &str| {
+        branch
+            .strip_prefix(branches.prefix.as_str())
+            .filter(|task| !task.is_empty() && !live.contains(*task))
+            .map(str::to_string)
+    };
+    let worktrees = linked_worktrees(&ctx.repo_root);
+    let mut pruned = 0;
+    let mut skipped = 0;
+
+    for branch in local_branches(&ctx.repo_root) {
+        let Some(task) = dead_task(&branch) else {
+            continue;
+        };
+        if current.as_deref() == Some(branch.as_str()) {
+            println!("Skipped {}: it is checked out", branch);
+            skipped += 1;
+            continue;
+        }
+        if !merged.contains(&branch) {
+            println!(
+                "Skipped {}: not merged into {}",
+                branch, branches.merged_into
+            );
+            skipped += 1;
+            continue;
+        }
+        let worktree = worktrees
+            .iter()
+            .find(|worktree| worktree.branch.as_deref() == Some(branch.as_str()));
+        if dry_run {
+            if let Some(worktree) = worktree {
+                println!("Would remove worktree {}", worktree.path.display());
+            }
+            println!("Would delete branch {} (task '{}')", branch, task);
+            pruned += 1;
+            continue;
+        }
+        if let Some(worktree) = worktree {
+            if let Err(err) = remove_worktree(&ctx.repo_root, &worktree.path) {
+                println!("Skipped {}: {}", branch, err);
+                skipped += 1;
+                continue;
+            }
+            println!("Removed worktree {}", worktree.path.display());
+        }
+        delete_branch(&ctx.repo_root, &branch)?;
+        println!("Deleted branch {} (task '{}')", branch, task);
+        pruned += 1;
+    }
+
+    if pruned == 0 && skipped == 0 {
+        println!("{}", "Nothing to prune".dimmed());
+    }
+    Ok(())
+}
+
+pub fn cmd_reorder(ctx: &CommandContext, task: &str, position: usize) -> Result<()> {
+    validate_task_name(task)?;
+    if position == 0 {
+        bail!("Position must be 1 or greater");
+    }
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    let task_state = ctx.store.load_task(task)?;
+    if task_state.stage != "build" {
+        bail!("Reorder is only supported for build stage tasks");
+    }
+    if task_state.held {
+        bail!("Task '{}' is held. Activate it before reordering.", task);
+    }
+
+    let mut stage_tasks: Vec<TaskState> = ctx
+        .store
+        .list_tasks()
+        .into_iter()
+        .filter(|t| !t.held && t.stage == "build")
+        .collect();
+    if stage_tasks.is_empty() {
+        bail!("No build tasks to reorder");
+    }
+
+    stage_tasks.sort_by(|a, b| {
+        let ar = a.queue_rank.unwrap_or(i64::MAX);
+        let br = b.queue_rank.unwrap_or(i64::MAX);
+        ar.cmp(&br).then_with(|| a.added_at.cmp(&b.added_at))
+    });
+
+    let current_index = stage_tasks
+        .iter()
+        .position(|t| t.task == task)
+        .ok_or_else(|| anyhow::anyhow!("Task '{}' is not in the build queue", task))?;
+
+    let mut ordered = Vec::with_capacity(stage_tasks.len());
+    for (idx, item) in stage_tasks.into_iter().enumerate() {
+        if idx != current_index {
+            ordered.push(item);
+        }
+    }
+    let insert_index = std::cmp::min(position - 1, ordered.len());
+    ordered.insert(insert_index, task_state);
+
+    for (idx, item) in ordered.iter().enumerate() {
+        let new_rank = (idx + 1) as i64;
+        if item.queue_rank == Some(new_rank) {
+            continue;
+        }
+        ctx.store.update_task(&item.task, |task_state| {
+            task_state.queue_rank = Some(new_rank);
+            task_state.updated_at = now_iso();
+            Ok(())
+        })?;
+    }
+
+    println!(
+        "Reordered '{}' to position {} in build queue.",
+        task,
+        insert_index + 1
+    );
+    let mut build_tasks: Vec<TaskState> = ctx
+        .store
+        .list_tasks()
+        .into_iter()
+        .filter(|t| !t.held && t.stage == "build")
+        .collect();
+    build_tasks.sort_by(|a, b| {
+        let ar = a.queue_rank.unwrap_or(i64::MAX);
+        let br = b.queue_rank.unwrap_or(i64::MAX);
+        ar.cmp(&br).then_with(|| a.added_at.cmp(&b.added_at))
+    });
+    let issue_counts = match ctx.store.list_issues() {
+        Ok(issues) => count_open_issues(&issues),
+        Err(err) => {
+            eprintln!("Warning: failed to load issues: {}", err);
+            Default::default()
+        }
+    };
+    println!("{}:", ctx.agent.stage_label("build"));
+    for task in build_tasks {
+        let issue_count = issue_counts.per_task.get(&task.task).copied().unwrap_or(0);
+        if issue_count > 0 {
+            println!(
+                "  {} {} [issues: {}]",
+                task.status.styled(),
+                task.task,
+                issue_count
+            );
+        } else {
+            println!("  {} {}", task.status.styled(), task.task);
+        }
+    }
+    Ok(())
+}
+
+pub fn cmd_start(ctx: &CommandContext) -> Result<()> {
+    let mut task_name: Option<String> = None;
+    let mut stage = ctx.agent.initial_stage().to_string();
+    let handoff_stage = ctx.agent.handoff_stage();
+
+    loop {
+        if let Some(task) = task_name.as_ref() {
+            if ctx.store.task_exists(task) {
+                ctx.store.update_task(task, |task_state| {
+                    // Preserve Issues status so issue injection works in run_stage
+                    if task_state.status != TaskStatus::Issues {
+                        task_state.status = TaskStatus::Running;
+                    }
+                    task_state.updated_at = now_iso();
+                    Ok(())
+                })?;
+            }
+        }
+
+        let result = run_stage(
+            ctx,
+            task_name.as_deref(),
+            &stage,
+            None,
+            ReviewFinishMode::Queue,
+        )?;
+        match result {
+            StageResult::Finished(session) => {
+                if task_name.is_none() {
+                    if let Some(task) = session.task.clone() {
+                        task_name = Some(task);
+                    }
+                }
+                let next_stage = session
+                    .next_stage
+                    .clone()
+                    .or_else(|| ctx.agent.next_stage(&stage).map(|s| s.to_string()));
+                if let Some(next_stage) = next_stage {
+                    if let Some(handoff) = handoff_stage {
+                        if next_stage == handoff {
+                            if let Some(task) = task_name.as_ref() {
+                                println!("Task '{}' is ready.", task);
+                                println!("Run 'mung run {}' or 'mung run-queue' to start.", task);
+                            }
+                            return Ok(());
+                        }
+                    }
+                    if next_stage == "completed" {
+                        println!("Task completed.");
+                        return Ok(());
+                    }
+                    stage = next_stage;
+                    continue;
+                }
+
+                bail!("No next stage provided.");
+            }
+            StageResult::Interrupted => {
+                if let Some(task) = task_name.as_ref() {
+                    if ctx.store.task_exists(task) {
+                        ctx.store.update_task(task, |task_state| {
+                            task_state.status = TaskStatus::Incomplete;
+                            task_state.updated_at = now_iso();
+                            Ok(())
+                        })?;
+                    }
+                }
+                return Ok(());
+            }
+            StageResult::NoFinish => {
+                if let Some(task) = task_name.as_ref() {
+                    if ctx.store.task_exists(task) {
+                        mark_task_failed(ctx, task, &stage)?;
+                    }
+                    bail!("Task '{}' exited without completing stage {}", task, stage);
+                } else {
+                    bail!("Interview ended without creating a task");
+                }
+            }
+        }
+    }
+}
+
+/// Claims `task` for a run. Taking over a claim whose holder died is recorded in
+/// the task history and, with `[claims] handover_note`, noted for the next session.
+fn claim_for_run(ctx: &CommandContext, task: &str) -> Result<Option<ClaimGuard>> {
+    let claim = ctx
+        .store
+        .claim_task(task, ctx.config.defaults.claim_ttl_seconds, &ctx.host)?;
+    if claim.is_some() {
+        ctx.store
+            .append_event(&Event::new(task, "claimed", ctx.actor(None)))?;
+    }
+    let Some(stolen) = claim.as_ref().and_then(|guard| guard.stolen_from.as_ref()) else {
+        return Ok(claim);
+    };
+    println!(
+        "Took over stale claim on '{}' from {} pid {}",
+        task, stolen.host, stolen.pid
+    );
+    let mut event = Event::new(task, "claim_stolen", ctx.actor(None));
+    event.note = Some(format!(
+        "from {} pid {}, claimed at {}",
+        stolen.host, stolen.pid, stolen.started_at
+    ));
+    ctx.store.append_event(&event)?;
+    if ctx.config.claims.handover_note && ctx.store.task_exists(task) {
+        let stolen_at = now_iso();
+        ctx.store.update_task(task, |task_state| {
+            let last_session = task_state
+                .last_session
+                .as_deref()
+                .map(|session| format!(" Its last session was {session}."))
+                .unwrap_or_default();
+            task_state.handover = Some(format!(
+                "## HANDOVER\n\nThe previous run of this task ({} pid {}, claimed at {}) died mid-flight during stage '{}'; its claim was taken over at {}.{} Check `git status` for partial, uncommitted work before continuing.",
+                stolen.host, stolen.pid, stolen.started_at, task_state.stage, stolen_at, last_session
+            ));
+            Ok(())
+        })?;
+    }
+    Ok(claim)
+}
+
+/// `mung run <task>`: runs the task's stages until it completes or a session ends
+/// without finishing. `once` stops after the current stage; `stage` runs that
+/// stage once instead.
+pub fn cmd_run(ctx: &CommandContext, task: &str, stage: Option<&str>, once: bool) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!(
+            "Task '{}' not found. Run 'mung queue {}' to add it first.",
+            task,
+            task
+        );
+    }
+    reconcile_running_tasks(ctx, ctx.dry_run)?;
+    if let Some(stage) = stage {
+        return run_single_stage(ctx, task, stage);
+    }
+    if ctx.dry_run {
+        let task_state = ctx.store.load_task(task)?;
+        if task_state.stage == "completed" {
+            println!("{}", msg("task_completed", &[("task", &task)]));
+            return Ok(());
+        }
+        return preview_stage(
+            ctx,
+            Some(task),
+            &task_state.stage,
+            None,
+            ReviewFinishMode::Queue,
+        );
+    }
+    let claim = claim_for_run(ctx, task)?;
+    let Some(_guard) = claim else {
+        bail!("{}", msg("task_claimed", &[("task", &task)]));
+    };
+
+    loop {
+        let task_state = ctx.store.load_task(task)?;
+        if task_state.stage == "completed" {
+            println!("{}", msg("task_completed", &[("task", &task)]));
+            return Ok(());
+        }
+        if task_state.awaiting_approval.is_some() {
+            if !wait_for_approval(ctx, task)? {
+                return Ok(());
+            }
+            continue;
+        }
+        ensure_not_blocked(&task_state)?;
+
+        if task_state.held {
+            release_hold(ctx, task)?;
+            println!("{}", msg("task_activated", &[("task", &task)]));
+        }
+
+        ctx.store.update_task(task, |task_state| {
+            // Preserve Issues status so issue injection works in run_stage
+            if task_state.status != TaskStatus::Issues {
+                task_state.status = TaskStatus::Running;
+            }
+            task_state.updated_at = now_iso();
+            Ok(())
+        })?;
+
+        let result = run_stage(
+            ctx,
+            Some(task),
+            &task_state.stage,
+            None,
+            ReviewFinishMode::Queue,
+        )?;
+        match result {
+            StageResult::Finished(_) if once => {
+                let next = ctx.store.load_task(task)?;
+                println!(
+                    "Finished {} for '{}' (stage: {}). Run 'mung run {} --once' for the next stage.",
+                    task_state.stage,
+                    task,
+                    ctx.agent.stage_label(&next.stage),
+                    task
+                );
+                return Ok(());
+            }
+            StageResult::Finished(_) => continue,
+            StageResult::Interrupted => {
+                ctx.store.update_task(task, |task_state| {
+                    task_state.status = TaskStatus::Incomplete;
+                    task_state.updated_at = now_iso();
+                    Ok(())
+                })?;
+                return Ok(());
+            }
+            StageResult::NoFinish => {
+                ctx.store.update_task(task, |task_state| {
+                    task_state.status = TaskStatus::Incomplete;
+                    task_state.updated_at = now_iso();
+                    Ok(())
+                })?;
+                println!("Session ended. Run 'mung run {}' to continue.", task);
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// `mung run <task> --stage <stage>`: runs `stage` once whatever stage the task is
+/// at. The recorded stage only changes when the session finishes, and then as
+/// finishing `stage` would move it (re-running planning leaves the task in build).
+fn run_single_stage(ctx: &CommandContext, task: &str, stage: &str) -> Result<()> {
+    if stage == "completed" || !ctx.agent.stages().contains(&stage) {
+        bail!(
+            "Unknown stage: {} (use one of: {})",
+            stage,
+            ctx.agent
+                .stages()
+                .iter()
+                .filter(|stage| **stage != "completed")
+                .copied()
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    if ctx.dry_run {
+        return preview_stage(ctx, Some(task), stage, None, ReviewFinishMode::Queue);
+    }
+    let claim = claim_for_run(ctx, task)?;
+    let Some(_guard) = claim else {
+        bail!("{}", msg("task_claimed", &[("task", &task)]));
+    };
+    let task_state = ctx.store.load_task(task)?;
+    ensure_not_blocked(&task_state)?;
+    if let Some(pending) = task_state.awaiting_approval.as_ref() {
+        bail!(
+            "Task '{}' is awaiting approval to enter {}. Approve or reject it first.",
+            task,
+            pending.stage
+        );
+    }
+
+    ctx.store.update_task(task, |task_state| {
+        if task_state.status != TaskStatus::Issues {
+            task_state.status = TaskStatus::Running;
+        }
+        task_state.updated_at = now_iso();
+        Ok(())
+    })?;
+    let result = run_stage(ctx, Some(task), stage, None, ReviewFinishMode::Queue)?;
+    match result {
+        StageResult::Finished(_) => {
+            let task_state = ctx.store.load_task(task)?;
+            println!(
+                "Ran {} for '{}' (stage: {})",
+                stage,
+                task,
+                ctx.agent.stage_label(&task_state.stage)
+            );
+        }
+        StageResult::Interrupted | StageResult::NoFinish => {
+            ctx.store.update_task(task, |task_state| {
+                task_state.status = TaskStatus::Incomplete;
+                task_state.updated_at = now_iso();
+                Ok(())
+            })?;
+            if matches!(result, StageResult::NoFinish) {
+                println!(
+                    "Session ended before {} finished; the task stays at stage {}.",
+                    stage,
+                    ctx.agent.stage_label(&task_state.stage)
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `loop_limit` falls back to `[defaults] loop_limit`, then 4; 0 means 100.
+pub fn cmd_run_queue(
+    ctx: &CommandContext,
+    loop_limit: Option<usize>,
+    tags: &[String],
+) -> Result<()> {
+    let tasks = ctx.store.list_tasks();
+    if !tasks.iter().any(|task| has_tags(task, tags)) {
+        println!("{}", msg("no_tasks", &[]));
+        return Ok(());
+    }
+    if !ctx.dry_run {
+        let mut pruned = prune_expired_sessions(ctx, false)?;
+        pruned.extend(prune_expired_claims(ctx, false)?);
+        if !pruned.is_empty() {
+            println!(
+                "Pruned {} old session(s) and claim(s) (see [sessions] in .agents/mung.toml)",
+                pruned.len()
+            );
+        }
+    }
+    reconcile_running_tasks(ctx, ctx.dry_run)?;
+
+    let mut current_task: Option<String> = None;
+    let mut current_claim: Option<ClaimGuard> = None;
+    let mut review_loops = 0usize;
+    let loop_limit = loop_limit.or(ctx.config.defaults.loop_limit).unwrap_or(4);
+    let loop_limit = if loop_limit == 0 { 100 } else { loop_limit };
+
+    loop {
+        if let Some(task_name) = current_task.clone() {
+            if !ctx.store.task_exists(&task_name) {
+                current_task = None;
+                current_claim = None;
+                continue;
+            }
+            let task_state = ctx.store.load_task(&task_name)?;
+            if task_state.held || task_state.status == TaskStatus::Blocked {
+                current_task = None;
+                current_claim = None;
+                continue;
+            }
+            if task_state.stage == "completed" {
+                current_task = None;
+                current_claim = None;
+                continue;
+            }
+            if task_state.awaiting_approval.is_some() {
+                if !wait_for_approval(ctx, &task_state.task)? {
+                    if INTERRUPTED.load(Ordering::SeqCst) {
+                        return Ok(());
+                    }
+                    current_task = None;
+                    current_claim = None;
+                }
+                continue;
+            }
+            if !ctx
+                .agent
+                .queue_stages()
+                .contains(&task_state.stage.as_str())
+            {
+                println!(
+                    "Task '{}' moved to stage '{}' (not handled by run-queue).",
+                    task_state.task, task_state.stage
+                );
+                return Ok(());
+            }
+            if current_claim.is_none() {
+                let claim = claim_for_run(ctx, &task_state.task)?;
+                let Some(guard) = claim else {
+                    println!("{}", msg("task_claimed", &[("task", &task_state.task)]));
+                    return Ok(());
+                };
+                current_claim = Some(guard);
+            }
+
+            ctx.store.update_task(&task_name, |task_state| {
+                // Preserve Issues status so issue injection works in run_stage
+                if task_state.status != TaskStatus::Issues {
+                    task_state.status = TaskStatus::Running;
+                }
+                task_state.updated_at = now_iso();
+                Ok(())
+            })?;
+
+            let stage_name = task_state.stage.clone();
+            let result = run_stage(
+                ctx,
+                Some(&task_state.task),
+                &task_state.stage,
+                None,
+                ReviewFinishMode::Queue,
+            )?;
+            match result {
+                StageResult::Finished(_) => {
+                    if stage_name == "review" {
+                        let task_state = ctx.store.load_task(&task_name)?;
+                        if task_state.stage == "build" {
+                            review_loops += 1;
+                            if review_loops >= loop_limit {
+                                let reason =
+                                    format!("exceeded review/build loop limit ({loop_limit})");
+                                block_task(ctx, &task_state.task, &reason)?;
+                                println!("Task '{}' {}; blocking.", task_state.task, reason);
+                                current_task = None;
+                                current_claim = None;
+                                review_loops = 0;
+                                continue;
+                            }
+                        }
+                    }
+                    continue;
+                }
+                StageResult::Interrupted => {
+                    ctx.store.update_task(&task_name, |task_state| {
+                        task_state.status = TaskStatus::Incomplete;
                         task_state.updated_at = now_iso();
                         Ok(())
                     })?;
                     return Ok(());
                 }
-                StageResult::NoFinish => {
-                    update_task(&task_path, |task_state| {
-                        task_state.status = TaskStatus::Failed;
-                        task_state.updated_at = now_iso();
-                        Ok(())
-                    })?;
-                    return Ok(());
+                StageResult::NoFinish => {
+                    mark_task_failed(ctx, &task_state.task, &stage_name)?;
+                    return Ok(());
+                }
+            }
+        }
+
+        wake_snoozed_tasks(ctx)?;
+        report_escalations(&escalate_issue_priorities(ctx, false)?);
+        let urgent = breached_tasks(&check_issue_slas(ctx)?);
+        let tasks = with_tags(passed_over(ctx, ctx.store.list_tasks()), tags);
+        let Some(task_state) = next_eligible_task(ctx.agent, &ctx.config.queue, &tasks, &urgent)
+        else {
+            println!("{}", msg("queue_complete", &[]));
+            return Ok(());
+        };
+        if ctx.dry_run {
+            return preview_stage(
+                ctx,
+                Some(&task_state.task),
+                &task_state.stage,
+                None,
+                ReviewFinishMode::Queue,
+            );
+        }
+
+        let claim = claim_for_run(ctx, &task_state.task)?;
+        let Some(guard) = claim else {
+            // Another worker has it; the next pass sees its claim and skips it.
+            continue;
+        };
+        current_claim = Some(guard);
+        current_task = Some(task_state.task);
+        review_loops = 0;
+    }
+}
+
+pub fn cmd_run_next(ctx: &CommandContext, task: Option<&str>) -> Result<()> {
+    let tasks = ctx.store.list_tasks();
+    if tasks.is_empty() {
+        println!("{}", msg("no_tasks", &[]));
+        return Ok(());
+    }
+    reconcile_running_tasks(ctx, ctx.dry_run)?;
+
+    if let Some(task) = task {
+        validate_task_name(task)?;
+        if !ctx.store.task_exists(task) {
+            bail!("{}", msg("task_not_found", &[("task", &task)]));
+        }
+        let task_state = ctx.store.load_task(task)?;
+        if task_state.stage == "completed" {
+            println!("{}", msg("task_completed", &[("task", &task)]));
+            return Ok(());
+        }
+        if task_state.status == TaskStatus::Running {
+            bail!("{}", msg("task_running", &[("task", &task)]));
+        }
+        if let Some(pending) = task_state.awaiting_approval.as_ref() {
+            println!(
+                "Task '{}' is waiting for approval to enter {}. Run 'mung approve {}'.",
+                task, pending.stage, task
+            );
+            return Ok(());
+        }
+        ensure_not_blocked(&task_state)?;
+        if ctx.dry_run {
+            return preview_stage(
+                ctx,
+                Some(task),
+                &task_state.stage,
+                None,
+                ReviewFinishMode::Queue,
+            );
+        }
+        if task_state.held {
+            release_hold(ctx, task)?;
+            println!("{}", msg("task_activated", &[("task", &task)]));
+        }
+        ctx.store.update_task(task, |task_state| {
+            // Preserve Issues status so issue injection works in run_stage
+            if task_state.status != TaskStatus::Issues {
+                task_state.status = TaskStatus::Running;
+            }
+            task_state.updated_at = now_iso();
+            Ok(())
+        })?;
+
+        let result = run_stage(
+            ctx,
+            Some(task),
+            &task_state.stage,
+            None,
+            ReviewFinishMode::Queue,
+        )?;
+        match result {
+            StageResult::Finished(_) => {}
+            StageResult::Interrupted => {
+                ctx.store.update_task(task, |task_state| {
+                    task_state.status = TaskStatus::Incomplete;
+                    task_state.updated_at = now_iso();
+                    Ok(())
+                })?;
+            }
+            StageResult::NoFinish => {
+                mark_task_failed(ctx, task, &task_state.stage)?;
+            }
+        }
+        return Ok(());
+    }
+
+    wake_snoozed_tasks(ctx)?;
+    report_escalations(&escalate_issue_priorities(ctx, false)?);
+    let urgent = breached_tasks(&check_issue_slas(ctx)?);
+    let (task_state, _guard) = loop {
+        let tasks = passed_over(ctx, ctx.store.list_tasks());
+        let Some(task_state) = next_eligible_task(ctx.agent, &ctx.config.queue, &tasks, &urgent)
+        else {
+            println!("{}", msg("no_eligible_tasks", &[]));
+            return Ok(());
+        };
+        if ctx.dry_run {
+            return preview_stage(
+                ctx,
+                Some(&task_state.task),
+                &task_state.stage,
+                None,
+                ReviewFinishMode::Queue,
+            );
+        }
+        match claim_for_run(ctx, &task_state.task)? {
+            Some(guard) => break (task_state, guard),
+            None => {
+                println!("{}", msg("task_claimed", &[("task", &task_state.task)]));
+            }
+        }
+    };
+
+    ctx.store.update_task(&task_state.task, |task_state| {
+        // Preserve Issues status so issue injection works in run_stage
+        if task_state.status != TaskStatus::Issues {
+            task_state.status = TaskStatus::Running;
+        }
+        task_state.updated_at = now_iso();
+        Ok(())
+    })?;
+
+    let result = run_stage(
+        ctx,
+        Some(&task_state.task),
+        &task_state.stage,
+        None,
+        ReviewFinishMode::Queue,
+    )?;
+    match result {
+        StageResult::Finished(_) => {}
+        StageResult::Interrupted => {
+            ctx.store.update_task(&task_state.task, |task_state| {
+                task_state.status = TaskStatus::Incomplete;
+                task_state.updated_at = now_iso();
+                Ok(())
+            })?;
+        }
+        StageResult::NoFinish => {
+            mark_task_failed(ctx, &task_state.task, &task_state.stage)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Sent when `mung resume` is given no prompt of its own.
+const RESUME_PROMPT: &str = "You were interrupted. Continue the current stage where you left off, then finish the session as instructed earlier.";
+
+/// `mung resume <session>`: reopens a failed or interrupted session and continues
+/// the model's own conversation instead of starting the stage prompt over.
+pub fn cmd_resume(ctx: &CommandContext, session_id: &str, prompt: &[String]) -> Result<()> {
+    if session_id.is_empty() || session_id.contains('/') || session_id.starts_with('.') {
+        bail!("Invalid session id '{}'", session_id);
+    }
+    let session = ctx
+        .store
+        .load_session(session_id)
+        .with_context(|| format!("Session '{}' not found", session_id))?;
+    match session.status {
+        SessionStatus::Finished => bail!("Session '{}' already finished", session_id),
+        SessionStatus::Skipped => bail!("Session '{}' was skipped; nothing ran", session_id),
+        SessionStatus::Running if !crate::state::is_orphaned_session(&session, &ctx.host) => {
+            bail!("Session '{}' is still running", session_id)
+        }
+        _ => {}
+    }
+    let Some(model_session) = session.model_session.clone() else {
+        bail!(
+            "Session '{}' has no model conversation id to resume; run 'mung run-next' to restart the stage",
+            session_id
+        );
+    };
+    let model = match session.model.as_deref() {
+        Some(model) => Model::from_str(model)?,
+        None => ctx.model_choice.model,
+    };
+    let task = session.task.as_deref();
+    let mut _guard = None;
+    if let Some(task) = task {
+        let task_state = ctx.store.load_task(task)?;
+        if task_state.stage != session.stage {
+            bail!(
+                "Task '{}' has moved on to stage {} since session '{}' ({})",
+                task,
+                task_state.stage,
+                session_id,
+                session.stage
+            );
+        }
+        ensure_not_blocked(&task_state)?;
+        let Some(guard) = claim_for_run(ctx, task)? else {
+            bail!("{}", msg("task_claimed", &[("task", &task)]));
+        };
+        _guard = Some(guard);
+        ctx.store.update_task(task, |task_state| {
+            // Preserve Issues status so issue injection works in run_stage
+            if task_state.status != TaskStatus::Issues {
+                task_state.status = TaskStatus::Running;
+            }
+            task_state.updated_at = now_iso();
+            Ok(())
+        })?;
+    }
+    ctx.store.update_session(session_id, |session_state| {
+        session_state.status = SessionStatus::Running;
+        session_state.finished_at = None;
+        session_state.pid = std::process::id();
+        session_state.host = ctx.host.clone();
+        Ok(())
+    })?;
+    if let Some(task) = task {
+        let mut event = Event::new(task, "resumed", ctx.actor(None));
+        event.note = Some(format!("{} session {}", session.stage, session_id));
+        ctx.store.append_event(&event)?;
+    }
+
+    println!(
+        "Resuming session {} ({} {}) on {}",
+        session_id,
+        task.unwrap_or("-"),
+        session.stage,
+        model.as_str()
+    );
+    let _terminal_guard = TerminalGuard::capture();
+    let prompt = if prompt.is_empty() {
+        RESUME_PROMPT.to_string()
+    } else {
+        prompt.join(" ")
+    };
+    let mut command = model
+        .resume_command(ctx.config.custom_model.as_ref(), &model_session)?
+        .with_args(&model_args(ctx, model));
+    if headless(ctx) {
+        command = model.headless(command);
+    }
+    let workdir = stage_workdir(ctx, &session.stage, task)?;
+    let result = supervise_stage(
+        ctx,
+        command.build(&prompt),
+        session_id,
+        task,
+        &workdir,
+        false,
+    )?;
+    let Some(task) = task else {
+        return Ok(());
+    };
+    match result {
+        StageResult::Finished(_) => {}
+        StageResult::Interrupted => {
+            ctx.store.update_task(task, |task_state| {
+                task_state.status = TaskStatus::Incomplete;
+                task_state.updated_at = now_iso();
+                Ok(())
+            })?;
+        }
+        StageResult::NoFinish => mark_task_failed(ctx, task, &session.stage)?,
+    }
+    Ok(())
+}
+
+/// The body scaffold for `issue_type`: `issue-templates/<type>.md` from the prompt
+/// directory, falling back to the built-in one. Types without a template get none.
+fn issue_template(ctx: &CommandContext, issue_type: &IssueType) -> Option<String> {
+    let name = format!("issue-templates/{}.md", issue_type);
+    for root in prompt_roots(ctx) {
+        if let Ok(template) = read_text(&root.join(&name)) {
+            return Some(template);
+        }
+    }
+    ctx.agent.embedded_prompt(&name).map(str::to_string)
+}
+
+/// Headings (`## Repro`) of `template` that `body` doesn't have.
+fn missing_template_sections(template: &str, body: &str) -> Vec<String> {
+    let headings: HashSet<String> = body
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("## "))
+        .map(|heading| heading.trim().to_lowercase())
+        .collect();
+    template
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("## "))
+        .map(str::trim)
+        .filter(|heading| !headings.contains(&heading.to_lowercase()))
+        .map(str::to_string)
+        .collect()
+}
+
+fn cmd_issue_template(ctx: &CommandContext, issue_type: &str) -> Result<()> {
+    let issue_type = IssueType::from_str(issue_type)?;
+    let Some(template) = issue_template(ctx, &issue_type) else {
+        bail!("No template for {} issues", issue_type);
+    };
+    print!("{}", template);
+    Ok(())
+}
+
+fn mark_task_failed(ctx: &CommandContext, task: &str, stage: &str) -> Result<()> {
+    ctx.store.update_task(task, |task_state| {
+        task_state.status = TaskStatus::Failed;
+        task_state.updated_at = now_iso();
+        Ok(())
+    })?;
+    notify(
+        ctx,
+        NotifyEvent::Failed,
+        &format!("Task '{}' failed: {} ended without finishing", task, stage),
+    );
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_issue_add(
+    ctx: &CommandContext,
+    title: String,
+    task: Option<String>,
+    priority: Option<String>,
+    issue_type: Option<String>,
+    source: Option<String>,
+    file: Option<String>,
+    stage: Option<String>,
+    body: Option<String>,
+    stdin_body: bool,
+    labels: &[String],
+    attachments: &[PathBuf],
+) -> Result<()> {
+    if stdin_body && body.is_some() {
+        bail!("Use --body or --stdin-body, not both");
+    }
+    for path in attachments {
+        if !path.is_file() {
+            bail!("Attachment not found: {}", path.display());
+        }
+    }
+    if title.trim().is_empty() {
+        bail!("Issue title cannot be empty");
+    }
+    let body = if stdin_body {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        input
+    } else {
+        body.unwrap_or_default()
+    };
+    let priority = parse_priority(priority.as_deref())?.unwrap_or(IssuePriority::P2);
+    let issue_type = parse_issue_type(issue_type.as_deref())?.unwrap_or(IssueType::Build);
+    let template = issue_template(ctx, &issue_type);
+    let body = if body.trim().is_empty() {
+        template.map(|template| template.trim().to_string())
+    } else {
+        if let Some(template) = template.as_deref() {
+            let missing = missing_template_sections(template, &body);
+            if !missing.is_empty() {
+                eprintln!(
+                    "Warning: issue body is missing the {} template's sections: {} (see 'mung issue template {}')",
+                    issue_type,
+                    missing.join(", "),
+                    issue_type
+                );
+            }
+        }
+        Some(body.trim().to_string())
+    };
+    let source = parse_issue_source(source.as_deref())?.unwrap_or(IssueSource::Manual);
+    let labels = normalize_labels(labels)?;
+    let task = if let Some(task) = task {
+        validate_task_name(&task)?;
+        Some(task)
+    } else {
+        None
+    };
+
+    let mut issue = new_issue(
+        title,
+        IssueStatus::Open,
+        priority,
+        task.clone(),
+        issue_type.clone(),
+        source,
+        file,
+        body,
+    );
+    issue.labels = labels;
+    issue.attachments = attach_files(ctx, &issue.id, attachments)?;
+    let duplicates = match ctx.config.issues.dedupe {
+        DedupeMode::Off => Vec::new(),
+        DedupeMode::Warn | DedupeMode::Link => {
+            let open = open_issues(ctx)?;
+            similar_issues(&issue, &open, ISSUE_DUPLICATE_THRESHOLD)
+                .into_iter()
+                .filter_map(|(id, score)| {
+                    let original = open.iter().find(|other| other.id == id)?;
+                    Some((original.clone(), score))
+                })
+                .collect()
+        }
+    };
+    if ctx.config.issues.dedupe == DedupeMode::Link {
+        if let Some((original, _)) = duplicates.first() {
+            mark_duplicate(&mut issue, original);
+        }
+    }
+    ctx.store.save_issue(&issue)?;
+
+    println!("{}", msg("issue_created", &[("id", &issue.id)]));
+    if let Some(original) = issue.duplicate_of.as_deref() {
+        println!(
+            "Resolved as a duplicate of open issue {} (similarity {:.2})",
+            original, duplicates[0].1
+        );
+        return Ok(());
+    }
+    for (original, score) in &duplicates {
+        eprintln!(
+            "{} issue {} looks like open issue {} \"{}\" (similarity {:.2}); if it is the same problem, run 'mung issue dedupe --link'",
+            "Warning:".yellow(),
+            issue.id,
+            original.id,
+            original.title,
+            score
+        );
+    }
+
+    if let Some(task) = task {
+        if let Some(stage) = stage.as_deref() {
+            validate_issue_stage(ctx.agent, stage)?;
+        }
+        let default_stage = issue_default_stage(ctx.agent, &issue_type);
+        update_task_for_issue(
+            ctx.store.as_ref(),
+            &task,
+            stage.as_deref(),
+            default_stage.as_deref(),
+        )?;
+    }
+
+    if issue.priority == IssuePriority::P0 {
+        notify_p0_issues(ctx, &issue);
+    }
+    Ok(())
+}
+
+pub fn open_issues(ctx: &CommandContext) -> Result<Vec<Issue>> {
+    Ok(ctx
+        .store
+        .list_issues()?
+        .into_iter()
+        .filter(|issue| issue.status == IssueStatus::Open)
+        .collect())
+}
+
+/// Resolves `issue` as filed again for `original`.
+fn mark_duplicate(issue: &mut Issue, original: &Issue) {
+    issue.status = IssueStatus::Resolved;
+    issue.duplicate_of = Some(original.id.clone());
+    issue.updated_at = now_iso();
+    issue.body = Some(append_resolution(
+        issue.body.take(),
+        &format!("Duplicate of {} ({})", original.id, original.title),
+    ));
+}
+
+/// Lists open issues on the same task that look alike; `--link` resolves the newer
+/// issue of each pair as a duplicate of the older one.
+fn cmd_issue_dedupe(ctx: &CommandContext, threshold: f64, link: bool) -> Result<()> {
+    if !(0.0..=1.0).contains(&threshold) {
+        bail!("Threshold must be between 0 and 1");
+    }
+    let issues = open_issues(ctx)?;
+    let pairs = duplicate_issue_pairs(&issues, threshold);
+    if pairs.is_empty() {
+        println!("{}", "No likely duplicate issues".dimmed());
+        return Ok(());
+    }
+    let find = |id: &str| issues.iter().find(|issue| issue.id == id);
+    let mut linked: HashSet<String> = HashSet::new();
+    let mut touched_tasks = BTreeSet::new();
+    println!("{}", "Likely duplicate issues:".bold());
+    for (a, b, score) in pairs {
+        let (Some(a), Some(b)) = (find(&a), find(&b)) else {
+            continue;
+        };
+        let (original, duplicate) = if (&a.created_at, &a.id) <= (&b.created_at, &b.id) {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        println!(
+            "  {:.2} {} \"{}\" <- {} \"{}\"",
+            score, original.id, original.title, duplicate.id, duplicate.title
+        );
+        if !link || linked.contains(&original.id) || !linked.insert(duplicate.id.clone()) {
+            continue;
+        }
+        let mut duplicate = duplicate.clone();
+        mark_duplicate(&mut duplicate, original);
+        ctx.store.save_issue(&duplicate)?;
+        if let Some(task) = duplicate.task.clone() {
+            touched_tasks.insert(task);
+        }
+    }
+    for task in touched_tasks {
+        if ctx.store.task_exists(&task) {
+            sync_task_status_for_issues(ctx.store.as_ref(), &task)?;
+        }
+    }
+    if link {
+        println!("Resolved {} duplicate issue(s)", linked.len());
+    }
+    Ok(())
+}
+
+/// Notifies once a task's open P0 issues reach `[notifications] p0_threshold`.
+fn notify_p0_issues(ctx: &CommandContext, issue: &Issue) {
+    let Some(task) = issue.task.as_deref() else {
+        return;
+    };
+    let open_p0 = ctx
+        .store
+        .list_issues()
+        .map(|issues| {
+            issues
+                .iter()
+                .filter(|other| {
+                    other.status == IssueStatus::Open
+                        && other.priority == IssuePriority::P0
+                        && other.task.as_deref() == Some(task)
+                })
+                .count()
+        })
+        .unwrap_or(0);
+    if open_p0 >= ctx.config.notifications.p0_threshold {
+        notify(
+            ctx,
+            NotifyEvent::P0Issues,
+            &format!(
+                "Task '{}' has {} open P0 issue(s); latest: {}",
+                task, open_p0, issue.title
+            ),
+        );
+    }
+}
+
+fn cmd_issue_resolve(
+    ctx: &CommandContext,
+    id: &str,
+    resolution: Option<String>,
+    steps: Vec<String>,
+) -> Result<()> {
+    if !ctx.store.issue_exists(id) {
+        bail!("{}", msg("issue_not_found", &[("id", &id)]));
+    }
+    let steps = steps
+        .iter()
+        .map(|step| parse_step_id(step))
+        .collect::<Result<Vec<_>>>()?;
+    let mut issue = ctx.store.load_issue(id)?;
+    for step in steps {
+        if !issue.steps.contains(&step) {
+            issue.steps.push(step);
+        }
+    }
+    issue.status = IssueStatus::Resolved;
+    issue.updated_at = now_iso();
+    if let Some(resolution) = resolution.as_deref() {
+        issue.body = Some(append_resolution(issue.body.take(), resolution));
+    }
+    ctx.store.save_issue(&issue)?;
+
+    if let Some(task) = issue.task.as_ref() {
+        sync_task_status_for_issues(ctx.store.as_ref(), task)?;
+    }
+
+    println!("{}", msg("issue_resolved", &[("id", &id)]));
+    crate::jira::comment_on_resolve(ctx, &issue, resolution.as_deref());
+    Ok(())
+}
+
+fn cmd_issue_comment(
+    ctx: &CommandContext,
+    id: &str,
+    body: Option<String>,
+    stdin_body: bool,
+    by: Option<&str>,
+) -> Result<()> {
+    if !ctx.store.issue_exists(id) {
+        bail!("{}", msg("issue_not_found", &[("id", &id)]));
+    }
+    if stdin_body && body.is_some() {
+        bail!("Use --body or --stdin-body, not both");
+    }
+    let comment = if stdin_body {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        input
+    } else {
+        body.unwrap_or_default()
+    };
+    if comment.trim().is_empty() {
+        bail!("Comment is empty (pass --body or --stdin-body)");
+    }
+    let actor = ctx.actor(by);
+    let author = match actor.session.as_deref() {
+        Some(session) => format!("{} (session {})", actor.user, session),
+        None => actor.user,
+    };
+    let mut issue = ctx.store.load_issue(id)?;
+    issue.updated_at = now_iso();
+    issue.body = Some(append_comment(
+        issue.body.take(),
+        &author,
+        &issue.updated_at,
+        &comment,
+    ));
+    ctx.store.save_issue(&issue)?;
+    println!("Commented on issue {}", id);
+    Ok(())
+}
+
+fn cmd_issue_assign(
+    ctx: &CommandContext,
+    id: &str,
+    task: &str,
+    stage: Option<String>,
+) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.issue_exists(id) {
+        bail!("{}", msg("issue_not_found", &[("id", &id)]));
+    }
+    let mut issue = ctx.store.load_issue(id)?;
+    issue.task = Some(task.to_string());
+    issue.updated_at = now_iso();
+    ctx.store.save_issue(&issue)?;
+
+    if issue.status == IssueStatus::Resolved {
+        println!("Assigned resolved issue {} to {}", id, task);
+        return Ok(());
+    }
+
+    if let Some(stage) = stage.as_deref() {
+        validate_issue_stage(ctx.agent, stage)?;
+    }
+    let default_stage = issue_default_stage(ctx.agent, &issue.issue_type);
+    update_task_for_issue(
+        ctx.store.as_ref(),
+        task,
+        stage.as_deref(),
+        default_stage.as_deref(),
+    )?;
+    println!("Assigned issue {} to {}", id, task);
+    Ok(())
+}
+
+const ISSUE_EXPORT_COLUMNS: [&str; 17] = [
+    "id",
+    "title",
+    "status",
+    "priority",
+    "task",
+    "type",
+    "source",
+    "created_at",
+    "updated_at",
+    "file",
+    "steps",
+    "labels",
+    "attachments",
+    "escalated_at",
+    "duplicate_of",
+    "origin",
+    "body",
+];
+
+/// `mung issue export`: every field of the matching issues on stdout, as a JSON
+/// array or as CSV with one row per issue. Read-only: no escalation or expiry runs.
+fn cmd_issue_export(
+    ctx: &CommandContext,
+    format: &str,
+    status: Option<&str>,
+    include_archived: bool,
+) -> Result<()> {
+    let csv = match format.trim().to_lowercase().as_str() {
+        "json" => false,
+        "csv" => true,
+        other => bail!("Invalid export format: {} (use json or csv)", other),
+    };
+    let mut issues = ctx.store.list_issues()?;
+    if include_archived {
+        issues.extend(ctx.store.list_archived_issues()?);
+    }
+    let filter = IssueFilter {
+        status: parse_status_filter(status)?,
+        task: None,
+        unassigned: false,
+        issue_type: None,
+        priority: None,
+        source: None,
+        labels: Vec::new(),
+    };
+    let mut issues = filter_issues(issues, &filter);
+    issues.sort_by(|a, b| (&a.created_at, &a.id).cmp(&(&b.created_at, &b.id)));
+    if !csv {
+        return print_json(&issues);
+    }
+
+    let mut out = std::io::stdout().lock();
+    writeln!(out, "{}", ISSUE_EXPORT_COLUMNS.join(","))?;
+    for issue in &issues {
+        let steps = issue
+            .steps
+            .iter()
+            .map(|id| format!("T{id}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let fields = [
+            issue.id.clone(),
+            issue.title.clone(),
+            issue.status.to_string(),
+            issue.priority.to_string(),
+            issue.task.clone().unwrap_or_default(),
+            issue.issue_type.to_string(),
+            issue.source.to_string(),
+            issue.created_at.clone(),
+            issue.updated_at.clone(),
+            issue.file.clone().unwrap_or_default(),
+            steps,
+            issue.labels.join(", "),
+            issue.attachments.join(", "),
+            issue.escalated_at.clone().unwrap_or_default(),
+            issue.duplicate_of.clone().unwrap_or_default(),
+            issue.origin.clone().unwrap_or_default(),
+            issue.body.clone().unwrap_or_default(),
+        ];
+        let row = fields
+            .iter()
+            .map(|field| csv_field(field))
+            .collect::<Vec<_>>()
+            .join(",");
+        writeln!(out, "{}", row)?;
+    }
+    Ok(())
+}
+
+/// Quotes a CSV field when it holds a comma, quote, or line break (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Copies `paths` into the issue's attachments directory, returning the stored
+/// file names. A name already taken gets a `-2`, `-3`, ... suffix.
+fn attach_files(ctx: &CommandContext, issue_id: &str, paths: &[PathBuf]) -> Result<Vec<String>> {
+    let dir = issue_attachments_dir(&ctx.agent_root, issue_id);
+    let mut names: Vec<String> = Vec::new();
+    for path in paths {
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            bail!("Attachment has no usable file name: {}", path.display());
+        };
+        // The frontmatter list is comma-separated.
+        let file_name = file_name.replace(',', "_");
+        let (stem, ext) = match file_name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem.to_string(), format!(".{ext}")),
+            _ => (file_name.clone(), String::new()),
+        };
+        let mut name = file_name.clone();
+        let mut n = 2;
+        while names.contains(&name) {
+            name = format!("{stem}-{n}{ext}");
+            n += 1;
+        }
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        fs::copy(path, dir.join(&name))
+            .with_context(|| format!("Failed to attach {}", path.display()))?;
+        names.push(name);
+    }
+    Ok(names)
+}
+
+fn cmd_issue_show(ctx: &CommandContext, id: &str) -> Result<()> {
+    // Print the file as written when there is one, hand edits included.
+    if let Some(path) = ctx.store.issue_file(id).filter(|path| path.exists()) {
+        let content = read_text(&path)?;
+        println!("{}", content);
+        if let Ok(issue) = parse_issue(&content) {
+            print_attachments(ctx, &issue);
+        }
+        return Ok(());
+    }
+    let issue = if ctx.store.issue_exists(id) {
+        ctx.store.load_issue(id)?
+    } else {
+        let archived = ctx.store.list_archived_issues()?;
+        match archived.into_iter().find(|issue| issue.id == id) {
+            Some(issue) => issue,
+            None => bail!("{}", msg("issue_not_found", &[("id", &id)])),
+        }
+    };
+    println!("{}", render_issue(&issue));
+    print_attachments(ctx, &issue);
+    Ok(())
+}
+
+fn print_attachments(ctx: &CommandContext, issue: &Issue) {
+    if issue.attachments.is_empty() {
+        return;
+    }
+    let dir = issue_attachments_dir(&ctx.agent_root, &issue.id);
+    println!();
+    println!("Attachments:");
+    for name in &issue.attachments {
+        let path = dir.join(name);
+        let shown = path.strip_prefix(&ctx.repo_root).unwrap_or(&path).display();
+        match fs::metadata(&path) {
+            Ok(meta) => println!("  {} ({} bytes)", shown, meta.len()),
+            Err(_) => println!("  {} (missing)", shown),
+        }
+    }
+}
+
+pub fn cmd_finish(
+    ctx: &CommandContext,
+    stage: Option<String>,
+    next_stage: Option<String>,
+    session_id: Option<String>,
+    task_arg: Option<String>,
+) -> Result<()> {
+    let stage = stage.unwrap_or_else(|| "task".to_string());
+    if !ctx.agent.valid_finish_stages().contains(&stage.as_str()) {
+        bail!("Unknown stage: {}", stage);
+    }
+
+    if let Some(ref next_stage) = next_stage {
+        if !ctx.agent.stages().contains(&next_stage.as_str()) {
+            bail!("Unknown next stage: {}", next_stage);
+        }
+    }
+
+    let session_id = crate::state::resolve_session_id(ctx.store.as_ref(), session_id)?;
+    let Ok(mut session) = ctx.store.load_session(&session_id) else {
+        bail!("Session not found: {}", session_id);
+    };
+
+    let task = task_arg
+        .or_else(|| env_var("MUNG_TASK", "METAGENT_TASK"))
+        .or_else(|| session.task.clone());
+
+    let task = if stage != "task" {
+        if let Some(task) = task {
+            task
+        } else {
+            find_unique_task(ctx.store.as_ref(), &stage)?.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "MUNG_TASK (or METAGENT_TASK) not set and no unique task found for stage '{}'",
+                    stage
+                )
+            })?
+        }
+    } else {
+        task.unwrap_or_default()
+    };
+
+    let resolved_next = if let Some(next) = next_stage.clone() {
+        next
+    } else if stage == "task" {
+        "completed".to_string()
+    } else {
+        ctx.agent
+            .next_stage(&stage)
+            .map(|s| s.to_string())
+            .ok_or_else(|| anyhow::anyhow!("No next stage for {}", stage))?
+    };
+
+    session.status = SessionStatus::Finished;
+    session.finished_at = Some(now_iso());
+    session.next_stage = Some(resolved_next.clone());
+    if !task.is_empty() {
+        session.task = Some(task.clone());
+    }
+    ctx.store.save_session(&session)?;
+
+    let has_open_issues = if !task.is_empty() {
+        if ctx.agent == AgentKind::Code {
+            expire_advisory_issues(ctx)?;
+        }
+        task_has_open_issues(ctx.store.as_ref(), &task)?
+    } else {
+        false
+    };
+
+    // Don't allow moving to completed if there are open issues
+    let resolved_next = if has_open_issues && resolved_next == "completed" {
+        "build".to_string()
+    } else {
+        resolved_next
+    };
+
+    let escalate_stage = if stage == "review" && !task.is_empty() {
+        review_escalation(ctx, &task, &resolved_next)?
+    } else {
+        None
+    };
+
+    let reviewed_head = if stage == "review" {
+        head_commit(&stage_checkout(ctx, &stage, &task))
+    } else {
+        None
+    };
+    let reviewed_spec = if stage == "spec-review" && !task.is_empty() {
+        Some(spec_hashes(&task_dir(&ctx.agent_root, &task).join("spec")))
+    } else {
+        None
+    };
+
+    let gated = !task.is_empty() && ctx.config.approval.requires(&resolved_next);
+
+    if !task.is_empty() {
+        if !ctx.store.task_exists(&task) {
+            bail!("{}", msg("task_not_found", &[("task", &task)]));
+        }
+        ctx.store.update_task(&task, |task_state| {
+            if let Some(escalate) = escalate_stage.as_ref() {
+                if !task_state.escalated_stages.contains(escalate) {
+                    task_state.escalated_stages.push(escalate.clone());
+                }
+            }
+            if stage == "review" {
+                if let Some(head) = reviewed_head.as_ref() {
+                    task_state.reviewed_commit = Some(head.clone());
                 }
             }
+            if let Some(hashes) = reviewed_spec.as_ref() {
+                task_state.spec_hashes = hashes.clone();
+            }
+            task_state.updated_at = now_iso();
+            task_state.last_session = Some(session_id.clone());
+            let status = determine_next_status(
+                &stage,
+                next_stage.is_some(),
+                &resolved_next,
+                has_open_issues,
+            );
+            if gated {
+                task_state.status = TaskStatus::Pending;
+                task_state.awaiting_approval = Some(PendingApproval {
+                    stage: resolved_next.clone(),
+                    status,
+                    requested_at: now_iso(),
+                });
+            } else {
+                task_state.stage = resolved_next.clone();
+                task_state.status = status;
+            }
+            Ok(())
+        })?;
+        let mut event = Event::new(&task, "finished", ctx.actor(None));
+        event.from = Some(stage.clone());
+        event.to = Some(resolved_next.clone());
+        event.note = Some(format!("session {}", session_id));
+        ctx.store.append_event(&event)?;
+        if let Err(err) = snapshot_spec(&task_dir(&ctx.agent_root, &task), &stage) {
+            eprintln!(
+                "Warning: failed to snapshot the spec of '{}': {:#}",
+                task, err
+            );
         }
+        if ctx.config.commit.applies(&stage) && !ctx.dry_run {
+            commit_stage(ctx, &task, &stage, &resolved_next, &session_id)?;
+        }
+    }
 
-        let tasks = list_tasks(&ctx.agent_root);
-        let Some(task_state) = next_eligible_task(ctx.agent, &tasks) else {
-            println!("Queue processing complete.");
-            return Ok(());
-        };
-
-        let claim = claim_task(&ctx.agent_root, &task_state.task, 3600, &ctx.host)?;
-        let Some(guard) = claim else {
-            continue;
-        };
-        current_claim = Some(guard);
-        current_task = Some(task_state.task);
-        review_loops = 0;
+    if gated {
+        println!(
+            "Entering {} requires approval. Run 'mung approve {}' to continue.",
+            resolved_next, task
+        );
+        let mut event = Event::new(&task, "approval_requested", ctx.actor(None));
+        event.from = Some(stage.clone());
+        event.to = Some(resolved_next.clone());
+        ctx.store.append_event(&event)?;
+        notify_approval_needed(ctx, &task, &resolved_next);
+        return Ok(());
     }
+    println!("{}", msg("task_advanced", &[("stage", &resolved_next)]));
+    if !task.is_empty() {
+        crate::linear::stage_changed(ctx, &task);
+    }
+    if resolved_next == "completed" && !task.is_empty() {
+        notify(
+            ctx,
+            NotifyEvent::Completed,
+            &format!("Task '{}' completed", task),
+        );
+    }
+    Ok(())
 }
 
-pub fn cmd_run_next(ctx: &CommandContext, task: Option<&str>) -> Result<()> {
-    let tasks = list_tasks(&ctx.agent_root);
-    if tasks.is_empty() {
-        println!("No tasks");
-        return Ok(());
+/// `[commit] on_finish`: commits whatever the stage changed, so each session's work
+/// is its own commit. A failed commit is reported but doesn't undo the finish.
+fn commit_stage(
+    ctx: &CommandContext,
+    task: &str,
+    stage: &str,
+    next: &str,
+    session_id: &str,
+) -> Result<()> {
+    let message = ctx.config.commit.message(task, stage, next, session_id);
+    match commit_all(&stage_checkout(ctx, stage, task), &message) {
+        Ok(Some(commit)) => {
+            println!("Committed {} ({} {})", short_commit(&commit), task, stage);
+            let mut event = Event::new(task, "committed", ctx.actor(None));
+            event.from = Some(stage.to_string());
+            event.note = Some(commit);
+            ctx.store.append_event(&event)?;
+        }
+        Ok(None) => println!("Nothing to commit after {} of '{}'", stage, task),
+        Err(err) => eprintln!("Warning: commit after {} failed: {:#}", stage, err),
     }
-    reconcile_running_tasks(&ctx.agent_root)?;
+    Ok(())
+}
 
-    if let Some(task) = task {
+/// Staged diffs longer than this are cut short in the commit prompt.
+const COMMIT_DIFF_LIMIT: usize = 60_000;
+
+/// `mung commit`: asks the model for a commit message covering the staged changes,
+/// with the task's stage and plan for context, then commits with it once confirmed
+/// (`yes` skips the question; `print_only` never commits).
+pub fn cmd_commit(
+    ctx: &CommandContext,
+    task: Option<String>,
+    yes: bool,
+    print_only: bool,
+) -> Result<()> {
+    let task = task.or_else(|| env_var("MUNG_TASK", "METAGENT_TASK"));
+    if let Some(task) = task.as_deref() {
         validate_task_name(task)?;
-        let task_path = task_state_path(&ctx.agent_root, task);
-        if !task_path.exists() {
-            bail!("Task '{}' not found", task);
+        if !ctx.store.task_exists(task) {
+            bail!("{}", msg("task_not_found", &[("task", &task)]));
         }
-        let task_state = load_task(&task_path)?;
-        if task_state.stage == "completed" {
-            println!("Task '{}' completed.", task);
+    }
+    let Some(diff) = staged_diff(&ctx.repo_root) else {
+        bail!("Nothing is staged; run 'git add' first");
+    };
+    let prompt = commit_prompt(ctx, task.as_deref(), &diff)?;
+
+    let model = resolve_model(ctx, "commit", None, false)?;
+    if ctx.dry_run {
+        println!("Dry run: commit");
+        return print_dry_run(ctx, model, &ctx.repo_root, None, task.as_deref(), &prompt);
+    }
+    let model = available_model(ctx, model)?;
+    let mut child = model
+        .print_command(ctx.config.custom_model.as_ref())?
+        .with_args(&model_args(ctx, model))
+        .build(&prompt);
+    child
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .current_dir(&ctx.repo_root);
+    apply_process_env(&mut child, ctx, None, task.as_deref());
+    let output = child.output().context("Failed to start commit model")?;
+    if !output.status.success() {
+        bail!("Commit model exited with {}", output.status);
+    }
+    let message = clean_commit_message(&String::from_utf8_lossy(&output.stdout));
+    if message.is_empty() {
+        bail!("The model returned an empty commit message");
+    }
+    println!("{}", message);
+    if print_only {
+        return Ok(());
+    }
+    if !yes {
+        if !io::stdin().is_terminal() {
+            println!("Not committing without a terminal; pass --yes to commit.");
             return Ok(());
         }
-        if task_state.status == TaskStatus::Running {
-            bail!("Task '{}' is currently running", task);
-        }
-        if task_state.held {
-            update_task(&task_path, |task_state| {
-                task_state.held = false;
-                task_state.updated_at = now_iso();
-                Ok(())
-            })?;
-            println!("Activating held task '{}'", task);
+        if !confirm("\nCommit with this message? (y/N) ")? {
+            return Ok(());
         }
-        update_task(&task_path, |task_state| {
-            // Preserve Issues status so issue injection works in run_stage
-            if task_state.status != TaskStatus::Issues {
-                task_state.status = TaskStatus::Running;
-            }
-            task_state.updated_at = now_iso();
-            Ok(())
-        })?;
+    }
+    let commit = commit_staged(&ctx.repo_root, &message)?;
+    println!("Committed {}", short_commit(&commit));
+    if let Some(task) = task.as_deref() {
+        let mut event = Event::new(task, "committed", ctx.actor(None));
+        event.note = Some(commit);
+        ctx.store.append_event(&event)?;
+    }
+    Ok(())
+}
 
-        let result = run_stage(
-            ctx,
-            Some(task),
-            &task_state.stage,
-            None,
-            ReviewFinishMode::Queue,
-        )?;
-        match result {
-            StageResult::Finished(_) => {}
-            StageResult::Interrupted => {
-                update_task(&task_path, |task_state| {
-                    task_state.status = TaskStatus::Incomplete;
-                    task_state.updated_at = now_iso();
-                    Ok(())
-                })?;
-            }
-            StageResult::NoFinish => {
-                update_task(&task_path, |task_state| {
-                    task_state.status = TaskStatus::Failed;
-                    task_state.updated_at = now_iso();
-                    Ok(())
-                })?;
-            }
+fn commit_prompt(ctx: &CommandContext, task: Option<&str>, diff: &str) -> Result<String> {
+    let mut prompt = String::new();
+    if let Ok(how) = load_how_prompt(ctx, "commit") {
+        prompt.push_str(how.replace("{task}", task.unwrap_or("scope")).trim());
+        prompt.push_str("\n\n");
+    }
+    if let Some(task) = task {
+        let task_state = ctx.store.load_task(task)?;
+        prompt.push_str(&format!(
+            "## TASK\n\nTask: {} (stage: {})\n",
+            task, task_state.stage
+        ));
+        if let Some(description) = task_state.description.as_deref() {
+            prompt.push_str(&format!("Description: {}\n", description));
         }
-        return Ok(());
+        let plan_path = task_dir(&ctx.agent_root, task).join(plan_file_name(ctx.agent));
+        if let Ok(plan) = read_text(&plan_path) {
+            prompt.push_str(&format!("\n### Plan\n\n{}\n", plan.trim()));
+        }
+        prompt.push('\n');
+    }
+    prompt.push_str(&format!(
+        "## STAGED CHANGES\n\n```diff\n{}\n```\n\n",
+        truncate_diff(diff, COMMIT_DIFF_LIMIT)
+    ));
+    prompt.push_str(
+        "Write a conventional-commit message for exactly these staged changes. \
+         Reply with only the message: the subject line, a blank line, then the body. \
+         Do not run git or change any files.\n",
+    );
+    Ok(prompt)
+}
+
+/// `diff` cut to at most `limit` characters, saying so when it was cut.
+fn truncate_diff(diff: &str, limit: usize) -> String {
+    match diff.char_indices().nth(limit) {
+        Some((cut, _)) => format!("{}\n(diff truncated)", diff[..cut].trim_end()),
+        None => diff.trim_end().to_string(),
     }
+}
 
-    let tasks = list_tasks(&ctx.agent_root);
-    let Some(task_state) = next_eligible_task(ctx.agent, &tasks) else {
-        println!("No eligible tasks.");
-        return Ok(());
+/// The model's reply without surrounding whitespace or a wrapping code fence.
+fn clean_commit_message(reply: &str) -> String {
+    let reply = reply.trim();
+    let unfenced = reply
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(|inner| inner.split_once('\n').map_or("", |(_, body)| body));
+    unfenced.unwrap_or(reply).trim().to_string()
+}
+
+/// Applies a stage transition held at an `[approval]` gate.
+/// The approver's identity, the time, and `note` go to the event history.
+pub fn cmd_approve(
+    ctx: &CommandContext,
+    task: &str,
+    by: Option<&str>,
+    note: Option<&str>,
+) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    let mut approved = None;
+    let mut held = false;
+    ctx.store.update_task(task, |task_state| {
+        let Some(pending) = task_state.awaiting_approval.take() else {
+            return Ok(());
+        };
+        approved = Some((task_state.stage.clone(), pending.stage.clone()));
+        task_state.stage = pending.stage;
+        task_state.status = pending.status;
+        task_state.blocked_reason = None;
+        task_state.updated_at = now_iso();
+        held = task_state.held;
+        Ok(())
+    })?;
+    let Some((from, stage)) = approved else {
+        bail!("Task '{}' is not awaiting approval", task);
     };
+    let actor = ctx.actor(by);
+    let user = actor.user.clone();
+    let mut event = Event::new(task, "approved", actor);
+    event.from = Some(from);
+    event.to = Some(stage.clone());
+    event.note = note.map(str::to_string);
+    ctx.store.append_event(&event)?;
+    println!(
+        "Approved '{}' by {}: advanced stage to {}",
+        task, user, stage
+    );
+    crate::linear::stage_changed(ctx, task);
+    if stage == "completed" {
+        notify(
+            ctx,
+            NotifyEvent::Completed,
+            &format!("Task '{}' completed (approved by {})", task, user),
+        );
+    }
+    if held {
+        println!(
+            "Task is in the backlog; run 'mung activate {}' to queue it.",
+            task
+        );
+    }
+    Ok(())
+}
 
-    let claim = claim_task(&ctx.agent_root, &task_state.task, 3600, &ctx.host)?;
-    let Some(_guard) = claim else {
-        println!("Task '{}' is already claimed.", task_state.task);
+/// The task's branch point is the repo HEAD when its first stage runs; reviews diff
+/// from there, or from the HEAD at the last finished review.
+fn record_base_commit(ctx: &CommandContext, task: &str) -> Result<()> {
+    let Some(head) = head_commit(&ctx.repo_root) else {
         return Ok(());
     };
-
-    let task_path = task_state_path(&ctx.agent_root, &task_state.task);
-    update_task(&task_path, |task_state| {
-        // Preserve Issues status so issue injection works in run_stage
-        if task_state.status != TaskStatus::Issues {
-            task_state.status = TaskStatus::Running;
+    ctx.store.update_task(task, |task_state| {
+        if task_state.base_commit.is_none() {
+            task_state.base_commit = Some(head.clone());
         }
-        task_state.updated_at = now_iso();
         Ok(())
     })?;
-
-    let result = run_stage(
-        ctx,
-        Some(&task_state.task),
-        &task_state.stage,
-        None,
-        ReviewFinishMode::Queue,
-    )?;
-    match result {
-        StageResult::Finished(_) => {}
-        StageResult::Interrupted => {
-            update_task(&task_path, |task_state| {
-                task_state.status = TaskStatus::Incomplete;
-                task_state.updated_at = now_iso();
-                Ok(())
-            })?;
-        }
-        StageResult::NoFinish => {
-            update_task(&task_path, |task_state| {
-                task_state.status = TaskStatus::Failed;
-                task_state.updated_at = now_iso();
-                Ok(())
-            })?;
-        }
-    }
-
     Ok(())
 }
 
-fn cmd_issue_add(
-    ctx: &CommandContext,
-    title: String,
-    task: Option<String>,
-    priority: Option<String>,
-    issue_type: Option<String>,
-    source: Option<String>,
-    file: Option<String>,
-    stage: Option<String>,
-    body: Option<String>,
-    stdin_body: bool,
-) -> Result<()> {
-    if stdin_body && body.is_some() {
-        bail!("Use --body or --stdin-body, not both");
-    }
-    if title.trim().is_empty() {
-        bail!("Issue title cannot be empty");
-    }
-    let body = if stdin_body {
-        let mut input = String::new();
-        std::io::stdin().read_to_string(&mut input)?;
-        input
-    } else {
-        body.unwrap_or_default()
+fn review_change_summary(repo_root: &Path, task: &TaskState) -> String {
+    let (base, label) = match (task.reviewed_commit.as_ref(), task.base_commit.as_ref()) {
+        (Some(reviewed), _) => (reviewed, "last review"),
+        (None, Some(base)) => (base, "task start"),
+        (None, None) => return String::new(),
     };
-    let body = if body.trim().is_empty() {
-        None
-    } else {
-        Some(body.trim().to_string())
+    let Some(summary) = change_summary(repo_root, base) else {
+        return String::new();
     };
+    let mut output = format!(
+        "## CHANGE SUMMARY\n\nChanges since {} ({}):\n",
+        short_commit(&summary.base),
+        label
+    );
+    if summary.files.is_empty() {
+        output.push_str("No files changed.\n");
+        return output;
+    }
+    output.push_str(&format!("{}\n\n", summary.stat));
+    for (status, path) in &summary.files {
+        output.push_str(&format!("- {status} {path}\n"));
+    }
+    output.push_str("\nStart the review from these files, then widen scope only where needed.\n");
+    output
+}
 
-    let priority = parse_priority(priority.as_deref())?.unwrap_or(IssuePriority::P2);
-    let issue_type = parse_issue_type(issue_type.as_deref())?.unwrap_or(IssueType::Build);
-    let source = parse_issue_source(source.as_deref())?.unwrap_or(IssueSource::Manual);
-    let task = if let Some(task) = task {
-        validate_task_name(&task)?;
-        Some(task)
-    } else {
-        None
+/// Spec files changed since the last spec review, plus open spec issues, so a
+/// spec-review-issues session can skip sections that were already approved.
+fn spec_change_summary(ctx: &CommandContext, task: &TaskState) -> String {
+    if task.spec_hashes.is_empty() {
+        return String::new();
+    }
+    let current = spec_hashes(&task_dir(&ctx.agent_root, &task.task).join("spec"));
+    let changed = changed_spec_files(&task.spec_hashes, &current);
+    let spec_issues: Vec<Issue> = match ctx.store.list_issues() {
+        Ok(issues) => issues
+            .into_iter()
+            .filter(|issue| {
+                issue.status == IssueStatus::Open
+                    && issue.issue_type == IssueType::Spec
+                    && issue.task.as_deref() == Some(task.task.as_str())
+            })
+            .collect(),
+        Err(err) => {
+            eprintln!("Warning: failed to load issues: {}", err);
+            Vec::new()
+        }
     };
+    let all_files: Vec<String> = current.keys().cloned().collect();
+    let mut sections: Vec<(&str, String)> = changed.clone();
+    for issue in &spec_issues {
+        if let Some(file) = issue_spec_file(issue, &all_files) {
+            if !sections.iter().any(|(_, name)| name == file) {
+                sections.push(("issue", file.clone()));
+            }
+        }
+    }
 
-    let issue = new_issue(
-        title,
-        IssueStatus::Open,
-        priority,
-        task.clone(),
-        issue_type.clone(),
-        source,
-        file,
-        body,
+    let mut output = String::from("## CHANGED SPEC SECTIONS\n\n");
+    if changed.is_empty() {
+        output.push_str("No spec files changed since the last spec review.\n");
+    } else {
+        output.push_str("Spec files changed since the last spec review:\n");
+    }
+    for (status, file) in &sections {
+        output.push_str(&format!("- {status} spec/{file}\n"));
+    }
+    if !spec_issues.is_empty() {
+        output.push_str("\nOpen spec issues:\n");
+        for issue in &spec_issues {
+            let file = issue
+                .file
+                .as_deref()
+                .map(|file| format!(" ({file})"))
+                .unwrap_or_default();
+            output.push_str(&format!(
+                "- {} [{}] {}{}\n",
+                issue.id, issue.priority, issue.title, file
+            ));
+        }
+    }
+    output.push_str(
+        "\nStudy only the sections listed above; the other spec files passed spec review. Edit them only if an issue requires it.\n",
     );
-    let path = issue_path(&ctx.agent_root, &issue.id);
-    crate::issues::save_issue(&path, &issue)?;
+    output
+}
 
-    if let Some(task) = task {
-        if let Some(stage) = stage.as_deref() {
-            validate_issue_stage(ctx.agent, stage)?;
+/// Which spec files of an existing task are filled in and which are still the task
+/// template, so the spec session asks about gaps instead of starting over. Empty
+/// when nothing has been written yet.
+fn spec_progress_summary(ctx: &CommandContext, task: &TaskState) -> String {
+    let progress = spec_progress(&task_dir(&ctx.agent_root, &task.task).join("spec"));
+    if !progress.iter().any(|file| file.filled) {
+        return String::new();
+    }
+    let mut output = String::from("## SPEC PROGRESS\n\nFilled in:\n");
+    for file in progress.iter().filter(|file| file.filled) {
+        output.push_str(&format!("- spec/{}", file.file));
+        if !file.empty_sections.is_empty() {
+            output.push_str(&format!(
+                " (empty sections: {})",
+                file.empty_sections.join(", ")
+            ));
         }
-        let default_stage = issue_default_stage(ctx.agent, &issue_type);
-        update_task_for_issue(
-            &ctx.agent_root,
-            &task,
-            stage.as_deref(),
-            default_stage.as_deref(),
-        )?;
+        output.push('\n');
+    }
+    let templates: Vec<&SpecFileProgress> = progress.iter().filter(|file| !file.filled).collect();
+    if !templates.is_empty() {
+        output.push_str("\nStill template:\n");
+        for file in templates {
+            output.push_str(&format!("- spec/{}\n", file.file));
+        }
+    }
+    output.push_str("\nRead the filled-in files first and do not re-interview about them; focus questions and research on template files and empty sections.\n");
+    output
+}
+
+/// A review that sends work back with more open issues than the policy threshold
+/// escalates the stage it is sending the task to.
+fn review_escalation(ctx: &CommandContext, task: &str, next_stage: &str) -> Result<Option<String>> {
+    let Some(policy) = ctx.config.escalation.as_ref() else {
+        return Ok(None);
+    };
+    if !policy.applies_to(next_stage) || ctx.agent != AgentKind::Code {
+        return Ok(None);
+    }
+    let open = ctx
+        .store
+        .list_issues()?
+        .iter()
+        .filter(|issue| issue.status == IssueStatus::Open && issue.task.as_deref() == Some(task))
+        .count();
+    if open <= policy.review_issue_threshold {
+        return Ok(None);
     }
+    println!(
+        "Review found {} open issues (threshold {}); escalating '{}' to {}.",
+        open,
+        policy.review_issue_threshold,
+        next_stage,
+        policy.expensive_model()?.as_str()
+    );
+    Ok(Some(next_stage.to_string()))
+}
+
+/// Range diffs longer than this are left out of the review prompt; the file list stays.
+const REVIEW_DIFF_LIMIT: usize = 40_000;
 
-    println!("Created issue {}", issue.id);
+/// `range` (`--since <ref>` or `--diff <a..b>`) puts that diff in front of the
+/// reviewer, on top of the usual change summary.
+pub fn cmd_review(
+    ctx: &CommandContext,
+    task: &str,
+    focus: Option<String>,
+    changed_only: bool,
+    range: Option<&str>,
+) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    let mut focus_section = focus.map(|text| {
+        format!(
+            "## FOCUS AREA\n\nThe user has requested special attention to:\n> {text}\n\nPrioritize investigating this area first, then continue with full review."
+        )
+    });
+    if changed_only {
+        let task_state = ctx.store.load_task(task)?;
+        let checkout = stage_checkout(ctx, "review", task);
+        let files = task_changed_files(&checkout, task_state.base_commit.as_deref(), task);
+        if files.is_empty() {
+            eprintln!(
+                "Warning: no changed files found for '{}'; running a full review.",
+                task
+            );
+        } else {
+            let mut scope = String::from(
+                "## REVIEW SCOPE\n\nReview only these files touched by the task; do not open issues for code outside them:\n",
+            );
+            for file in &files {
+                scope.push_str(&format!("- {file}\n"));
+            }
+            focus_section = Some(match focus_section {
+                Some(focus) => format!("{focus}\n\n{scope}"),
+                None => scope,
+            });
+        }
+    }
+    if let Some(range) = range {
+        let checkout = stage_checkout(ctx, "review", task);
+        let scope = review_range_section(&checkout, range)?;
+        focus_section = Some(match focus_section {
+            Some(focus) => format!("{focus}\n\n{scope}"),
+            None => scope,
+        });
+    }
+    if ctx.dry_run {
+        return preview_stage(
+            ctx,
+            Some(task),
+            "review",
+            focus_section.as_deref(),
+            ReviewFinishMode::Manual,
+        );
+    }
+    run_stage(
+        ctx,
+        Some(task),
+        "review",
+        focus_section.as_deref(),
+        ReviewFinishMode::Manual,
+    )?;
     Ok(())
 }
 
-fn cmd_issue_resolve(ctx: &CommandContext, id: &str, resolution: Option<String>) -> Result<()> {
-    let path = issue_path(&ctx.agent_root, id);
-    if !path.exists() {
-        bail!("Issue '{}' not found (run `mung issues` to list IDs)", id);
+fn review_range_section(checkout: &Path, range: &str) -> Result<String> {
+    let Some(summary) = change_summary(checkout, range) else {
+        bail!(
+            "git could not diff '{}'; pass a commit, branch, or a..b range",
+            range
+        );
+    };
+    let mut section = format!("## REVIEW RANGE\n\nChanges in {}:\n", range);
+    if summary.files.is_empty() {
+        section.push_str("No files changed.\n");
+        return Ok(section);
     }
-    let mut issue = crate::issues::load_issue(&path)?;
-    issue.status = IssueStatus::Resolved;
-    issue.updated_at = now_iso();
-    if let Some(resolution) = resolution {
-        issue.body = Some(append_resolution(issue.body.take(), &resolution));
+    section.push_str(&format!("{}\n\n", summary.stat));
+    for (status, path) in &summary.files {
+        section.push_str(&format!("- {status} {path}\n"));
     }
-    crate::issues::save_issue(&path, &issue)?;
-
-    if let Some(task) = issue.task.as_ref() {
-        sync_task_status_for_issues(&ctx.agent_root, task)?;
+    let patch = diff_patch(checkout, range).unwrap_or_default();
+    if patch.chars().count() <= REVIEW_DIFF_LIMIT {
+        section.push_str(&format!("\n```diff\n{}\n```\n", patch.trim_end()));
     }
+    section.push_str(
+        "\nFocus the review on these changes; read surrounding code only as needed to judge them.\n",
+    );
+    Ok(section)
+}
 
-    println!("Resolved issue {}", id);
+pub fn cmd_spec_review(ctx: &CommandContext, task: &str) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    run_stage(
+        ctx,
+        Some(task),
+        "spec-review",
+        None,
+        ReviewFinishMode::Queue,
+    )?;
     Ok(())
 }
 
-fn cmd_issue_assign(
+/// `mung spec-diff <task>`: how the spec changed between the snapshots finished
+/// stages left in `spec-history/`. `from` and `to` pick the latest snapshot of a
+/// stage; the defaults are the first snapshot and the current spec.
+pub fn cmd_spec_diff(
     ctx: &CommandContext,
-    id: &str,
     task: &str,
-    stage: Option<String>,
+    from: Option<&str>,
+    to: Option<&str>,
 ) -> Result<()> {
     validate_task_name(task)?;
-    let path = issue_path(&ctx.agent_root, id);
-    if !path.exists() {
-        bail!("Issue '{}' not found (run `mung issues` to list IDs)", id);
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
     }
-    let mut issue = crate::issues::load_issue(&path)?;
-    issue.task = Some(task.to_string());
-    issue.updated_at = now_iso();
-    crate::issues::save_issue(&path, &issue)?;
-
-    if issue.status == IssueStatus::Resolved {
-        println!("Assigned resolved issue {} to {}", id, task);
-        return Ok(());
+    let dir = task_dir(&ctx.agent_root, task);
+    let snapshots = spec_snapshots(&dir);
+    if snapshots.is_empty() {
+        bail!(
+            "No spec snapshots for '{}' yet; one is taken each time a stage finishes",
+            task
+        );
     }
+    let pick = |stage: &str| {
+        snapshots
+            .iter()
+            .rev()
+            .find(|snapshot| snapshot.stage == stage)
+            .map(|snapshot| snapshot.name.clone())
+            .ok_or_else(|| {
+                anyhow::anyhow!("No spec snapshot from stage '{}' for '{}'", stage, task)
+            })
+    };
+    let from = match from {
+        Some(stage) => pick(stage)?,
+        None => snapshots[0].name.clone(),
+    };
+    let to = to.map(pick).transpose()?;
 
-    if let Some(stage) = stage.as_deref() {
-        validate_issue_stage(ctx.agent, stage)?;
+    println!("{}", "Spec snapshots:".bold());
+    for snapshot in &snapshots {
+        println!("  {}", snapshot.name);
+    }
+    let from_path = format!("{}/{}", SPEC_HISTORY_DIR, from);
+    let to_path = match to.as_deref() {
+        Some(name) => format!("{}/{}", SPEC_HISTORY_DIR, name),
+        None => "spec".to_string(),
+    };
+    println!(
+        "\n{} {} -> {}",
+        "Diff".bold(),
+        from,
+        to.as_deref().unwrap_or("current spec")
+    );
+    let diff = diff_dirs(&dir, &from_path, &to_path)?;
+    if diff.trim().is_empty() {
+        println!("No changes.");
+    } else {
+        print!("{}", diff);
     }
-    let default_stage = issue_default_stage(ctx.agent, &issue.issue_type);
-    update_task_for_issue(
-        &ctx.agent_root,
-        task,
-        stage.as_deref(),
-        default_stage.as_deref(),
-    )?;
-    println!("Assigned issue {} to {}", id, task);
     Ok(())
 }
 
-fn cmd_issue_show(ctx: &CommandContext, id: &str) -> Result<()> {
-    let path = issue_path(&ctx.agent_root, id);
-    if !path.exists() {
-        bail!("Issue '{}' not found (run `mung issues` to list IDs)", id);
+pub fn cmd_research(ctx: &CommandContext, task: &str, focus: Option<String>) -> Result<()> {
+    ensure_code_agent(ctx)?;
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+
+    let prompt = load_prompt_by_name(ctx, "RESEARCH_PROMPT.md")?;
+    let repo_root_str = ctx.repo_root.display().to_string();
+    let focus_section = focus.map(|text| {
+        format!(
+            "## FOCUS AREA\n\nFocus on the following:\n> {text}\n\nPrioritize this area first, then continue with full research."
+        )
+    });
+    let context = PromptContext {
+        repo_root: &repo_root_str,
+        task: Some(task),
+        session: None,
+        issues_header: "",
+        issues_mode: "",
+        review_finish_instructions: "",
+        parallelism_mode: "",
+        focus_section: focus_section.as_deref().unwrap_or(""),
+        change_summary: "",
+    };
+    let rendered = render_prompt(&prompt, &context);
+
+    let _terminal_guard = TerminalGuard::capture();
+    let model = available_model(ctx, resolve_model(ctx, "build", None, false)?)?;
+    let mut child = model
+        .command(ctx.config.custom_model.as_ref())?
+        .with_args(&model_args(ctx, model))
+        .build(&rendered);
+    child
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .current_dir(&ctx.repo_root);
+    apply_process_env(&mut child, ctx, None, Some(task));
+    let status = child.status().context("Failed to start research model")?;
+
+    if !status.success() {
+        bail!("Research command failed");
     }
-    let content = read_text(&path)?;
-    println!("{}", content);
     Ok(())
 }
 
-pub fn cmd_finish(
-    ctx: &CommandContext,
-    stage: Option<String>,
-    next_stage: Option<String>,
-    session_id: Option<String>,
-    task_arg: Option<String>,
-) -> Result<()> {
-    let stage = stage.unwrap_or_else(|| "task".to_string());
-    if !ctx.agent.valid_finish_stages().contains(&stage.as_str()) {
-        bail!("Unknown stage: {}", stage);
+pub fn cmd_how(ctx: &CommandContext, topic: Option<&str>) -> Result<()> {
+    let topics = list_how_topics(ctx)?;
+    if topic.is_none() {
+        if topics.is_empty() {
+            println!("{}", "No how topics available".dimmed());
+        } else {
+            println!("{}", "How topics:".bold());
+            for topic in topics {
+                println!("  {topic}");
+            }
+        }
+        return Ok(());
     }
 
-    if let Some(ref next_stage) = next_stage {
-        if !ctx.agent.stages().contains(&next_stage.as_str()) {
-            bail!("Unknown next stage: {}", next_stage);
-        }
+    let normalized = normalize_how_topic(topic.unwrap());
+    if normalized.is_empty() {
+        bail!("Topic cannot be empty");
     }
 
-    let session_id = crate::state::resolve_session_id(&ctx.agent_root, session_id)?;
-    let session_path = crate::util::session_state_path(&ctx.agent_root, &session_id);
-    if !session_path.exists() {
-        bail!("Session not found: {}", session_id);
+    let content = load_how_prompt(ctx, &normalized)?;
+    println!("{content}");
+    Ok(())
+}
+
+pub fn format_duration(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else if seconds < 3600 {
+        format!("{}m{:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}h{:02}m", seconds / 3600, (seconds % 3600) / 60)
     }
+}
 
-    let mut session = load_session(&session_path)?;
+pub fn session_duration_seconds(session: &SessionState) -> Option<i64> {
+    let started = chrono::DateTime::parse_from_rfc3339(&session.started_at).ok()?;
+    let finished = match session.finished_at.as_deref() {
+        Some(finished) => chrono::DateTime::parse_from_rfc3339(finished)
+            .ok()?
+            .with_timezone(&chrono::Utc),
+        None => chrono::Utc::now(),
+    };
+    Some(finished.signed_duration_since(started).num_seconds())
+}
 
-    let task = task_arg
-        .or_else(|| env_var("MUNG_TASK", "METAGENT_TASK"))
-        .or_else(|| session.task.clone());
+pub fn session_outcome(ctx: &CommandContext, session: &SessionState) -> String {
+    match session.status {
+        SessionStatus::Running if crate::state::is_orphaned_session(session, &ctx.host) => {
+            "orphaned (run 'mung reconcile')".to_string()
+        }
+        SessionStatus::Running => "in progress".to_string(),
+        SessionStatus::Failed => "no finish".to_string(),
+        SessionStatus::Skipped => match session.next_stage.as_deref() {
+            Some(next) => format!("skipped -> {}", next),
+            None => "skipped".to_string(),
+        },
+        SessionStatus::Finished => match session.next_stage.as_deref() {
+            Some(next) => format!("-> {}", next),
+            None => "finished".to_string(),
+        },
+    }
+}
 
-    let task = if stage != "task" {
-        if let Some(task) = task {
-            task
-        } else {
-            find_unique_task(&ctx.agent_root, &stage)?.ok_or_else(|| {
-                anyhow::anyhow!(
-                    "MUNG_TASK (or METAGENT_TASK) not set and no unique task found for stage '{}'",
-                    stage
-                )
-            })?
+/// Makes `session_id` the task's `last_session`, linking the previous one behind it.
+fn link_session(ctx: &CommandContext, task: &str, session_id: &str) -> Result<()> {
+    let mut previous = None;
+    ctx.store.update_task(task, |task_state| {
+        previous = task_state.last_session.replace(session_id.to_string());
+        task_state.session_count += 1;
+        task_state.last_run_at = Some(now_iso());
+        Ok(())
+    })?;
+    ctx.store.update_session(session_id, |session_state| {
+        session_state.previous_session = previous;
+        Ok(())
+    })
+}
+
+/// A task's sessions, oldest first. Follows the `last_session` chain and falls back
+/// to scanning all sessions when there is no complete chain (tasks from before
+/// sessions were linked, or deleted session directories).
+pub fn task_sessions(ctx: &CommandContext, task_state: &TaskState) -> Vec<SessionState> {
+    let expected = task_state.session_count as usize;
+    let mut sessions = Vec::new();
+    let mut next = task_state.last_session.clone();
+    let mut complete = true;
+    while let Some(session_id) = next.take() {
+        let Ok(session) = ctx.store.load_session(&session_id) else {
+            complete = false;
+            break;
+        };
+        next = session.previous_session.clone();
+        sessions.push(session);
+        if sessions.len() > expected {
+            break;
         }
-    } else {
-        task.unwrap_or_default()
-    };
+    }
+    if expected == 0 || !complete || sessions.len() != expected {
+        sessions = scan_task_sessions(ctx, &task_state.task);
+    }
+    sessions.sort_by(|a, b| {
+        a.started_at
+            .cmp(&b.started_at)
+            .then_with(|| a.session_id.cmp(&b.session_id))
+    });
+    sessions
+}
 
-    let resolved_next = if let Some(next) = next_stage.clone() {
-        next
-    } else if stage == "task" {
-        "completed".to_string()
-    } else {
-        ctx.agent
-            .next_stage(&stage)
-            .map(|s| s.to_string())
-            .ok_or_else(|| anyhow::anyhow!("No next stage for {}", stage))?
-    };
+pub fn cmd_session(ctx: &CommandContext, command: SessionCommands) -> Result<()> {
+    match command {
+        SessionCommands::Last { task } => cmd_session_last(ctx, &task),
+        SessionCommands::List { task, status } => {
+            cmd_session_list(ctx, task.as_deref(), status.as_deref())
+        }
+        SessionCommands::Show { session } => cmd_session_show(ctx, &session),
+        SessionCommands::Clean {
+            older_than,
+            keep,
+            dry_run,
+        } => cmd_session_clean(ctx, older_than, keep, dry_run),
+    }
+}
 
-    session.status = SessionStatus::Finished;
-    session.finished_at = Some(now_iso());
-    session.next_stage = Some(resolved_next.clone());
-    if !task.is_empty() {
-        session.task = Some(task.clone());
+pub fn cmd_claims(ctx: &CommandContext, command: ClaimCommands) -> Result<()> {
+    match command {
+        ClaimCommands::List => cmd_claims_list(ctx),
     }
-    save_session(&session_path, &session)?;
+}
 
-    let has_open_issues = if !task.is_empty() {
-        task_has_open_issues(&ctx.agent_root, &task)?
-    } else {
-        false
+/// Every claim with its holder, age, last heartbeat, and whether it is live.
+fn cmd_claims_list(ctx: &CommandContext) -> Result<()> {
+    let now = chrono::Utc::now();
+    let seconds_since = |at: &str| {
+        chrono::DateTime::parse_from_rfc3339(at)
+            .ok()
+            .map(|at| now.signed_duration_since(at).num_seconds())
     };
+    let mut entries = Vec::new();
+    for task in ctx.store.list_claims() {
+        let live = ctx.store.has_active_claim(&task)?;
+        entries.push((task.clone(), ctx.store.read_claim(&task), live));
+    }
 
-    // Don't allow moving to completed if there are open issues
-    let resolved_next = if has_open_issues && resolved_next == "completed" {
-        "build".to_string()
-    } else {
-        resolved_next
-    };
+    if json_output() {
+        let entries: Vec<serde_json::Value> = entries
+            .iter()
+            .map(|(task, claim, live)| {
+                json!({
+                    "task": task,
+                    "claim": claim,
+                    "live": live,
+                    "age_seconds": claim.as_ref().and_then(|claim| seconds_since(&claim.started_at)),
+                })
+            })
+            .collect();
+        return print_json(&entries);
+    }
+    if entries.is_empty() {
+        println!("{}", "No claims".dimmed());
+        return Ok(());
+    }
+    for (task, claim, live) in &entries {
+        let state = if *live {
+            "live".green().to_string()
+        } else {
+            "stale".red().to_string()
+        };
+        let Some(claim) = claim else {
+            println!("{}  {}  {}", task, "(unreadable)".dimmed(), state);
+            continue;
+        };
+        let heartbeat = claim
+            .heartbeat_at
+            .as_deref()
+            .and_then(seconds_since)
+            .map(|seconds| format!(", heartbeat {} ago", format_duration(seconds)))
+            .unwrap_or_default();
+        println!(
+            "{}  pid {}@{}  {}{}  {}",
+            task,
+            claim.pid,
+            claim.host,
+            seconds_since(&claim.started_at)
+                .map(format_duration)
+                .unwrap_or_else(|| "?".to_string()),
+            heartbeat.dimmed(),
+            state
+        );
+    }
+    Ok(())
+}
 
-    if !task.is_empty() {
-        let task_path = task_state_path(&ctx.agent_root, &task);
-        if !task_path.exists() {
-            bail!("Task '{}' not found", task);
+/// Removes the claim on `task` once nobody holds it. `force` also drops a claim
+/// that still looks live, for a holder on a host that is gone, but never one held
+/// by a running process on this machine.
+pub fn cmd_unclaim(ctx: &CommandContext, task: &str, force: bool) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.list_claims().iter().any(|claim| claim == task) {
+        println!("No claim on '{}'", task);
+        return Ok(());
+    }
+    let claim = ctx.store.read_claim(task);
+    let holder = claim
+        .as_ref()
+        .map(|claim| format!("{} pid {}", claim.host, claim.pid))
+        .unwrap_or_else(|| "an unknown holder".to_string());
+    if !ctx.store.remove_stale_claim(task)? {
+        if !force {
+            bail!(
+                "Claim on '{}' is held by {}. Pass --force if that host is gone.",
+                task,
+                holder
+            );
         }
-        update_task(&task_path, |task_state| {
-            task_state.stage = resolved_next.clone();
-            task_state.updated_at = now_iso();
-            task_state.last_session = Some(session_id.clone());
-            task_state.status = determine_next_status(
-                &stage,
-                next_stage.is_some(),
-                &resolved_next,
-                has_open_issues,
+        if claim
+            .as_ref()
+            .is_some_and(|claim| claim.host == ctx.host && pid_alive(claim.pid))
+        {
+            bail!(
+                "Claim on '{}' is held by running process {} on this host; stop it first",
+                task,
+                holder
             );
-            Ok(())
-        })?;
+        }
+        ctx.store.release_claim(task)?;
     }
-
-    println!("Advanced stage to {}", resolved_next);
+    let mut event = Event::new(task, "unclaimed", ctx.actor(None));
+    event.note = Some(format!("released claim held by {}", holder));
+    ctx.store.append_event(&event)?;
+    println!("Released claim on '{}' (held by {})", task, holder);
     Ok(())
 }
 
-pub fn cmd_review(ctx: &CommandContext, task: &str, focus: Option<String>) -> Result<()> {
-    validate_task_name(task)?;
-    let task_path = task_state_path(&ctx.agent_root, task);
-    if !task_path.exists() {
-        bail!("Task '{}' not found", task);
-    }
-    let focus_section = focus.map(|text| {
-        format!(
-            "## FOCUS AREA\n\nThe user has requested special attention to:\n> {text}\n\nPrioritize investigating this area first, then continue with full review."
-        )
+/// Sessions with their status and age, newest first.
+fn cmd_session_list(ctx: &CommandContext, task: Option<&str>, status: Option<&str>) -> Result<()> {
+    let status = status
+        .map(|value| match value.trim().to_lowercase().as_str() {
+            "running" => Ok(SessionStatus::Running),
+            "finished" => Ok(SessionStatus::Finished),
+            "failed" => Ok(SessionStatus::Failed),
+            "skipped" => Ok(SessionStatus::Skipped),
+            other => Err(anyhow::anyhow!(
+                "Unknown session status '{}' (use running, finished, failed, or skipped)",
+                other
+            )),
+        })
+        .transpose()?;
+    let now = chrono::Utc::now();
+    let mut sessions: Vec<SessionState> = ctx
+        .store
+        .list_sessions()
+        .into_iter()
+        .filter(|session| task.is_none() || session.task.as_deref() == task)
+        .filter(|session| status.is_none() || status.as_ref() == Some(&session.status))
+        .collect();
+    sessions.sort_by(|a, b| {
+        b.started_at
+            .cmp(&a.started_at)
+            .then_with(|| b.session_id.cmp(&a.session_id))
     });
-    run_stage(
-        ctx,
-        Some(task),
-        "review",
-        focus_section.as_deref(),
-        ReviewFinishMode::Manual,
-    )?;
-    Ok(())
-}
 
-pub fn cmd_spec_review(ctx: &CommandContext, task: &str) -> Result<()> {
-    validate_task_name(task)?;
-    let task_path = task_state_path(&ctx.agent_root, task);
-    if !task_path.exists() {
-        bail!("Task '{}' not found", task);
+    if json_output() {
+        let mut entries = Vec::new();
+        for session in &sessions {
+            let mut value = serde_json::to_value(session)?;
+            value["age_seconds"] = json!(session_age_seconds(session, now));
+            entries.push(value);
+        }
+        return print_json(&entries);
+    }
+    if sessions.is_empty() {
+        println!("{}", "No sessions".dimmed());
+        return Ok(());
+    }
+    for session in &sessions {
+        let status = match session.status {
+            SessionStatus::Running => session.status.to_string().yellow().to_string(),
+            SessionStatus::Finished => session.status.to_string().green().to_string(),
+            SessionStatus::Failed => session.status.to_string().red().to_string(),
+            SessionStatus::Skipped => session.status.to_string().dimmed().to_string(),
+        };
+        println!(
+            "{}  {}  {}  {}  {}",
+            session.session_id,
+            session.task.as_deref().unwrap_or("-"),
+            session.stage,
+            status,
+            session_age_seconds(session, now)
+                .map(format_age)
+                .unwrap_or_else(|| "?".to_string())
+                .dimmed()
+        );
     }
-    run_stage(
-        ctx,
-        Some(task),
-        "spec-review",
-        None,
-        ReviewFinishMode::Queue,
-    )?;
     Ok(())
 }
 
-pub fn cmd_research(ctx: &CommandContext, task: &str, focus: Option<String>) -> Result<()> {
-    ensure_code_agent(ctx)?;
-    validate_task_name(task)?;
-    let task_path = task_state_path(&ctx.agent_root, task);
-    if !task_path.exists() {
-        bail!("Task '{}' not found", task);
+/// Prints the session's `session.json` as stored, or the record in that form when
+/// the state backend keeps no file.
+fn cmd_session_show(ctx: &CommandContext, session_id: &str) -> Result<()> {
+    if session_id.is_empty() || session_id.contains('/') || session_id.starts_with('.') {
+        bail!("Invalid session id '{}'", session_id);
     }
-
-    let prompt = load_prompt_by_name(ctx, "RESEARCH_PROMPT.md")?;
-    let repo_root_str = ctx.repo_root.display().to_string();
-    let focus_section = focus.map(|text| {
-        format!(
-            "## FOCUS AREA\n\nFocus on the following:\n> {text}\n\nPrioritize this area first, then continue with full research."
-        )
-    });
-    let context = PromptContext {
-        repo_root: &repo_root_str,
-        task: Some(task),
-        session: None,
-        issues_header: "",
-        issues_mode: "",
-        review_finish_instructions: "",
-        parallelism_mode: "",
-        focus_section: focus_section.as_deref().unwrap_or(""),
+    let path = session_state_path(&ctx.agent_root, session_id);
+    if path.exists() {
+        println!("{}", read_text(&path)?.trim_end());
+        return Ok(());
+    }
+    let Ok(session) = ctx.store.load_session(session_id) else {
+        bail!("Session '{}' not found", session_id);
     };
-    let rendered = render_prompt(&prompt, &context);
-
-    let _terminal_guard = TerminalGuard::capture();
-    let model = resolve_model(&ctx.model_choice, ctx.agent, "build", None);
-    let (cmd, args) = model.command();
-    let mut child = Command::new(cmd);
-    child
-        .args(args)
-        .arg(rendered)
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .current_dir(&ctx.repo_root);
-    apply_process_env(&mut child, ctx, None, Some(task));
-    let status = child.status().context("Failed to start research model")?;
+    println!("{}", serde_json::to_string_pretty(&session)?);
+    Ok(())
+}
 
-    if !status.success() {
-        bail!("Research command failed");
+fn cmd_session_clean(
+    ctx: &CommandContext,
+    older_than_days: u64,
+    keep: Option<usize>,
+    dry_run: bool,
+) -> Result<()> {
+    let keep = keep.unwrap_or(ctx.config.sessions.keep_per_task);
+    let pruned = prune_sessions(ctx, older_than_days, keep, dry_run)?;
+    if dry_run {
+        for session_id in &pruned {
+            println!("Would delete {}", session_id);
+        }
+        println!(
+            "{} session(s) older than {} day(s) would be deleted",
+            pruned.len(),
+            older_than_days
+        );
+    } else {
+        println!(
+            "Deleted {} session(s) older than {} day(s)",
+            pruned.len(),
+            older_than_days
+        );
     }
     Ok(())
 }
 
-pub fn cmd_how(ctx: &CommandContext, topic: Option<&str>) -> Result<()> {
-    let topics = list_how_topics(ctx)?;
-    if topic.is_none() {
-        if topics.is_empty() {
-            println!("{}", "No how topics available".dimmed());
-        } else {
-            println!("{}", "How topics:".bold());
-            for topic in topics {
-                println!("  {topic}");
+/// Finished and failed sessions that ended `days` or more ago, except the
+/// newest `keep` of each task and the one its `last_session` names (`session
+/// last` starts from it). Deleted unless `dry_run`; returns their ids.
+fn prune_sessions(
+    ctx: &CommandContext,
+    days: u64,
+    keep: usize,
+    dry_run: bool,
+) -> Result<Vec<String>> {
+    let now = chrono::Utc::now();
+    let mut kept: HashSet<String> = ctx
+        .store
+        .list_tasks()
+        .into_iter()
+        .filter_map(|task| task.last_session)
+        .collect();
+    let mut sessions = ctx.store.list_sessions();
+    sessions.sort_by(|a, b| {
+        b.started_at
+            .cmp(&a.started_at)
+            .then_with(|| b.session_id.cmp(&a.session_id))
+    });
+    let mut per_task: HashMap<String, usize> = HashMap::new();
+    for session in &sessions {
+        if let Some(task) = session.task.as_ref() {
+            let seen = per_task.entry(task.clone()).or_default();
+            if *seen < keep {
+                kept.insert(session.session_id.clone());
             }
+            *seen += 1;
+        }
+    }
+    let mut pruned: Vec<String> = sessions
+        .into_iter()
+        .filter(|session| session.status != SessionStatus::Running)
+        .filter(|session| !kept.contains(&session.session_id))
+        .filter(|session| {
+            session_age_seconds(session, now).is_some_and(|age| age >= days as i64 * 24 * 60 * 60)
+        })
+        .map(|session| session.session_id)
+        .collect();
+    pruned.sort();
+    if !dry_run {
+        for session_id in &pruned {
+            ctx.store.remove_session(session_id)?;
         }
-        return Ok(());
     }
+    Ok(pruned)
+}
 
-    let normalized = normalize_how_topic(topic.unwrap());
-    if normalized.is_empty() {
-        bail!("Topic cannot be empty");
+/// `[sessions] prune_after_days` for sessions. Returns one line per removal, as
+/// `reconcile` reports repairs.
+fn prune_expired_sessions(ctx: &CommandContext, dry_run: bool) -> Result<Vec<String>> {
+    let Some(days) = ctx.config.sessions.prune_after_days else {
+        return Ok(Vec::new());
+    };
+    Ok(
+        prune_sessions(ctx, days, ctx.config.sessions.keep_per_task, dry_run)?
+            .into_iter()
+            .map(|session| format!("session {}: older than {} day(s) -> deleted", session, days))
+            .collect(),
+    )
+}
+
+/// `[sessions] prune_after_days` for claim files nobody holds. `reconcile`
+/// removes every stale claim regardless of age; this is the queue-run version.
+fn prune_expired_claims(ctx: &CommandContext, dry_run: bool) -> Result<Vec<String>> {
+    let Some(days) = ctx.config.sessions.prune_after_days else {
+        return Ok(Vec::new());
+    };
+    let mut removed = Vec::new();
+    let max_age = Duration::from_secs(days * 24 * 60 * 60);
+    for claim in ctx.store.list_claims() {
+        let old = fs::metadata(claim_path(&ctx.agent_root, &claim))
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age >= max_age);
+        if !old || ctx.store.has_active_claim(&claim)? {
+            continue;
+        }
+        removed.push(format!(
+            "claim {}: older than {} day(s) -> removed",
+            claim, days
+        ));
+        if !dry_run {
+            ctx.store.remove_stale_claim(&claim)?;
+        }
     }
+    Ok(removed)
+}
 
-    let content = load_how_prompt(ctx, &normalized)?;
-    println!("{content}");
+/// Seconds since the session finished, or since it started if it never did.
+fn session_age_seconds(session: &SessionState, now: chrono::DateTime<chrono::Utc>) -> Option<i64> {
+    let since = session
+        .finished_at
+        .as_deref()
+        .unwrap_or(&session.started_at);
+    let since = chrono::DateTime::parse_from_rfc3339(since).ok()?;
+    Some(now.signed_duration_since(since).num_seconds())
+}
+
+fn format_age(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    if seconds < 3600 {
+        format!("{}m ago", seconds / 60)
+    } else if seconds < 86400 {
+        format!("{}h ago", seconds / 3600)
+    } else {
+        format!("{}d ago", seconds / 86400)
+    }
+}
+
+/// The task's most recent session, found through `last_session`.
+fn cmd_session_last(ctx: &CommandContext, task: &str) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    let task_state = ctx.store.load_task(task)?;
+    let Some(session_id) = task_state.last_session.as_deref() else {
+        bail!("Task '{}' has no sessions yet", task);
+    };
+    let session = ctx
+        .store
+        .load_session(session_id)
+        .with_context(|| format!("Session '{}' not found", session_id))?;
+    let dir = session_dir(&ctx.agent_root, session_id);
+
+    if json_output() {
+        let mut value = serde_json::to_value(&session)?;
+        value["duration_seconds"] = json!(session_duration_seconds(&session));
+        value["outcome"] = json!(session_outcome(ctx, &session));
+        value["dir"] = json!(dir.display().to_string());
+        return print_json(&value);
+    }
+    println!("{}", session.session_id.bold());
+    println!(
+        "  stage:    {}{}",
+        session.stage,
+        session
+            .model
+            .as_deref()
+            .map(|model| format!(" ({model})"))
+            .unwrap_or_default()
+    );
+    println!(
+        "  status:   {} ({})",
+        session.status,
+        session_outcome(ctx, &session)
+    );
+    println!(
+        "  started:  {} ({})",
+        session.started_at,
+        session_duration_seconds(&session)
+            .map(format_duration)
+            .unwrap_or_else(|| "?".to_string())
+    );
+    println!(
+        "  previous: {}",
+        session.previous_session.as_deref().unwrap_or("-")
+    );
+    println!("  dir:      {}", dir.display());
     Ok(())
 }
 
-fn build_task_history(agent_root: &Path, task: &str) -> Result<String> {
-    let sessions_dir = agent_root.join("sessions");
-    let entries = match fs::read_dir(&sessions_dir) {
-        Ok(entries) => entries,
-        Err(_) => return Ok(String::new()),
+pub fn scan_task_sessions(ctx: &CommandContext, task: &str) -> Vec<SessionState> {
+    ctx.store
+        .list_sessions()
+        .into_iter()
+        .filter(|session| session.task.as_deref() == Some(task))
+        .collect()
+}
+
+/// The model's working directory for a stage run; see `[workdirs]`.
+fn stage_workdir(ctx: &CommandContext, stage: &str, task: Option<&str>) -> Result<PathBuf> {
+    let (checkout, isolated) = match task {
+        Some(task) if ctx.config.worktrees.applies(stage) => (task_worktree(ctx, task)?, true),
+        _ => (ctx.repo_root.clone(), false),
     };
+    let dir = ctx.config.workdir(&checkout, stage, task);
+    // A dry run doesn't create the worktree it would use.
+    let pending_worktree = isolated && ctx.dry_run;
+    if !dir.is_dir() && !pending_worktree {
+        bail!(
+            "Working directory {} for stage '{}' does not exist (see [workdirs] in .agents/mung.toml)",
+            dir.display(),
+            stage
+        );
+    }
+    Ok(dir)
+}
 
-    let mut sessions = Vec::new();
-    for entry in entries.flatten() {
-        let path = entry.path().join("session.json");
-        if !path.exists() {
-            continue;
-        }
-        if let Ok(session) = load_session(&path) {
-            if session.task.as_deref() == Some(task) {
-                sessions.push((session.started_at, session.stage));
-            }
+fn worktree_path(ctx: &CommandContext, task: &str) -> PathBuf {
+    ctx.repo_root.join(".agents").join("worktrees").join(task)
+}
+
+/// The task's `[worktrees]` checkout, created on the task branch the first time an
+/// isolated stage runs. It starts from HEAD, so uncommitted spec or plan edits in
+/// the main checkout are not in it.
+fn task_worktree(ctx: &CommandContext, task: &str) -> Result<PathBuf> {
+    let path = worktree_path(ctx, task);
+    if path.is_dir() || ctx.dry_run {
+        return Ok(path);
+    }
+    let branch = format!("{}{}", ctx.config.branches.prefix, task);
+    add_worktree(&ctx.repo_root, &path, &branch)
+        .with_context(|| format!("Failed to create a worktree for '{}'", task))?;
+    println!("Created worktree {} on branch {}", path.display(), branch);
+    let task_files = task_dir(&ctx.agent_root, task);
+    if let Ok(relative) = task_files.strip_prefix(&ctx.repo_root) {
+        let relative = relative.display().to_string();
+        if !uncommitted_changes(&ctx.repo_root, &[relative.as_str()]).is_empty() {
+            eprintln!(
+                "Warning: uncommitted changes under {} are not in the worktree; commit them so the stage sees them.",
+                relative
+            );
         }
     }
-    if sessions.is_empty() {
-        return Ok(String::new());
+    Ok(path)
+}
+
+/// Where git work for `stage` of `task` happens: its worktree when the stage runs
+/// in one that exists, else the repo.
+fn stage_checkout(ctx: &CommandContext, stage: &str, task: &str) -> PathBuf {
+    let path = worktree_path(ctx, task);
+    if ctx.config.worktrees.applies(stage) && path.is_dir() {
+        path
+    } else {
+        ctx.repo_root.clone()
     }
-    sessions.sort_by(|a, b| a.0.cmp(&b.0));
+}
 
-    let mut parts: Vec<String> = Vec::new();
-    let mut current_stage = String::new();
-    let mut current_count = 0usize;
-    for (_, stage) in sessions {
-        if current_count == 0 {
-            current_stage = stage;
-            current_count = 1;
-            continue;
+/// `mung merge <task>`: brings the task branch from its `[worktrees]` worktree back
+/// into the current branch. Uncommitted work in the worktree is committed first.
+/// `rebase` replays the branch onto HEAD and fast-forwards instead of making a merge
+/// commit. Afterwards the worktree and branch are removed unless `keep` is set, so
+/// the next isolated stage starts fresh from the merged HEAD.
+pub fn cmd_merge(ctx: &CommandContext, task: &str, rebase: bool, keep: bool) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    let branch = format!("{}{}", ctx.config.branches.prefix, task);
+    if !local_branches(&ctx.repo_root).contains(&branch) {
+        bail!("Task '{}' has no branch {} to merge", task, branch);
+    }
+    if current_branch(&ctx.repo_root).as_deref() == Some(branch.as_str()) {
+        bail!(
+            "{} is checked out here; switch to the branch to merge into first",
+            branch
+        );
+    }
+    let worktree = worktree_path(ctx, task);
+    let has_worktree = worktree.is_dir();
+    if has_worktree {
+        let message = format!("mung: {} work in progress", task);
+        if let Some(commit) = commit_all(&worktree, &message)? {
+            println!(
+                "Committed uncommitted worktree changes as {}",
+                short_commit(&commit)
+            );
         }
-        if stage == current_stage {
-            current_count += 1;
-        } else {
-            parts.push(format_stage_history(&current_stage, current_count));
-            current_stage = stage;
-            current_count = 1;
+    }
+    if rebase {
+        if !has_worktree {
+            bail!("--rebase needs the worktree at {}", worktree.display());
         }
+        let head = head_commit(&ctx.repo_root).context("Failed to read HEAD")?;
+        rebase_onto(&worktree, &head).with_context(|| {
+            format!(
+                "Rebase stopped; resolve it in {} (or 'git rebase --abort' there), then run 'mung merge {}' again",
+                worktree.display(),
+                task
+            )
+        })?;
+    }
+    let message = format!("Merge task '{}'", task);
+    merge_branch(&ctx.repo_root, &branch, &message, rebase).with_context(|| {
+        format!(
+            "Merge of {} stopped; resolve and commit it (or 'git merge --abort'), then run 'mung merge {}' again",
+            branch, task
+        )
+    })?;
+    let head = head_commit(&ctx.repo_root).unwrap_or_default();
+    println!(
+        "Merged {} into {} ({})",
+        branch,
+        current_branch(&ctx.repo_root).unwrap_or_default(),
+        short_commit(&head)
+    );
+    let mut event = Event::new(task, "merged", ctx.actor(None));
+    event.from = Some(branch.clone());
+    event.note = Some(head);
+    ctx.store.append_event(&event)?;
+    if keep {
+        return Ok(());
     }
-    if current_count > 0 {
-        parts.push(format_stage_history(&current_stage, current_count));
+    if has_worktree {
+        remove_worktree(&ctx.repo_root, &worktree)?;
+        println!("Removed worktree {}", worktree.display());
     }
-
-    Ok(parts.join("->"))
+    delete_branch(&ctx.repo_root, &branch)?;
+    println!("Deleted branch {}", branch);
+    Ok(())
 }
 
 fn apply_process_env(
@@ -1827,26 +6644,31 @@ fn apply_process_env(
     session_id: Option<&str>,
     task: Option<&str>,
 ) {
-    cmd.env("MUNG_AGENT", ctx.agent.name());
-    cmd.env("METAGENT_AGENT", ctx.agent.name());
-    cmd.env("MUNG_REPO_ROOT", ctx.repo_root.as_os_str());
-    cmd.env("METAGENT_REPO_ROOT", ctx.repo_root.as_os_str());
+    cmd.envs(process_env(ctx, session_id, task));
+}
+
+/// Variables a model process gets, under both the current and legacy prefix.
+fn process_env(
+    ctx: &CommandContext,
+    session_id: Option<&str>,
+    task: Option<&str>,
+) -> Vec<(&'static str, String)> {
+    let repo_root = ctx.repo_root.display().to_string();
+    let mut vars = vec![
+        ("MUNG_AGENT", ctx.agent.name().to_string()),
+        ("METAGENT_AGENT", ctx.agent.name().to_string()),
+        ("MUNG_REPO_ROOT", repo_root.clone()),
+        ("METAGENT_REPO_ROOT", repo_root),
+    ];
     if let Some(session_id) = session_id {
-        cmd.env("MUNG_SESSION", session_id);
-        cmd.env("METAGENT_SESSION", session_id);
+        vars.push(("MUNG_SESSION", session_id.to_string()));
+        vars.push(("METAGENT_SESSION", session_id.to_string()));
     }
     if let Some(task) = task {
-        cmd.env("MUNG_TASK", task);
-        cmd.env("METAGENT_TASK", task);
-    }
-}
-
-fn format_stage_history(stage: &str, count: usize) -> String {
-    if count > 1 {
-        format!("{stage}({count}x)")
-    } else {
-        stage.to_string()
+        vars.push(("MUNG_TASK", task.to_string()));
+        vars.push(("METAGENT_TASK", task.to_string()));
     }
+    vars
 }
 
 fn list_how_topics(ctx: &CommandContext) -> Result<Vec<String>> {
@@ -1896,11 +6718,9 @@ fn normalize_how_topic(raw: &str) -> String {
         if ch.is_ascii_alphanumeric() {
             out.push(ch);
             last_dash = false;
-        } else if ch == '-' || ch == '_' || ch.is_whitespace() {
-            if !last_dash && !out.is_empty() {
-                out.push('-');
-                last_dash = true;
-            }
+        } else if (ch == '-' || ch == '_' || ch.is_whitespace()) && !last_dash && !out.is_empty() {
+            out.push('-');
+            last_dash = true;
         }
     }
     if out.ends_with('-') {
@@ -1937,16 +6757,19 @@ pub fn cmd_set_stage(
     if !ctx.agent.stages().contains(&stage) {
         bail!("Unknown stage: {}", stage);
     }
-    let task_path = task_state_path(&ctx.agent_root, task);
-    if !task_path.exists() {
-        bail!("Task '{}' not found", task);
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
     }
 
     let resolved_status = if let Some(status) = status {
-        TaskStatus::from_str(&status)?
+        let status = TaskStatus::from_str(&status)?;
+        if status == TaskStatus::Blocked {
+            bail!("Use 'mung block {} --reason <why>' to block a task", task);
+        }
+        status
     } else {
         let has_open_issues = if ctx.agent == AgentKind::Code {
-            task_has_open_issues(&ctx.agent_root, task)?
+            task_has_open_issues(ctx.store.as_ref(), task)?
         } else {
             false
         };
@@ -1960,9 +6783,10 @@ pub fn cmd_set_stage(
     };
 
     let status_for_update = resolved_status.clone();
-    update_task(&task_path, |task_state| {
+    ctx.store.update_task(task, |task_state| {
         task_state.stage = stage.to_string();
         task_state.status = status_for_update;
+        task_state.blocked_reason = None;
         task_state.updated_at = now_iso();
         Ok(())
     })?;
@@ -1971,6 +6795,94 @@ pub fn cmd_set_stage(
         "Set '{}' to stage '{}' (status: {})",
         task, stage, resolved_status
     );
+    crate::linear::stage_changed(ctx, task);
+    Ok(())
+}
+
+/// `mung skip <task>`: moves the task to its next stage without running a model,
+/// recording a `skipped` session so the task's history still shows the stage.
+pub fn cmd_skip(ctx: &CommandContext, task: &str, reason: Option<&str>) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    let task_state = ctx.store.load_task(task)?;
+    let stage = task_state.stage.clone();
+    let Some(next) = ctx.agent.next_stage(&stage) else {
+        bail!("Task '{}' is {}; there is no stage to skip", task, stage);
+    };
+    if task_state.status == TaskStatus::Running {
+        bail!("Task '{}' is running. Finish it before skipping.", task);
+    }
+    ensure_not_blocked(&task_state)?;
+    if task_state.awaiting_approval.is_some() {
+        bail!(
+            "Task '{}' is awaiting approval. Approve or reject it first.",
+            task
+        );
+    }
+    let has_open_issues =
+        ctx.agent == AgentKind::Code && task_has_open_issues(ctx.store.as_ref(), task)?;
+    if has_open_issues && next == "completed" {
+        bail!(
+            "Task '{}' has open issues; resolve them before skipping to completed",
+            task
+        );
+    }
+
+    let session_id = crate::state::new_session_id(&ctx.agent_root);
+    create_session(
+        ctx.store.as_ref(),
+        &ctx.agent_root,
+        &session_id,
+        ctx.agent.name(),
+        &stage,
+        Some(task),
+        &ctx.repo_root,
+        &ctx.host,
+        "none",
+    )?;
+    ctx.store.update_session(&session_id, |session| {
+        session.status = SessionStatus::Skipped;
+        session.finished_at = Some(now_iso());
+        session.next_stage = Some(next.to_string());
+        session.model = None;
+        Ok(())
+    })?;
+    link_session(ctx, task, &session_id)?;
+    ctx.store.update_task(task, |task_state| {
+        task_state.stage = next.to_string();
+        task_state.status = if has_open_issues {
+            TaskStatus::Issues
+        } else if next == "completed" {
+            TaskStatus::Completed
+        } else {
+            TaskStatus::Pending
+        };
+        task_state.updated_at = now_iso();
+        Ok(())
+    })?;
+    let mut event = Event::new(task, "skipped", ctx.actor(None));
+    event.from = Some(stage.clone());
+    event.to = Some(next.to_string());
+    event.note = reason
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty())
+        .map(str::to_string);
+    ctx.store.append_event(&event)?;
+
+    println!(
+        "{}",
+        msg(
+            "stage_skipped",
+            &[
+                ("stage", &ctx.agent.stage_label(&stage)),
+                ("task", &task),
+                ("next", &ctx.agent.stage_label(next)),
+            ]
+        )
+    );
+    crate::linear::stage_changed(ctx, task);
     Ok(())
 }
 
@@ -2009,6 +6921,7 @@ pub fn cmd_debug(
         review_finish_instructions: "",
         parallelism_mode: &parallelism_mode,
         focus_section: "",
+        change_summary: "",
     };
     let mut rendered = render_prompt(&prompt, &context);
     if !bug_text.trim().is_empty() {
@@ -2016,11 +6929,22 @@ pub fn cmd_debug(
         rendered = format!("{bug_block}{rendered}");
     }
 
-    let (cmd, args) = Model::Codex.command();
-    let mut child = Command::new(cmd);
+    // Debugging runs on codex unless a custom backend was picked explicitly.
+    let model = if ctx.model_choice.model == Model::Custom {
+        Model::Custom
+    } else {
+        Model::Codex
+    };
+    if ctx.dry_run {
+        println!("Dry run: debug");
+        return print_dry_run(ctx, model, &ctx.repo_root, None, None, &rendered);
+    }
+    let model = available_model(ctx, model)?;
+    let mut child = model
+        .command(ctx.config.custom_model.as_ref())?
+        .with_args(&model_args(ctx, model))
+        .build(&rendered);
     child
-        .args(args)
-        .arg(rendered)
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
@@ -2028,32 +6952,122 @@ pub fn cmd_debug(
     apply_process_env(&mut child, ctx, None, None);
     let status = child.status().context("Failed to start debug model")?;
 
-    if !status.success() {
-        bail!("Debug command failed");
+    if !status.success() {
+        bail!("Debug command failed");
+    }
+    Ok(())
+}
+
+fn run_stage(
+    ctx: &CommandContext,
+    task: Option<&str>,
+    stage: &str,
+    focus_section: Option<&str>,
+    review_mode: ReviewFinishMode,
+) -> Result<StageResult> {
+    let mut result = run_stage_once(ctx, task, stage, focus_section, review_mode)?;
+    if !matches!(result, StageResult::NoFinish) {
+        return Ok(result);
+    }
+    let Some(task_name) = task else {
+        return Ok(result);
+    };
+    if escalate_task_stage(ctx, task_name, stage)? {
+        result = run_stage_once(ctx, task, stage, focus_section, review_mode)?;
+    }
+    let retry = &ctx.config.retry;
+    let mut attempt = 0;
+    while matches!(result, StageResult::NoFinish) && attempt < retry.attempts {
+        attempt += 1;
+        let delay = retry.backoff(attempt);
+        println!(
+            "Stage {} of '{}' ended without finishing; retry {}/{} in {}s",
+            stage, task_name, attempt, retry.attempts, delay
+        );
+        let mut event = Event::new(task_name, "stage_retried", ctx.actor(None));
+        event.note = Some(format!(
+            "{} attempt {} of {} after {}s",
+            stage, attempt, retry.attempts, delay
+        ));
+        ctx.store.append_event(&event)?;
+        let mut remaining = Duration::from_secs(delay);
+        while !remaining.is_zero() {
+            let slice = remaining.min(heartbeat_interval(ctx));
+            if !sleep_unless_interrupted(slice) {
+                return Ok(StageResult::Interrupted);
+            }
+            remaining -= slice;
+            refresh_claim(ctx, task_name);
+        }
+        result = run_stage_once(ctx, task, stage, focus_section, review_mode)?;
+    }
+    Ok(result)
+}
+
+/// How often a running stage refreshes its task's claim: a quarter of the TTL, so
+/// a slow heartbeat or two doesn't let another run take the task over.
+pub fn heartbeat_interval(ctx: &CommandContext) -> Duration {
+    Duration::from_secs((ctx.config.defaults.claim_ttl_seconds / 4).max(1))
+}
+
+pub fn refresh_claim(ctx: &CommandContext, task: &str) {
+    if let Err(err) = ctx.store.heartbeat_claim(task) {
+        eprintln!("Warning: failed to refresh claim on '{}': {:#}", task, err);
+    }
+}
+
+/// Sleeps for `duration`; returns false as soon as Ctrl-C is pressed.
+fn sleep_unless_interrupted(duration: Duration) -> bool {
+    let deadline = std::time::Instant::now() + duration;
+    while std::time::Instant::now() < deadline {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(100));
     }
-    Ok(())
+    !INTERRUPTED.load(Ordering::SeqCst)
 }
 
-fn run_stage(
+/// Marks a cheap-first stage as escalated after a failed attempt. Returns true when
+/// the stage should be retried on the expensive model.
+fn escalate_task_stage(ctx: &CommandContext, task: &str, stage: &str) -> Result<bool> {
+    let Some(policy) = ctx.config.escalation.as_ref() else {
+        return Ok(false);
+    };
+    if ctx.model_choice.explicit || !policy.applies_to(stage) {
+        return Ok(false);
+    }
+    let Ok(task_state) = ctx.store.load_task(task) else {
+        return Ok(false);
+    };
+    if task_state.escalated_stages.iter().any(|s| s == stage) {
+        return Ok(false);
+    }
+    ctx.store.update_task(task, |task_state| {
+        task_state.escalated_stages.push(stage.to_string());
+        task_state.updated_at = now_iso();
+        Ok(())
+    })?;
+    println!(
+        "Stage '{}' failed on {}; escalating '{}' to {}.",
+        stage,
+        policy.cheap_model()?.as_str(),
+        task,
+        policy.expensive_model()?.as_str()
+    );
+    Ok(true)
+}
+
+/// Status the prompt sees (open issues read as `issues`) and the model that runs
+/// the stage.
+fn stage_model(
     ctx: &CommandContext,
     task: Option<&str>,
+    task_state: Option<&TaskState>,
     stage: &str,
-    focus_section: Option<&str>,
-    review_mode: ReviewFinishMode,
-) -> Result<StageResult> {
-    let _terminal_guard = TerminalGuard::capture();
-    let task_state = task.and_then(|task_name| {
-        let path = task_state_path(&ctx.agent_root, task_name);
-        load_task(&path).ok()
-    });
-    let task_status = task_state.as_ref().map(|task| task.status.clone());
-    let custom_prompt = task_state
-        .as_ref()
-        .and_then(|task| task.prompt.as_ref())
-        .map(|value| value.trim().to_string())
-        .filter(|value| !value.is_empty());
+) -> Result<(Option<TaskStatus>, Model)> {
     let has_open_issues = if let Some(task_name) = task {
-        match task_has_open_issues(&ctx.agent_root, task_name) {
+        match task_has_open_issues(ctx.store.as_ref(), task_name) {
             Ok(has_open) => has_open,
             Err(err) => {
                 eprintln!("Warning: failed to load issues: {}", err);
@@ -2066,17 +7080,175 @@ fn run_stage(
     let effective_status = if has_open_issues {
         Some(TaskStatus::Issues)
     } else {
-        task_status.clone()
+        task_state.map(|task| task.status.clone())
     };
-    let model = resolve_model(
-        &ctx.model_choice,
-        ctx.agent,
+    let escalated = task_state
+        .map(|task| task.escalated_stages.iter().any(|s| s == stage))
+        .unwrap_or(false);
+    let model = resolve_model(ctx, stage, effective_status.as_ref(), escalated)?;
+    Ok((effective_status, model))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn stage_prompt_record(
+    ctx: &CommandContext,
+    task: Option<&str>,
+    task_state: Option<&TaskState>,
+    stage: &str,
+    focus_section: Option<&str>,
+    review_mode: ReviewFinishMode,
+    effective_status: Option<&TaskStatus>,
+    model: Model,
+    session_id: &str,
+) -> PromptRecord {
+    let handover = task_state.and_then(|task| task.handover.clone());
+    let custom_prompt = task_state
+        .and_then(|task| task.prompt.as_ref())
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    if let Some(prompt) = custom_prompt {
+        return PromptRecord {
+            stage: stage.to_string(),
+            task: task.map(|task| task.to_string()),
+            session: session_id.to_string(),
+            model: model.as_str().to_string(),
+            repo_root: ctx.repo_root.display().to_string(),
+            custom_prompt: Some(prompt),
+            finish_marker: ctx.config.finish.marker,
+            handover,
+            ..PromptRecord::default()
+        };
+    }
+    let issues_context_status = if stage == "review" {
+        None
+    } else {
+        effective_status
+    };
+    let (issues_header, issues_mode) = issues_text(ctx.agent, issues_context_status, task);
+    let review_finish_instructions = if stage == "review" {
+        build_review_finish_instructions(review_mode, &ctx.repo_root, task, session_id)
+    } else {
+        String::new()
+    };
+    let change_summary = match (stage, task_state) {
+        ("review", Some(task)) => {
+            let summary = review_change_summary(&stage_checkout(ctx, stage, &task.task), task);
+            let stale = pinged_issues_section(ctx, task);
+            match (summary.is_empty(), stale.is_empty()) {
+                (_, true) => summary,
+                (true, false) => stale,
+                (false, false) => format!("{summary}\n\n{stale}"),
+            }
+        }
+        ("spec-review-issues", Some(task)) => spec_change_summary(ctx, task),
+        ("spec", Some(task)) if ctx.config.spec.progress_summary => {
+            spec_progress_summary(ctx, task)
+        }
+        _ => String::new(),
+    };
+    PromptRecord {
+        stage: stage.to_string(),
+        task: task.map(|task| task.to_string()),
+        session: session_id.to_string(),
+        model: model.as_str().to_string(),
+        repo_root: ctx.repo_root.display().to_string(),
+        custom_prompt: None,
+        issues_header,
+        issues_mode,
+        review_finish_instructions,
+        parallelism_mode: parallelism_text(model),
+        focus_section: focus_section.unwrap_or("").to_string(),
+        change_summary,
+        finish_marker: ctx.config.finish.marker,
+        handover,
+    }
+}
+
+/// Session id shown in dry-run prompts; no session directory is created for it.
+const DRY_RUN_SESSION: &str = "dry-run";
+
+/// `--dry-run`: renders the prompt `run_stage` would send for `stage` and prints
+/// it with the model and env, without creating a session or touching the task.
+fn preview_stage(
+    ctx: &CommandContext,
+    task: Option<&str>,
+    stage: &str,
+    focus_section: Option<&str>,
+    review_mode: ReviewFinishMode,
+) -> Result<()> {
+    let task_state = task.and_then(|task_name| ctx.store.load_task(task_name).ok());
+    let (effective_status, model) = stage_model(ctx, task, task_state.as_ref(), stage)?;
+    let workdir = stage_workdir(ctx, stage, task)?;
+    let record = stage_prompt_record(
+        ctx,
+        task,
+        task_state.as_ref(),
         stage,
+        focus_section,
+        review_mode,
         effective_status.as_ref(),
+        model,
+        DRY_RUN_SESSION,
     );
+    let rendered = render_recorded_prompt(ctx, &record)?;
+    match task {
+        Some(task) => println!("Dry run: stage {} of '{}'", stage, task),
+        None => println!("Dry run: stage {}", stage),
+    }
+    print_dry_run(ctx, model, &workdir, Some(DRY_RUN_SESSION), task, &rendered)
+}
+
+fn print_dry_run(
+    ctx: &CommandContext,
+    model: Model,
+    workdir: &Path,
+    session_id: Option<&str>,
+    task: Option<&str>,
+    rendered: &str,
+) -> Result<()> {
+    let program = model
+        .command(ctx.config.custom_model.as_ref())?
+        .build(rendered)
+        .get_program()
+        .to_string_lossy()
+        .to_string();
+    println!("Model: {} ({})", model.as_str(), program);
+    let extra = model_args(ctx, model);
+    if !extra.is_empty() {
+        println!("Model args: {}", extra.join(" "));
+    }
+    println!("Workdir: {}", workdir.display());
+    println!("Env:");
+    for (key, value) in process_env(ctx, session_id, task) {
+        println!("  {}={}", key, value);
+    }
+    println!("Prompt:");
+    println!("{}", rendered);
+    Ok(())
+}
+
+fn run_stage_once(
+    ctx: &CommandContext,
+    task: Option<&str>,
+    stage: &str,
+    focus_section: Option<&str>,
+    review_mode: ReviewFinishMode,
+) -> Result<StageResult> {
+    let _terminal_guard = TerminalGuard::capture();
+    let task_state = task.and_then(|task_name| ctx.store.load_task(task_name).ok());
+    let (effective_status, model) = stage_model(ctx, task, task_state.as_ref(), stage)?;
+    let model = available_model(ctx, model)?;
+
+    if let Some(task_state) = task_state.as_ref() {
+        if task_state.base_commit.is_none() {
+            record_base_commit(ctx, &task_state.task)?;
+        }
+    }
 
-    let session_id = crate::state::new_session_id();
+    let workdir = stage_workdir(ctx, stage, task)?;
+    let session_id = crate::state::new_session_id(&ctx.agent_root);
     let session = create_session(
+        ctx.store.as_ref(),
         &ctx.agent_root,
         &session_id,
         ctx.agent.name(),
@@ -2084,122 +7256,401 @@ fn run_stage(
         task,
         &ctx.repo_root,
         &ctx.host,
+        model.as_str(),
     )?;
+    if let Some(task_name) = task.filter(|task_name| ctx.store.task_exists(task_name)) {
+        link_session(ctx, task_name, &session_id)?;
+    }
 
-    let rendered = if let Some(prompt) = custom_prompt.as_ref() {
-        if let Some(task_name) = task {
-            let finish_instruction =
-                build_prompt_task_finish_instruction(ctx, stage, task_name, &session.session_id);
-            format!("{prompt}\n\n{finish_instruction}")
-        } else {
-            prompt.clone()
-        }
-    } else {
-        let prompt_template = load_stage_prompt(ctx, stage, task)?;
-        let issues_context_status = if stage == "review" {
-            None
-        } else {
-            effective_status.as_ref()
-        };
-        let (issues_header, issues_mode) = issues_text(ctx.agent, issues_context_status, task);
-        let review_finish_instructions = if stage == "review" {
-            build_review_finish_instructions(review_mode, &ctx.repo_root, task, &session.session_id)
-        } else {
-            String::new()
-        };
-        let parallelism_mode = parallelism_text(model);
-        let focus_section = focus_section.unwrap_or("");
-        let repo_root_str = ctx.repo_root.display().to_string();
-        let prompt_context = PromptContext {
-            repo_root: &repo_root_str,
-            task,
-            session: Some(&session.session_id),
-            issues_header: &issues_header,
-            issues_mode: &issues_mode,
-            review_finish_instructions: &review_finish_instructions,
-            parallelism_mode: &parallelism_mode,
-            focus_section,
-        };
+    let record = stage_prompt_record(
+        ctx,
+        task,
+        task_state.as_ref(),
+        stage,
+        focus_section,
+        review_mode,
+        effective_status.as_ref(),
+        model,
+        &session.session_id,
+    );
+    let rendered = render_recorded_prompt(ctx, &record)?;
+    save_prompt_record(ctx, &record, &rendered);
+    if let (Some(task_name), Some(_)) = (task, record.handover.as_ref()) {
+        ctx.store.update_task(task_name, |task_state| {
+            task_state.handover = None;
+            Ok(())
+        })?;
+    }
+    let pinged = task_state
+        .as_ref()
+        .is_some_and(|task_state| !task_state.pinged_issues.is_empty());
+    if let (Some(task_name), "review", true) = (task, stage, pinged) {
+        ctx.store.update_task(task_name, |task_state| {
+            task_state.pinged_issues.clear();
+            Ok(())
+        })?;
+    }
 
-        let mut rendered = render_prompt(&prompt_template, &prompt_context);
-        if let Some(task) = task {
-            rendered = format!("Task: {task}\n\n{rendered}");
-        }
-        rendered
+    let mut command = model
+        .command(ctx.config.custom_model.as_ref())?
+        .with_args(&model_args(ctx, model));
+    if headless(ctx) {
+        command = model.headless(command);
+    }
+    let model_session = new_uuid();
+    if let Some(args) = model.session_id_args(&model_session) {
+        command.args.extend(args);
+        ctx.store.update_session(&session_id, |session_state| {
+            session_state.model_session = Some(model_session.clone());
+            Ok(())
+        })?;
+    }
+    let result = supervise_stage(
+        ctx,
+        command.build(&rendered),
+        &session_id,
+        task,
+        &workdir,
+        record.custom_prompt.is_some(),
+    )?;
+    record_model_session(ctx, &session_id);
+    Ok(result)
+}
+
+/// Stores the conversation id a model printed (`session id: ...`) so the
+/// session can be resumed. Needs a transcript; claude's id is set up front.
+fn record_model_session(ctx: &CommandContext, session_id: &str) {
+    let Ok(session) = ctx.store.load_session(session_id) else {
+        return;
+    };
+    if session.model_session.is_some() {
+        return;
+    }
+    let transcript_path = session_dir(&ctx.agent_root, session_id).join(TRANSCRIPT_FILE);
+    let Some(model_session) = transcript::find_model_session(&transcript_path) else {
+        return;
     };
+    let result = ctx.store.update_session(session_id, |session_state| {
+        session_state.model_session = Some(model_session);
+        Ok(())
+    });
+    if let Err(err) = result {
+        eprintln!("Warning: failed to record model session: {}", err);
+    }
+}
 
-    let (cmd, args) = model.command();
-    let mut child = Command::new(cmd);
-    child.args(args);
-    child.arg(rendered);
-    child.stdin(Stdio::inherit());
-    child.stdout(Stdio::inherit());
-    child.stderr(Stdio::inherit());
-    child.current_dir(&ctx.repo_root);
-    apply_process_env(&mut child, ctx, Some(&session_id), task);
+/// Runs a model process for `session_id` until the session finishes (through
+/// `mung finish` or a finish marker), the process exits, or Ctrl-C. With
+/// `custom_prompt`, a clean exit completes the task.
+fn supervise_stage(
+    ctx: &CommandContext,
+    mut child: Command,
+    session_id: &str,
+    task: Option<&str>,
+    workdir: &Path,
+    custom_prompt: bool,
+) -> Result<StageResult> {
+    let headless = headless(ctx);
+    let capture = transcript::capture_enabled(&ctx.config) || ctx.config.finish.marker || headless;
+    child.stdin(if headless {
+        Stdio::null()
+    } else {
+        Stdio::inherit()
+    });
+    if capture {
+        child.stdout(Stdio::piped());
+        child.stderr(Stdio::piped());
+    } else {
+        child.stdout(Stdio::inherit());
+        child.stderr(Stdio::inherit());
+    }
+    child.current_dir(workdir);
+    apply_process_env(&mut child, ctx, Some(session_id), task);
     let mut child = child.spawn().context("Failed to start model process")?;
+    let transcript_path = session_dir(&ctx.agent_root, session_id).join(TRANSCRIPT_FILE);
+    let mut transcript = if capture {
+        Some(Transcript::attach(&mut child, &transcript_path)?)
+    } else {
+        None
+    };
+    let mut markers = ctx
+        .config
+        .finish
+        .marker
+        .then(|| MarkerScanner::new(&transcript_path));
+    // Keep the task's claim fresh so a long stage doesn't look abandoned.
+    let mut last_heartbeat = Instant::now();
 
-    let session_path = crate::util::session_state_path(&ctx.agent_root, &session_id);
     let process_status = loop {
         if INTERRUPTED.load(Ordering::SeqCst) {
-            terminate_child(&mut child);
+            terminate_tree(&mut child);
+            if let Some(transcript) = transcript.take() {
+                transcript.finish();
+            }
             return Ok(StageResult::Interrupted);
         }
 
-        if let Ok(session_state) = load_session(&session_path) {
+        if let Ok(session_state) = ctx.store.load_session(session_id) {
             if session_state.status == SessionStatus::Finished {
-                terminate_child(&mut child);
+                terminate_tree(&mut child);
+                if let Some(transcript) = transcript.take() {
+                    transcript.finish();
+                }
                 return Ok(StageResult::Finished(session_state));
             }
         }
 
+        if let Some(scanner) = markers.as_mut() {
+            if let Some(marker) = scanner.poll(false) {
+                finish_from_marker(ctx, session_id, task, marker);
+                continue;
+            }
+        }
+
         if let Some(status) = child.try_wait()? {
             break status;
         }
 
+        if let Some(task) = task {
+            if last_heartbeat.elapsed() >= heartbeat_interval(ctx) {
+                refresh_claim(ctx, task);
+                last_heartbeat = Instant::now();
+            }
+        }
+
         thread::sleep(Duration::from_millis(500));
     };
+    if let Some(transcript) = transcript.take() {
+        transcript.finish();
+    }
+    if let Some(marker) = markers.as_mut().and_then(|scanner| scanner.poll(true)) {
+        finish_from_marker(ctx, session_id, task, marker);
+    }
 
-    if let Ok(session_state) = load_session(&session_path) {
+    if let Ok(session_state) = ctx.store.load_session(session_id) {
         if session_state.status == SessionStatus::Finished {
             return Ok(StageResult::Finished(session_state));
         }
     }
 
-    if custom_prompt.is_some() && process_status.success() {
-        update_session(&session_path, |session_state| {
+    if custom_prompt && process_status.success() {
+        ctx.store.update_session(session_id, |session_state| {
             session_state.status = SessionStatus::Finished;
             session_state.finished_at = Some(now_iso());
             session_state.next_stage = Some("completed".to_string());
             Ok(())
         })?;
         if let Some(task_name) = task {
-            let task_path = task_state_path(&ctx.agent_root, task_name);
-            if task_path.exists() {
-                update_task(&task_path, |task_state| {
+            if ctx.store.task_exists(task_name) {
+                ctx.store.update_task(task_name, |task_state| {
                     task_state.stage = "completed".to_string();
                     task_state.status = TaskStatus::Completed;
-                    task_state.last_session = Some(session_id.clone());
+                    task_state.last_session = Some(session_id.to_string());
                     task_state.updated_at = now_iso();
                     Ok(())
                 })?;
+                crate::linear::stage_changed(ctx, task_name);
+                notify(
+                    ctx,
+                    NotifyEvent::Completed,
+                    &format!("Task '{}' completed", task_name),
+                );
             }
         }
-        if let Ok(session_state) = load_session(&session_path) {
+        if let Ok(session_state) = ctx.store.load_session(session_id) {
             return Ok(StageResult::Finished(session_state));
         }
     }
 
-    update_session(&session_path, |session_state| {
-        session_state.status = SessionStatus::Failed;
-        session_state.finished_at = Some(now_iso());
-        Ok(())
-    })
-    .ok();
+    ctx.store
+        .update_session(session_id, |session_state| {
+            session_state.status = SessionStatus::Failed;
+            session_state.finished_at = Some(now_iso());
+            Ok(())
+        })
+        .ok();
 
     Ok(StageResult::NoFinish)
 }
 
+/// Applies a `MUNG_FINISH:` line from model output as if the model had run
+/// `mung finish`. Markers naming another session are ignored.
+fn finish_from_marker(
+    ctx: &CommandContext,
+    session_id: &str,
+    task: Option<&str>,
+    marker: FinishMarker,
+) {
+    if marker
+        .session
+        .as_deref()
+        .is_some_and(|session| session != session_id)
+    {
+        eprintln!("Warning: ignoring finish marker for another session");
+        return;
+    }
+    println!(
+        "Finish marker found in model output (stage {})",
+        marker.stage
+    );
+    if let Err(err) = cmd_finish(
+        ctx,
+        Some(marker.stage),
+        marker.next,
+        Some(session_id.to_string()),
+        task.map(str::to_string),
+    ) {
+        eprintln!("Warning: finish marker rejected: {}", err);
+    }
+}
+
+/// Renders a stage prompt from its recorded inputs using the current templates.
+fn render_recorded_prompt(ctx: &CommandContext, record: &PromptRecord) -> Result<String> {
+    let mut rendered = render_stage_text(ctx, record)?;
+    if let Some(handover) = record.handover.as_deref() {
+        rendered = format!("{handover}\n\n{rendered}");
+    }
+    if record.finish_marker {
+        return Ok(format!(
+            "{rendered}\n\n{}",
+            finish_marker_text(&record.stage, &record.session)
+        ));
+    }
+    Ok(rendered)
+}
+
+fn render_stage_text(ctx: &CommandContext, record: &PromptRecord) -> Result<String> {
+    let task = record.task.as_deref();
+    if let Some(prompt) = record.custom_prompt.as_ref() {
+        return Ok(match task {
+            Some(task_name) => {
+                let finish_instruction = build_prompt_task_finish_instruction(
+                    ctx,
+                    &record.stage,
+                    task_name,
+                    &record.session,
+                );
+                format!("{prompt}\n\n{finish_instruction}")
+            }
+            None => prompt.clone(),
+        });
+    }
+    let prompt_template = load_stage_prompt(ctx, &record.stage, task)?;
+    let mut rendered = render_prompt(&prompt_template, &record.context());
+    if let Some(task) = task {
+        rendered = format!("Task: {task}\n\n{rendered}");
+    }
+    Ok(rendered)
+}
+
+fn save_prompt_record(ctx: &CommandContext, record: &PromptRecord, rendered: &str) {
+    let dir = session_dir(&ctx.agent_root, &record.session);
+    let result = serde_json::to_string_pretty(record)
+        .map_err(anyhow::Error::from)
+        .and_then(|data| write_text(&dir.join(PROMPT_RECORD_FILE), &data))
+        .and_then(|_| write_text(&dir.join(PROMPT_TEXT_FILE), rendered));
+    if let Err(err) = result {
+        eprintln!("Warning: failed to record prompt: {}", err);
+    }
+}
+
+/// `mung prompt <stage>`: the prompt a session of `stage` would get, rendered
+/// with `task`'s state. Which template won goes to stderr so stdout can be piped.
+pub fn cmd_prompt(ctx: &CommandContext, stage: &str, task: Option<&str>) -> Result<()> {
+    if let Some(task) = task {
+        validate_task_name(task)?;
+        if !ctx.store.task_exists(task) {
+            bail!("{}", msg("task_not_found", &[("task", &task)]));
+        }
+    }
+    let task_state = task.and_then(|task_name| ctx.store.load_task(task_name).ok());
+    let (effective_status, model) = stage_model(ctx, task, task_state.as_ref(), stage)?;
+    let record = stage_prompt_record(
+        ctx,
+        task,
+        task_state.as_ref(),
+        stage,
+        None,
+        ReviewFinishMode::Queue,
+        effective_status.as_ref(),
+        model,
+        DRY_RUN_SESSION,
+    );
+    if record.custom_prompt.is_some() {
+        eprintln!("Source: custom prompt in task.json (mung task --prompt)");
+    } else {
+        match resolve_stage_prompt(ctx, stage, task)?.0 {
+            PromptSource::File(path) => eprintln!("Source: {}", path.display()),
+            PromptSource::Embedded(name) => eprintln!("Source: embedded {}", name),
+        }
+    }
+    println!("{}", render_recorded_prompt(ctx, &record)?);
+    Ok(())
+}
+
+pub fn cmd_replay(ctx: &CommandContext, session_id: &str, rerender: bool, run: bool) -> Result<()> {
+    let dir = session_dir(&ctx.agent_root, session_id);
+    let record_path = dir.join(PROMPT_RECORD_FILE);
+    if !record_path.exists() {
+        bail!(
+            "No recorded prompt for session '{}' (sessions record prompts from this version on)",
+            session_id
+        );
+    }
+    let record: PromptRecord = serde_json::from_str(&read_text(&record_path)?)
+        .with_context(|| format!("Failed to parse {}", record_path.display()))?;
+    let recorded = read_text(&dir.join(PROMPT_TEXT_FILE)).unwrap_or_default();
+
+    let prompt = if rerender {
+        let rendered = render_recorded_prompt(ctx, &record)?;
+        if rendered == recorded {
+            eprintln!("Re-rendered prompt matches the recording.");
+        } else {
+            eprintln!("Re-rendered prompt differs from the recording.");
+        }
+        rendered
+    } else {
+        recorded
+    };
+
+    if !run {
+        println!("{}", prompt);
+        return Ok(());
+    }
+
+    let model = if ctx.model_choice.explicit {
+        ctx.model_choice.model
+    } else {
+        Model::from_str(&record.model)?
+    };
+    println!(
+        "Replaying session {} ({} {}) on {} in read-only mode",
+        session_id,
+        record.task.as_deref().unwrap_or("-"),
+        record.stage,
+        model.as_str()
+    );
+    let _terminal_guard = TerminalGuard::capture();
+    let mut child = model
+        .command(ctx.config.custom_model.as_ref())?
+        .with_args(&model_args(ctx, model))
+        .build(&prompt);
+    child
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .current_dir(stage_workdir(ctx, &record.stage, record.task.as_deref())?);
+    apply_process_env(&mut child, ctx, Some(session_id), record.task.as_deref());
+    // The replayed prompt still names the original session; keep it from touching state.
+    child.env("MUNG_READ_ONLY", "1");
+    child.env("METAGENT_READ_ONLY", "1");
+    let status = child.status().context("Failed to start replay model")?;
+    if !status.success() {
+        bail!("Replay model exited with {}", status);
+    }
+    Ok(())
+}
+
 fn bootstrap_needed(agent_root: &Path) -> Result<bool> {
     let agents_path = agent_root.join("AGENTS.md");
     let spec_path = agent_root.join("SPEC.md");
@@ -2258,14 +7709,15 @@ fn run_bootstrap(ctx: &CommandContext) -> Result<()> {
         review_finish_instructions: "",
         parallelism_mode: &parallelism_mode,
         focus_section: "",
+        change_summary: "",
     };
     let prompt_text = render_prompt(&prompt, &context);
 
-    let (cmd, args) = model.command();
-    let mut child = Command::new(cmd);
+    let mut child = model
+        .command(ctx.config.custom_model.as_ref())?
+        .with_args(&model_args(ctx, model))
+        .build(&prompt_text);
     child
-        .args(args)
-        .arg(prompt_text)
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
@@ -2279,49 +7731,509 @@ fn run_bootstrap(ctx: &CommandContext) -> Result<()> {
     Ok(())
 }
 
+/// Stage models run without a terminal: stdin closed, claude in `-p` and codex in
+/// `exec` mode, output captured to the session transcript. For queues under nohup
+/// or CI, where there is no TTY to hand over.
+fn headless(ctx: &CommandContext) -> bool {
+    ctx.model_choice.headless || ctx.config.defaults.headless
+}
+
+/// Flags appended to `model`'s command line: its `[model_args]` entry, then
+/// `--model-args`. The fake model takes none; its arguments are the prompt.
+fn model_args(ctx: &CommandContext, model: Model) -> Vec<String> {
+    if model == Model::Fake {
+        return Vec::new();
+    }
+    ctx.config
+        .model_args
+        .get(model.as_str())
+        .into_iter()
+        .flatten()
+        .chain(&ctx.model_choice.model_args)
+        .cloned()
+        .collect()
+}
+
+/// `model` if its CLI can be started, else `[defaults] fallback_model` with a
+/// warning, so a queue left running doesn't stop on a shell without that CLI. A
+/// model named with `--model` is never swapped out; its absence is an error.
+fn available_model(ctx: &CommandContext, model: Model) -> Result<Model> {
+    let custom = ctx.config.custom_model.as_ref();
+    let program = model.command(custom)?.program;
+    if is_executable(&program) {
+        return Ok(model);
+    }
+    if ctx.model_choice.explicit && model == ctx.model_choice.model {
+        bail!(
+            "Model '{}' was asked for with --model or MUNG_MODEL, but '{}' is not an executable on PATH",
+            model.as_str(),
+            program
+        );
+    }
+    let Some(fallback) = ctx
+        .config
+        .defaults
+        .fallback_model
+        .as_deref()
+        .map(Model::from_str)
+        .transpose()?
+    else {
+        bail!(
+            "Model '{}' is not available: '{}' is not an executable on PATH (set [defaults] fallback_model to run another model instead)",
+            model.as_str(),
+            program
+        );
+    };
+    let fallback_program = fallback.command(custom)?.program;
+    if fallback == model || !is_executable(&fallback_program) {
+        bail!(
+            "Model '{}' is not available ('{}' not found), and neither is fallback_model '{}' ('{}')",
+            model.as_str(),
+            program,
+            fallback.as_str(),
+            fallback_program
+        );
+    }
+    eprintln!(
+        "Warning: '{}' not found on PATH; running {} instead ([defaults] fallback_model)",
+        program,
+        fallback.as_str()
+    );
+    Ok(fallback)
+}
+
 fn resolve_model(
-    choice: &ModelChoice,
-    agent: AgentKind,
+    ctx: &CommandContext,
     stage: &str,
     task_status: Option<&TaskStatus>,
-) -> Model {
+    escalated: bool,
+) -> Result<Model> {
+    let choice = &ctx.model_choice;
+    // Fake and custom backends replace the hosted CLIs outright, so the
+    // codex-for-issues rule and stage defaults do not apply.
+    if choice.explicit && matches!(choice.model, Model::Fake | Model::Custom) {
+        return Ok(choice.model);
+    }
     if task_status == Some(&TaskStatus::Issues) && !(choice.force_model && choice.explicit) {
-        return Model::Codex;
+        return Ok(Model::Codex);
     }
     if choice.explicit {
-        return choice.model;
+        return Ok(choice.model);
+    }
+    if let Some(policy) = ctx.config.escalation.as_ref() {
+        if policy.applies_to(stage) {
+            return if escalated {
+                policy.expensive_model()
+            } else {
+                policy.cheap_model()
+            };
+        }
     }
-    if let Some(stage_model) = agent.model_for_stage(stage) {
-        return stage_model;
+    if let Some(config_model) = ctx.config.model_for_stage(stage) {
+        return Ok(config_model);
     }
-    choice.model
+    if let Some(stage_model) = ctx.agent.model_for_stage(stage) {
+        return Ok(stage_model);
+    }
+    Ok(choice.model)
 }
 
 fn prompt_roots(ctx: &CommandContext) -> [&Path; 2] {
     [ctx.prompt_root.as_path(), ctx.legacy_prompt_root.as_path()]
 }
 
-fn reconcile_running_tasks(agent_root: &Path) -> Result<()> {
-    let tasks = list_tasks(agent_root);
+fn reconcile_running_tasks(ctx: &CommandContext, dry_run: bool) -> Result<Vec<String>> {
+    let mut repairs = Vec::new();
+    let tasks = ctx.store.list_tasks();
     for task in tasks
         .iter()
         .filter(|t| t.status == TaskStatus::Running && t.stage != "completed")
     {
-        if has_active_claim(agent_root, &task.task)? || has_active_session(agent_root, &task.task)?
+        if ctx.store.has_active_claim(&task.task)?
+            || has_active_session(ctx.store.as_ref(), &task.task)?
         {
             continue;
         }
-        let task_path = task_state_path(agent_root, &task.task);
-        update_task(&task_path, |task_state| {
+        repairs.push(format!(
+            "task {}: running without a live claim or session -> incomplete",
+            task.task
+        ));
+        if dry_run {
+            continue;
+        }
+        ctx.store.update_task(&task.task, |task_state| {
             task_state.status = TaskStatus::Incomplete;
             task_state.updated_at = now_iso();
             Ok(())
         })?;
     }
+    Ok(repairs)
+}
+
+/// Read-only dashboard: running sessions, claims, queue depth per stage, open issues
+/// per task, SLA breaches, and stale claims in one view.
+pub fn cmd_status(ctx: &CommandContext) -> Result<()> {
+    // Observe only: never take claims or mark sessions failed while looking.
+    crate::state::set_read_only(true);
+    let tasks = ctx.store.list_tasks();
+    let issues = ctx.store.list_issues().unwrap_or_else(|err| {
+        eprintln!("Warning: failed to load issues: {}", err);
+        Vec::new()
+    });
+    let issue_counts = count_open_issues(&issues);
+    let breaches = find_breaches(&ctx.config.sla, &issues, chrono::Utc::now());
+
+    let mut sessions: Vec<SessionState> = ctx
+        .store
+        .list_sessions()
+        .into_iter()
+        .filter(|session| session.status == SessionStatus::Running)
+        .collect();
+    sessions.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+
+    let mut claims = Vec::new();
+    for task in ctx.store.list_claims() {
+        let live = ctx.store.has_active_claim(&task)?;
+        // The task's last session is where a stale claim's run left off.
+        let last_session = ctx
+            .store
+            .load_task(&task)
+            .ok()
+            .and_then(|task_state| task_state.last_session);
+        claims.push((
+            task.clone(),
+            ctx.store.read_claim(&task),
+            live,
+            last_session,
+        ));
+    }
+
+    let mut depth: Vec<(&str, usize)> = Vec::new();
+    for stage in ctx.agent.stages() {
+        let count = tasks
+            .iter()
+            .filter(|task| !task.held && task.stage == *stage)
+            .count();
+        if count > 0 {
+            depth.push((stage, count));
+        }
+    }
+    let held = tasks.iter().filter(|task| task.held).count();
+    let blocked: Vec<(&str, &str)> = tasks
+        .iter()
+        .filter(|task| task.status == TaskStatus::Blocked)
+        .map(|task| {
+            (
+                task.task.as_str(),
+                task.blocked_reason.as_deref().unwrap_or(""),
+            )
+        })
+        .collect();
+    let awaiting: Vec<(&str, &str)> = tasks
+        .iter()
+        .filter_map(|task| {
+            task.awaiting_approval
+                .as_ref()
+                .map(|pending| (task.task.as_str(), pending.stage.as_str()))
+        })
+        .collect();
+    let next = next_eligible_task(
+        ctx.agent,
+        &ctx.config.queue,
+        &tasks,
+        &breached_tasks(&breaches),
+    );
+    let mut per_task: Vec<(&String, &usize)> = issue_counts.per_task.iter().collect();
+    per_task.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    if json_output() {
+        let sessions: Vec<serde_json::Value> = sessions
+            .iter()
+            .map(|session| {
+                let mut value = serde_json::to_value(session).unwrap_or_default();
+                value["orphaned"] = json!(crate::state::is_orphaned_session(session, &ctx.host));
+                value
+            })
+            .collect();
+        let claims: Vec<serde_json::Value> = claims
+            .iter()
+            .map(|(task, claim, live, last_session)| {
+                json!({ "task": task, "claim": claim, "live": live, "last_session": last_session })
+            })
+            .collect();
+        let depth: serde_json::Map<String, serde_json::Value> = depth
+            .iter()
+            .map(|(stage, count)| (stage.to_string(), json!(count)))
+            .collect();
+        return print_json(&json!({
+            "agent": ctx.agent.name(),
+            "running_sessions": sessions,
+            "claims": claims,
+            "queue": { "stages": depth, "held": held, "next": next.map(|task| task.task) },
+            "awaiting_approval": awaiting
+                .iter()
+                .map(|(task, stage)| json!({ "task": task, "stage": stage }))
+                .collect::<Vec<_>>(),
+            "blocked": blocked
+                .iter()
+                .map(|(task, reason)| json!({ "task": task, "reason": reason }))
+                .collect::<Vec<_>>(),
+            "open_issues": {
+                "per_task": issue_counts.per_task,
+                "unassigned": issue_counts.unassigned,
+            },
+            "sla_breaches": breaches.len(),
+        }));
+    }
+
+    println!("{}", format!("Status ({} agent)", ctx.agent.name()).bold());
+
+    println!();
+    println!("Running sessions: {}", sessions.len());
+    for session in &sessions {
+        let orphaned = if crate::state::is_orphaned_session(session, &ctx.host) {
+            format!(" {}", "[orphaned]".red())
+        } else {
+            String::new()
+        };
+        println!(
+            "  {} {} ({}{}) pid {}@{} since {}{}",
+            session.session_id,
+            session.task.as_deref().unwrap_or("-"),
+            session.stage,
+            session
+                .model
+                .as_deref()
+                .map(|model| format!(", {model}"))
+                .unwrap_or_default(),
+            session.pid,
+            session.host,
+            session.started_at,
+            orphaned
+        );
+    }
+
+    println!();
+    println!("Claims: {}", claims.len());
+    for (task, claim, live, last_session) in &claims {
+        let holder = claim
+            .as_ref()
+            .map(|claim| {
+                format!(
+                    " pid {}@{} since {}",
+                    claim.pid, claim.host, claim.started_at
+                )
+            })
+            .unwrap_or_default();
+        let state = if *live {
+            "live".green().to_string()
+        } else {
+            let last = last_session
+                .as_deref()
+                .map(|session| format!(", last session {session}"))
+                .unwrap_or_default();
+            format!("{}{}", "stale".red(), last)
+        };
+        println!("  {}{} [{}]", task, holder, state);
+    }
+
+    println!();
+    println!("Queue:");
+    if depth.is_empty() && held == 0 {
+        println!("  {}", msg("no_tasks", &[]).dimmed());
+    }
+    for (stage, count) in &depth {
+        println!("  {}: {}", ctx.agent.stage_label(stage), count);
+    }
+    if held > 0 {
+        println!("  Backlog: {}", held);
+    }
+    if let Some(next) = next {
+        println!("  Next: {} ({})", next.task, next.stage);
+    }
+    for (task, stage) in &awaiting {
+        println!(
+            "  {} {} -> {} (run 'mung approve {}')",
+            "Awaiting approval:".yellow(),
+            task,
+            stage,
+            task
+        );
+    }
+    for (task, reason) in &blocked {
+        println!("  {} {} ({})", "Blocked:".red(), task, reason);
+    }
+
+    if ctx.agent == AgentKind::Code {
+        println!();
+        let total: usize = issue_counts.per_task.values().sum::<usize>() + issue_counts.unassigned;
+        println!("Open issues: {}", total);
+        for (task, count) in per_task {
+            println!("  {}: {}", task, count);
+        }
+        if issue_counts.unassigned > 0 {
+            println!("  (unassigned): {}", issue_counts.unassigned);
+        }
+        if !breaches.is_empty() {
+            println!("  {}", format!("SLA breaches: {}", breaches.len()).red());
+        }
+    }
+
+    let stale: Vec<&String> = claims
+        .iter()
+        .filter(|(_, _, live, _)| !live)
+        .map(|(task, _, _, _)| task)
+        .collect();
+    let orphaned = sessions
+        .iter()
+        .filter(|session| crate::state::is_orphaned_session(session, &ctx.host))
+        .count();
+    if !stale.is_empty() || orphaned > 0 {
+        println!();
+        println!(
+            "{} {} stale claim(s), {} orphaned session(s); run 'mung reconcile' to repair",
+            "Attention:".yellow(),
+            stale.len(),
+            orphaned
+        );
+    }
+    Ok(())
+}
+
+pub fn cmd_reconcile(ctx: &CommandContext, dry_run: bool) -> Result<()> {
+    if dry_run {
+        // Probe without locking or marking sessions failed along the way.
+        crate::state::set_read_only(true);
+    }
+    let agent_root = &ctx.agent_root;
+    let mut repairs = Vec::new();
+
+    // Directories renamed by hand; only the files backend keeps state inside them.
+    let tasks_dir = agent_root.join("tasks");
+    if let Ok(entries) = fs::read_dir(&tasks_dir) {
+        for entry in entries.flatten() {
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            let task_path = entry.path().join("task.json");
+            let Ok(task_state) = load_task(&task_path) else {
+                continue;
+            };
+            if task_state.task == dir_name {
+                continue;
+            }
+            repairs.push(format!(
+                "task {}: state points at missing directory '{}' -> renamed to '{}'",
+                dir_name, task_state.task, dir_name
+            ));
+            if !dry_run {
+                update_task(&task_path, |task_state| {
+                    task_state.task = dir_name.clone();
+                    task_state.updated_at = now_iso();
+                    Ok(())
+                })?;
+            }
+        }
+    }
+
+    for session in ctx.store.list_sessions() {
+        if !crate::state::is_orphaned_session(&session, &ctx.host) {
+            continue;
+        }
+        repairs.push(format!(
+            "session {}: running with dead pid {} -> failed",
+            session.session_id, session.pid
+        ));
+        if dry_run {
+            continue;
+        }
+        ctx.store
+            .update_session(&session.session_id, |session_state| {
+                session_state.status = SessionStatus::Failed;
+                session_state.finished_at = Some(now_iso());
+                Ok(())
+            })?;
+    }
+
+    repairs.extend(reconcile_running_tasks(ctx, dry_run)?);
+    repairs.extend(prune_expired_sessions(ctx, dry_run)?);
+
+    for claim in ctx.store.list_claims() {
+        let task_missing = !task_dir(agent_root, &claim).exists();
+        if ctx.store.has_active_claim(&claim)? {
+            if task_missing {
+                println!(
+                    "Note: claim {} is held by a live process but the task is missing (left in place)",
+                    claim
+                );
+            }
+            continue;
+        }
+        let suffix = if task_missing { " (task missing)" } else { "" };
+        repairs.push(format!(
+            "claim {}: no live holder{} -> removed",
+            claim, suffix
+        ));
+        if !dry_run {
+            ctx.store.remove_stale_claim(&claim)?;
+        }
+    }
+
+    if ctx.agent == AgentKind::Code {
+        for mut issue in ctx.store.list_issues()? {
+            if issue.status != IssueStatus::Open {
+                continue;
+            }
+            let Some(task) = issue.task.clone() else {
+                continue;
+            };
+            if ctx.store.task_exists(&task) {
+                continue;
+            }
+            repairs.push(format!(
+                "issue {}: references deleted task '{}' -> unassigned",
+                issue.id, task
+            ));
+            if dry_run {
+                continue;
+            }
+            issue.task = None;
+            issue.updated_at = now_iso();
+            ctx.store.save_issue(&issue)?;
+        }
+    }
+
+    if repairs.is_empty() {
+        println!("{}", "Nothing to reconcile".dimmed());
+        return Ok(());
+    }
+    let heading = if dry_run {
+        "Would repair:"
+    } else {
+        "Repaired:"
+    };
+    println!("{}", heading.bold());
+    for repair in &repairs {
+        println!("  {}", repair);
+    }
     Ok(())
 }
 
 fn load_stage_prompt(ctx: &CommandContext, stage: &str, task: Option<&str>) -> Result<String> {
+    resolve_stage_prompt(ctx, stage, task).map(|(_, prompt)| prompt)
+}
+
+/// Where a stage prompt template came from.
+enum PromptSource {
+    File(PathBuf),
+    Embedded(String),
+}
+
+/// Finds the template for `stage`: a path configured for a custom agent, then
+/// ~/.mung/<agent>, then the legacy ~/.metagent/<agent>, then the embedded copy.
+fn resolve_stage_prompt(
+    ctx: &CommandContext,
+    stage: &str,
+    task: Option<&str>,
+) -> Result<(PromptSource, String)> {
     let prompt_path = ctx
         .agent
         .prompt_file_for_stage(stage, task)
@@ -2331,13 +8243,15 @@ fn load_stage_prompt(ctx: &CommandContext, stage: &str, task: Option<&str>) -> R
         if !prompt_path.exists() {
             bail!("Prompt file not found: {}", prompt_path.display());
         }
-        return read_text(&prompt_path);
+        let prompt = read_text(&prompt_path)?;
+        return Ok((PromptSource::File(prompt_path), prompt));
     }
 
     for root in prompt_roots(ctx) {
         let prompt_file = root.join(&prompt_path);
         if prompt_file.exists() {
-            return read_text(&prompt_file);
+            let prompt = read_text(&prompt_file)?;
+            return Ok((PromptSource::File(prompt_file), prompt));
         }
     }
 
@@ -2346,7 +8260,7 @@ fn load_stage_prompt(ctx: &CommandContext, stage: &str, task: Option<&str>) -> R
         .map(|name| name.to_string_lossy().to_string())
         .unwrap_or_default();
     if let Some(embedded) = ctx.agent.embedded_prompt(&file_name) {
-        return Ok(embedded.to_string());
+        return Ok((PromptSource::Embedded(file_name), embedded.to_string()));
     }
 
     let prompt_file = ctx.prompt_root.join(&prompt_path);
@@ -2367,8 +8281,8 @@ fn load_prompt_by_name(ctx: &CommandContext, name: &str) -> Result<String> {
     bail!("Prompt file not found: {}", prompt_file.display());
 }
 
-fn find_unique_task(agent_root: &Path, stage: &str) -> Result<Option<String>> {
-    let tasks = list_tasks(agent_root);
+fn find_unique_task(store: &dyn StateStore, stage: &str) -> Result<Option<String>> {
+    let tasks = store.list_tasks();
     let mut matches: Vec<TaskState> = tasks
         .into_iter()
         .filter(|task| {
@@ -2447,7 +8361,7 @@ fn parse_issue_source(value: Option<&str>) -> Result<Option<IssueSource>> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 struct CanonicalPlanStep {
     line: usize,
     done: bool,
@@ -2457,7 +8371,7 @@ struct CanonicalPlanStep {
     title: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 struct ChecklistStep {
     line: usize,
     done: bool,
@@ -2531,6 +8445,32 @@ fn parse_checklist_step(line: &str, line_number: usize) -> Option<ChecklistStep>
     })
 }
 
+fn linked_step_issues(store: &dyn StateStore, task: &str) -> HashMap<u32, Vec<Issue>> {
+    // Archived issues keep their step links so plan history survives retention.
+    let issues = match store.list_issues().and_then(|mut issues| {
+        issues.extend(store.list_archived_issues()?);
+        Ok(issues)
+    }) {
+        Ok(issues) => issues,
+        Err(err) => {
+            eprintln!("Warning: failed to load issues: {}", err);
+            return HashMap::new();
+        }
+    };
+    let mut linked: HashMap<u32, Vec<Issue>> = HashMap::new();
+    let mut issues: Vec<Issue> = issues
+        .into_iter()
+        .filter(|issue| issue.task.as_deref() == Some(task) && !issue.steps.is_empty())
+        .collect();
+    sort_issues(&mut issues);
+    for issue in issues {
+        for step in &issue.steps {
+            linked.entry(*step).or_default().push(issue.clone());
+        }
+    }
+    linked
+}
+
 fn issue_default_stage(agent: AgentKind, issue_type: &IssueType) -> Option<String> {
     if agent != AgentKind::Code {
         return None;
@@ -2552,16 +8492,15 @@ fn validate_issue_stage(agent: AgentKind, stage: &str) -> Result<()> {
 }
 
 fn update_task_for_issue(
-    agent_root: &Path,
+    store: &dyn StateStore,
     task: &str,
     stage_override: Option<&str>,
     default_stage: Option<&str>,
 ) -> Result<()> {
-    let task_path = task_state_path(agent_root, task);
-    if !task_path.exists() {
-        bail!("Task '{}' not found", task);
+    if !store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
     }
-    update_task(&task_path, |task_state| {
+    store.update_task(task, |task_state| {
         if let Some(stage) = stage_override {
             task_state.stage = stage.to_string();
         } else if task_state.stage == "completed" {
@@ -2576,16 +8515,18 @@ fn update_task_for_issue(
     Ok(())
 }
 
-fn sync_task_status_for_issues(agent_root: &Path, task: &str) -> Result<()> {
-    let task_path = task_state_path(agent_root, task);
-    if !task_path.exists() {
-        bail!("Task '{}' not found", task);
+pub fn sync_task_status_for_issues(store: &dyn StateStore, task: &str) -> Result<()> {
+    if !store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
     }
-    let issues = list_issues(agent_root)?;
+    let issues = store.list_issues()?;
     let has_open = issues
         .iter()
         .any(|issue| issue.status == IssueStatus::Open && issue.task.as_deref() == Some(task));
-    update_task(&task_path, |task_state| {
+    store.update_task(task, |task_state| {
+        if task_state.status == TaskStatus::Blocked {
+            return Ok(());
+        }
         if has_open {
             task_state.status = TaskStatus::Issues;
         } else if task_state.stage == "completed" {
@@ -2599,172 +8540,13 @@ fn sync_task_status_for_issues(agent_root: &Path, task: &str) -> Result<()> {
     Ok(())
 }
 
-fn task_has_open_issues(agent_root: &Path, task: &str) -> Result<bool> {
-    let issues = list_issues(agent_root)?;
+fn task_has_open_issues(store: &dyn StateStore, task: &str) -> Result<bool> {
+    let issues = store.list_issues()?;
     Ok(issues
         .iter()
         .any(|issue| issue.status == IssueStatus::Open && issue.task.as_deref() == Some(task)))
 }
 
-fn next_eligible_task(agent: AgentKind, tasks: &[TaskState]) -> Option<TaskState> {
-    for stage in agent.queue_stages() {
-        let mut stage_tasks: Vec<TaskState> = tasks
-            .iter()
-            .filter(|t| {
-                !t.held
-                    && t.stage == *stage
-                    && matches!(
-                        t.status,
-                        TaskStatus::Pending | TaskStatus::Incomplete | TaskStatus::Issues
-                    )
-            })
-            .cloned()
-            .collect();
-        if stage_tasks.is_empty() {
-            continue;
-        }
-        if *stage == "build" {
-            stage_tasks.sort_by(|a, b| {
-                let ar = a.queue_rank.unwrap_or(i64::MAX);
-                let br = b.queue_rank.unwrap_or(i64::MAX);
-                ar.cmp(&br).then_with(|| a.added_at.cmp(&b.added_at))
-            });
-        } else {
-            stage_tasks.sort_by(|a, b| a.added_at.cmp(&b.added_at));
-        }
-        return stage_tasks.into_iter().next();
-    }
-    // Safety net: pick up completed tasks that still have Issues status
-    let mut issues_tasks: Vec<TaskState> = tasks
-        .iter()
-        .filter(|t| !t.held && t.stage == "completed" && t.status == TaskStatus::Issues)
-        .cloned()
-        .collect();
-    if !issues_tasks.is_empty() {
-        issues_tasks.sort_by(|a, b| a.added_at.cmp(&b.added_at));
-        // Override stage to build since completed has no prompt
-        return issues_tasks.into_iter().next().map(|mut t| {
-            t.stage = "build".to_string();
-            t
-        });
-    }
-    None
-}
-
-fn send_signal(child: &mut std::process::Child, signal: i32) {
-    let pid = child.id() as i32;
-    send_signal_to_pid(pid, signal);
-}
-
-fn send_signal_to_pid(pid: i32, signal: i32) {
-    unsafe {
-        let _ = libc::kill(pid, signal);
-    }
-}
-
-fn pid_alive(pid: i32) -> bool {
-    unsafe { libc::kill(pid, 0) == 0 }
-}
-
-fn collect_descendant_pids(root_pid: i32) -> Vec<i32> {
-    let output = match Command::new("ps").args(["-axo", "pid=,ppid="]).output() {
-        Ok(output) if output.status.success() => output,
-        _ => return Vec::new(),
-    };
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut children_by_parent: HashMap<i32, Vec<i32>> = HashMap::new();
-    for line in stdout.lines() {
-        let mut parts = line.split_whitespace();
-        let pid = parts.next().and_then(|value| value.parse::<i32>().ok());
-        let ppid = parts.next().and_then(|value| value.parse::<i32>().ok());
-        if let (Some(pid), Some(ppid)) = (pid, ppid) {
-            children_by_parent.entry(ppid).or_default().push(pid);
-        }
-    }
-
-    let mut descendants = Vec::new();
-    let mut stack = vec![root_pid];
-    while let Some(parent) = stack.pop() {
-        if let Some(children) = children_by_parent.get(&parent) {
-            for child in children {
-                descendants.push(*child);
-                stack.push(*child);
-            }
-        }
-    }
-    descendants.sort_unstable();
-    descendants.dedup();
-    descendants
-}
-
-fn signal_process_tree(
-    child: &mut std::process::Child,
-    signal: i32,
-    known_descendants: &mut HashSet<i32>,
-) {
-    let root_pid = child.id() as i32;
-    known_descendants.extend(collect_descendant_pids(root_pid));
-
-    // Signal descendants first so wrapper exits don't orphan deeper children.
-    let mut descendants: Vec<i32> = known_descendants
-        .iter()
-        .copied()
-        .filter(|pid| pid_alive(*pid))
-        .collect();
-    descendants.sort_unstable();
-    descendants.reverse();
-    for pid in descendants {
-        send_signal_to_pid(pid, signal);
-    }
-
-    send_signal(child, signal);
-}
-
-fn wait_for_process_tree_exit(
-    child: &mut std::process::Child,
-    known_descendants: &mut HashSet<i32>,
-    timeout: Duration,
-) -> bool {
-    let start = Instant::now();
-    let mut root_exited = false;
-    while start.elapsed() < timeout {
-        if !root_exited {
-            match child.try_wait() {
-                Ok(Some(_)) => root_exited = true,
-                Ok(None) => {}
-                Err(_) => root_exited = true,
-            }
-        }
-        known_descendants.retain(|pid| pid_alive(*pid));
-        if root_exited && known_descendants.is_empty() {
-            return true;
-        }
-        thread::sleep(Duration::from_millis(100));
-    }
-    false
-}
-
-fn terminate_child(child: &mut std::process::Child) {
-    const SIGINT_ATTEMPTS: usize = 3;
-    let mut known_descendants = HashSet::new();
-    for _ in 0..SIGINT_ATTEMPTS {
-        signal_process_tree(child, libc::SIGINT, &mut known_descendants);
-        if wait_for_process_tree_exit(child, &mut known_descendants, Duration::from_millis(500)) {
-            return;
-        }
-    }
-
-    signal_process_tree(child, libc::SIGTERM, &mut known_descendants);
-    if wait_for_process_tree_exit(child, &mut known_descendants, Duration::from_secs(1)) {
-        return;
-    }
-
-    signal_process_tree(child, libc::SIGKILL, &mut known_descendants);
-    let _ = wait_for_process_tree_exit(child, &mut known_descendants, Duration::from_secs(1));
-    let _ = child.kill();
-    let _ = wait_for_process_tree_exit(child, &mut known_descendants, Duration::from_secs(1));
-}
-
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum ReviewFinishMode {
     Queue,
@@ -2772,6 +8554,7 @@ enum ReviewFinishMode {
 }
 
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 enum StageResult {
     Finished(SessionState),
     Interrupted,
@@ -2815,3 +8598,108 @@ When you have fully completed this one-off task, run:\n\
 Then exit immediately. Do not start a review pass."
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::memory::MemoryStore;
+
+    fn add_task(store: &dyn StateStore, task: &str, stage: &str, added_at: &str) {
+        create_task_state(store, "code", task, stage, added_at, false, None, None).unwrap();
+    }
+
+    fn add_issue(store: &dyn StateStore, task: &str, issue_type: IssueType) -> Issue {
+        let issue = new_issue(
+            format!("Issue for {task}"),
+            IssueStatus::Open,
+            IssuePriority::P2,
+            Some(task.to_string()),
+            issue_type,
+            IssueSource::Review,
+            None,
+            None,
+        );
+        store.save_issue(&issue).unwrap();
+        issue
+    }
+
+    #[test]
+    fn issue_reopens_completed_task_at_default_stage() {
+        let store: &dyn StateStore = &MemoryStore::new();
+        add_task(store, "done", "completed", "2026-01-01T00:00:00Z");
+        store
+            .update_task("done", |task| {
+                task.status = TaskStatus::Completed;
+                Ok(())
+            })
+            .unwrap();
+
+        let default_stage = issue_default_stage(AgentKind::Code, &IssueType::Spec);
+        update_task_for_issue(store, "done", None, default_stage.as_deref()).unwrap();
+
+        let task = store.load_task("done").unwrap();
+        assert_eq!(task.stage, "spec-review-issues");
+        assert_eq!(task.status, TaskStatus::Issues);
+    }
+
+    #[test]
+    fn resolving_last_issue_restores_task_status() {
+        let store: &dyn StateStore = &MemoryStore::new();
+        add_task(store, "fixing", "build", "2026-01-01T00:00:00Z");
+        let mut issue = add_issue(store, "fixing", IssueType::Build);
+        update_task_for_issue(store, "fixing", None, Some("build")).unwrap();
+        assert!(task_has_open_issues(store, "fixing").unwrap());
+
+        issue.status = IssueStatus::Resolved;
+        store.save_issue(&issue).unwrap();
+        sync_task_status_for_issues(store, "fixing").unwrap();
+
+        assert!(!task_has_open_issues(store, "fixing").unwrap());
+        assert_eq!(
+            store.load_task("fixing").unwrap().status,
+            TaskStatus::Pending
+        );
+    }
+
+    #[test]
+    fn snooze_rejects_bad_units_and_overflowing_counts() {
+        assert!(parse_snooze(None, Some("3d")).unwrap().is_some());
+        let err = parse_snooze(None, Some("3ä")).unwrap_err();
+        assert!(err.to_string().contains("Invalid duration"), "{err}");
+        let err = parse_snooze(None, Some("99999999999999w")).unwrap_err();
+        assert!(err.to_string().contains("too far ahead"), "{err}");
+        assert!(parse_snooze(None, Some("ä")).is_err());
+    }
+
+    #[test]
+    fn unique_task_lookup_requires_single_match() {
+        let store: &dyn StateStore = &MemoryStore::new();
+        add_task(store, "one", "build", "2026-01-01T00:00:00Z");
+        assert_eq!(
+            find_unique_task(store, "build").unwrap().as_deref(),
+            Some("one")
+        );
+        add_task(store, "two", "build", "2026-01-02T00:00:00Z");
+        assert_eq!(find_unique_task(store, "build").unwrap(), None);
+    }
+
+    #[test]
+    fn review_with_next_stage_marks_issues() {
+        assert_eq!(
+            determine_next_status("review", true, "build", false),
+            TaskStatus::Issues
+        );
+        assert_eq!(
+            determine_next_status("review", true, "spec-review-issues", false),
+            TaskStatus::Pending
+        );
+        assert_eq!(
+            determine_next_status("build", false, "completed", false),
+            TaskStatus::Completed
+        );
+        assert_eq!(
+            determine_next_status("build", false, "completed", true),
+            TaskStatus::Issues
+        );
+    }
+}
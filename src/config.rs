@@ -0,0 +1,854 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::agent::AgentKind;
+use crate::issues::IssuePriority;
+use crate::model::Model;
+use crate::util::{read_text, validate_task_name};
+
+const CONFIG_FILE: &str = "mung.toml";
+const LEGACY_CONFIG_FILE: &str = "metagent.toml";
+
+/// Repo-level settings from `.agents/mung.toml` (or legacy `.agents/metagent.toml`).
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoConfig {
+    pub defaults: Defaults,
+    pub agents: Vec<AgentDefinition>,
+    pub stage_models: BTreeMap<String, String>,
+    /// `[model_args]`: extra flags per model CLI, e.g. `claude = ["--max-turns", "50"]`.
+    pub model_args: BTreeMap<String, Vec<String>>,
+    pub workdirs: WorkdirConfig,
+    pub escalation: Option<EscalationPolicy>,
+    pub custom_model: Option<CustomModelConfig>,
+    pub sla: Vec<SlaRule>,
+    pub gitignore: GitFilesConfig,
+    pub issues: IssueRetention,
+    pub transcripts: TranscriptConfig,
+    pub queue: QueueConfig,
+    pub approval: ApprovalConfig,
+    pub install: InstallConfig,
+    pub branches: BranchConfig,
+    pub spec: SpecConfig,
+    pub finish: FinishConfig,
+    pub notifications: NotificationConfig,
+    pub claims: ClaimsConfig,
+    pub retry: RetryConfig,
+    pub sessions: SessionsConfig,
+    pub state: StateConfig,
+    pub concurrency: ConcurrencyConfig,
+    pub commit: CommitConfig,
+    pub worktrees: WorktreeConfig,
+    pub jira: Option<JiraConfig>,
+    pub linear: Option<LinearConfig>,
+}
+
+/// `[defaults]`: repo-wide fallbacks for settings otherwise taken from flags and env
+/// vars. Flags and `MUNG_*` variables still win.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    pub agent: Option<String>,
+    pub model: Option<String>,
+    /// Model to run instead when a stage's model CLI isn't installed or executable.
+    pub fallback_model: Option<String>,
+    /// Run models without a terminal, like `--headless`.
+    pub headless: bool,
+    pub claim_ttl_seconds: u64,
+    pub loop_limit: Option<usize>,
+    /// Message catalog for CLI output (`MUNG_LOCALE` wins); prompts stay English.
+    pub locale: Option<String>,
+}
+
+impl Default for Defaults {
+    fn default() -> Self {
+        Self {
+            agent: None,
+            model: None,
+            fallback_model: None,
+            headless: false,
+            claim_ttl_seconds: 300,
+            loop_limit: None,
+            locale: None,
+        }
+    }
+}
+
+/// `[[agents]]`: a user-defined pipeline. `stages` run in order and finish into
+/// `completed`; prompts are paths under `.agents/<name>/` (default
+/// `prompts/<stage>.md`).
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AgentDefinition {
+    pub name: String,
+    pub stages: Vec<String>,
+    #[serde(default)]
+    pub queue_stages: Vec<String>,
+    #[serde(default)]
+    pub initial_stage: Option<String>,
+    #[serde(default)]
+    pub prompts: BTreeMap<String, String>,
+}
+
+impl AgentDefinition {
+    pub fn initial_stage(&self) -> &str {
+        self.initial_stage
+            .as_deref()
+            .or(self.stages.first().map(String::as_str))
+            .unwrap_or("completed")
+    }
+
+    fn validate(&self) -> Result<()> {
+        let name = &self.name;
+        if matches!(name.as_str(), "code" | "writer") {
+            bail!("agent '{name}' is built in and cannot be redefined");
+        }
+        validate_task_name(name).with_context(|| format!("invalid agent name '{name}'"))?;
+        if self.stages.is_empty() {
+            bail!("agent '{name}' must list at least one stage");
+        }
+        for (index, stage) in self.stages.iter().enumerate() {
+            if stage == "completed" || stage == "task" {
+                bail!("agent '{name}': stage '{stage}' is reserved");
+            }
+            if self.stages[..index].contains(stage) {
+                bail!("agent '{name}': stage '{stage}' is listed twice");
+            }
+        }
+        let referenced = self
+            .queue_stages
+            .iter()
+            .chain(self.initial_stage.iter())
+            .chain(self.prompts.keys());
+        for stage in referenced {
+            if !self.stages.contains(stage) {
+                bail!("agent '{name}': unknown stage '{stage}'");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run listed stages on a cheap model first; switch to the expensive model for the
+/// rest of the task once the stage fails or a review files too many issues.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EscalationPolicy {
+    #[serde(default)]
+    pub stages: Vec<String>,
+    pub cheap: String,
+    pub expensive: String,
+    #[serde(default = "default_review_issue_threshold")]
+    pub review_issue_threshold: usize,
+}
+
+/// `[custom_model]`: the program behind `--model custom`, e.g. a local Ollama agent
+/// or any OpenAI-compatible agent CLI. The prompt replaces `{prompt}` in `args`, or
+/// is passed as the last argument. `env` is added to the inherited environment.
+/// `resume_args` replace `args` for `mung resume`, with the model's conversation
+/// id in place of `{model_session}`. `print_args` replace `args` when mung wants a
+/// printed reply to one prompt (`mung commit`).
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CustomModelConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub resume_args: Option<Vec<String>>,
+    #[serde(default)]
+    pub print_args: Option<Vec<String>>,
+}
+
+/// `[[sla]]` rule: open issues of `priority` older than `max_age_hours` jump their
+/// task to the front of the queue and run `notify` once.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SlaRule {
+    pub priority: String,
+    pub max_age_hours: u64,
+    #[serde(default)]
+    pub notify: Option<String>,
+}
+
+/// `[jira]`: where `mung jira pull` reads tickets from and where `issue resolve`
+/// comments back. The API token is read from the `token_env` variable so the file
+/// can be committed. With `email` set requests use Jira Cloud basic auth (email and
+/// API token); without it the token is sent as a bearer token (Data Center PAT).
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct JiraConfig {
+    pub base_url: String,
+    pub project: String,
+    #[serde(default = "default_jira_token_env")]
+    pub token_env: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default = "default_true")]
+    pub comment_on_resolve: bool,
+}
+
+fn default_jira_token_env() -> String {
+    "JIRA_API_TOKEN".to_string()
+}
+
+/// `[linear]`: the team `mung sync linear` turns into tasks, and the workflow state
+/// each stage moves the Linear issue to (`[linear.states]`, stage = state name).
+/// Stages left out of `states` don't touch Linear. The API key is read from the
+/// `token_env` variable.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LinearConfig {
+    pub team: String,
+    #[serde(default = "default_linear_token_env")]
+    pub token_env: String,
+    /// Only sync issues carrying this label.
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default = "default_linear_states")]
+    pub states: BTreeMap<String, String>,
+}
+
+fn default_linear_token_env() -> String {
+    "LINEAR_API_KEY".to_string()
+}
+
+fn default_linear_states() -> BTreeMap<String, String> {
+    [
+        ("build", "In Progress"),
+        ("review", "In Review"),
+        ("completed", "Done"),
+    ]
+    .into_iter()
+    .map(|(stage, state)| (stage.to_string(), state.to_string()))
+    .collect()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// `[issues]`: resolved issues untouched for `archive_after_days` move to
+/// `issues/archive/`. Unset keeps them in place forever. `dedupe` decides what
+/// `issue add` does with an issue that matches an open one on the same task.
+/// `escalate` raises the priority of open issues as they age.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct IssueRetention {
+    pub archive_after_days: Option<u64>,
+    /// Open P3 issues of type `other` older than this are resolved as expired, so
+    /// advisory findings stop holding tasks out of `completed`.
+    pub expire_advisory_after_days: Option<u64>,
+    pub dedupe: DedupeMode,
+    pub escalate: Vec<PriorityEscalation>,
+}
+
+/// `[[issues.escalate]]`: an open `from` issue created more than `after_days` ago
+/// moves to `to`. Rules chain, so P3 -> P2 at 14 days and P2 -> P1 at 30 days takes
+/// a P3 issue to P1 after a month.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PriorityEscalation {
+    pub from: String,
+    pub to: String,
+    pub after_days: u64,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DedupeMode {
+    Off,
+    /// Print the likely originals and keep the new issue open.
+    #[default]
+    Warn,
+    /// Resolve the new issue as a duplicate of the closest match.
+    Link,
+}
+
+/// `[queue]`: how `run-next`/`run-queue` pick between tasks in different stages.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueueConfig {
+    pub policy: QueuePolicy,
+    pub fairness: Fairness,
+}
+
+/// Groups that `run-next`/`run-queue` take turns between: the group whose tasks ran
+/// least recently goes first, and `policy` picks within it.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Fairness {
+    /// One queue for everyone.
+    #[default]
+    None,
+    Owner,
+    /// A task with several labels takes part in each of their turns.
+    Label,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum QueuePolicy {
+    /// Earliest queue stage first (code: spec-review-issues, build, review).
+    #[default]
+    StageOrder,
+    /// Latest queue stage first, so in-flight work (reviews) drains before new builds.
+    FinishFirst,
+    /// Oldest task first regardless of stage; ranked tasks still lead.
+    OldestFirst,
+}
+
+impl QueuePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::StageOrder => "stage-order",
+            Self::FinishFirst => "finish-first",
+            Self::OldestFirst => "oldest-first",
+        }
+    }
+}
+
+/// `[approval]`: entering any of `stages` waits for `mung approve <task>`. `notify`
+/// runs once when a task reaches the gate; run-queue blocks the task after
+/// `timeout_minutes` without approval.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApprovalConfig {
+    pub stages: Vec<String>,
+    pub notify: Option<String>,
+    pub timeout_minutes: u64,
+}
+
+impl Default for ApprovalConfig {
+    fn default() -> Self {
+        Self {
+            stages: Vec::new(),
+            notify: None,
+            timeout_minutes: 60,
+        }
+    }
+}
+
+impl ApprovalConfig {
+    pub fn requires(&self, stage: &str) -> bool {
+        self.stages.iter().any(|candidate| candidate == stage)
+    }
+}
+
+/// `[notifications]`: chat webhooks posted to when a task completes or fails, or
+/// when a task's open P0 issues reach `p0_threshold`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationConfig {
+    pub webhooks: Vec<Webhook>,
+    /// Events to send; all of them by default.
+    pub on: Vec<NotifyEvent>,
+    pub p0_threshold: usize,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            webhooks: Vec::new(),
+            on: vec![
+                NotifyEvent::Completed,
+                NotifyEvent::Failed,
+                NotifyEvent::P0Issues,
+            ],
+            p0_threshold: 1,
+        }
+    }
+}
+
+impl NotificationConfig {
+    pub fn wants(&self, event: NotifyEvent) -> bool {
+        !self.webhooks.is_empty() && self.on.contains(&event)
+    }
+}
+
+/// `[[notifications.webhooks]]`: set `url`, or `url_env` to keep the secret URL out
+/// of the repo.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub kind: WebhookKind,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub url_env: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookKind {
+    Slack,
+    Discord,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotifyEvent {
+    Completed,
+    Failed,
+    P0Issues,
+}
+
+/// `[install]`: extra slash-command directories `mung install` links prompts into,
+/// for tools beyond Claude and Codex.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct InstallConfig {
+    pub command_dirs: Vec<ToolCommandDir>,
+}
+
+/// `[finish]`: with `marker` on, stage prompts also offer a `MUNG_FINISH:` output
+/// line as a way to finish, for environments where the model can't run commands.
+/// Model output is piped (and saved as a transcript) so it can be scanned.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct FinishConfig {
+    pub marker: bool,
+}
+
+/// `[commit]`: with `on_finish` on, finishing a stage of a task runs `git add -A`
+/// and commits with `message`, where `{task}`, `{stage}`, `{next}`, and `{session}`
+/// are filled in. `stages` limits this to those stages (default: every stage).
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommitConfig {
+    pub on_finish: bool,
+    pub stages: Vec<String>,
+    pub message: String,
+}
+
+impl Default for CommitConfig {
+    fn default() -> Self {
+        Self {
+            on_finish: false,
+            stages: Vec::new(),
+            message: "mung: {task} {stage} -> {next}\n\nSession: {session}".to_string(),
+        }
+    }
+}
+
+impl CommitConfig {
+    /// Whether finishing `stage` commits.
+    pub fn applies(&self, stage: &str) -> bool {
+        self.on_finish && (self.stages.is_empty() || self.stages.iter().any(|s| s == stage))
+    }
+
+    pub fn message(&self, task: &str, stage: &str, next: &str, session: &str) -> String {
+        self.message
+            .replace("{task}", task)
+            .replace("{stage}", stage)
+            .replace("{next}", next)
+            .replace("{session}", session)
+    }
+}
+
+/// `[worktrees]`: the stages in `stages` run in a git worktree of their own at
+/// `.agents/worktrees/<task>/`, on the task branch (`[branches] prefix`), so a
+/// broken run never touches the main checkout. `mung merge <task>` brings the
+/// branch back.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorktreeConfig {
+    pub stages: Vec<String>,
+}
+
+impl WorktreeConfig {
+    pub fn applies(&self, stage: &str) -> bool {
+        self.stages.iter().any(|s| s == stage)
+    }
+}
+
+/// `[claims]`: with `handover_note` on, taking over a claim whose holder died
+/// leaves a note in the task's next session prompt saying the previous run was
+/// cut off mid-flight.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClaimsConfig {
+    pub handover_note: bool,
+}
+
+/// `[sessions]`: with `prune_after_days`, `run-queue` and `reconcile` delete
+/// finished and failed sessions, and claim files with no live holder, that are
+/// older than that. The newest `keep_per_task` sessions of each task, and the
+/// one its `last_session` names, always stay.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionsConfig {
+    pub prune_after_days: Option<u64>,
+    pub keep_per_task: usize,
+}
+
+impl Default for SessionsConfig {
+    fn default() -> Self {
+        Self {
+            prune_after_days: None,
+            keep_per_task: 1,
+        }
+    }
+}
+
+/// `[state]`: where task, session, and issue state lives. `backend = "sqlite"`
+/// keeps it in one database at `path` (relative to `.agents/<agent>/`, default
+/// `state.db`) and needs a build with the `sqlite` feature. Specs, plans, prompts,
+/// transcripts, claims, and the event log stay files either way.
+///
+/// `backend = "remote"` shares state, claims, and the event log with other
+/// machines through the `mung serve` at `url`; specs and plans travel via git.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct StateConfig {
+    pub backend: StateBackend,
+    pub path: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StateBackend {
+    #[default]
+    Files,
+    Sqlite,
+    Remote,
+}
+
+impl StateConfig {
+    /// The database file, for backends that keep one.
+    pub fn database(&self, agent_root: &Path) -> Option<PathBuf> {
+        match self.backend {
+            StateBackend::Files | StateBackend::Remote => None,
+            StateBackend::Sqlite => {
+                Some(agent_root.join(self.path.as_deref().unwrap_or("state.db")))
+            }
+        }
+    }
+}
+
+/// `[concurrency]`: how many worker processes `run-queue` and `run-next` start
+/// (`--jobs` wins). Workers share the queue through claims, so each task still
+/// runs once.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConcurrencyConfig {
+    pub run_queue: usize,
+    pub run_next: usize,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            run_queue: 1,
+            run_next: 1,
+        }
+    }
+}
+
+/// `[retry]`: a stage that ends without finishing (including a model that exits
+/// non-zero) runs again up to `attempts` times before the task is marked failed,
+/// waiting `backoff_seconds`, doubling each time up to `max_backoff_seconds`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    pub attempts: u32,
+    pub backoff_seconds: u64,
+    pub max_backoff_seconds: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            attempts: 0,
+            backoff_seconds: 30,
+            max_backoff_seconds: 600,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Wait before retry number `attempt` (1-based).
+    pub fn backoff(&self, attempt: u32) -> u64 {
+        let factor = 1u64
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u64::MAX);
+        self.backoff_seconds
+            .saturating_mul(factor)
+            .min(self.max_backoff_seconds)
+    }
+}
+
+/// `[spec]`: when `progress_summary` is on, `run` on a task whose spec is partly
+/// written tells the spec session which files and sections are still template.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpecConfig {
+    pub progress_summary: bool,
+}
+
+/// `[branches]`: task branches are named `<prefix><task>`; `mung prune` removes the
+/// merged ones whose task was archived or deleted.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct BranchConfig {
+    pub prefix: String,
+    /// Branch or commit a task branch must be merged into before it is pruned.
+    pub merged_into: String,
+}
+
+impl Default for BranchConfig {
+    fn default() -> Self {
+        Self {
+            prefix: "mung/".to_string(),
+            merged_into: "HEAD".to_string(),
+        }
+    }
+}
+
+/// `[workdirs]`: directories, relative to the repo root, that the model runs in
+/// instead of the root. A `tasks` entry wins over a `stages` entry.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkdirConfig {
+    pub stages: BTreeMap<String, String>,
+    pub tasks: BTreeMap<String, String>,
+}
+
+/// `[[install.command_dirs]]`: `path` may start with `~/`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ToolCommandDir {
+    pub tool: String,
+    pub path: String,
+}
+
+/// `[transcripts]`: tee model output into `sessions/<id>/transcript.log`. The model
+/// then writes to a pipe instead of the terminal, so it is opt-in.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct TranscriptConfig {
+    pub capture: bool,
+}
+
+/// `[gitignore]`: ignore and attribute rules mung keeps in a managed block of
+/// `.agents/.gitignore` and `.agents/.gitattributes`. Patterns are relative to `.agents/`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitFilesConfig {
+    pub manage: bool,
+    pub ignore: Vec<String>,
+    pub attributes: Vec<String>,
+}
+
+impl Default for GitFilesConfig {
+    fn default() -> Self {
+        Self {
+            manage: true,
+            ignore: [
+                "*/sessions/",
+                "*/claims/",
+                "*/schedule/",
+                "worktrees/",
+                "*.lock",
+                "*.tmp",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            attributes: ["*/tasks/*/task.json linguist-generated=true"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }
+    }
+}
+
+fn default_review_issue_threshold() -> usize {
+    3
+}
+
+impl EscalationPolicy {
+    pub fn applies_to(&self, stage: &str) -> bool {
+        self.stages.iter().any(|candidate| candidate == stage)
+    }
+
+    pub fn cheap_model(&self) -> Result<Model> {
+        Model::from_str(&self.cheap).context("escalation.cheap")
+    }
+
+    pub fn expensive_model(&self) -> Result<Model> {
+        Model::from_str(&self.expensive).context("escalation.expensive")
+    }
+}
+
+impl RepoConfig {
+    /// `[stage_models]` entry for `stage`, else `[defaults] model`.
+    pub fn model_for_stage(&self, stage: &str) -> Option<Model> {
+        self.stage_models
+            .get(stage)
+            .or(self.defaults.model.as_ref())
+            .and_then(|model| Model::from_str(model).ok())
+    }
+
+    /// Where the model runs for `stage` of `task`: a `[workdirs]` entry under
+    /// `repo_root`, else `repo_root` itself.
+    pub fn workdir(&self, repo_root: &Path, stage: &str, task: Option<&str>) -> PathBuf {
+        task.and_then(|task| self.workdirs.tasks.get(task))
+            .or_else(|| self.workdirs.stages.get(stage))
+            .map(|dir| repo_root.join(dir))
+            .unwrap_or_else(|| repo_root.to_path_buf())
+    }
+}
+
+pub fn config_path(repo_root: &Path) -> PathBuf {
+    let agents_dir = repo_root.join(".agents");
+    let legacy = agents_dir.join(LEGACY_CONFIG_FILE);
+    let primary = agents_dir.join(CONFIG_FILE);
+    if !primary.exists() && legacy.exists() {
+        return legacy;
+    }
+    primary
+}
+
+pub fn load_config(repo_root: &Path) -> Result<RepoConfig> {
+    let path = config_path(repo_root);
+    if !path.exists() {
+        return Ok(RepoConfig::default());
+    }
+    let content = read_text(&path)?;
+    let config: RepoConfig =
+        toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+    config
+        .validate()
+        .with_context(|| format!("Invalid config {}", path.display()))?;
+    Ok(config)
+}
+
+impl RepoConfig {
+    fn validate(&self) -> Result<()> {
+        for (index, agent) in self.agents.iter().enumerate() {
+            agent.validate()?;
+            if self.agents[..index]
+                .iter()
+                .any(|other| other.name == agent.name)
+            {
+                bail!("agent '{}' is defined twice", agent.name);
+            }
+        }
+        if let Some(agent) = self.defaults.agent.as_ref() {
+            if !self.agents.iter().any(|custom| &custom.name == agent) {
+                AgentKind::from_str(agent)?;
+            }
+        }
+        if self.defaults.claim_ttl_seconds == 0 {
+            bail!("defaults.claim_ttl_seconds must be greater than zero");
+        }
+        if self.concurrency.run_queue == 0 || self.concurrency.run_next == 0 {
+            bail!("[concurrency] values must be greater than zero");
+        }
+        for model in self
+            .defaults
+            .model
+            .iter()
+            .chain(self.defaults.fallback_model.iter())
+            .chain(self.stage_models.values())
+        {
+            if Model::from_str(model)? == Model::Custom && self.custom_model.is_none() {
+                bail!("model 'custom' is configured but [custom_model] is not set");
+            }
+        }
+        for model in self.model_args.keys() {
+            Model::from_str(model).with_context(|| format!("[model_args] {model}"))?;
+        }
+        for (key, dir) in self
+            .workdirs
+            .stages
+            .iter()
+            .chain(self.workdirs.tasks.iter())
+        {
+            let path = Path::new(dir);
+            if dir.trim().is_empty()
+                || path.is_absolute()
+                || path
+                    .components()
+                    .any(|part| part == std::path::Component::ParentDir)
+            {
+                bail!(
+                    "workdirs entry '{}' must be a directory inside the repo, got '{}'",
+                    key,
+                    dir
+                );
+            }
+        }
+        for task in self.workdirs.tasks.keys() {
+            validate_task_name(task)?;
+        }
+        if let Some(policy) = self.escalation.as_ref() {
+            for model in [policy.cheap_model()?, policy.expensive_model()?] {
+                if model == Model::Custom && self.custom_model.is_none() {
+                    bail!("escalation uses 'custom' but [custom_model] is not set");
+                }
+            }
+            if policy.stages.is_empty() {
+                bail!("escalation.stages must list at least one stage");
+            }
+        }
+        if let Some(custom) = self.custom_model.as_ref() {
+            if custom.command.trim().is_empty() {
+                bail!("custom_model.command must not be empty");
+            }
+        }
+        for rule in &self.sla {
+            IssuePriority::from_str(&rule.priority)?;
+        }
+        for rule in &self.issues.escalate {
+            let from = IssuePriority::from_str(&rule.from)?;
+            let to = IssuePriority::from_str(&rule.to)?;
+            if to.weight() >= from.weight() {
+                bail!(
+                    "issues.escalate: {} -> {} does not raise the priority",
+                    from,
+                    to
+                );
+            }
+        }
+        for webhook in &self.notifications.webhooks {
+            if webhook.url.is_some() == webhook.url_env.is_some() {
+                bail!("each [[notifications.webhooks]] entry needs exactly one of url or url_env");
+            }
+        }
+        if let Some(jira) = self.jira.as_ref() {
+            if !jira.base_url.starts_with("http://") && !jira.base_url.starts_with("https://") {
+                bail!("jira.base_url must be an http(s) URL");
+            }
+            if jira.project.trim().is_empty() {
+                bail!("jira.project must not be empty");
+            }
+        }
+        if let Some(linear) = self.linear.as_ref() {
+            if linear.team.trim().is_empty() {
+                bail!("linear.team must not be empty");
+            }
+            if let Some((stage, _)) = linear
+                .states
+                .iter()
+                .find(|(_, state)| state.trim().is_empty())
+            {
+                bail!("linear.states.{} must name a workflow state", stage);
+            }
+        }
+        if self.commit.message.trim().is_empty() {
+            bail!("commit.message must not be empty");
+        }
+        if self.notifications.p0_threshold == 0 {
+            bail!("notifications.p0_threshold must be greater than zero");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,356 @@
+use anyhow::{bail, Result};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::agent::AgentKind;
+use crate::commands::{install_config, PROMPT_HOME_DIR};
+use crate::config::{load_config, RepoConfig, StateBackend};
+use crate::install::command_dirs;
+use crate::state::{is_orphaned_session, load_task};
+use crate::store::open_store;
+use crate::util::{find_on_path, home_dir, json_output, print_json};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+    Ok,
+    Warn,
+    Fail,
+}
+
+#[derive(Serialize, Debug)]
+struct Check {
+    check: String,
+    level: Level,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<String>,
+}
+
+impl Check {
+    fn new(check: &str, level: Level, detail: impl Into<String>) -> Self {
+        Self {
+            check: check.to_string(),
+            level,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn hint(mut self, hint: &str) -> Self {
+        self.hint = Some(hint.to_string());
+        self
+    }
+}
+
+/// `mung doctor`: read-only checks of model CLIs, installed prompts and slash
+/// commands, and the current repo's `.agents/` state. Fails when any check fails;
+/// warnings point at `mung install` or `mung reconcile`.
+pub fn cmd_doctor(agent: AgentKind, repo_root: Option<&Path>) -> Result<()> {
+    // Probing claims must not take their locks.
+    crate::state::set_read_only(true);
+    let mut checks = Vec::new();
+    check_models(&mut checks, repo_root);
+    let home = home_dir()?;
+    check_prompts(&mut checks, &home);
+    check_slash_commands(&mut checks, &home);
+    match repo_root {
+        Some(repo_root) => check_repo(&mut checks, agent, repo_root),
+        None => checks.push(Check::new(
+            "repo",
+            Level::Warn,
+            "not inside a git repo; skipped .agents checks",
+        )),
+    }
+
+    let failures = checks
+        .iter()
+        .filter(|check| check.level == Level::Fail)
+        .count();
+    if json_output() {
+        print_json(&checks)?;
+    } else {
+        let width = checks
+            .iter()
+            .map(|check| check.check.len())
+            .max()
+            .unwrap_or(0);
+        for check in &checks {
+            let level = match check.level {
+                Level::Ok => "ok  ".green().to_string(),
+                Level::Warn => "warn".yellow().to_string(),
+                Level::Fail => "fail".red().to_string(),
+            };
+            println!("{}  {:<width$}  {}", level, check.check, check.detail);
+            if let Some(hint) = check.hint.as_deref() {
+                println!("      {:<width$}  {}", "", hint.dimmed());
+            }
+        }
+    }
+    if failures > 0 {
+        bail!("doctor found {} problem(s)", failures);
+    }
+    Ok(())
+}
+
+fn check_models(checks: &mut Vec<Check>, repo_root: Option<&Path>) {
+    let mut programs = vec!["claude".to_string(), "codex".to_string()];
+    let custom = repo_root
+        .and_then(|repo_root| load_config(repo_root).ok())
+        .and_then(|config| config.custom_model)
+        .map(|custom| custom.command);
+    programs.extend(custom.clone());
+    let mut runnable = 0;
+    for program in &programs {
+        let Some(path) = find_on_path(program) else {
+            checks.push(
+                Check::new(program, Level::Warn, "not found on PATH")
+                    .hint("install it or pick another model with --model"),
+            );
+            continue;
+        };
+        // A custom command may not understand --version; finding it is enough.
+        let ran = Some(program) == custom.as_ref()
+            || Command::new(&path)
+                .arg("--version")
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .map(|status| status.success())
+                .unwrap_or(false);
+        if ran {
+            runnable += 1;
+            checks.push(Check::new(program, Level::Ok, path.display().to_string()));
+        } else {
+            checks.push(Check::new(
+                program,
+                Level::Fail,
+                format!("{} --version failed", path.display()),
+            ));
+        }
+    }
+    if runnable == 0 && custom.is_none() {
+        checks.push(
+            Check::new("models", Level::Fail, "no runnable model CLI")
+                .hint("install claude or codex, or configure [custom_model]"),
+        );
+    }
+}
+
+fn check_prompts(checks: &mut Vec<Check>, home: &Path) {
+    for agent in [AgentKind::Code, AgentKind::Writer] {
+        let name = format!("prompts/{}", agent.name());
+        let dir = home.join(PROMPT_HOME_DIR).join(agent.name());
+        if !dir.is_dir() {
+            checks.push(
+                Check::new(&name, Level::Fail, format!("{} missing", dir.display()))
+                    .hint("run 'mung install'"),
+            );
+            continue;
+        }
+        let mut missing = Vec::new();
+        let mut outdated = Vec::new();
+        for (file, content) in agent.install_prompts() {
+            match fs::read_to_string(dir.join(file)) {
+                Ok(installed) if installed == content => {}
+                Ok(_) => outdated.push(file),
+                Err(_) => missing.push(file),
+            }
+        }
+        if !missing.is_empty() {
+            checks.push(
+                Check::new(
+                    &name,
+                    Level::Fail,
+                    format!("missing {}", missing.join(", ")),
+                )
+                .hint("run 'mung install'"),
+            );
+        } else if !outdated.is_empty() {
+            checks.push(
+                Check::new(
+                    &name,
+                    Level::Warn,
+                    format!("older than this binary: {}", outdated.join(", ")),
+                )
+                .hint("run 'mung prompts diff', then 'mung prompts update'"),
+            );
+        } else {
+            checks.push(Check::new(&name, Level::Ok, dir.display().to_string()));
+        }
+    }
+}
+
+fn check_slash_commands(checks: &mut Vec<Check>, home: &Path) {
+    for dir in command_dirs(home, &install_config()) {
+        let name = format!("commands/{}", dir.tool);
+        let Ok(entries) = fs::read_dir(&dir.path) else {
+            checks.push(
+                Check::new(
+                    &name,
+                    Level::Warn,
+                    format!("{} missing", dir.path.display()),
+                )
+                .hint("run 'mung install' to link slash commands"),
+            );
+            continue;
+        };
+        let broken: Vec<String> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.symlink_metadata()
+                    .map(|meta| meta.file_type().is_symlink())
+                    .unwrap_or(false)
+                    && fs::metadata(path).is_err()
+            })
+            .map(|path| {
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        if broken.is_empty() {
+            checks.push(Check::new(&name, Level::Ok, dir.path.display().to_string()));
+        } else {
+            checks.push(
+                Check::new(
+                    &name,
+                    Level::Fail,
+                    format!("broken links: {}", broken.join(", ")),
+                )
+                .hint("run 'mung install' to relink"),
+            );
+        }
+    }
+}
+
+fn check_repo(checks: &mut Vec<Check>, agent: AgentKind, repo_root: &Path) {
+    let agent_root = repo_root.join(".agents").join(agent.name());
+    if !agent_root.is_dir() {
+        checks.push(
+            Check::new(
+                "layout",
+                Level::Warn,
+                format!(".agents/{}/ not found", agent.name()),
+            )
+            .hint("run 'mung init' in this repo"),
+        );
+        return;
+    }
+    let config = match load_config(repo_root) {
+        Ok(config) => {
+            checks.push(Check::new("config", Level::Ok, ".agents/mung.toml"));
+            config
+        }
+        Err(err) => {
+            checks.push(Check::new("config", Level::Fail, format!("{:#}", err)));
+            RepoConfig::default()
+        }
+    };
+    let store = match open_store(&agent_root, &config.state) {
+        Ok(store) => {
+            if let Some(database) = config.state.database(&agent_root) {
+                let detail = format!(
+                    "{} tasks in {}",
+                    store.list_tasks().len(),
+                    database.display()
+                );
+                checks.push(Check::new("state", Level::Ok, detail));
+            }
+            store
+        }
+        Err(err) => {
+            checks.push(Check::new("state", Level::Fail, format!("{:#}", err)));
+            return;
+        }
+    };
+
+    let mut problems = Vec::new();
+    let mut renamed = Vec::new();
+    let mut dirs = vec!["tasks"];
+    if agent == AgentKind::Code {
+        dirs.push("issues");
+    }
+    for dir in dirs {
+        if !agent_root.join(dir).is_dir() {
+            problems.push(format!("{dir}/ missing"));
+        }
+    }
+    let files_backend = config.state.backend == StateBackend::Files;
+    let task_dirs = fs::read_dir(agent_root.join("tasks")).ok();
+    if let Some(entries) = task_dirs.filter(|_| files_backend) {
+        for entry in entries.flatten().filter(|entry| entry.path().is_dir()) {
+            let dir_name = entry.file_name().to_string_lossy().to_string();
+            match load_task(&entry.path().join("task.json")) {
+                Ok(task) if task.task != dir_name => renamed.push(dir_name),
+                Ok(_) => {}
+                Err(_) => problems.push(format!("tasks/{dir_name}/task.json unreadable")),
+            }
+        }
+    }
+    if !problems.is_empty() {
+        checks.push(Check::new("layout", Level::Fail, problems.join("; ")));
+    } else if !renamed.is_empty() {
+        checks.push(
+            Check::new(
+                "layout",
+                Level::Warn,
+                format!("task.json names another task: {}", renamed.join(", ")),
+            )
+            .hint("run 'mung reconcile'"),
+        );
+    } else {
+        checks.push(Check::new(
+            "layout",
+            Level::Ok,
+            format!(".agents/{}/", agent.name()),
+        ));
+    }
+
+    let stale: Vec<String> = store
+        .list_claims()
+        .into_iter()
+        .filter(|claim| !store.has_active_claim(claim).unwrap_or(true))
+        .collect();
+    if stale.is_empty() {
+        checks.push(Check::new("claims", Level::Ok, "no stale claims"));
+    } else {
+        checks.push(
+            Check::new(
+                "claims",
+                Level::Warn,
+                format!("stale: {}", stale.join(", ")),
+            )
+            .hint("run 'mung reconcile' to remove them"),
+        );
+    }
+
+    let host = hostname::get()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let orphaned: Vec<String> = store
+        .list_sessions()
+        .into_iter()
+        .filter(|session| is_orphaned_session(session, &host))
+        .map(|session| session.session_id)
+        .collect();
+    if orphaned.is_empty() {
+        checks.push(Check::new("sessions", Level::Ok, "no orphaned sessions"));
+    } else {
+        checks.push(
+            Check::new(
+                "sessions",
+                Level::Warn,
+                format!("running with a dead pid: {}", orphaned.join(", ")),
+            )
+            .hint("run 'mung reconcile' to mark them failed"),
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::util::{env_var, now_iso};
+
+pub const EVENTS_FILE: &str = "events.jsonl";
+
+/// Who caused an event: a person's identity plus the process (and session, when the
+/// change came from inside a model run).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Actor {
+    pub user: String,
+    pub host: String,
+    pub pid: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+}
+
+impl Actor {
+    /// `user` overrides the detected identity (`MUNG_USER`, git `user.name <user.email>`,
+    /// then `$USER`).
+    pub fn current(repo_root: &Path, host: &str, user: Option<&str>) -> Self {
+        let user = user
+            .map(str::to_string)
+            .or_else(|| env_var("MUNG_USER", "METAGENT_USER"))
+            .or_else(|| crate::git::user_identity(repo_root))
+            .or_else(|| std::env::var("USER").ok())
+            .unwrap_or_else(|| "unknown".to_string());
+        Self {
+            user,
+            host: host.to_string(),
+            pid: std::process::id(),
+            session: env_var("MUNG_SESSION", "METAGENT_SESSION"),
+        }
+    }
+}
+
+/// One line of `.agents/<agent>/events.jsonl`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Event {
+    pub at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    pub event: String,
+    pub actor: Actor,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Event {
+    pub fn new(task: &str, event: &str, actor: Actor) -> Self {
+        Self {
+            at: now_iso(),
+            task: Some(task.to_string()),
+            event: event.to_string(),
+            actor,
+            from: None,
+            to: None,
+            note: None,
+        }
+    }
+}
+
+pub fn events_path(agent_root: &Path) -> PathBuf {
+    agent_root.join(EVENTS_FILE)
+}
+
+/// Appends one JSON line; a single `O_APPEND` write keeps concurrent writers from
+/// interleaving.
+pub fn append_event(agent_root: &Path, event: &Event) -> Result<()> {
+    let path = events_path(agent_root);
+    let mut line = serde_json::to_string(event)?;
+    line.push('\n');
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Events in the order they were written. Lines that fail to parse are skipped.
+pub fn list_events(agent_root: &Path) -> Result<Vec<Event>> {
+    let path = events_path(agent_root);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
@@ -0,0 +1,153 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use crate::commands::{cmd_finish, cmd_issue, CommandContext, IssueCommands};
+use crate::util::{env_var, read_text, write_text};
+
+/// Script for the built-in fake model: `default` runs for any stage without an
+/// entry in `stages`. `reply` is what it prints when asked a one-off question.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct FakeScript {
+    pub default: Option<Vec<FakeAction>>,
+    pub stages: HashMap<String, Vec<FakeAction>>,
+    pub reply: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "action", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum FakeAction {
+    Sleep {
+        ms: u64,
+    },
+    Write {
+        path: String,
+        #[serde(default)]
+        content: String,
+    },
+    Issue {
+        title: String,
+        #[serde(default)]
+        priority: Option<String>,
+        #[serde(default, rename = "type")]
+        issue_type: Option<String>,
+        #[serde(default)]
+        source: Option<String>,
+        #[serde(default)]
+        body: Option<String>,
+        #[serde(default)]
+        labels: Vec<String>,
+    },
+    Finish {
+        #[serde(default)]
+        next: Option<String>,
+    },
+    Exit {
+        #[serde(default)]
+        code: i32,
+    },
+}
+
+pub fn load_script(path: &Path) -> Result<FakeScript> {
+    let content = read_text(path)?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse fake agent script {}", path.display()))
+}
+
+fn script_actions(stage: &str) -> Result<Vec<FakeAction>> {
+    let Some(path) = env_var("MUNG_FAKE_AGENT", "METAGENT_FAKE_AGENT") else {
+        return Ok(vec![FakeAction::Finish { next: None }]);
+    };
+    let mut script = load_script(Path::new(&path))?;
+    if let Some(actions) = script.stages.remove(stage) {
+        return Ok(actions);
+    }
+    Ok(script
+        .default
+        .unwrap_or_else(|| vec![FakeAction::Finish { next: None }]))
+}
+
+/// Entry point of the hidden `fake-agent` subcommand that `--model fake` spawns in
+/// place of a model CLI. Without a script it finishes the session immediately;
+/// without a session (the bootstrap prompt from `init`) it does nothing.
+pub fn cmd_fake_agent(ctx: &CommandContext) -> Result<()> {
+    if std::env::var_os("MUNG_FAKE_PRINT").is_some() {
+        let script = match env_var("MUNG_FAKE_AGENT", "METAGENT_FAKE_AGENT") {
+            Some(path) => load_script(Path::new(&path))?,
+            None => FakeScript::default(),
+        };
+        println!(
+            "{}",
+            script
+                .reply
+                .unwrap_or_else(|| "chore: fake reply".to_string())
+        );
+        return Ok(());
+    }
+    let Some(session_id) = env_var("MUNG_SESSION", "METAGENT_SESSION") else {
+        println!("fake agent: no session, nothing to do");
+        return Ok(());
+    };
+    // Real models print their conversation id the same way; `resume` picks it up.
+    println!("session id: fake-{}", session_id);
+    let session = ctx.store.load_session(&session_id)?;
+    let task = session.task.clone();
+    let finish_stage = if ctx
+        .agent
+        .valid_finish_stages()
+        .contains(&session.stage.as_str())
+    {
+        session.stage.clone()
+    } else {
+        "task".to_string()
+    };
+
+    for action in script_actions(&session.stage)? {
+        match action {
+            FakeAction::Sleep { ms } => thread::sleep(Duration::from_millis(ms)),
+            FakeAction::Write { path, content } => {
+                write_text(&ctx.repo_root.join(path), &content)?;
+            }
+            FakeAction::Issue {
+                title,
+                priority,
+                issue_type,
+                source,
+                body,
+                labels,
+            } => {
+                cmd_issue(
+                    ctx,
+                    IssueCommands::Add {
+                        title,
+                        task: task.clone(),
+                        priority,
+                        issue_type,
+                        source,
+                        file: None,
+                        stage: None,
+                        body,
+                        stdin_body: false,
+                        labels,
+                        attachments: Vec::new(),
+                    },
+                )?;
+            }
+            FakeAction::Finish { next } => {
+                cmd_finish(
+                    ctx,
+                    Some(finish_stage.clone()),
+                    next,
+                    Some(session_id.clone()),
+                    task.clone(),
+                )?;
+            }
+            FakeAction::Exit { code } => std::process::exit(code),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,294 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Files changed between a base commit and the working tree, with `git diff` stats.
+pub struct ChangeSummary {
+    pub base: String,
+    pub stat: String,
+    pub files: Vec<(String, String)>,
+}
+
+fn git_output(repo_root: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo_root)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Runs a git command that changes the repo, surfacing git's own error message.
+fn git_run(repo_root: &Path, args: &[&str]) -> Result<()> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo_root)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+pub fn head_commit(repo_root: &Path) -> Option<String> {
+    let head = git_output(repo_root, &["rev-parse", "HEAD"])?;
+    let head = head.trim();
+    if head.is_empty() {
+        None
+    } else {
+        Some(head.to_string())
+    }
+}
+
+pub fn current_branch(repo_root: &Path) -> Option<String> {
+    let branch = git_output(repo_root, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    let branch = branch.trim();
+    if branch.is_empty() {
+        None
+    } else {
+        Some(branch.to_string())
+    }
+}
+
+/// `Name <email>` from git config, or whichever half is set.
+pub fn user_identity(repo_root: &Path) -> Option<String> {
+    let config = |key: &str| {
+        git_output(repo_root, &["config", key])
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    match (config("user.name"), config("user.email")) {
+        (Some(name), Some(email)) => Some(format!("{name} <{email}>")),
+        (name, email) => name.or(email),
+    }
+}
+
+/// Tracked files under `path` that the current ignore rules would exclude.
+pub fn tracked_ignored_files(repo_root: &Path, path: &str) -> Vec<String> {
+    git_output(
+        repo_root,
+        &[
+            "ls-files",
+            "--cached",
+            "--ignored",
+            "--exclude-standard",
+            "--",
+            path,
+        ],
+    )
+    .map(|names| {
+        names
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Files changed since `base` (or across an `a..b` range), with `git diff` stats.
+pub fn change_summary(repo_root: &Path, base: &str) -> Option<ChangeSummary> {
+    let stat = git_output(repo_root, &["diff", "--shortstat", base])?;
+    let names = git_output(repo_root, &["diff", "--name-status", base])?;
+    let files = names
+        .lines()
+        .filter_map(|line| {
+            let (status, path) = line.split_once('\t')?;
+            Some((status.trim().to_string(), path.trim().to_string()))
+        })
+        .collect();
+    Some(ChangeSummary {
+        base: base.to_string(),
+        stat: stat.trim().to_string(),
+        files,
+    })
+}
+
+/// `git diff <range>`: a base commit (against the working tree) or `a..b`.
+pub fn diff_patch(repo_root: &Path, range: &str) -> Option<String> {
+    git_output(repo_root, &["diff", range])
+}
+
+/// `git diff --no-index` between two directories (paths relative to `cwd`); empty
+/// when they match.
+pub fn diff_dirs(cwd: &Path, from: &str, to: &str) -> Result<String> {
+    let output = Command::new("git")
+        .args(["diff", "--no-index", "--stat", "--patch", from, to])
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run git")?;
+    // Exit status 1 just means the directories differ.
+    if !matches!(output.status.code(), Some(0 | 1)) {
+        bail!(
+            "git diff failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Files touched by a task: the diff from its base commit plus any commit whose
+/// message mentions the task name.
+pub fn task_changed_files(repo_root: &Path, base: Option<&str>, task: &str) -> Vec<String> {
+    let mut files = Vec::new();
+    if let Some(base) = base {
+        if let Some(names) = git_output(repo_root, &["diff", "--name-only", base]) {
+            files.extend(names.lines().map(|line| line.trim().to_string()));
+        }
+    }
+    let grep = format!("--grep={task}");
+    if let Some(names) = git_output(
+        repo_root,
+        &["log", "--fixed-strings", &grep, "--name-only", "--format="],
+    ) {
+        files.extend(names.lines().map(|line| line.trim().to_string()));
+    }
+    files.retain(|file| !file.is_empty());
+    files.sort();
+    files.dedup();
+    files
+}
+
+pub fn short_commit(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
+
+/// A linked worktree and the branch checked out in it.
+pub struct Worktree {
+    pub path: PathBuf,
+    pub branch: Option<String>,
+}
+
+pub fn local_branches(repo_root: &Path) -> Vec<String> {
+    git_output(
+        repo_root,
+        &["for-each-ref", "--format=%(refname:short)", "refs/heads/"],
+    )
+    .map(|names| names.lines().map(|line| line.trim().to_string()).collect())
+    .unwrap_or_default()
+}
+
+/// Local branches whose tips are reachable from `target`.
+pub fn merged_branches(repo_root: &Path, target: &str) -> Option<Vec<String>> {
+    let names = git_output(
+        repo_root,
+        &["branch", "--format=%(refname:short)", "--merged", target],
+    )?;
+    Some(names.lines().map(|line| line.trim().to_string()).collect())
+}
+
+/// Worktrees other than the main one.
+pub fn linked_worktrees(repo_root: &Path) -> Vec<Worktree> {
+    let Some(listing) = git_output(repo_root, &["worktree", "list", "--porcelain"]) else {
+        return Vec::new();
+    };
+    let mut worktrees: Vec<Worktree> = Vec::new();
+    for line in listing.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            worktrees.push(Worktree {
+                path: PathBuf::from(path),
+                branch: None,
+            });
+        } else if let Some(branch) = line.strip_prefix("branch ") {
+            if let Some(worktree) = worktrees.last_mut() {
+                worktree.branch = Some(branch.trim_start_matches("refs/heads/").to_string());
+            }
+        }
+    }
+    // The first entry is always the main worktree.
+    worktrees.into_iter().skip(1).collect()
+}
+
+/// `git worktree remove`, which refuses worktrees with uncommitted changes.
+pub fn remove_worktree(repo_root: &Path, path: &Path) -> Result<()> {
+    git_run(
+        repo_root,
+        &["worktree", "remove", &path.display().to_string()],
+    )
+}
+
+/// Checks `branch` out in a new worktree at `path`, creating the branch from HEAD
+/// when it doesn't exist yet.
+pub fn add_worktree(repo_root: &Path, path: &Path, branch: &str) -> Result<()> {
+    let path = path.display().to_string();
+    if local_branches(repo_root).iter().any(|name| name == branch) {
+        git_run(repo_root, &["worktree", "add", "-q", &path, branch])
+    } else {
+        git_run(repo_root, &["worktree", "add", "-q", "-b", branch, &path])
+    }
+}
+
+/// `git status --porcelain` for `paths` (everything when empty); empty when clean.
+pub fn uncommitted_changes(repo_root: &Path, paths: &[&str]) -> Vec<String> {
+    let mut args = vec!["status", "--porcelain", "--"];
+    args.extend_from_slice(paths);
+    git_output(repo_root, &args)
+        .map(|status| status.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Merges `branch` into the checked-out branch: fast-forward only, or always as a
+/// merge commit with `message`.
+pub fn merge_branch(
+    repo_root: &Path,
+    branch: &str,
+    message: &str,
+    fast_forward_only: bool,
+) -> Result<()> {
+    if fast_forward_only {
+        git_run(repo_root, &["merge", "-q", "--ff-only", branch])
+    } else {
+        git_run(
+            repo_root,
+            &["merge", "-q", "--no-ff", "-m", message, branch],
+        )
+    }
+}
+
+/// Rebases the branch checked out at `checkout` onto `upstream`.
+pub fn rebase_onto(checkout: &Path, upstream: &str) -> Result<()> {
+    git_run(checkout, &["rebase", "-q", upstream])
+}
+
+/// Stages everything (`git add -A`) and commits it with `message`. Returns the new
+/// commit, or `None` when there was nothing to commit.
+pub fn commit_all(repo_root: &Path, message: &str) -> Result<Option<String>> {
+    git_run(repo_root, &["add", "-A"])?;
+    if staged_diff(repo_root).is_none() {
+        return Ok(None);
+    }
+    commit_staged(repo_root, message).map(Some)
+}
+
+/// `git diff --cached` with a `--stat` header, or `None` when nothing is staged.
+pub fn staged_diff(repo_root: &Path) -> Option<String> {
+    let diff = git_output(repo_root, &["diff", "--cached", "--stat", "--patch"])?;
+    if diff.trim().is_empty() {
+        None
+    } else {
+        Some(diff)
+    }
+}
+
+/// Commits what is staged with `message` and returns the new commit.
+pub fn commit_staged(repo_root: &Path, message: &str) -> Result<String> {
+    git_run(repo_root, &["commit", "-q", "-m", message])?;
+    head_commit(repo_root).context("Commit succeeded but HEAD could not be read")
+}
+
+/// Force-deletes a local branch; check `merged_branches` first.
+pub fn delete_branch(repo_root: &Path, branch: &str) -> Result<()> {
+    git_run(repo_root, &["branch", "-D", branch])
+}
@@ -0,0 +1,113 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::config::GitFilesConfig;
+use crate::util::write_text;
+
+const BLOCK_START: &str = "# >>> mung managed (edit [gitignore] in .agents/mung.toml) >>>";
+const BLOCK_END: &str = "# <<< mung managed <<<";
+
+/// Replaces the managed block in `existing` with `lines`, appending the block when it
+/// is missing. Lines outside the block are left alone.
+pub fn apply_managed_block(existing: &str, lines: &[String]) -> String {
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+    let mut state = 0;
+    for line in existing.lines() {
+        match state {
+            0 if line.trim() == BLOCK_START => state = 1,
+            0 => before.push(line),
+            1 if line.trim() == BLOCK_END => state = 2,
+            1 => {}
+            _ => after.push(line),
+        }
+    }
+    while before.last().is_some_and(|line| line.trim().is_empty()) {
+        before.pop();
+    }
+
+    let mut out = String::new();
+    for line in &before {
+        out.push_str(line);
+        out.push('\n');
+    }
+    if !lines.is_empty() {
+        if !before.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(BLOCK_START);
+        out.push('\n');
+        for line in lines {
+            out.push_str(line);
+            out.push('\n');
+        }
+        out.push_str(BLOCK_END);
+        out.push('\n');
+    }
+    for line in &after {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+fn managed_files(agents_dir: &Path, config: &GitFilesConfig) -> Vec<(PathBuf, String)> {
+    [
+        (agents_dir.join(".gitignore"), &config.ignore),
+        (agents_dir.join(".gitattributes"), &config.attributes),
+    ]
+    .into_iter()
+    .map(|(path, lines)| {
+        let existing = std::fs::read_to_string(&path).unwrap_or_default();
+        let updated = apply_managed_block(&existing, lines);
+        (path, updated)
+    })
+    .collect()
+}
+
+/// Files under `.agents/` whose managed block differs from the config.
+pub fn stale_git_files(agents_dir: &Path, config: &GitFilesConfig) -> Vec<PathBuf> {
+    managed_files(agents_dir, config)
+        .into_iter()
+        .filter(|(path, updated)| std::fs::read_to_string(path).unwrap_or_default() != *updated)
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// Writes the managed blocks and returns the files that changed.
+pub fn sync_git_files(agents_dir: &Path, config: &GitFilesConfig) -> Result<Vec<PathBuf>> {
+    let mut changed = Vec::new();
+    for (path, updated) in managed_files(agents_dir, config) {
+        let existing = std::fs::read_to_string(&path).unwrap_or_default();
+        if existing == updated || (updated.is_empty() && !path.exists()) {
+            continue;
+        }
+        write_text(&path, &updated)?;
+        changed.push(path);
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_managed_block, BLOCK_END, BLOCK_START};
+
+    #[test]
+    fn managed_block_is_replaced_in_place() {
+        let lines = vec!["*/sessions/".to_string()];
+        let first = apply_managed_block("notes.txt\n", &lines);
+        assert_eq!(
+            first,
+            format!("notes.txt\n\n{BLOCK_START}\n*/sessions/\n{BLOCK_END}\n")
+        );
+        assert_eq!(apply_managed_block(&first, &lines), first);
+
+        let edited = format!("{first}local.md\n");
+        let updated = apply_managed_block(&edited, &["*.tmp".to_string()]);
+        assert_eq!(
+            updated,
+            format!("notes.txt\n\n{BLOCK_START}\n*.tmp\n{BLOCK_END}\nlocal.md\n")
+        );
+        assert_eq!(apply_managed_block(&updated, &[]), "notes.txt\nlocal.md\n");
+    }
+}
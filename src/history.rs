@@ -0,0 +1,477 @@
+use anyhow::{bail, Result};
+use owo_colors::OwoColorize;
+use serde_json::json;
+use std::str::FromStr;
+
+use crate::commands::{
+    format_duration, scan_task_sessions, session_duration_seconds, session_outcome, task_sessions,
+    CommandContext,
+};
+use crate::events::Event;
+use crate::messages::msg;
+use crate::state::{SessionState, SessionStatus, TaskState, TaskStatus};
+use crate::util::{json_output, now_iso, print_json, validate_task_name};
+
+/// `task` plus every name it had before, following `renamed` events backwards.
+fn task_names(events: &[Event], task: &str) -> Vec<String> {
+    let mut names = vec![task.to_string()];
+    for event in events.iter().rev() {
+        if event.event != "renamed" || event.to.as_ref() != names.last() {
+            continue;
+        }
+        if let Some(from) = event.from.as_ref() {
+            if !names.contains(from) {
+                names.push(from.clone());
+            }
+        }
+    }
+    names
+}
+
+const APPROVAL_EVENTS: &[&str] = &["approval_requested", "approved"];
+
+/// Session history, newest first, optionally for one task.
+pub fn cmd_log(ctx: &CommandContext, task: Option<&str>, limit: Option<usize>) -> Result<()> {
+    if let Some(task) = task {
+        validate_task_name(task)?;
+    }
+    let mut sessions: Vec<SessionState> = match task {
+        // Sessions of archived or deleted tasks are still listed.
+        Some(task) => match ctx.store.load_task(task) {
+            Ok(task_state) => task_sessions(ctx, &task_state),
+            Err(_) => scan_task_sessions(ctx, task),
+        },
+        None => ctx.store.list_sessions(),
+    };
+    sessions.sort_by(|a, b| {
+        b.started_at
+            .cmp(&a.started_at)
+            .then_with(|| b.session_id.cmp(&a.session_id))
+    });
+    if let Some(limit) = limit {
+        sessions.truncate(limit);
+    }
+
+    if json_output() {
+        let mut entries = Vec::new();
+        for session in &sessions {
+            let mut value = serde_json::to_value(session)?;
+            value["duration_seconds"] = json!(session_duration_seconds(session));
+            value["outcome"] = json!(session_outcome(ctx, session));
+            entries.push(value);
+        }
+        return print_json(&entries);
+    }
+    if sessions.is_empty() {
+        println!("{}", "No sessions".dimmed());
+        return Ok(());
+    }
+    for session in &sessions {
+        let status = match session.status {
+            SessionStatus::Running => session.status.to_string().yellow().to_string(),
+            SessionStatus::Finished => session.status.to_string().green().to_string(),
+            SessionStatus::Failed => session.status.to_string().red().to_string(),
+            SessionStatus::Skipped => session.status.to_string().dimmed().to_string(),
+        };
+        println!(
+            "{}  {}  {}  {}{}  {}  {}  {}",
+            session.started_at.dimmed(),
+            session.session_id,
+            session.task.as_deref().unwrap_or("-"),
+            session.stage,
+            session
+                .model
+                .as_deref()
+                .map(|model| format!(" ({model})"))
+                .unwrap_or_default(),
+            session_duration_seconds(session)
+                .map(format_duration)
+                .unwrap_or_else(|| "?".to_string()),
+            status,
+            session_outcome(ctx, session)
+        );
+    }
+    Ok(())
+}
+
+fn format_event(event: &Event) -> String {
+    let mut line = event.event.replace('_', " ");
+    match (event.from.as_deref(), event.to.as_deref()) {
+        (Some(from), Some(to)) => line.push_str(&format!(" {from} -> {to}")),
+        (None, Some(to)) => line.push_str(&format!(" -> {to}")),
+        _ => {}
+    }
+    let session = event
+        .actor
+        .session
+        .as_deref()
+        .map(|session| format!(", session {session}"))
+        .unwrap_or_default();
+    line.push_str(&format!(
+        " by {} ({} pid {}{})",
+        event.actor.user, event.actor.host, event.actor.pid, session
+    ));
+    if let Some(note) = event.note.as_deref() {
+        line.push_str(&format!(": {note}"));
+    }
+    line
+}
+
+/// One task's sessions and recorded events, oldest first.
+pub fn cmd_history(ctx: &CommandContext, task: &str) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    let sessions = task_sessions(ctx, &ctx.store.load_task(task)?);
+    let events = ctx.store.list_events()?;
+    let names = task_names(&events, task);
+    let events: Vec<Event> = events
+        .into_iter()
+        .filter(|event| {
+            event
+                .task
+                .as_ref()
+                .is_some_and(|event_task| names.contains(event_task))
+        })
+        .collect();
+
+    if json_output() {
+        return print_json(&json!({
+            "task": task,
+            "sessions": sessions,
+            "events": events,
+        }));
+    }
+
+    let mut entries: Vec<(String, String)> = sessions
+        .iter()
+        .map(|session| {
+            (
+                session.started_at.clone(),
+                format!(
+                    "session {} {}{} {} {}",
+                    session.session_id,
+                    session.stage,
+                    session
+                        .model
+                        .as_deref()
+                        .map(|model| format!(" ({model})"))
+                        .unwrap_or_default(),
+                    session.status,
+                    session_outcome(ctx, session)
+                ),
+            )
+        })
+        .collect();
+    entries.extend(
+        events
+            .iter()
+            .map(|event| (event.at.clone(), format_event(event))),
+    );
+    // Stable sort keeps write order for events stamped in the same second.
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    println!("{}", format!("History for '{}'", task).bold());
+    if entries.is_empty() {
+        println!("  {}", "(none yet)".dimmed());
+    }
+    for (at, line) in entries {
+        println!("  {}  {}", at.dimmed(), line);
+    }
+    Ok(())
+}
+
+/// Approval trail across tasks: who let which task into a gated stage, and when.
+pub fn cmd_audit(ctx: &CommandContext, task: Option<&str>) -> Result<()> {
+    if let Some(task) = task {
+        validate_task_name(task)?;
+    }
+    let events = ctx.store.list_events()?;
+    let names = task.map(|task| task_names(&events, task));
+    let events: Vec<Event> = events
+        .into_iter()
+        .filter(|event| APPROVAL_EVENTS.contains(&event.event.as_str()))
+        .filter(|event| match (&names, event.task.as_ref()) {
+            (None, _) => true,
+            (Some(names), Some(event_task)) => names.contains(event_task),
+            (Some(_), None) => false,
+        })
+        .collect();
+    if json_output() {
+        return print_json(&events);
+    }
+    if events.is_empty() {
+        println!("{}", "No approval records".dimmed());
+        return Ok(());
+    }
+    for event in &events {
+        println!(
+            "{}  {}  {}",
+            event.at.dimmed(),
+            event.task.as_deref().unwrap_or("-"),
+            format_event(event)
+        );
+    }
+    Ok(())
+}
+
+/// `mung events`: the event history across tasks, oldest first; `limit` keeps the
+/// most recent entries.
+pub fn cmd_events(
+    ctx: &CommandContext,
+    task: Option<&str>,
+    kind: Option<&str>,
+    limit: Option<usize>,
+) -> Result<()> {
+    if let Some(task) = task {
+        validate_task_name(task)?;
+    }
+    let events = ctx.store.list_events()?;
+    let names = task.map(|task| task_names(&events, task));
+    let mut events: Vec<Event> = events
+        .into_iter()
+        .filter(|event| kind.is_none_or(|kind| event.event == kind))
+        .filter(|event| match (&names, event.task.as_ref()) {
+            (None, _) => true,
+            (Some(names), Some(event_task)) => names.contains(event_task),
+            (Some(_), None) => false,
+        })
+        .collect();
+    if let Some(limit) = limit {
+        events.drain(..events.len().saturating_sub(limit));
+    }
+    if json_output() {
+        return print_json(&events);
+    }
+    if events.is_empty() {
+        println!("{}", "No events".dimmed());
+        return Ok(());
+    }
+    for event in &events {
+        println!(
+            "{}  {}  {}",
+            event.at.dimmed(),
+            event.task.as_deref().unwrap_or("-"),
+            format_event(event)
+        );
+    }
+    Ok(())
+}
+
+/// The stages a task went through, from its recorded history: `spec->planning(2x)->build`,
+/// counting the sessions run in each visit to a stage.
+pub fn build_task_history(task_state: &TaskState) -> String {
+    let mut visits: Vec<(&str, usize)> = Vec::new();
+    let mut session = None;
+    for entry in &task_state.transitions {
+        if visits.last().is_none_or(|(stage, _)| *stage != entry.stage) {
+            visits.push((&entry.stage, 0));
+        }
+        if entry.session.is_some() && entry.session != session {
+            if let Some((_, runs)) = visits.last_mut() {
+                *runs += 1;
+            }
+        }
+        session = entry.session.clone();
+    }
+    visits
+        .into_iter()
+        .map(|(stage, runs)| format_stage_history(stage, runs))
+        .collect::<Vec<_>>()
+        .join("->")
+}
+
+fn format_stage_history(stage: &str, count: usize) -> String {
+    if count > 1 {
+        format!("{stage}({count}x)")
+    } else {
+        stage.to_string()
+    }
+}
+
+const TRANSITION_EVENTS: &[&str] = &["stage_changed", "status_changed"];
+
+/// Reverts the task's most recent stage/status transition from the event history.
+/// The undo is itself logged, so repeating it steps further back.
+pub fn cmd_undo(ctx: &CommandContext, task: &str) -> Result<()> {
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        bail!("{}", msg("task_not_found", &[("task", &task)]));
+    }
+    let events = ctx.store.list_events()?;
+    let names = task_names(&events, task);
+    let events: Vec<&Event> = events
+        .iter()
+        .filter(|event| {
+            event
+                .task
+                .as_ref()
+                .is_some_and(|event_task| names.contains(event_task))
+        })
+        .collect();
+    let Some(change) = last_transition(&events) else {
+        bail!("No stage or status change recorded for '{}'", task);
+    };
+    let stage = change.iter().find(|event| event.event == "stage_changed");
+    let status = change.iter().find(|event| event.event == "status_changed");
+    let restored_status = status
+        .and_then(|event| event.from.as_deref())
+        .map(TaskStatus::from_str)
+        .transpose()?;
+    // `set-stage` clears the block reason; the last `blocked` event still has it.
+    let blocked_reason = events
+        .iter()
+        .rev()
+        .find(|event| event.event == "blocked")
+        .and_then(|event| event.note.clone());
+
+    ctx.store.update_task(task, |task_state| {
+        if task_state.status == TaskStatus::Running {
+            bail!("Task '{}' is running. Finish it before undoing.", task);
+        }
+        let current = [
+            (stage, task_state.stage.clone()),
+            (status, task_state.status.to_string()),
+        ];
+        for (event, value) in current {
+            if let Some(event) = event {
+                if event.to.as_deref() != Some(value.as_str()) {
+                    bail!(
+                        "Task '{}' changed since {} at {}; set it with 'mung set-stage' instead",
+                        task,
+                        event.event.replace('_', " "),
+                        event.at
+                    );
+                }
+            }
+        }
+        if let Some(from) = stage.and_then(|event| event.from.as_ref()) {
+            task_state.stage = from.clone();
+        }
+        if let Some(restored) = restored_status.clone() {
+            task_state.blocked_reason = if restored == TaskStatus::Blocked {
+                blocked_reason.clone()
+            } else {
+                None
+            };
+            task_state.status = restored;
+        }
+        task_state.updated_at = now_iso();
+        Ok(())
+    })?;
+
+    let reverted: Vec<String> = change
+        .iter()
+        .map(|event| {
+            format!(
+                "{} {} -> {}",
+                event.event.trim_end_matches("_changed"),
+                event.to.as_deref().unwrap_or("?"),
+                event.from.as_deref().unwrap_or("?")
+            )
+        })
+        .collect();
+    let mut event = Event::new(task, "undone", ctx.actor(None));
+    event.note = Some(reverted.join(", "));
+    ctx.store.append_event(&event)?;
+    println!("Reverted '{}': {}", task, reverted.join(", "));
+    Ok(())
+}
+
+/// The newest transition not already undone. One update logs its stage and status
+/// changes together (same process, same second); an `undone` event cancels the
+/// undo's own change and the transition it reverted.
+fn last_transition<'a>(events: &[&'a Event]) -> Option<Vec<&'a Event>> {
+    let mut groups: Vec<Vec<&Event>> = Vec::new();
+    for event in events {
+        if event.event == "undone" {
+            groups.push(vec![event]);
+            continue;
+        }
+        if !TRANSITION_EVENTS.contains(&event.event.as_str()) {
+            continue;
+        }
+        let joins = groups.last().is_some_and(|group| {
+            group.iter().all(|other| {
+                other.event != "undone"
+                    && other.event != event.event
+                    && other.actor.pid == event.actor.pid
+                    && other.at == event.at
+            })
+        });
+        match groups.last_mut() {
+            Some(group) if joins => group.push(event),
+            _ => groups.push(vec![event]),
+        }
+    }
+    let mut skip = 0;
+    for group in groups.into_iter().rev() {
+        if group[0].event == "undone" {
+            skip += 2;
+        } else if skip > 0 {
+            skip -= 1;
+        } else {
+            return Some(group);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{create_task_state, TRANSITION_LIMIT};
+    use crate::store::memory::MemoryStore;
+    use crate::store::StateStore;
+
+    fn add_task(store: &dyn StateStore, task: &str, stage: &str, added_at: &str) {
+        create_task_state(store, "code", task, stage, added_at, false, None, None).unwrap();
+    }
+
+    #[test]
+    fn history_counts_sessions_per_stage_visit() {
+        let store: &dyn StateStore = &MemoryStore::new();
+        add_task(store, "retried", "build", "2026-01-01T00:00:00Z");
+        let step = |stage: &str, status: TaskStatus, session: &str| {
+            store
+                .update_task("retried", |task| {
+                    task.stage = stage.to_string();
+                    task.status = status;
+                    task.last_session = Some(session.to_string());
+                    Ok(())
+                })
+                .unwrap();
+        };
+        step("build", TaskStatus::Running, "s1");
+        step("build", TaskStatus::Incomplete, "s1");
+        step("build", TaskStatus::Running, "s2");
+        step("review", TaskStatus::Pending, "s2");
+        step("review", TaskStatus::Running, "s3");
+        step("build", TaskStatus::Issues, "s3");
+
+        let task = store.load_task("retried").unwrap();
+        assert_eq!(build_task_history(&task), "build(2x)->review->build");
+    }
+
+    #[test]
+    fn transitions_keep_only_the_latest_entries() {
+        let store: &dyn StateStore = &MemoryStore::new();
+        add_task(store, "busy", "build", "2026-01-01T00:00:00Z");
+        for run in 0..TRANSITION_LIMIT {
+            store
+                .update_task("busy", |task| {
+                    task.last_session = Some(format!("s{run}"));
+                    Ok(())
+                })
+                .unwrap();
+        }
+        let task = store.load_task("busy").unwrap();
+        assert_eq!(task.transitions.len(), TRANSITION_LIMIT);
+        assert_eq!(task.transitions[0].session.as_deref(), Some("s0"));
+        assert_eq!(
+            task.transitions.last().unwrap().session,
+            Some(format!("s{}", TRANSITION_LIMIT - 1))
+        );
+    }
+}
@@ -0,0 +1,162 @@
+//! HTTP through curl, shared by the remote store, webhooks, and the Jira and
+//! Linear integrations. curl keeps the binary free of an HTTP stack.
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// One request. Everything, URL and headers included, reaches curl as a config
+/// file on stdin (`--config -`), so tokens never show up in `ps` or `/proc`.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<String>,
+    /// URL-encoded onto the query string (`-G`).
+    pub query: Vec<(String, String)>,
+    /// Sent as the body with `Content-Type: application/json`.
+    pub json: Option<Value>,
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Request {
+    pub fn new(method: &str, url: impl Into<String>) -> Self {
+        Self {
+            method: method.to_string(),
+            url: url.into(),
+            headers: Vec::new(),
+            query: Vec::new(),
+            json: None,
+            timeout_secs: 30,
+        }
+    }
+
+    pub fn header(mut self, header: impl Into<String>) -> Self {
+        self.headers.push(header.into());
+        self
+    }
+
+    pub fn query(mut self, key: &str, value: &str) -> Self {
+        self.query.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn json(mut self, body: &Value) -> Self {
+        self.json = Some(body.clone());
+        self
+    }
+
+    pub fn timeout_secs(mut self, seconds: u64) -> Self {
+        self.timeout_secs = seconds;
+        self
+    }
+
+    /// Runs curl. Fails only when curl itself does (unreachable host, timeout);
+    /// any HTTP status comes back as a `Response`.
+    pub fn send(&self) -> Result<Response> {
+        let mut child = Command::new("curl")
+            .args(["-sS", "--config", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to run curl")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(self.config().as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            bail!(
+                "curl failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let (body, status) = stdout.rsplit_once('\n').unwrap_or(("", &stdout));
+        let status = status.trim().parse().context("no HTTP status from curl")?;
+        Ok(Response {
+            status,
+            body: body.to_string(),
+        })
+    }
+
+    fn config(&self) -> String {
+        let mut lines = vec![
+            option("url", &self.url),
+            option("request", &self.method),
+            option("max-time", &self.timeout_secs.to_string()),
+            option("write-out", "\n%{http_code}"),
+        ];
+        lines.extend(self.headers.iter().map(|header| option("header", header)));
+        if !self.query.is_empty() {
+            lines.push("get".to_string());
+            lines.extend(
+                self.query
+                    .iter()
+                    .map(|(key, value)| option("data-urlencode", &format!("{key}={value}"))),
+            );
+        }
+        if let Some(json) = &self.json {
+            lines.push(option("header", "Content-Type: application/json"));
+            lines.push(option("data-raw", &json.to_string()));
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+impl Response {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// The body as JSON; an empty body is `null`.
+    pub fn json(&self) -> Result<Value> {
+        if self.body.trim().is_empty() {
+            return Ok(Value::Null);
+        }
+        serde_json::from_str(&self.body).context("response is not JSON")
+    }
+}
+
+/// `name = "value"` with curl's config-file escapes.
+fn option(name: &str, value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    for ch in value.chars() {
+        match ch {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            other => quoted.push(other),
+        }
+    }
+    format!("{name} = \"{quoted}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Request;
+    use serde_json::json;
+
+    #[test]
+    fn secrets_and_bodies_go_into_the_config() {
+        let config = Request::new("POST", "https://example.test/api")
+            .header("Authorization: Bearer s3cr\"t")
+            .query("jql", "project = X")
+            .json(&json!({ "text": "line\nbreak" }))
+            .config();
+        assert!(config.contains("url = \"https://example.test/api\"\n"));
+        assert!(config.contains("header = \"Authorization: Bearer s3cr\\\"t\"\n"));
+        assert!(config.contains("write-out = \"\\n%{http_code}\"\n"));
+        assert!(config.contains("get\ndata-urlencode = \"jql=project = X\"\n"));
+        assert!(config.contains("data-raw = \"{\\\"text\\\":\\\"line\\\\nbreak\\\"}\"\n"));
+    }
+}
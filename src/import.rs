@@ -0,0 +1,313 @@
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::commands::{sync_task_status_for_issues, CommandContext};
+use crate::issues::{new_issue, Issue, IssuePriority, IssueSource, IssueStatus, IssueType};
+use crate::util::{now_iso, read_text, validate_task_name};
+
+/// What `issue import --from` reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// A JSON array from `gh issue list --json ...` or the REST issues API.
+    Github,
+    /// One JSON object per line, using the field names `issue export` writes.
+    Jsonl,
+}
+
+impl FromStr for ImportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "github" => Ok(Self::Github),
+            "jsonl" => Ok(Self::Jsonl),
+            other => bail!("Invalid import format: {} (use github or jsonl)", other),
+        }
+    }
+}
+
+/// `mung issue import`: turns an external tracker dump into local issues with
+/// source `import`. Fields are mapped best-effort; a record's `origin` (its URL or
+/// external id) is kept so importing the same dump again skips what is already here.
+pub fn cmd_issue_import(
+    ctx: &CommandContext,
+    from: &str,
+    file: &Path,
+    task: Option<&str>,
+    dry_run: bool,
+) -> Result<()> {
+    let format = ImportFormat::from_str(from)?;
+    if let Some(task) = task {
+        validate_task_name(task)?;
+        if !ctx.store.task_exists(task) {
+            bail!("Task '{}' not found", task);
+        }
+    }
+    let content = read_text(file)?;
+    let records = match format {
+        ImportFormat::Github => {
+            let value: Value = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {} as JSON", file.display()))?;
+            let Value::Array(records) = value else {
+                bail!("{} must hold a JSON array of issues", file.display());
+            };
+            records
+        }
+        ImportFormat::Jsonl => content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("{}:{}: invalid JSON", file.display(), index + 1))
+            })
+            .collect::<Result<Vec<Value>>>()?,
+    };
+
+    let issues = records
+        .iter()
+        .map(|record| match format {
+            ImportFormat::Github => github_issue(record),
+            ImportFormat::Jsonl => jsonl_issue(record),
+        })
+        .collect();
+    save_imported(ctx, issues, task, dry_run)
+}
+
+/// Saves converted tracker records (`None` for records that couldn't be used),
+/// skipping any whose `origin` an existing issue already has. `task` overrides the
+/// record's own task; a task that doesn't exist leaves the issue unassigned.
+pub fn save_imported(
+    ctx: &CommandContext,
+    issues: Vec<Option<Issue>>,
+    task: Option<&str>,
+    dry_run: bool,
+) -> Result<()> {
+    let mut known: HashSet<String> = ctx
+        .store
+        .list_issues()?
+        .into_iter()
+        .chain(ctx.store.list_archived_issues()?)
+        .filter_map(|issue| issue.origin)
+        .collect();
+    let mut imported = 0;
+    let mut skipped = 0;
+    let mut tasks = BTreeSet::new();
+    for (index, issue) in issues.into_iter().enumerate() {
+        let Some(mut issue) = issue else {
+            eprintln!(
+                "Warning: record {} has no title or is not an issue (skipping)",
+                index + 1
+            );
+            skipped += 1;
+            continue;
+        };
+        if let Some(origin) = issue.origin.as_ref() {
+            if !known.insert(origin.clone()) {
+                skipped += 1;
+                continue;
+            }
+        }
+        if let Some(task) = task {
+            issue.task = Some(task.to_string());
+        }
+        if let Some(name) = issue.task.as_deref() {
+            if !ctx.store.task_exists(name) {
+                eprintln!(
+                    "Warning: task '{}' not found; importing '{}' unassigned",
+                    name, issue.title
+                );
+                issue.task = None;
+            }
+        }
+        println!("  [{}] {}", issue.priority, issue.title);
+        if !dry_run {
+            ctx.store.save_issue(&issue)?;
+            tasks.extend(issue.task.clone());
+        }
+        imported += 1;
+    }
+    for task in tasks {
+        sync_task_status_for_issues(ctx.store.as_ref(), &task)?;
+    }
+    let verb = if dry_run { "Would import" } else { "Imported" };
+    println!(
+        "{} {} issue(s), skipped {} (already imported or unusable)",
+        verb, imported, skipped
+    );
+    Ok(())
+}
+
+fn github_issue(record: &Value) -> Option<Issue> {
+    // The REST API lists pull requests alongside issues.
+    if record.get("pull_request").is_some() {
+        return None;
+    }
+    let title = single_line(record.get("title")?.as_str()?)?;
+    let labels: Vec<String> = record
+        .get("labels")
+        .and_then(Value::as_array)
+        .map(|labels| {
+            labels
+                .iter()
+                .filter_map(|label| label.as_str().or_else(|| label.get("name")?.as_str()))
+                .map(|label| label.replace(',', " ").trim().to_string())
+                .filter(|label| !label.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let closed = text(record, &["state"]).is_some_and(|state| state.eq_ignore_ascii_case("closed"));
+    let origin = text(record, &["html_url", "url"]).or_else(|| {
+        record
+            .get("number")
+            .map(|number| format!("github#{number}"))
+    });
+
+    let mut issue = new_issue(
+        title,
+        if closed {
+            IssueStatus::Resolved
+        } else {
+            IssueStatus::Open
+        },
+        labels
+            .iter()
+            .find_map(|label| priority_from_label(label))
+            .unwrap_or(IssuePriority::P2),
+        None,
+        labels
+            .iter()
+            .find_map(|label| type_from_label(label))
+            .unwrap_or(IssueType::Build),
+        IssueSource::Import,
+        None,
+        text(record, &["body"]).filter(|body| !body.trim().is_empty()),
+    );
+    issue.labels = labels;
+    issue.origin = origin;
+    apply_timestamps(
+        &mut issue,
+        record,
+        &["createdAt", "created_at"],
+        &["updatedAt", "updated_at"],
+    );
+    Some(issue)
+}
+
+fn jsonl_issue(record: &Value) -> Option<Issue> {
+    let title = single_line(record.get("title")?.as_str()?)?;
+    let status = text(record, &["status", "state"])
+        .map(|status| match status.to_lowercase().as_str() {
+            "resolved" | "closed" | "done" | "fixed" => IssueStatus::Resolved,
+            _ => IssueStatus::Open,
+        })
+        .unwrap_or(IssueStatus::Open);
+    let priority = text(record, &["priority"])
+        .and_then(|value| {
+            IssuePriority::from_str(&value)
+                .ok()
+                .or_else(|| priority_from_label(&value))
+        })
+        .unwrap_or(IssuePriority::P2);
+    let issue_type = text(record, &["type", "issue_type"])
+        .and_then(|value| {
+            IssueType::from_str(&value)
+                .ok()
+                .or_else(|| type_from_label(&value))
+        })
+        .unwrap_or(IssueType::Build);
+    let labels = match record.get("labels") {
+        Some(Value::Array(labels)) => labels
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|label| label.replace(',', " ").trim().to_string())
+            .filter(|label| !label.is_empty())
+            .collect(),
+        Some(Value::String(labels)) => labels
+            .split(',')
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut issue = new_issue(
+        title,
+        status,
+        priority,
+        text(record, &["task"]).filter(|task| validate_task_name(task).is_ok()),
+        issue_type,
+        IssueSource::Import,
+        text(record, &["file"]).and_then(|file| single_line(&file)),
+        text(record, &["body", "description"]).filter(|body| !body.trim().is_empty()),
+    );
+    issue.labels = labels;
+    issue.origin = text(record, &["origin", "url"])
+        .or_else(|| text(record, &["id", "key"]).map(|id| format!("jsonl#{id}")))
+        .and_then(|origin| single_line(&origin));
+    apply_timestamps(
+        &mut issue,
+        record,
+        &["created_at", "createdAt"],
+        &["updated_at", "updatedAt"],
+    );
+    Some(issue)
+}
+
+/// The first of `keys` holding a string (or number), as text.
+fn text(record: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| match record.get(*key)? {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    })
+}
+
+/// Frontmatter values are one line each.
+pub fn single_line(value: &str) -> Option<String> {
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!value.is_empty()).then_some(value)
+}
+
+/// Keeps the tracker's timestamps when they parse, so aging rules see the real age.
+fn apply_timestamps(issue: &mut Issue, record: &Value, created: &[&str], updated: &[&str]) {
+    let timestamp = |keys: &[&str]| {
+        text(record, keys).filter(|value| chrono::DateTime::parse_from_rfc3339(value).is_ok())
+    };
+    if let Some(created) = timestamp(created) {
+        issue.created_at = created;
+    }
+    issue.updated_at = timestamp(updated).unwrap_or_else(now_iso);
+}
+
+/// Reads `P1`, `priority: high`, `priority/critical`, and similar label spellings.
+pub fn priority_from_label(label: &str) -> Option<IssuePriority> {
+    let label = label.to_lowercase();
+    let value = label
+        .strip_prefix("priority")
+        .map(|rest| rest.trim_start_matches([':', '/', '-', ' ']))
+        .unwrap_or(&label)
+        .trim();
+    match value {
+        "p0" | "critical" | "blocker" | "urgent" | "highest" => Some(IssuePriority::P0),
+        "p1" | "high" => Some(IssuePriority::P1),
+        "p2" | "medium" | "normal" => Some(IssuePriority::P2),
+        "p3" | "low" | "lowest" | "minor" | "trivial" => Some(IssuePriority::P3),
+        _ => None,
+    }
+}
+
+pub fn type_from_label(label: &str) -> Option<IssueType> {
+    match label.to_lowercase().trim() {
+        "bug" | "defect" | "regression" | "crash" => Some(IssueType::Bug),
+        "test" | "tests" | "testing" | "flaky-test" | "flaky" => Some(IssueType::Test),
+        "perf" | "performance" => Some(IssueType::Perf),
+        "spec" | "design" | "documentation" | "docs" => Some(IssueType::Spec),
+        "enhancement" | "feature" | "task" | "story" => Some(IssueType::Build),
+        _ => None,
+    }
+}
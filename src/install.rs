@@ -0,0 +1,216 @@
+use anyhow::{Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::agent::AgentKind;
+use crate::config::InstallConfig;
+use crate::util::write_text;
+
+/// Where one tool looks for slash-command markdown files.
+pub struct CommandDir {
+    pub tool: String,
+    pub path: PathBuf,
+}
+
+fn xdg_config_home(home: &Path) -> PathBuf {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".config"))
+}
+
+/// `~/...` relative to `home`; other relative paths are taken relative to `home` too.
+pub fn expand_home(home: &Path, path: &str) -> PathBuf {
+    if path == "~" {
+        return home.to_path_buf();
+    }
+    if let Some(rest) = path.strip_prefix("~/") {
+        return home.join(rest);
+    }
+    home.join(path)
+}
+
+/// Claude: `$CLAUDE_CONFIG_DIR`, else `~/.claude`, else an existing
+/// `$XDG_CONFIG_HOME/claude`. Codex: `$CODEX_HOME`, else `~/.codex`. Then any
+/// `[[install.command_dirs]]` from the repo config.
+pub fn command_dirs(home: &Path, config: &InstallConfig) -> Vec<CommandDir> {
+    let claude_root = env::var_os("CLAUDE_CONFIG_DIR")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            let dotdir = home.join(".claude");
+            let xdg = xdg_config_home(home).join("claude");
+            if !dotdir.exists() && xdg.is_dir() {
+                xdg
+            } else {
+                dotdir
+            }
+        });
+    let codex_root = env::var_os("CODEX_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".codex"));
+
+    let mut dirs = vec![
+        CommandDir {
+            tool: "claude".to_string(),
+            path: claude_root.join("commands"),
+        },
+        CommandDir {
+            tool: "codex".to_string(),
+            path: codex_root.join("prompts"),
+        },
+    ];
+    for extra in &config.command_dirs {
+        let path = expand_home(home, &extra.path);
+        if dirs.iter().any(|dir| dir.path == path) {
+            continue;
+        }
+        dirs.push(CommandDir {
+            tool: extra.tool.clone(),
+            path,
+        });
+    }
+    dirs
+}
+
+/// Pristine copies of what was last installed, beside the prompts in
+/// `~/.mung/<agent>/`. They tell local edits apart from upstream changes.
+const PROMPT_BASE_DIR: &str = ".base";
+
+/// How an installed prompt compares with the one embedded in this binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptState {
+    Current,
+    Missing,
+    /// Unedited since install; this binary ships a newer version.
+    Outdated,
+    /// Edited locally; this binary ships the version it was installed from.
+    Edited,
+    /// Edited locally and changed upstream, or installed before bases were kept.
+    Diverged,
+}
+
+impl PromptState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Current => "current",
+            Self::Missing => "missing",
+            Self::Outdated => "outdated",
+            Self::Edited => "edited",
+            Self::Diverged => "diverged",
+        }
+    }
+}
+
+pub struct InstalledPrompt {
+    /// `<agent>/<file>`, e.g. `code/BUILD_PROMPT.md`.
+    pub name: String,
+    pub path: PathBuf,
+    base: PathBuf,
+    pub embedded: &'static str,
+    pub state: PromptState,
+}
+
+/// What `update_prompt` did to one file.
+pub enum PromptUpdate {
+    Unchanged,
+    Installed,
+    Updated,
+    Merged,
+    KeptEdits,
+    /// Local edits could not be merged; they were moved to this `.bak` file.
+    BackedUp(PathBuf),
+}
+
+/// Every prompt the built-in agents install under `prompt_home`, with its state.
+pub fn installed_prompts(prompt_home: &Path) -> Vec<InstalledPrompt> {
+    let mut prompts = Vec::new();
+    for agent in [AgentKind::Code, AgentKind::Writer] {
+        let dir = prompt_home.join(agent.name());
+        for (file, embedded) in agent.install_prompts() {
+            let path = dir.join(file);
+            let base = dir.join(PROMPT_BASE_DIR).join(file);
+            let installed = fs::read_to_string(&path).ok();
+            let recorded = fs::read_to_string(&base).ok();
+            let state = match (installed.as_deref(), recorded.as_deref()) {
+                (None, _) => PromptState::Missing,
+                (Some(installed), _) if installed == embedded => PromptState::Current,
+                (Some(installed), Some(base)) if installed == base => PromptState::Outdated,
+                (Some(_), Some(base)) if base == embedded => PromptState::Edited,
+                _ => PromptState::Diverged,
+            };
+            prompts.push(InstalledPrompt {
+                name: format!("{}/{}", agent.name(), file),
+                path,
+                base,
+                embedded,
+                state,
+            });
+        }
+    }
+    prompts
+}
+
+/// Brings one prompt up to the embedded version without losing local edits:
+/// unedited files are replaced, edits on an unchanged upstream are kept, and
+/// diverged files get a three-way merge (`git merge-file`). A merge with
+/// conflicts, or a file with no recorded base, is saved as `<file>.bak` first.
+pub fn update_prompt(prompt: &InstalledPrompt) -> Result<PromptUpdate> {
+    let update = match prompt.state {
+        PromptState::Current => PromptUpdate::Unchanged,
+        PromptState::Edited => PromptUpdate::KeptEdits,
+        PromptState::Missing => {
+            write_text(&prompt.path, prompt.embedded)?;
+            PromptUpdate::Installed
+        }
+        PromptState::Outdated => {
+            write_text(&prompt.path, prompt.embedded)?;
+            PromptUpdate::Updated
+        }
+        PromptState::Diverged => match merge_prompt(prompt)? {
+            Some(merged) => {
+                write_text(&prompt.path, &merged)?;
+                PromptUpdate::Merged
+            }
+            None => {
+                let backup = PathBuf::from(format!("{}.bak", prompt.path.display()));
+                fs::copy(&prompt.path, &backup)
+                    .with_context(|| format!("Failed to back up {}", prompt.path.display()))?;
+                write_text(&prompt.path, prompt.embedded)?;
+                PromptUpdate::BackedUp(backup)
+            }
+        },
+    };
+    write_text(&prompt.base, prompt.embedded)?;
+    Ok(update)
+}
+
+/// The installed prompt with upstream changes since its base merged in, or
+/// `None` when there is no base or the merge conflicts.
+fn merge_prompt(prompt: &InstalledPrompt) -> Result<Option<String>> {
+    if !prompt.base.exists() {
+        return Ok(None);
+    }
+    let upstream = env::temp_dir().join(format!(
+        "mung-prompt-{}-{}",
+        std::process::id(),
+        prompt.name.replace('/', "-")
+    ));
+    write_text(&upstream, prompt.embedded)?;
+    let output = Command::new("git")
+        .args(["merge-file", "-p", "-q"])
+        .arg(&prompt.path)
+        .arg(&prompt.base)
+        .arg(&upstream)
+        .output();
+    fs::remove_file(&upstream).ok();
+    let output = output.context("Failed to run git merge-file")?;
+    // Exit 0 is a clean merge; a positive code counts conflicts.
+    if output.status.code() != Some(0) {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+}
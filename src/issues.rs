@@ -1,7 +1,9 @@
 use anyhow::{anyhow, bail, Context, Result};
+use serde::{Serialize, Serializer};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -22,8 +24,12 @@ impl IssueStatus {
             Self::Resolved => "resolved",
         }
     }
+}
+
+impl FromStr for IssueStatus {
+    type Err = anyhow::Error;
 
-    pub fn from_str(value: &str) -> Result<Self> {
+    fn from_str(value: &str) -> Result<Self> {
         match value.trim().to_lowercase().as_str() {
             "open" => Ok(Self::Open),
             "resolved" => Ok(Self::Resolved),
@@ -64,8 +70,12 @@ impl IssuePriority {
             Self::P3 => 3,
         }
     }
+}
 
-    pub fn from_str(value: &str) -> Result<Self> {
+impl FromStr for IssuePriority {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
         let normalized = value.trim().to_lowercase();
         let token = normalized.strip_prefix('p').unwrap_or(&normalized);
         match token {
@@ -105,8 +115,12 @@ impl IssueType {
             Self::Other => "other",
         }
     }
+}
 
-    pub fn from_str(value: &str) -> Result<Self> {
+impl FromStr for IssueType {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
         match value.trim().to_lowercase().as_str() {
             "spec" => Ok(Self::Spec),
             "build" => Ok(Self::Build),
@@ -131,6 +145,10 @@ pub enum IssueSource {
     Debug,
     Submit,
     Manual,
+    /// Brought in from another tracker by `issue import`.
+    Import,
+    /// Filed from a plan step by `plan to-issues`.
+    Plan,
 }
 
 impl IssueSource {
@@ -140,15 +158,23 @@ impl IssueSource {
             Self::Debug => "debug",
             Self::Submit => "submit",
             Self::Manual => "manual",
+            Self::Import => "import",
+            Self::Plan => "plan",
         }
     }
+}
 
-    pub fn from_str(value: &str) -> Result<Self> {
+impl FromStr for IssueSource {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
         match value.trim().to_lowercase().as_str() {
             "review" => Ok(Self::Review),
             "debug" => Ok(Self::Debug),
             "submit" => Ok(Self::Submit),
             "manual" => Ok(Self::Manual),
+            "import" => Ok(Self::Import),
+            "plan" => Ok(Self::Plan),
             other => bail!("Invalid issue source: {}", other),
         }
     }
@@ -160,18 +186,42 @@ impl std::fmt::Display for IssueSource {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Issue enums serialize as the same strings used in issue frontmatter.
+macro_rules! serialize_as_str {
+    ($($ty:ty),*) => {
+        $(impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        })*
+    };
+}
+
+serialize_as_str!(IssueStatus, IssuePriority, IssueType, IssueSource);
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Issue {
     pub id: String,
     pub title: String,
     pub status: IssueStatus,
     pub priority: IssuePriority,
     pub task: Option<String>,
+    #[serde(rename = "type")]
     pub issue_type: IssueType,
     pub source: IssueSource,
     pub created_at: String,
     pub updated_at: String,
     pub file: Option<String>,
+    pub steps: Vec<u32>,
+    /// Free-form tags such as `security` or `flaky-test`.
+    pub labels: Vec<String>,
+    /// File names under `issues/<id>/attachments/`.
+    pub attachments: Vec<String>,
+    pub escalated_at: Option<String>,
+    /// The open issue this one was filed again for.
+    pub duplicate_of: Option<String>,
+    /// Where an imported issue came from, e.g. a GitHub issue URL.
+    pub origin: Option<String>,
     pub body: Option<String>,
 }
 
@@ -190,6 +240,8 @@ pub struct IssueFilter {
     pub issue_type: Option<IssueType>,
     pub priority: Option<IssuePriority>,
     pub source: Option<IssueSource>,
+    /// Issues must carry every one of these labels.
+    pub labels: Vec<String>,
 }
 
 #[derive(Debug, Default)]
@@ -215,6 +267,20 @@ pub fn issue_path(agent_root: &Path, issue_id: &str) -> PathBuf {
     issues_dir(agent_root).join(format!("{issue_id}.md"))
 }
 
+/// Files attached with `issue add --attach`; stays put when the issue is archived.
+pub fn issue_attachments_dir(agent_root: &Path, issue_id: &str) -> PathBuf {
+    issues_dir(agent_root).join(issue_id).join("attachments")
+}
+
+/// Resolved issues past the retention window live here, out of the hot scan path.
+pub fn issues_archive_dir(agent_root: &Path) -> PathBuf {
+    issues_dir(agent_root).join("archive")
+}
+
+pub fn archived_issue_path(agent_root: &Path, issue_id: &str) -> PathBuf {
+    issues_archive_dir(agent_root).join(format!("{issue_id}.md"))
+}
+
 pub fn load_issue(path: &Path) -> Result<Issue> {
     let content = fs::read_to_string(path)
         .with_context(|| format!("Failed to read issue {}", path.display()))?;
@@ -222,17 +288,63 @@ pub fn load_issue(path: &Path) -> Result<Issue> {
 }
 
 pub fn save_issue(path: &Path, issue: &Issue) -> Result<()> {
+    crate::state::ensure_writable()?;
     let content = render_issue(issue);
     write_text_atomic(path, &content)
 }
 
 pub fn list_issues(agent_root: &Path) -> Result<Vec<Issue>> {
-    let dir = issues_dir(agent_root);
+    list_issues_in(&issues_dir(agent_root))
+}
+
+pub fn list_archived_issues(agent_root: &Path) -> Result<Vec<Issue>> {
+    list_issues_in(&issues_archive_dir(agent_root))
+}
+
+/// Moves an issue file into `issues/archive/`.
+pub fn archive_issue(agent_root: &Path, issue_id: &str) -> Result<()> {
+    let source = issue_path(agent_root, issue_id);
+    let dest = archived_issue_path(agent_root, issue_id);
+    ensure_dir(&issues_archive_dir(agent_root))?;
+    fs::rename(&source, &dest).with_context(|| {
+        format!(
+            "Failed to archive issue {} to {}",
+            source.display(),
+            dest.display()
+        )
+    })
+}
+
+/// Whether a resolved issue's last update is more than `days` before `now`.
+pub fn resolved_before(issue: &Issue, days: u64, now: chrono::DateTime<chrono::Utc>) -> bool {
+    if issue.status != IssueStatus::Resolved {
+        return false;
+    }
+    chrono::DateTime::parse_from_rfc3339(&issue.updated_at)
+        .map(|updated| now.signed_duration_since(updated).num_days() >= days as i64)
+        .unwrap_or(false)
+}
+
+/// Whether an open advisory issue (P3, type `other`) was created more than `days`
+/// before `now`.
+pub fn advisory_expired(issue: &Issue, days: u64, now: chrono::DateTime<chrono::Utc>) -> bool {
+    if issue.status != IssueStatus::Open
+        || issue.priority != IssuePriority::P3
+        || issue.issue_type != IssueType::Other
+    {
+        return false;
+    }
+    chrono::DateTime::parse_from_rfc3339(&issue.created_at)
+        .map(|created| now.signed_duration_since(created).num_days() >= days as i64)
+        .unwrap_or(false)
+}
+
+fn list_issues_in(dir: &Path) -> Result<Vec<Issue>> {
     let mut issues = Vec::new();
     if !dir.exists() {
         return Ok(issues);
     }
-    let entries = fs::read_dir(&dir)
+    let entries = fs::read_dir(dir)
         .with_context(|| format!("Failed to read issues directory {}", dir.display()))?;
     for entry in entries.flatten() {
         let path = entry.path();
@@ -276,6 +388,13 @@ pub fn filter_issues(mut issues: Vec<Issue>, filter: &IssueFilter) -> Vec<Issue>
                 return false;
             }
         }
+        if !filter
+            .labels
+            .iter()
+            .all(|label| issue.labels.contains(label))
+        {
+            return false;
+        }
 
         match filter.status {
             IssueStatusFilter::Open => issue.status == IssueStatus::Open,
@@ -370,6 +489,30 @@ pub fn parse_issue(content: &str) -> Result<Issue> {
             Some(trimmed.to_string())
         }
     });
+    let steps = frontmatter
+        .get("steps")
+        .map(|value| parse_step_list(&id, value))
+        .unwrap_or_default();
+    let labels = frontmatter
+        .get("labels")
+        .map(|value| parse_list(value))
+        .unwrap_or_default();
+    let attachments = frontmatter
+        .get("attachments")
+        .map(|value| parse_list(value))
+        .unwrap_or_default();
+    let escalated_at = frontmatter
+        .get("escalated_at")
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    let duplicate_of = frontmatter
+        .get("duplicate_of")
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    let origin = frontmatter
+        .get("origin")
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
     let body = if body.trim().is_empty() {
         None
     } else {
@@ -387,10 +530,82 @@ pub fn parse_issue(content: &str) -> Result<Issue> {
         created_at,
         updated_at,
         file,
+        steps,
+        labels,
+        attachments,
+        escalated_at,
+        duplicate_of,
+        origin,
         body,
     })
 }
 
+/// Parses a plan step reference like `T17` (or bare `17`).
+pub fn parse_step_id(value: &str) -> Result<u32> {
+    let trimmed = value.trim();
+    let digits = trimmed
+        .strip_prefix('T')
+        .or_else(|| trimmed.strip_prefix('t'))
+        .unwrap_or(trimmed);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        bail!("Invalid plan step: {} (expected T<number>)", trimmed);
+    }
+    digits
+        .parse::<u32>()
+        .with_context(|| format!("Invalid plan step: {}", trimmed))
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    let trimmed = value.trim();
+    if trimmed == "-" {
+        return Vec::new();
+    }
+    trimmed
+        .split(',')
+        .map(str::trim)
+        .filter(|label| !label.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Trims `--label` values and drops repeats. Labels are stored comma-separated, so
+/// a label can't contain a comma.
+pub fn normalize_labels(labels: &[String]) -> Result<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for label in labels {
+        let label = label.trim();
+        if label.is_empty() {
+            bail!("Issue labels cannot be empty");
+        }
+        if label.contains(',') {
+            bail!("Issue label '{}' cannot contain a comma", label);
+        }
+        if !normalized.iter().any(|known| known == label) {
+            normalized.push(label.to_string());
+        }
+    }
+    Ok(normalized)
+}
+
+/// The `steps:` ids of issue `id`. A hand-edited entry that isn't `T<number>` is
+/// dropped with a warning rather than making the whole issue unreadable.
+fn parse_step_list(id: &str, value: &str) -> Vec<u32> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() || trimmed == "-" {
+        return Vec::new();
+    }
+    trimmed
+        .split(',')
+        .filter_map(|step| match parse_step_id(step) {
+            Ok(step) => Some(step),
+            Err(err) => {
+                eprintln!("Warning: issue {}: {} (ignored)", id, err);
+                None
+            }
+        })
+        .collect()
+}
+
 pub fn render_issue(issue: &Issue) -> String {
     let task = issue.task.as_deref().unwrap_or("-");
     let file = issue.file.as_deref().unwrap_or("-");
@@ -406,6 +621,30 @@ pub fn render_issue(issue: &Issue) -> String {
     lines.push(format!("created_at: {}", issue.created_at));
     lines.push(format!("updated_at: {}", issue.updated_at));
     lines.push(format!("file: {}", file));
+    if !issue.steps.is_empty() {
+        let steps = issue
+            .steps
+            .iter()
+            .map(|id| format!("T{id}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        lines.push(format!("steps: {}", steps));
+    }
+    if !issue.labels.is_empty() {
+        lines.push(format!("labels: {}", issue.labels.join(", ")));
+    }
+    if !issue.attachments.is_empty() {
+        lines.push(format!("attachments: {}", issue.attachments.join(", ")));
+    }
+    if let Some(escalated_at) = issue.escalated_at.as_ref() {
+        lines.push(format!("escalated_at: {}", escalated_at));
+    }
+    if let Some(duplicate_of) = issue.duplicate_of.as_ref() {
+        lines.push(format!("duplicate_of: {}", duplicate_of));
+    }
+    if let Some(origin) = issue.origin.as_ref() {
+        lines.push(format!("origin: {}", origin));
+    }
     lines.push("---".to_string());
     if let Some(body) = issue.body.as_ref() {
         if !body.trim().is_empty() {
@@ -480,6 +719,35 @@ pub fn append_resolution(body: Option<String>, resolution: &str) -> String {
     result.trim().to_string()
 }
 
+/// Appends a `### Comment` section attributed to `author` at `at`.
+pub fn append_comment(body: Option<String>, author: &str, at: &str, comment: &str) -> String {
+    let mut result = body.unwrap_or_default();
+    if !result.is_empty() {
+        result.push_str("\n\n");
+    }
+    result.push_str(&format!("### Comment from {author} at {at}\n"));
+    result.push_str(comment.trim());
+    result.trim().to_string()
+}
+
+/// Appends an `### Escalated` section recording a priority bump at `at`.
+pub fn append_escalation(
+    body: Option<String>,
+    from: &IssuePriority,
+    to: &IssuePriority,
+    at: &str,
+    note: &str,
+) -> String {
+    let mut result = body.unwrap_or_default();
+    if !result.is_empty() {
+        result.push_str("\n\n");
+    }
+    result.push_str(&format!("### Escalated from {from} to {to} at {at}\n"));
+    result.push_str(note.trim());
+    result.trim().to_string()
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn new_issue(
     title: String,
     status: IssueStatus,
@@ -502,6 +770,12 @@ pub fn new_issue(
         created_at: now.clone(),
         updated_at: now,
         file,
+        steps: Vec::new(),
+        labels: Vec::new(),
+        attachments: Vec::new(),
+        escalated_at: None,
+        duplicate_of: None,
+        origin: None,
         body,
     }
 }
@@ -0,0 +1,349 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Value};
+
+use crate::commands::{cmd_task, CommandContext, TaskOptions};
+use crate::config::JiraConfig;
+use crate::http::Request;
+use crate::import::{priority_from_label, save_imported, single_line, type_from_label};
+use crate::issues::{new_issue, Issue, IssuePriority, IssueSource, IssueStatus, IssueType};
+use crate::messages::msg;
+use crate::util::validate_task_name;
+
+/// Tickets fetched per search request.
+const PAGE_SIZE: usize = 100;
+
+#[derive(clap::Subcommand, Debug)]
+pub enum JiraCommands {
+    /// File Jira tickets as issues (or tasks); default: the project's unfinished tickets
+    Pull {
+        #[arg(help = "Ticket keys, e.g. PROJ-12")]
+        keys: Vec<String>,
+        #[arg(long, help = "Pull the tickets matching this JQL instead")]
+        jql: Option<String>,
+        #[arg(long, help = "Assign the pulled issues to this task")]
+        task: Option<String>,
+        #[arg(long, help = "Create a task per ticket instead of issues")]
+        as_tasks: bool,
+        #[arg(long, help = "List what would be pulled without saving")]
+        dry_run: bool,
+    },
+}
+
+pub fn cmd_jira(ctx: &CommandContext, command: JiraCommands) -> Result<()> {
+    match command {
+        JiraCommands::Pull {
+            keys,
+            jql,
+            task,
+            as_tasks,
+            dry_run,
+        } => cmd_jira_pull(
+            ctx,
+            &keys,
+            jql.as_deref(),
+            task.as_deref(),
+            as_tasks,
+            dry_run,
+        ),
+    }
+}
+
+/// `mung jira pull`: fetches `keys` (or the tickets matching `jql`, by default the
+/// project's unfinished ones) and files them as issues, or with `as_tasks` creates
+/// a task per ticket. Issues keep the ticket's browse URL as `origin`, which is how
+/// `issue resolve` finds the ticket to comment on and how a second pull skips them.
+pub fn cmd_jira_pull(
+    ctx: &CommandContext,
+    keys: &[String],
+    jql: Option<&str>,
+    task: Option<&str>,
+    as_tasks: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let jira = jira_config(ctx)?;
+    if let Some(task) = task {
+        validate_task_name(task)?;
+        if !ctx.store.task_exists(task) {
+            bail!("{}", msg("task_not_found", &[("task", &task)]));
+        }
+    }
+    let jql = match (keys.is_empty(), jql) {
+        (false, Some(_)) => bail!("Pass ticket keys or --jql, not both"),
+        (false, None) => format!("key in ({})", keys.join(", ")),
+        (true, Some(jql)) => jql.to_string(),
+        (true, None) => format!(
+            "project = \"{}\" AND statusCategory != Done ORDER BY created",
+            jira.project
+        ),
+    };
+    let tickets = search(jira, &jql)?;
+
+    if as_tasks {
+        for ticket in &tickets {
+            let Some(key) = ticket["key"].as_str() else {
+                continue;
+            };
+            let name = key.to_lowercase();
+            if ctx.store.task_exists(&name) {
+                println!("{}", msg("sync_skipped", &[("key", &key), ("task", &name)]));
+                continue;
+            }
+            let summary = ticket["fields"]["summary"].as_str().unwrap_or_default();
+            println!(
+                "{}",
+                msg(
+                    "sync_task",
+                    &[("key", &key), ("task", &name), ("title", &summary)]
+                )
+            );
+            if !dry_run {
+                cmd_task(
+                    ctx,
+                    &name,
+                    TaskOptions {
+                        description: Some(format!("{key}: {summary} ({})", browse_url(jira, key))),
+                        labels: string_list(&ticket["fields"]["labels"]),
+                        ..TaskOptions::default()
+                    },
+                )?;
+            }
+        }
+        return Ok(());
+    }
+    let issues = tickets
+        .iter()
+        .map(|ticket| ticket_issue(jira, ticket))
+        .collect();
+    save_imported(ctx, issues, task, dry_run)
+}
+
+/// Comments the resolution on the Jira ticket an issue was pulled from. Does
+/// nothing for other issues or when `[jira] comment_on_resolve` is off; failures
+/// are warnings so a resolve never fails because Jira is unreachable.
+pub fn comment_on_resolve(ctx: &CommandContext, issue: &Issue, resolution: Option<&str>) {
+    let Some(jira) = ctx
+        .config
+        .jira
+        .as_ref()
+        .filter(|jira| jira.comment_on_resolve)
+    else {
+        return;
+    };
+    let Some(key) = issue
+        .origin
+        .as_deref()
+        .and_then(|origin| ticket_key(jira, origin))
+    else {
+        return;
+    };
+    let mut text = format!("Resolved in mung (issue {}).", issue.id);
+    if let Some(resolution) = resolution.map(str::trim).filter(|text| !text.is_empty()) {
+        text.push_str("\n\n");
+        text.push_str(resolution);
+    }
+    let path = format!("/rest/api/2/issue/{key}/comment");
+    match request(jira, "POST", &path, &[], Some(&json!({ "body": text }))) {
+        Ok(_) => println!("{}", msg("jira_commented", &[("key", &key)])),
+        Err(err) => eprintln!(
+            "{}",
+            msg(
+                "jira_comment_failed",
+                &[("key", &key), ("error", &format!("{:#}", err))]
+            )
+        ),
+    }
+}
+
+fn jira_config(ctx: &CommandContext) -> Result<&JiraConfig> {
+    ctx.config
+        .jira
+        .as_ref()
+        .context("Set [jira] base_url and project in .agents/mung.toml")
+}
+
+fn browse_url(jira: &JiraConfig, key: &str) -> String {
+    format!("{}/browse/{}", jira.base_url.trim_end_matches('/'), key)
+}
+
+/// The ticket key in an issue's `origin`, if it points at this Jira.
+fn ticket_key<'a>(jira: &JiraConfig, origin: &'a str) -> Option<&'a str> {
+    let prefix = browse_url(jira, "");
+    origin
+        .strip_prefix(&prefix)
+        .filter(|key| !key.is_empty() && !key.contains('/'))
+}
+
+fn search(jira: &JiraConfig, jql: &str) -> Result<Vec<Value>> {
+    let mut tickets = Vec::new();
+    loop {
+        let start = tickets.len().to_string();
+        let page_size = PAGE_SIZE.to_string();
+        let page = request(
+            jira,
+            "GET",
+            "/rest/api/2/search",
+            &[
+                ("jql", jql),
+                ("startAt", &start),
+                ("maxResults", &page_size),
+                (
+                    "fields",
+                    "summary,description,priority,issuetype,status,labels,created,updated",
+                ),
+            ],
+            None,
+        )?;
+        let issues = page["issues"].as_array().cloned().unwrap_or_default();
+        let fetched = issues.len();
+        tickets.extend(issues);
+        let total = page["total"].as_u64().unwrap_or(0) as usize;
+        if fetched == 0 || tickets.len() >= total {
+            return Ok(tickets);
+        }
+    }
+}
+
+fn ticket_issue(jira: &JiraConfig, ticket: &Value) -> Option<Issue> {
+    let key = ticket["key"].as_str()?;
+    let fields = &ticket["fields"];
+    let title = single_line(fields["summary"].as_str()?)?;
+    let done = fields["status"]["statusCategory"]["key"].as_str() == Some("done");
+    let labels = string_list(&fields["labels"]);
+    let mut issue = new_issue(
+        title,
+        if done {
+            IssueStatus::Resolved
+        } else {
+            IssueStatus::Open
+        },
+        fields["priority"]["name"]
+            .as_str()
+            .and_then(priority_from_label)
+            .unwrap_or(IssuePriority::P2),
+        None,
+        fields["issuetype"]["name"]
+            .as_str()
+            .and_then(type_from_label)
+            .unwrap_or(IssueType::Build),
+        IssueSource::Import,
+        None,
+        fields["description"]
+            .as_str()
+            .filter(|text| !text.trim().is_empty())
+            .map(|text| text.trim().to_string()),
+    );
+    issue.labels = labels;
+    issue.origin = Some(browse_url(jira, key));
+    if let Some(created) = jira_time(&fields["created"]) {
+        issue.created_at = created;
+    }
+    if let Some(updated) = jira_time(&fields["updated"]) {
+        issue.updated_at = updated;
+    }
+    Some(issue)
+}
+
+fn string_list(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|item| item.replace(',', " ").trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Jira writes `2024-02-01T10:00:00.000+0000`; issues use RFC 3339 in UTC.
+fn jira_time(value: &Value) -> Option<String> {
+    let text = value.as_str()?;
+    let parsed = DateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f%z")
+        .or_else(|_| DateTime::parse_from_rfc3339(text))
+        .ok()?;
+    Some(
+        parsed
+            .with_timezone(&Utc)
+            .to_rfc3339_opts(SecondsFormat::Secs, true),
+    )
+}
+
+/// One REST call through the shared curl helper, which keeps the credentials off
+/// the command line. `query` is URL-encoded onto a GET; `body` is sent as JSON.
+fn request(
+    jira: &JiraConfig,
+    method: &str,
+    path: &str,
+    query: &[(&str, &str)],
+    body: Option<&Value>,
+) -> Result<Value> {
+    let token = std::env::var(&jira.token_env)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .with_context(|| format!("Set {} to a Jira API token", jira.token_env))?;
+    let url = format!("{}{}", jira.base_url.trim_end_matches('/'), path);
+    let mut request = Request::new(method, url).header("Accept: application/json");
+    request = match jira.email.as_deref() {
+        Some(email) => request.header(format!(
+            "Authorization: Basic {}",
+            base64(format!("{email}:{token}").as_bytes())
+        )),
+        None => request.header(format!("Authorization: Bearer {token}")),
+    };
+    for (key, value) in query {
+        request = request.query(key, value);
+    }
+    if let Some(body) = body {
+        request = request.json(body);
+    }
+    let response = request
+        .send()
+        .with_context(|| format!("{} {} failed", method, path))?;
+    let value = response
+        .json()
+        .unwrap_or_else(|_| Value::String(response.body.clone()));
+    if !response.is_success() {
+        let message = value["errorMessages"]
+            .as_array()
+            .and_then(|messages| messages.first())
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("HTTP {}", response.status));
+        bail!("{} {}: {}", method, path, message);
+    }
+    Ok(value)
+}
+
+/// Standard base64 with padding, for the basic-auth header.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (index, byte)| {
+            n | (*byte as u32) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * index) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::base64;
+
+    #[test]
+    fn base64_pads_like_basic_auth_expects() {
+        assert_eq!(base64(b"bot@acme.com:tok"), "Ym90QGFjbWUuY29tOnRvaw==");
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(base64(b"abc"), "YWJj");
+    }
+}
@@ -0,0 +1,40 @@
+//! Task, issue, session, and queue state behind the `mung` CLI, for tools that
+//! want to read or drive a repo's `.agents/` directory without shelling out.
+//!
+//! [`store::StateStore`] is the entry point: [`store::FsStore`] reads and writes
+//! `.agents/<agent>/` with the same locks and atomic renames the CLI uses, and
+//! [`store::memory::MemoryStore`] keeps everything in process; [`store::open_store`]
+//! picks the backend `[state]` configures (SQLite with the `sqlite` feature). Task and session
+//! records live in [`state`], issues in [`issues`], the event history in
+//! [`events`], and queue ordering in [`queue`]. [`config::load_config`] reads
+//! `.agents/mung.toml`.
+//!
+//! ```no_run
+//! use mung_core::queue::next_eligible_task;
+//! use mung_core::store::{FsStore, StateStore};
+//! use mung_core::{agent::AgentKind, config::load_config};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let repo = std::path::Path::new(".");
+//! let config = load_config(repo)?;
+//! let store = FsStore::new(repo.join(".agents/code"));
+//! let tasks = store.list_tasks();
+//! let next = next_eligible_task(AgentKind::Code, &config.queue, &tasks, &Default::default());
+//! println!("{:?}", next.map(|task| task.task));
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod agent;
+pub mod assets;
+pub mod config;
+pub mod events;
+pub mod git;
+pub mod http;
+pub mod issues;
+pub mod model;
+pub mod process;
+pub mod queue;
+pub mod state;
+pub mod store;
+pub mod util;
@@ -0,0 +1,267 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::commands::{cmd_task, CommandContext, TaskOptions};
+use crate::config::LinearConfig;
+use crate::http::Request;
+use crate::messages::msg;
+use crate::util::{now_iso, task_dir, write_text};
+
+const API_URL: &str = "https://api.linear.app/graphql";
+
+/// Issues fetched per GraphQL page.
+const PAGE_SIZE: usize = 50;
+
+const ISSUES_QUERY: &str = "query($filter: IssueFilter, $first: Int, $after: String) {
+  issues(first: $first, after: $after, filter: $filter) {
+    nodes { identifier title description url labels { nodes { name } } state { name } }
+    pageInfo { hasNextPage endCursor }
+  }
+}";
+
+const STATE_QUERY: &str = "query($team: String!, $name: String!) {
+  workflowStates(filter: { team: { key: { eq: $team } }, name: { eq: $name } }) {
+    nodes { id }
+  }
+}";
+
+const UPDATE_MUTATION: &str = "mutation($id: String!, $state: String!) {
+  issueUpdate(id: $id, input: { stateId: $state }) { success }
+}";
+
+#[derive(clap::Subcommand, Debug)]
+pub enum SyncCommands {
+    /// Create a task per open Linear issue and push stage changes back as states
+    Linear {
+        #[arg(long, help = "List what would change without touching tasks or Linear")]
+        dry_run: bool,
+    },
+}
+
+pub fn cmd_sync(ctx: &CommandContext, command: SyncCommands) -> Result<()> {
+    match command {
+        SyncCommands::Linear { dry_run } => cmd_sync_linear(ctx, dry_run),
+    }
+}
+
+/// `mung sync linear`: every unfinished issue of the `[linear]` team becomes a task
+/// named after its identifier (`ENG-12` -> `eng-12`), with the issue description
+/// as `spec/overview.md`. The task keeps the issue URL as `origin`; for tasks that
+/// already have one, the Linear state is moved to match the task's stage.
+pub fn cmd_sync_linear(ctx: &CommandContext, dry_run: bool) -> Result<()> {
+    let linear = linear_config(ctx)?;
+    let mut filter = json!({
+        "team": { "key": { "eq": linear.team } },
+        "state": { "type": { "nin": ["completed", "canceled"] } },
+    });
+    if let Some(label) = linear.label.as_deref() {
+        filter["labels"] = json!({ "name": { "eq": label } });
+    }
+    let issues = fetch_issues(linear, &filter)?;
+    let linked: HashMap<String, String> = ctx
+        .store
+        .list_tasks()
+        .into_iter()
+        .filter_map(|task| Some((task.origin?, task.stage)))
+        .collect();
+
+    let mut created = 0;
+    let mut moved = 0;
+    for issue in &issues {
+        let (Some(identifier), Some(url)) = (issue["identifier"].as_str(), issue["url"].as_str())
+        else {
+            continue;
+        };
+        let title = issue["title"].as_str().unwrap_or_default();
+        if let Some(stage) = linked.get(url) {
+            let Some(state) = linear.states.get(stage) else {
+                continue;
+            };
+            if issue["state"]["name"].as_str() == Some(state.as_str()) {
+                continue;
+            }
+            println!(
+                "{}",
+                msg(
+                    "linear_move",
+                    &[("key", &identifier), ("stage", stage), ("state", state)]
+                )
+            );
+            if !dry_run {
+                set_state(linear, identifier, state)?;
+            }
+            moved += 1;
+            continue;
+        }
+        let name = identifier.to_lowercase();
+        if ctx.store.task_exists(&name) {
+            println!(
+                "{}",
+                msg("sync_skipped", &[("key", &identifier), ("task", &name)])
+            );
+            continue;
+        }
+        println!(
+            "{}",
+            msg(
+                "sync_task",
+                &[("key", &identifier), ("task", &name), ("title", &title)]
+            )
+        );
+        created += 1;
+        if dry_run {
+            continue;
+        }
+        let labels: Vec<String> = issue["labels"]["nodes"]
+            .as_array()
+            .map(|labels| {
+                labels
+                    .iter()
+                    .filter_map(|label| label["name"].as_str())
+                    .map(|label| label.replace(',', " ").trim().to_string())
+                    .filter(|label| !label.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        cmd_task(
+            ctx,
+            &name,
+            TaskOptions {
+                description: Some(format!("{identifier}: {title}")),
+                labels,
+                ..TaskOptions::default()
+            },
+        )?;
+        ctx.store.update_task(&name, |task_state| {
+            task_state.origin = Some(url.to_string());
+            task_state.updated_at = now_iso();
+            Ok(())
+        })?;
+        let description = issue["description"].as_str().unwrap_or_default().trim();
+        let mut overview = format!("# Overview\n\n{title}\n\nLinear: {identifier} ({url})\n");
+        if !description.is_empty() {
+            overview.push('\n');
+            overview.push_str(description);
+            overview.push('\n');
+        }
+        write_text(
+            &task_dir(&ctx.agent_root, &name).join("spec/overview.md"),
+            &overview,
+        )?;
+    }
+    let key = if dry_run {
+        "linear_sync_preview"
+    } else {
+        "linear_synced"
+    };
+    println!("{}", msg(key, &[("created", &created), ("moved", &moved)]));
+    Ok(())
+}
+
+/// Moves the Linear issue a task was synced from to the state `[linear.states]`
+/// maps its current stage to. Does nothing for other tasks or unmapped stages;
+/// failures are warnings so a stage change never fails because Linear is down.
+pub fn stage_changed(ctx: &CommandContext, task: &str) {
+    let Some(linear) = ctx.config.linear.as_ref() else {
+        return;
+    };
+    let Ok(task_state) = ctx.store.load_task(task) else {
+        return;
+    };
+    let Some(identifier) = task_state.origin.as_deref().and_then(issue_identifier) else {
+        return;
+    };
+    let Some(state) = linear.states.get(&task_state.stage) else {
+        return;
+    };
+    match set_state(linear, identifier, state) {
+        Ok(()) => println!(
+            "{}",
+            msg("linear_moved", &[("key", &identifier), ("state", state)])
+        ),
+        Err(err) => eprintln!(
+            "{}",
+            msg(
+                "linear_move_failed",
+                &[("key", &identifier), ("error", &format!("{:#}", err))]
+            )
+        ),
+    }
+}
+
+fn linear_config(ctx: &CommandContext) -> Result<&LinearConfig> {
+    ctx.config
+        .linear
+        .as_ref()
+        .context("Set [linear] team in .agents/mung.toml")
+}
+
+/// `ENG-12` from `https://linear.app/<org>/issue/ENG-12/<slug>`.
+fn issue_identifier(origin: &str) -> Option<&str> {
+    let (host, path) = origin.split_once("/issue/")?;
+    if !host.contains("linear.app") {
+        return None;
+    }
+    path.split('/').next().filter(|id| !id.is_empty())
+}
+
+fn fetch_issues(linear: &LinearConfig, filter: &Value) -> Result<Vec<Value>> {
+    let mut issues = Vec::new();
+    let mut after = Value::Null;
+    loop {
+        let data = request(
+            linear,
+            ISSUES_QUERY,
+            &json!({ "filter": filter, "first": PAGE_SIZE, "after": after }),
+        )?;
+        let page = &data["issues"];
+        issues.extend(page["nodes"].as_array().cloned().unwrap_or_default());
+        if page["pageInfo"]["hasNextPage"].as_bool() != Some(true) {
+            return Ok(issues);
+        }
+        after = page["pageInfo"]["endCursor"].clone();
+    }
+}
+
+fn set_state(linear: &LinearConfig, identifier: &str, state: &str) -> Result<()> {
+    let data = request(
+        linear,
+        STATE_QUERY,
+        &json!({ "team": linear.team, "name": state }),
+    )?;
+    let Some(state_id) = data["workflowStates"]["nodes"][0]["id"].as_str() else {
+        bail!("team {} has no workflow state '{}'", linear.team, state);
+    };
+    let data = request(
+        linear,
+        UPDATE_MUTATION,
+        &json!({ "id": identifier, "state": state_id }),
+    )?;
+    if data["issueUpdate"]["success"].as_bool() != Some(true) {
+        bail!("Linear did not accept the update of {}", identifier);
+    }
+    Ok(())
+}
+
+/// One GraphQL call through curl, like webhooks and the Jira integration. Returns
+/// the response's `data`.
+fn request(linear: &LinearConfig, query: &str, variables: &Value) -> Result<Value> {
+    let token = std::env::var(&linear.token_env)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .with_context(|| format!("Set {} to a Linear API key", linear.token_env))?;
+    let response = Request::new("POST", API_URL)
+        .header(format!("Authorization: {token}"))
+        .json(&json!({ "query": query, "variables": variables }))
+        .send()
+        .context("Linear request failed")?;
+    let value = response.json().unwrap_or(Value::Null);
+    if let Some(message) = value["errors"][0]["message"].as_str() {
+        bail!("Linear: {}", message);
+    }
+    if !response.is_success() {
+        bail!("Linear: HTTP {}", response.status);
+    }
+    Ok(value["data"].clone())
+}
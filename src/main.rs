@@ -1,25 +1,47 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::atomic::Ordering;
 
-mod agent;
-mod assets;
+mod aging;
+mod approval;
+mod bundle;
 mod commands;
-mod issues;
-mod model;
+mod doctor;
+mod fake;
+mod gitfiles;
+mod history;
+mod import;
+mod install;
+mod jira;
+mod linear;
+mod messages;
+mod notify;
 mod prompt;
-mod state;
-mod util;
+mod schedule;
+mod serve;
+mod similar;
+mod sla;
+mod spec;
+mod tour;
+mod transcript;
+mod workers;
+
+use mung_core::{
+    agent, config, events, git, http, issues, model, process, queue, state, store, util,
+};
 
 use agent::AgentKind;
 use commands::{
     cmd_debug, cmd_delete, cmd_finish, cmd_init, cmd_install, cmd_plan, cmd_queue, cmd_review,
-    cmd_run, cmd_run_queue, cmd_spec_review, cmd_start, cmd_task, cmd_uninstall, CommandContext,
-    IssueCommands, ModelChoice, INTERRUPTED,
+    cmd_run, cmd_run_queue, cmd_spec_review, cmd_start, cmd_task, cmd_uninstall, ClaimCommands,
+    CommandContext, IssueCommands, ModelChoice, PlanCommands, PromptsCommands, QueueFormat,
+    SessionCommands, TaskOptions, INTERRUPTED,
 };
 use model::Model;
-use util::{env_var, get_repo_root};
+use similar::DUPLICATE_THRESHOLD;
+use util::{env_var, get_repo_root, split_words};
 
 #[derive(Parser)]
 #[command(name = "mung")]
@@ -35,6 +57,34 @@ struct Cli {
     #[arg(long)]
     force_model: bool,
 
+    #[arg(
+        long,
+        allow_hyphen_values = true,
+        help = "Extra flags for the model CLI, split like a shell would, e.g. \"--max-turns 50\""
+    )]
+    model_args: Option<String>,
+
+    #[arg(
+        long = "model-arg",
+        value_name = "ARG",
+        allow_hyphen_values = true,
+        help = "One extra argument for the model CLI, passed as is (repeatable)"
+    )]
+    model_arg: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Run models without a terminal (claude -p, codex exec), output to the session transcript"
+    )]
+    headless: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Print queue, status, log, history, audit, issues, plan, and task output as JSON"
+    )]
+    json: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -43,25 +93,101 @@ struct Cli {
 enum Commands {
     Install,
     Uninstall,
+    Prompts {
+        #[command(subcommand)]
+        command: PromptsCommands,
+    },
     Init {
         path: Option<PathBuf>,
     },
+    Doctor,
+    Messages,
+    Tour {
+        #[arg(long, help = "Keep the scratch repo instead of deleting it afterwards")]
+        keep: bool,
+        #[arg(long, help = "Run every step without pausing")]
+        yes: bool,
+    },
     Start,
+    Gitignore {
+        #[arg(long, help = "Report drift without writing; fails if out of date")]
+        check: bool,
+    },
     Task {
         name: String,
         #[arg(long)]
         hold: bool,
         #[arg(long)]
         description: Option<String>,
+        #[arg(
+            long,
+            help = "Read a long description from this file; also seeds spec/overview.md"
+        )]
+        description_file: Option<PathBuf>,
+        #[arg(
+            long,
+            help = "Read the description from stdin, like --description-file"
+        )]
+        stdin_description: bool,
         #[arg(long)]
         prompt: Option<String>,
+        #[arg(
+            long = "depends-on",
+            help = "Task that must complete first (repeatable)"
+        )]
+        depends_on: Vec<String>,
+        #[arg(long, help = "Who the task is for (default: MUNG_USER, then git user)")]
+        owner: Option<String>,
+        #[arg(long = "label", help = "Label for queue fairness (repeatable)")]
+        labels: Vec<String>,
+        #[arg(
+            long = "tag",
+            help = "Area or team tag for queue --tag filtering (repeatable)"
+        )]
+        tags: Vec<String>,
+        #[arg(long, help = "Due date (YYYY-MM-DD, or none to clear)")]
+        due: Option<String>,
+        #[arg(long, help = "Effort: S, M, L, or hours like 6h (none to clear)")]
+        estimate: Option<String>,
+        #[arg(long, help = "P0-P3; the queue runs higher priorities first")]
+        priority: Option<String>,
+    },
+    Prioritize {
+        name: String,
+        #[arg(help = "P0, P1, P2, or P3")]
+        priority: String,
     },
     Hold {
         name: String,
+        #[arg(long, help = "Reactivate on this date (YYYY-MM-DD)")]
+        until: Option<String>,
+        #[arg(
+            long = "for",
+            conflicts_with = "until",
+            help = "Reactivate after this long, e.g. 12h, 7d, 2w"
+        )]
+        for_: Option<String>,
+        #[arg(long, help = "Why the task is parked; shown in the queue's backlog")]
+        reason: Option<String>,
     },
     Activate {
         name: String,
     },
+    Block {
+        name: String,
+        #[arg(long, help = "Why the task can't proceed")]
+        reason: String,
+    },
+    Unblock {
+        name: String,
+    },
+    Approve {
+        name: String,
+        #[arg(long, help = "Approver identity (default: MUNG_USER, then git user)")]
+        by: Option<String>,
+        #[arg(long, help = "Note recorded with the approval")]
+        note: Option<String>,
+    },
     Finish {
         stage: Option<String>,
         #[arg(long)]
@@ -73,45 +199,229 @@ enum Commands {
     },
     Run {
         name: String,
+        #[arg(
+            long,
+            help = "Run this stage once instead of the task's stages from its current one"
+        )]
+        stage: Option<String>,
+        #[arg(
+            long,
+            conflicts_with = "stage",
+            help = "Stop after the current stage instead of continuing to the next"
+        )]
+        once: bool,
+        #[arg(long, help = "Print the prompt, model, and env instead of running")]
+        dry_run: bool,
+        #[arg(
+            long = "claim-ttl",
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Seconds without a heartbeat before the claim is stale (default: [defaults] claim_ttl_seconds)"
+        )]
+        claim_ttl: Option<u64>,
     },
     #[command(name = "run-next", alias = "rn")]
     RunNext {
         name: Option<String>,
+        #[arg(long, help = "Print the prompt, model, and env instead of running")]
+        dry_run: bool,
+        #[arg(
+            long = "claim-ttl",
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Seconds without a heartbeat before the claim is stale (default: [defaults] claim_ttl_seconds)"
+        )]
+        claim_ttl: Option<u64>,
+        #[arg(
+            long,
+            short = 'j',
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Worker processes to run at once (default: [concurrency] run_next or 1)"
+        )]
+        jobs: Option<u64>,
     },
     #[command(alias = "q")]
     Queue {
         task: Option<String>,
+        #[arg(
+            long,
+            help = "Never take locks, claims, or write state (for dashboards)"
+        )]
+        read_only: bool,
+        #[arg(
+            long,
+            conflicts_with = "task",
+            help = "Redraw the board when tasks or issues change (read-only)"
+        )]
+        watch: bool,
+        #[arg(
+            long = "tag",
+            conflicts_with = "task",
+            help = "Only tasks with this tag (repeatable; all must match)"
+        )]
+        tags: Vec<String>,
+        #[arg(long, conflicts_with = "task", help = "Only tasks past their due date")]
+        overdue: bool,
+        #[arg(
+            long,
+            conflicts_with = "task",
+            help = "List active tasks in one list by updated, added, priority, or issues"
+        )]
+        sort: Option<String>,
+        #[arg(
+            long,
+            conflicts_with = "task",
+            help = "One line per task: status, stage, open issues, age"
+        )]
+        oneline: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["task", "oneline"],
+            help = "Add each task's description, last session, and plan progress"
+        )]
+        verbose: bool,
+        #[arg(
+            long,
+            default_value_t = 2,
+            requires = "watch",
+            help = "Seconds between redraws with --watch"
+        )]
+        interval: u64,
     },
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Plan {
+        #[arg(required = true)]
+        task: Option<String>,
+        #[command(subcommand)]
+        command: Option<PlanCommands>,
+    },
+    Context {
         task: String,
     },
+    Open {
+        #[arg(required_unless_present = "issue")]
+        task: Option<String>,
+        #[arg(long, help = "Open the task's spec/ directory", conflicts_with_all = ["plan", "issue"])]
+        spec: bool,
+        #[arg(long, help = "Open the task's plan", conflicts_with = "issue")]
+        plan: bool,
+        #[arg(long, help = "Open an issue file")]
+        issue: Option<String>,
+        #[arg(long, help = "Print the path instead of opening it")]
+        print: bool,
+    },
     #[command(name = "delete", alias = "dequeue")]
     Delete {
         name: String,
         #[arg(long)]
         force: bool,
     },
+    Rename {
+        old: String,
+        new: String,
+    },
+    Clone {
+        source: String,
+        new: String,
+    },
+    Export {
+        #[arg(long, help = "Bundle path (default: mung-<agent>-<date>.tar.gz)")]
+        output: Option<PathBuf>,
+        #[arg(long, help = "Also pack session records, prompts, and transcripts")]
+        include_sessions: bool,
+    },
+    Import {
+        bundle: PathBuf,
+        #[arg(long, help = "Overwrite tasks and issues that already exist")]
+        force: bool,
+    },
     Reorder {
         name: String,
         position: usize,
     },
+    #[command(name = "merge-tasks")]
+    MergeTasks {
+        #[arg(required = true, num_args = 2..)]
+        tasks: Vec<String>,
+        #[arg(long)]
+        into: String,
+    },
+    Dupes {
+        #[arg(long, default_value_t = DUPLICATE_THRESHOLD)]
+        threshold: f64,
+    },
+    Split {
+        task: String,
+        #[arg(long)]
+        into: Vec<String>,
+        #[arg(long = "map", help = "Mapping file with lines like `new-task: T1, T2`")]
+        map_file: Option<PathBuf>,
+    },
     #[command(name = "run-queue", alias = "rq")]
     RunQueue {
         #[arg(
             long,
-            default_value_t = 4,
-            help = "Max review->build loops before holding (0 = 100)"
+            help = "Max review->build loops before holding (default: [defaults] loop_limit or 4; 0 = 100)"
+        )]
+        r#loop: Option<usize>,
+        #[arg(long, help = "Print the prompt, model, and env instead of running")]
+        dry_run: bool,
+        #[arg(
+            long = "claim-ttl",
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Seconds without a heartbeat before the claim is stale (default: [defaults] claim_ttl_seconds)"
+        )]
+        claim_ttl: Option<u64>,
+        #[arg(
+            long,
+            short = 'j',
+            value_parser = clap::value_parser!(u64).range(1..),
+            help = "Worker processes to run at once (default: [concurrency] run_queue or 1)"
+        )]
+        jobs: Option<u64>,
+        #[arg(
+            long = "tag",
+            help = "Only run tasks with this tag (repeatable; all must match)"
         )]
-        r#loop: usize,
+        tags: Vec<String>,
     },
     Review {
         task: String,
         focus: Option<String>,
+        #[arg(long, help = "Limit the review to files touched by the task")]
+        changed_only: bool,
+        #[arg(
+            long,
+            value_name = "REF",
+            conflicts_with = "diff",
+            help = "Show the reviewer the diff from this commit or branch to the working tree"
+        )]
+        since: Option<String>,
+        #[arg(
+            long,
+            value_name = "RANGE",
+            help = "Show the reviewer the diff of this range (e.g. HEAD~5..HEAD)"
+        )]
+        diff: Option<String>,
+        #[arg(long, help = "Print the prompt, model, and env instead of running")]
+        dry_run: bool,
     },
     #[command(name = "spec-review")]
     SpecReview {
         task: String,
     },
+    #[command(name = "spec-diff")]
+    SpecDiff {
+        task: String,
+        #[arg(
+            long,
+            help = "Start from the spec as this stage left it (default: first snapshot)"
+        )]
+        from: Option<String>,
+        #[arg(
+            long,
+            help = "End at the spec as this stage left it (default: current spec)"
+        )]
+        to: Option<String>,
+    },
     Research {
         task: String,
         focus: Option<String>,
@@ -119,6 +429,134 @@ enum Commands {
     How {
         topic: Option<String>,
     },
+    Commit {
+        #[arg(long, help = "Task the staged changes belong to (default: MUNG_TASK)")]
+        task: Option<String>,
+        #[arg(long, short = 'y', help = "Commit without asking")]
+        yes: bool,
+        #[arg(long, conflicts_with = "yes", help = "Only print the message")]
+        print: bool,
+        #[arg(long, help = "Print the prompt, model, and env instead of running")]
+        dry_run: bool,
+    },
+    Status,
+    Log {
+        task: Option<String>,
+        #[arg(long, short = 'n', help = "Show at most this many sessions")]
+        limit: Option<usize>,
+    },
+    History {
+        task: String,
+    },
+    #[command(alias = "sessions")]
+    Session {
+        #[command(subcommand)]
+        command: SessionCommands,
+    },
+    #[command(alias = "claim")]
+    Claims {
+        #[command(subcommand)]
+        command: ClaimCommands,
+    },
+    Unclaim {
+        task: String,
+        #[arg(
+            long,
+            help = "Release a claim that still looks live (its host is gone)"
+        )]
+        force: bool,
+    },
+    Audit {
+        task: Option<String>,
+    },
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:7878", help = "Address to listen on")]
+        addr: String,
+        #[arg(
+            long,
+            help = "Require 'Authorization: Bearer <token>' with the token in this file (default: MUNG_SERVE_TOKEN)"
+        )]
+        token_file: Option<PathBuf>,
+        #[arg(long, help = "Answer every write with 403; never take locks or claims")]
+        read_only: bool,
+    },
+    Schedule {
+        #[arg(
+            help = "Cron expression, e.g. \"0 22 * * *\" (minute hour day month weekday) or @daily"
+        )]
+        cron: String,
+        #[arg(long, help = "Also run the queue once right away")]
+        now: bool,
+        #[arg(long, help = "Exit after this many queue runs")]
+        runs: Option<usize>,
+        #[arg(
+            last = true,
+            help = "Arguments passed to each run-queue (after --, e.g. -- --jobs 2)"
+        )]
+        queue_args: Vec<String>,
+    },
+    Jira {
+        #[command(subcommand)]
+        command: jira::JiraCommands,
+    },
+    Sync {
+        #[command(subcommand)]
+        command: linear::SyncCommands,
+    },
+    Events {
+        #[arg(long, help = "Only events for this task")]
+        task: Option<String>,
+        #[arg(long, help = "Only events of this kind (e.g. stage_changed, claimed)")]
+        kind: Option<String>,
+        #[arg(long, short = 'n', help = "Show at most this many recent events")]
+        limit: Option<usize>,
+    },
+    Reconcile {
+        #[arg(long, help = "Report repairs without applying them")]
+        dry_run: bool,
+    },
+    Prune {
+        #[arg(long, help = "List what would be removed without changing anything")]
+        dry_run: bool,
+    },
+    Merge {
+        task: String,
+        #[arg(long, help = "Rebase the task branch onto HEAD and fast-forward")]
+        rebase: bool,
+        #[arg(long, help = "Keep the worktree and task branch after merging")]
+        keep: bool,
+    },
+    Resume {
+        session: String,
+        #[arg(
+            trailing_var_arg = true,
+            help = "What to tell the model (default: continue where it left off)"
+        )]
+        prompt: Vec<String>,
+    },
+    Prompt {
+        stage: String,
+        #[arg(long, help = "Render with this task's state")]
+        task: Option<String>,
+    },
+    Replay {
+        session: String,
+        #[arg(
+            long,
+            help = "Re-render from the recorded inputs with the current templates"
+        )]
+        rerender: bool,
+        #[arg(
+            long,
+            help = "Run the prompt against a model (read-only) instead of printing it"
+        )]
+        run: bool,
+    },
+    #[command(name = "fake-agent", hide = true)]
+    FakeAgent {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        prompt: Vec<String>,
+    },
     #[command(name = "set-stage")]
     SetStage {
         name: String,
@@ -126,7 +564,19 @@ enum Commands {
         #[arg(long)]
         status: Option<String>,
     },
+    Skip {
+        name: String,
+        #[arg(long, help = "Why the stage isn't needed; kept in the event log")]
+        reason: Option<String>,
+    },
+    Undo {
+        task: String,
+    },
+    #[command(args_conflicts_with_subcommands = true)]
     Issues {
+        /// `mung issues <command>` is `mung issue <command>`.
+        #[command(subcommand)]
+        command: Option<IssueCommands>,
         #[arg(long)]
         task: Option<String>,
         #[arg(long)]
@@ -139,6 +589,10 @@ enum Commands {
         issue_type: Option<String>,
         #[arg(long)]
         source: Option<String>,
+        #[arg(long = "label", help = "Only issues with this label (repeatable)")]
+        labels: Vec<String>,
+        #[arg(long, help = "Also list issues moved to issues/archive/")]
+        include_archived: bool,
     },
     Issue {
         #[command(subcommand)]
@@ -149,6 +603,8 @@ enum Commands {
         file: Option<PathBuf>,
         #[arg(long)]
         stdin: bool,
+        #[arg(long, help = "Print the prompt, model, and env instead of running")]
+        dry_run: bool,
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         bug: Vec<String>,
     },
@@ -161,43 +617,142 @@ fn main() -> Result<()> {
     .context("Failed to install CTRL-C handler")?;
 
     let cli = Cli::parse();
+    // A broken config is reported by CommandContext::new; here it only supplies
+    // defaults and custom agent definitions.
+    let mut repo_defaults = config::Defaults::default();
+    let repo_root = get_repo_root(None).ok();
+    if let Some(repo_root) = repo_root.as_deref() {
+        if let Ok(repo_config) = config::load_config(repo_root) {
+            agent::register_custom_agents(repo_root, &repo_config.agents);
+            repo_defaults = repo_config.defaults;
+        }
+    }
+    messages::init(repo_root.as_deref(), repo_defaults.locale.as_deref());
     let agent_value = cli
         .agent
         .or_else(|| env_var("MUNG_AGENT", "METAGENT_AGENT"))
+        .or(repo_defaults.agent)
         .unwrap_or_else(|| "code".to_string());
     let agent = AgentKind::from_str(&agent_value)?;
 
-    let model_choice = resolve_model_choice(cli.model, cli.force_model)?;
+    let model_choice = resolve_model_choice(
+        cli.model,
+        cli.force_model,
+        cli.model_args,
+        cli.model_arg,
+        cli.headless,
+    )?;
+
+    let env_read_only = env_var("MUNG_READ_ONLY", "METAGENT_READ_ONLY")
+        .map(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false);
+    if env_read_only {
+        state::set_read_only(true);
+    }
+    util::set_json_output(cli.json);
 
     match cli.command.unwrap_or(Commands::Start) {
         Commands::Install => cmd_install(),
         Commands::Uninstall => cmd_uninstall(),
+        Commands::Prompts { command } => commands::cmd_prompts(command),
         Commands::Init { path } => cmd_init(agent, path, model_choice),
+        Commands::Tour { keep, yes } => tour::cmd_tour(keep, yes),
+        Commands::Doctor => doctor::cmd_doctor(agent, get_repo_root(None).ok().as_deref()),
+        Commands::Messages => messages::cmd_messages(),
         Commands::Start => {
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
             cmd_start(&ctx)
         }
+        Commands::Gitignore { check } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_gitignore(&ctx, check)
+        }
         Commands::Task {
             name,
             hold,
             description,
+            description_file,
+            stdin_description,
             prompt,
+            depends_on,
+            owner,
+            labels,
+            tags,
+            due,
+            estimate,
+            priority,
         } => {
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
-            cmd_task(&ctx, &name, hold, description, prompt)
+            let long_description =
+                commands::read_description_input(description_file.as_deref(), stdin_description)?;
+            if description.is_some() && long_description.is_some() {
+                bail!("Use --description or --description-file/--stdin-description, not both");
+            }
+            cmd_task(
+                &ctx,
+                &name,
+                TaskOptions {
+                    hold,
+                    description: description.or_else(|| long_description.clone()),
+                    prompt,
+                    depends_on,
+                    owner,
+                    labels,
+                    tags,
+                    due,
+                    estimate,
+                    priority,
+                },
+            )?;
+            match long_description {
+                Some(text) => commands::seed_overview(&ctx, &name, &text),
+                None => Ok(()),
+            }
         }
-        Commands::Hold { name } => {
+        Commands::Prioritize { name, priority } => {
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
-            commands::cmd_hold(&ctx, &name)
+            commands::cmd_prioritize(&ctx, &name, &priority)
+        }
+        Commands::Hold {
+            name,
+            until,
+            for_,
+            reason,
+        } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_hold(
+                &ctx,
+                &name,
+                until.as_deref(),
+                for_.as_deref(),
+                reason.as_deref(),
+            )
         }
         Commands::Activate { name } => {
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
             commands::cmd_activate(&ctx, &name)
         }
+        Commands::Block { name, reason } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_block(&ctx, &name, &reason)
+        }
+        Commands::Unblock { name } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_unblock(&ctx, &name)
+        }
+        Commands::Approve { name, by, note } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_approve(&ctx, &name, by.as_deref(), note.as_deref())
+        }
         Commands::Finish {
             stage,
             next,
@@ -208,45 +763,201 @@ fn main() -> Result<()> {
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
             cmd_finish(&ctx, stage, next, session, task)
         }
-        Commands::Run { name } => {
+        Commands::Run {
+            name,
+            stage,
+            once,
+            dry_run,
+            claim_ttl,
+        } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = run_context(agent, model_choice, repo_root, dry_run, claim_ttl)?;
+            cmd_run(&ctx, &name, stage.as_deref(), once)
+        }
+        Commands::RunNext {
+            name,
+            dry_run,
+            claim_ttl,
+            jobs,
+        } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = run_context(agent, model_choice, repo_root, dry_run, claim_ttl)?;
+            if name.is_some() && jobs.is_some_and(|jobs| jobs > 1) {
+                bail!("--jobs runs the next eligible tasks; drop the task name");
+            }
+            // A named task runs here; `[concurrency] run_next` is for picking.
+            let configured = if name.is_some() {
+                1
+            } else {
+                ctx.config.concurrency.run_next
+            };
+            let jobs = workers::worker_count(jobs.map(|jobs| jobs as usize), configured);
+            if jobs > 1 && !dry_run {
+                return workers::run_workers(jobs);
+            }
+            commands::cmd_run_next(&ctx, name.as_deref())
+        }
+        Commands::Queue {
+            task,
+            read_only,
+            watch,
+            tags,
+            overdue,
+            sort,
+            oneline,
+            verbose,
+            interval,
+        } => {
+            if read_only || watch {
+                state::set_read_only(true);
+            }
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
-            cmd_run(&ctx, &name)
+            let format = if oneline {
+                QueueFormat::Oneline
+            } else if verbose {
+                QueueFormat::Verbose
+            } else {
+                QueueFormat::Board
+            };
+            if watch {
+                return commands::cmd_queue_watch(
+                    &ctx,
+                    interval,
+                    &tags,
+                    overdue,
+                    sort.as_deref(),
+                    format,
+                );
+            }
+            cmd_queue(
+                &ctx,
+                task.as_deref(),
+                &tags,
+                overdue,
+                sort.as_deref(),
+                format,
+            )
         }
-        Commands::RunNext { name } => {
+        Commands::Plan { task, command } => {
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
-            commands::cmd_run_next(&ctx, name.as_deref())
+            match command {
+                Some(PlanCommands::ToIssues { task, dry_run }) => {
+                    commands::cmd_plan_to_issues(&ctx, &task, dry_run)
+                }
+                Some(PlanCommands::Check { task, steps }) => {
+                    commands::cmd_plan_check(&ctx, &task, &steps, true)
+                }
+                Some(PlanCommands::Uncheck { task, steps }) => {
+                    commands::cmd_plan_check(&ctx, &task, &steps, false)
+                }
+                Some(PlanCommands::Lint { task }) => commands::cmd_plan_lint(&ctx, &task),
+                None => cmd_plan(&ctx, task.as_deref().unwrap_or_default()),
+            }
         }
-        Commands::Queue { task } => {
+        Commands::Context { task } => {
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
-            cmd_queue(&ctx, task.as_deref())
+            commands::cmd_context(&ctx, &task)
         }
-        Commands::Plan { task } => {
+        Commands::Open {
+            task,
+            spec,
+            plan,
+            issue,
+            print,
+        } => {
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
-            cmd_plan(&ctx, &task)
+            commands::cmd_open(&ctx, task.as_deref(), spec, plan, issue.as_deref(), print)
         }
         Commands::Delete { name, force } => {
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
             cmd_delete(&ctx, &name, force)
         }
+        Commands::Rename { old, new } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_rename(&ctx, &old, &new)
+        }
+        Commands::Clone { source, new } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_clone(&ctx, &source, &new)
+        }
+        Commands::Export {
+            output,
+            include_sessions,
+        } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            bundle::cmd_export(&ctx, output, include_sessions)
+        }
+        Commands::Import { bundle, force } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            bundle::cmd_import(&ctx, &bundle, force)
+        }
         Commands::Reorder { name, position } => {
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
             commands::cmd_reorder(&ctx, &name, position)
         }
-        Commands::RunQueue { r#loop } => {
+        Commands::MergeTasks { tasks, into } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_merge_tasks(&ctx, &tasks, &into)
+        }
+        Commands::Dupes { threshold } => {
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
-            cmd_run_queue(&ctx, r#loop)
+            commands::cmd_dupes(&ctx, threshold)
         }
-        Commands::Review { task, focus } => {
+        Commands::Split {
+            task,
+            into,
+            map_file,
+        } => {
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
-            cmd_review(&ctx, &task, focus)
+            commands::cmd_split(&ctx, &task, into, map_file)
+        }
+        Commands::RunQueue {
+            r#loop,
+            dry_run,
+            claim_ttl,
+            jobs,
+            tags,
+        } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = run_context(agent, model_choice, repo_root, dry_run, claim_ttl)?;
+            let jobs = workers::worker_count(
+                jobs.map(|jobs| jobs as usize),
+                ctx.config.concurrency.run_queue,
+            );
+            if jobs > 1 && !dry_run {
+                return workers::run_workers(jobs);
+            }
+            cmd_run_queue(&ctx, r#loop, &tags)
+        }
+        Commands::Review {
+            task,
+            focus,
+            changed_only,
+            since,
+            diff,
+            dry_run,
+        } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = dry_run_context(agent, model_choice, repo_root, dry_run)?;
+            cmd_review(&ctx, &task, focus, changed_only, since.or(diff).as_deref())
+        }
+        Commands::SpecDiff { task, from, to } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_spec_diff(&ctx, &task, from.as_deref(), to.as_deref())
         }
         Commands::SpecReview { task } => {
             let repo_root = get_repo_root(None)?;
@@ -263,6 +974,124 @@ fn main() -> Result<()> {
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
             commands::cmd_how(&ctx, topic.as_deref())
         }
+        Commands::Commit {
+            task,
+            yes,
+            print,
+            dry_run,
+        } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = dry_run_context(agent, model_choice, repo_root, dry_run)?;
+            commands::cmd_commit(&ctx, task, yes, print)
+        }
+        Commands::Status => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_status(&ctx)
+        }
+        Commands::Log { task, limit } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            history::cmd_log(&ctx, task.as_deref(), limit)
+        }
+        Commands::History { task } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            history::cmd_history(&ctx, &task)
+        }
+        Commands::Session { command } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_session(&ctx, command)
+        }
+        Commands::Claims { command } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_claims(&ctx, command)
+        }
+        Commands::Unclaim { task, force } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_unclaim(&ctx, &task, force)
+        }
+        Commands::Audit { task } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            history::cmd_audit(&ctx, task.as_deref())
+        }
+        Commands::Serve {
+            addr,
+            token_file,
+            read_only,
+        } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            serve::cmd_serve(&ctx, &addr, token_file.as_deref(), read_only)
+        }
+        Commands::Schedule {
+            cron,
+            now,
+            runs,
+            queue_args,
+        } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            schedule::cmd_schedule(&ctx, &cron, now, runs, &queue_args)
+        }
+        Commands::Jira { command } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            jira::cmd_jira(&ctx, command)
+        }
+        Commands::Sync { command } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            linear::cmd_sync(&ctx, command)
+        }
+        Commands::Events { task, kind, limit } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            history::cmd_events(&ctx, task.as_deref(), kind.as_deref(), limit)
+        }
+        Commands::Reconcile { dry_run } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_reconcile(&ctx, dry_run)
+        }
+        Commands::Prune { dry_run } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_prune(&ctx, dry_run)
+        }
+        Commands::Merge { task, rebase, keep } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_merge(&ctx, &task, rebase, keep)
+        }
+        Commands::Resume { session, prompt } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_resume(&ctx, &session, &prompt)
+        }
+        Commands::Prompt { stage, task } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_prompt(&ctx, &stage, task.as_deref())
+        }
+        Commands::Replay {
+            session,
+            rerender,
+            run,
+        } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_replay(&ctx, &session, rerender, run)
+        }
+        Commands::FakeAgent { .. } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            fake::cmd_fake_agent(&ctx)
+        }
         Commands::SetStage {
             name,
             stage,
@@ -272,55 +1101,137 @@ fn main() -> Result<()> {
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
             commands::cmd_set_stage(&ctx, &name, &stage, status)
         }
+        Commands::Skip { name, reason } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_skip(&ctx, &name, reason.as_deref())
+        }
+        Commands::Undo { task } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            history::cmd_undo(&ctx, &task)
+        }
         Commands::Issues {
+            command: Some(command),
+            ..
+        } => {
+            let repo_root = get_repo_root(None)?;
+            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            commands::cmd_issue(&ctx, command)
+        }
+        Commands::Issues {
+            command: None,
             task,
             unassigned,
             status,
             priority,
             issue_type,
             source,
+            labels,
+            include_archived,
         } => {
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
-            commands::cmd_issues(&ctx, task, unassigned, status, priority, issue_type, source)
+            commands::cmd_issues(
+                &ctx,
+                task,
+                unassigned,
+                status,
+                priority,
+                issue_type,
+                source,
+                &labels,
+                include_archived,
+            )
         }
         Commands::Issue { command } => {
             let repo_root = get_repo_root(None)?;
             let ctx = CommandContext::new(agent, model_choice, repo_root)?;
             commands::cmd_issue(&ctx, command)
         }
-        Commands::Debug { file, stdin, bug } => {
+        Commands::Debug {
+            file,
+            stdin,
+            dry_run,
+            bug,
+        } => {
             let repo_root = get_repo_root(None)?;
-            let ctx = CommandContext::new(agent, model_choice, repo_root)?;
+            let ctx = dry_run_context(agent, model_choice, repo_root, dry_run)?;
             cmd_debug(&ctx, bug, file, stdin)
         }
     }
 }
 
-fn resolve_model_choice(flag: Option<String>, force_model_flag: bool) -> Result<ModelChoice> {
+/// A dry run only renders prompts, so it also runs read-only: nothing is claimed
+/// or written.
+fn dry_run_context(
+    agent: AgentKind,
+    model_choice: ModelChoice,
+    repo_root: PathBuf,
+    dry_run: bool,
+) -> Result<CommandContext> {
+    if dry_run {
+        state::set_read_only(true);
+    }
+    Ok(CommandContext {
+        dry_run,
+        ..CommandContext::new(agent, model_choice, repo_root)?
+    })
+}
+
+/// Context for the commands that claim tasks; `--claim-ttl` overrides the config.
+fn run_context(
+    agent: AgentKind,
+    model_choice: ModelChoice,
+    repo_root: PathBuf,
+    dry_run: bool,
+    claim_ttl: Option<u64>,
+) -> Result<CommandContext> {
+    let mut ctx = dry_run_context(agent, model_choice, repo_root, dry_run)?;
+    if let Some(seconds) = claim_ttl {
+        ctx.config.defaults.claim_ttl_seconds = seconds;
+    }
+    Ok(ctx)
+}
+
+fn resolve_model_choice(
+    flag: Option<String>,
+    force_model_flag: bool,
+    model_args_flag: Option<String>,
+    model_arg_flags: Vec<String>,
+    headless_flag: bool,
+) -> Result<ModelChoice> {
     let env_model = env_var("MUNG_MODEL", "METAGENT_MODEL");
     let env_force = env_var("MUNG_FORCE_MODEL", "METAGENT_FORCE_MODEL")
         .map(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
         .unwrap_or(false);
     let force_model = force_model_flag || env_force;
-
-    if let Some(flag) = flag {
-        return Ok(ModelChoice {
-            model: Model::from_str(&flag)?,
-            explicit: true,
-            force_model,
+    let mut model_args =
+        match model_args_flag.or_else(|| env_var("MUNG_MODEL_ARGS", "METAGENT_MODEL_ARGS")) {
+            Some(args) => split_words(&args).context("--model-args")?,
+            None => Vec::new(),
+        };
+    model_args.extend(model_arg_flags);
+    let headless = headless_flag
+        || env_var("MUNG_HEADLESS", "METAGENT_HEADLESS").is_some_and(|value| {
+            matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes")
         });
-    }
-    if let Some(env_model) = env_model {
-        return Ok(ModelChoice {
-            model: Model::from_str(&env_model)?,
-            explicit: true,
-            force_model,
-        });
-    }
+
+    let (model, explicit, force_model) =
+        if env_var("MUNG_FAKE_AGENT", "METAGENT_FAKE_AGENT").is_some() {
+            (Model::Fake, true, true)
+        } else if let Some(flag) = flag {
+            (Model::from_str(&flag)?, true, force_model)
+        } else if let Some(env_model) = env_model {
+            (Model::from_str(&env_model)?, true, force_model)
+        } else {
+            (Model::Claude, false, force_model)
+        };
     Ok(ModelChoice {
-        model: Model::Claude,
-        explicit: false,
+        model,
+        explicit,
         force_model,
+        model_args,
+        headless,
     })
 }
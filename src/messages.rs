@@ -0,0 +1,160 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::commands::PROMPT_HOME_DIR;
+use crate::util::{env_var, home_dir, read_text};
+
+/// Built-in English catalog. Templates name their arguments as `{name}`; a
+/// translation may reorder or drop them. It covers task and issue lifecycle
+/// messages, the queue runners, and the Jira, Linear, and `serve` output; listings,
+/// reports, and help text are not part of it yet. Model prompts stay English.
+const ENGLISH: &[(&str, &str)] = &[
+    ("task_not_found", "Task '{task}' not found"),
+    ("task_created", "Created task: {task}"),
+    ("task_removed", "Removed '{task}'"),
+    ("task_renamed", "Renamed '{old}' to '{new}'"),
+    ("task_blocked", "Blocked '{task}': {reason}"),
+    ("task_unblocked", "Unblocked '{task}'"),
+    ("task_advanced", "Advanced stage to {stage}"),
+    ("task_exists", "Task '{task}' already exists"),
+    ("task_completed", "Task '{task}' completed."),
+    ("task_running", "Task '{task}' is currently running"),
+    ("task_claimed", "Task '{task}' is already claimed."),
+    ("task_activated", "Activated '{task}'"),
+    ("task_held", "Held '{task}'"),
+    ("task_held_until", "Held '{task}' until {until}"),
+    (
+        "task_reactivated",
+        "Reactivated '{task}' (held until {until})",
+    ),
+    ("task_prioritized", "Set '{task}' priority to {priority}"),
+    (
+        "stage_skipped",
+        "Skipped {stage} for '{task}' (stage: {next})",
+    ),
+    ("queue_complete", "Queue processing complete."),
+    ("no_eligible_tasks", "No eligible tasks."),
+    ("aborted", "Aborted."),
+    ("dependency_not_found", "Dependency '{task}' not found"),
+    ("no_tasks", "No tasks"),
+    (
+        "issue_not_found",
+        "Issue '{id}' not found (run `mung issues` to list IDs)",
+    ),
+    ("issue_created", "Created issue {id}"),
+    ("issue_resolved", "Resolved issue {id}"),
+    ("no_issues", "No issues"),
+    ("sync_skipped", "  {key} (task '{task}' exists, skipped)"),
+    ("sync_task", "  {key} -> task '{task}': {title}"),
+    ("jira_commented", "Commented on {key}"),
+    (
+        "jira_comment_failed",
+        "Warning: failed to comment on Jira {key}: {error}",
+    ),
+    ("linear_move", "  {key} ({stage}) -> {state}"),
+    ("linear_moved", "Moved {key} to {state}"),
+    (
+        "linear_move_failed",
+        "Warning: failed to update Linear {key}: {error}",
+    ),
+    (
+        "linear_synced",
+        "Created {created} task(s), {moved} Linear state change(s)",
+    ),
+    (
+        "linear_sync_preview",
+        "Would create {created} task(s), {moved} Linear state change(s)",
+    ),
+    ("serve_listening", "Listening on http://{addr}"),
+    (
+        "serve_listening_read_only",
+        "Listening on http://{addr} (read-only)",
+    ),
+    (
+        "serve_no_token",
+        "Warning: serving on {addr} without a token; anyone who can reach it can change tasks",
+    ),
+];
+
+static CATALOG: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Selects the message locale: `MUNG_LOCALE`, then `[defaults] locale`, then
+/// English. A locale other than `en` is read from
+/// `.agents/locales/<locale>.toml` in the repo, else `~/.mung/locales/`; keys it
+/// leaves out fall back to English.
+pub fn init(repo_root: Option<&Path>, configured: Option<&str>) {
+    let locale = env_var("MUNG_LOCALE", "METAGENT_LOCALE")
+        .or_else(|| configured.map(str::to_string))
+        .unwrap_or_else(|| "en".to_string());
+    let catalog = match load_catalog(repo_root, locale.trim()) {
+        Ok(catalog) => catalog,
+        Err(err) => {
+            eprintln!("Warning: {:#}; using English messages", err);
+            HashMap::new()
+        }
+    };
+    CATALOG.set(catalog).ok();
+}
+
+fn load_catalog(repo_root: Option<&Path>, locale: &str) -> Result<HashMap<String, String>> {
+    if locale.is_empty() || locale == "en" {
+        return Ok(HashMap::new());
+    }
+    if !locale
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("invalid locale '{}'", locale);
+    }
+    let file = format!("{locale}.toml");
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if let Some(repo_root) = repo_root {
+        candidates.push(repo_root.join(".agents").join("locales").join(&file));
+    }
+    if let Ok(home) = home_dir() {
+        candidates.push(home.join(PROMPT_HOME_DIR).join("locales").join(&file));
+    }
+    let Some(path) = candidates.into_iter().find(|path| path.is_file()) else {
+        bail!("no message catalog for locale '{}'", locale);
+    };
+    let catalog: HashMap<String, String> = toml::from_str(&read_text(&path)?)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    for key in catalog.keys() {
+        if !ENGLISH.iter().any(|(known, _)| known == key) {
+            eprintln!("Warning: {} has unknown message '{}'", path.display(), key);
+        }
+    }
+    Ok(catalog)
+}
+
+/// The message for `key` in the selected locale with `{name}` placeholders filled
+/// from `args`.
+pub fn msg(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let template = CATALOG
+        .get()
+        .and_then(|catalog| catalog.get(key))
+        .map(String::as_str)
+        .or_else(|| {
+            ENGLISH
+                .iter()
+                .find(|(known, _)| *known == key)
+                .map(|(_, template)| *template)
+        })
+        .unwrap_or(key);
+    let mut text = template.to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    text
+}
+
+/// `mung messages`: the English catalog as TOML, a starting point for a translation.
+pub fn cmd_messages() -> Result<()> {
+    for (key, template) in ENGLISH {
+        println!("{} = {}", key, toml::Value::String(template.to_string()));
+    }
+    Ok(())
+}
@@ -1,32 +1,177 @@
 use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use std::process::Command;
+use std::str::FromStr;
+
+use crate::config::CustomModelConfig;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Model {
     Claude,
     Codex,
+    Custom,
+    Fake,
 }
 
-impl Model {
-    pub fn from_str(value: &str) -> Result<Self> {
-        match value {
-            "claude" => Ok(Self::Claude),
-            "codex" => Ok(Self::Codex),
-            _ => bail!("Unknown model: {value}"),
+/// Program, arguments, and extra environment used to launch a model.
+pub struct ModelCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+impl ModelCommand {
+    /// Substitutes `prompt` for `{prompt}` in the arguments, or appends it as the last
+    /// argument when none mention it.
+    pub fn build(&self, prompt: &str) -> Command {
+        let mut command = Command::new(&self.program);
+        if self.args.iter().any(|arg| arg.contains("{prompt}")) {
+            command.args(self.args.iter().map(|arg| arg.replace("{prompt}", prompt)));
+        } else {
+            command.args(&self.args).arg(prompt);
         }
+        command.envs(&self.env);
+        command
+    }
+
+    /// Appends user-supplied flags (`[model_args]`, `--model-args`) after the
+    /// model's own arguments.
+    pub fn with_args(mut self, args: &[String]) -> Self {
+        self.args.extend_from_slice(args);
+        self
     }
+}
 
-    #[allow(dead_code)]
+impl Model {
     pub fn as_str(&self) -> &'static str {
         match self {
             Self::Claude => "claude",
             Self::Codex => "codex",
+            Self::Custom => "custom",
+            Self::Fake => "fake",
+        }
+    }
+
+    /// `custom` is the repo's `[custom_model]` section; only `Model::Custom` reads it.
+    pub fn command(&self, custom: Option<&CustomModelConfig>) -> Result<ModelCommand> {
+        let (program, args) = match self {
+            Self::Claude => (
+                "claude".to_string(),
+                vec!["--dangerously-skip-permissions".to_string()],
+            ),
+            Self::Codex => (
+                "codex".to_string(),
+                vec!["--dangerously-bypass-approvals-and-sandbox".to_string()],
+            ),
+            Self::Custom => {
+                let Some(custom) = custom else {
+                    bail!("Model 'custom' needs a [custom_model] section in .agents/mung.toml");
+                };
+                return Ok(ModelCommand {
+                    program: custom.command.clone(),
+                    args: custom.args.clone(),
+                    env: custom.env.clone(),
+                });
+            }
+            // The fake model is this binary's hidden `fake-agent` subcommand.
+            Self::Fake => {
+                let exe = std::env::current_exe()
+                    .map(|path| path.display().to_string())
+                    .unwrap_or_else(|_| "mung".to_string());
+                (exe, vec!["fake-agent".to_string()])
+            }
+        };
+        Ok(ModelCommand {
+            program,
+            args,
+            env: BTreeMap::new(),
+        })
+    }
+
+    /// Arguments that give a new conversation the id `id`. Only claude takes one
+    /// up front; other models print theirs (`session id: ...`) and it is read
+    /// from the transcript.
+    pub fn session_id_args(&self, id: &str) -> Option<Vec<String>> {
+        match self {
+            Self::Claude => Some(vec!["--session-id".to_string(), id.to_string()]),
+            Self::Codex | Self::Custom | Self::Fake => None,
         }
     }
 
-    pub fn command(&self) -> (&'static str, &'static [&'static str]) {
+    /// Like `command`, but answering the prompt once on stdout and exiting, for
+    /// callers that read the reply instead of waiting for `mung finish`.
+    pub fn print_command(&self, custom: Option<&CustomModelConfig>) -> Result<ModelCommand> {
+        let mut command = self.command(custom)?;
         match self {
-            Self::Claude => ("claude", &["--dangerously-skip-permissions"]),
-            Self::Codex => ("codex", &["--dangerously-bypass-approvals-and-sandbox"]),
+            Self::Claude => command.args = vec!["-p".to_string()],
+            Self::Codex => command.args = vec!["exec".to_string()],
+            Self::Custom => {
+                let Some(print_args) = custom.and_then(|custom| custom.print_args.as_ref()) else {
+                    bail!(
+                        "Model 'custom' needs [custom_model] print_args to answer a one-off prompt"
+                    );
+                };
+                command.args = print_args.clone();
+            }
+            Self::Fake => {
+                command
+                    .env
+                    .insert("MUNG_FAKE_PRINT".to_string(), "1".to_string());
+            }
+        }
+        Ok(command)
+    }
+
+    /// `command` switched to the model's non-interactive mode: claude `-p`, codex
+    /// `exec`. Custom and fake commands already run without a terminal.
+    pub fn headless(&self, mut command: ModelCommand) -> ModelCommand {
+        match self {
+            Self::Claude => command.args.insert(0, "-p".to_string()),
+            Self::Codex => command.args.insert(0, "exec".to_string()),
+            Self::Custom | Self::Fake => {}
+        }
+        command
+    }
+
+    /// Like `command`, but continuing the model's own conversation `id`.
+    pub fn resume_command(
+        &self,
+        custom: Option<&CustomModelConfig>,
+        id: &str,
+    ) -> Result<ModelCommand> {
+        let mut command = self.command(custom)?;
+        match self {
+            Self::Claude => command
+                .args
+                .extend(["--resume".to_string(), id.to_string()]),
+            Self::Codex => command.args.extend(["resume".to_string(), id.to_string()]),
+            Self::Custom => {
+                let Some(resume_args) = custom.and_then(|custom| custom.resume_args.as_ref())
+                else {
+                    bail!("Model 'custom' needs [custom_model] resume_args to resume sessions");
+                };
+                command.args = resume_args
+                    .iter()
+                    .map(|arg| arg.replace("{model_session}", id))
+                    .collect();
+            }
+            // The fake model keeps no conversation; resuming runs its script again.
+            Self::Fake => {}
+        }
+        Ok(command)
+    }
+}
+
+impl FromStr for Model {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "claude" => Ok(Self::Claude),
+            "codex" => Ok(Self::Codex),
+            "custom" => Ok(Self::Custom),
+            "fake" => Ok(Self::Fake),
+            _ => bail!("Unknown model: {value}"),
         }
     }
 }
@@ -0,0 +1,71 @@
+use serde_json::json;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::commands::CommandContext;
+use crate::config::{NotifyEvent, Webhook, WebhookKind};
+
+/// Posts `message` to every configured webhook when `event` is enabled. Delivery
+/// problems are reported as warnings and never fail the command that triggered them.
+pub fn notify(ctx: &CommandContext, event: NotifyEvent, message: &str) {
+    let config = &ctx.config.notifications;
+    if !config.wants(event) {
+        return;
+    }
+    let repo = ctx
+        .repo_root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let text = format!("[mung {}/{}] {}", repo, ctx.agent.name(), message);
+    for webhook in &config.webhooks {
+        if let Err(err) = post(webhook, &text) {
+            eprintln!("Warning: notification failed: {}", err);
+        }
+    }
+}
+
+fn post(webhook: &Webhook, text: &str) -> Result<(), String> {
+    let url = match (webhook.url.as_ref(), webhook.url_env.as_ref()) {
+        (Some(url), _) => url.clone(),
+        (None, Some(var)) => std::env::var(var).map_err(|_| format!("{var} is not set"))?,
+        (None, None) => return Err("webhook has no url".to_string()),
+    };
+    let payload = match webhook.kind {
+        WebhookKind::Slack => json!({ "text": text }),
+        WebhookKind::Discord => json!({ "content": text }),
+    };
+    // curl keeps the binary free of an HTTP stack; the body goes over stdin so the
+    // URL is the only argument that varies.
+    let mut child = Command::new("curl")
+        .args([
+            "-sS",
+            "--fail",
+            "--max-time",
+            "10",
+            "-H",
+            "Content-Type: application/json",
+            "--data-binary",
+            "@-",
+            &url,
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("failed to run curl: {err}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(payload.to_string().as_bytes())
+            .map_err(|err| err.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|err| err.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "curl exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use std::process::Child;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+use std::collections::{HashMap, HashSet};
+#[cfg(unix)]
+use std::process::Command;
+#[cfg(windows)]
+use std::process::{Command, Stdio};
+
+/// Whether a process with this pid exists on this machine.
+#[cfg(unix)]
+pub fn pid_alive(pid: u32) -> bool {
+    unsafe { libc::kill(pid as i32, 0) == 0 }
+}
+
+#[cfg(windows)]
+pub fn pid_alive(pid: u32) -> bool {
+    let Ok(output) = Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/NH", "/FO", "CSV"])
+        .stderr(Stdio::null())
+        .output()
+    else {
+        return false;
+    };
+    // CSV rows look like "claude.exe","1234",...; no match prints an INFO line.
+    String::from_utf8_lossy(&output.stdout).contains(&format!("\",\"{pid}\","))
+}
+
+/// Stops `child` and everything it started: politely first, then by force. Unix
+/// signals the tree found through `ps`; Windows uses `taskkill /T`, which walks
+/// the tree itself.
+#[cfg(unix)]
+pub fn terminate_tree(child: &mut Child) {
+    const SIGINT_ATTEMPTS: usize = 3;
+    let mut known_descendants = HashSet::new();
+    for _ in 0..SIGINT_ATTEMPTS {
+        signal_process_tree(child, libc::SIGINT, &mut known_descendants);
+        if wait_for_process_tree_exit(child, &mut known_descendants, Duration::from_millis(500)) {
+            return;
+        }
+    }
+
+    signal_process_tree(child, libc::SIGTERM, &mut known_descendants);
+    if wait_for_process_tree_exit(child, &mut known_descendants, Duration::from_secs(1)) {
+        return;
+    }
+
+    signal_process_tree(child, libc::SIGKILL, &mut known_descendants);
+    let _ = wait_for_process_tree_exit(child, &mut known_descendants, Duration::from_secs(1));
+    let _ = child.kill();
+    let _ = wait_for_process_tree_exit(child, &mut known_descendants, Duration::from_secs(1));
+}
+
+#[cfg(windows)]
+pub fn terminate_tree(child: &mut Child) {
+    // Without /F, taskkill asks windowed processes to close; console tools ignore
+    // that, so follow up with /F once the grace period is over.
+    taskkill(child.id(), false);
+    if wait_for_exit(child, Duration::from_millis(1500)) {
+        return;
+    }
+    taskkill(child.id(), true);
+    let _ = child.kill();
+    let _ = wait_for_exit(child, Duration::from_secs(1));
+}
+
+#[cfg(windows)]
+fn taskkill(pid: u32, force: bool) {
+    let mut command = Command::new("taskkill");
+    command.args(["/PID", &pid.to_string(), "/T"]);
+    if force {
+        command.arg("/F");
+    }
+    let _ = command.stdout(Stdio::null()).stderr(Stdio::null()).status();
+}
+
+#[cfg(windows)]
+fn wait_for_exit(child: &mut Child, timeout: Duration) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if !matches!(child.try_wait(), Ok(None)) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+#[cfg(unix)]
+fn send_signal_to_pid(pid: i32, signal: i32) {
+    unsafe {
+        let _ = libc::kill(pid, signal);
+    }
+}
+
+#[cfg(unix)]
+fn collect_descendant_pids(root_pid: i32) -> Vec<i32> {
+    let output = match Command::new("ps").args(["-axo", "pid=,ppid="]).output() {
+        Ok(output) if output.status.success() => output,
+        _ => return Vec::new(),
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut children_by_parent: HashMap<i32, Vec<i32>> = HashMap::new();
+    for line in stdout.lines() {
+        let mut parts = line.split_whitespace();
+        let pid = parts.next().and_then(|value| value.parse::<i32>().ok());
+        let ppid = parts.next().and_then(|value| value.parse::<i32>().ok());
+        if let (Some(pid), Some(ppid)) = (pid, ppid) {
+            children_by_parent.entry(ppid).or_default().push(pid);
+        }
+    }
+
+    let mut descendants = Vec::new();
+    let mut stack = vec![root_pid];
+    while let Some(parent) = stack.pop() {
+        if let Some(children) = children_by_parent.get(&parent) {
+            for child in children {
+                descendants.push(*child);
+                stack.push(*child);
+            }
+        }
+    }
+    descendants.sort_unstable();
+    descendants.dedup();
+    descendants
+}
+
+#[cfg(unix)]
+fn signal_process_tree(child: &mut Child, signal: i32, known_descendants: &mut HashSet<i32>) {
+    let root_pid = child.id() as i32;
+    known_descendants.extend(collect_descendant_pids(root_pid));
+
+    // Signal descendants first so wrapper exits don't orphan deeper children.
+    let mut descendants: Vec<i32> = known_descendants
+        .iter()
+        .copied()
+        .filter(|pid| pid_alive(*pid as u32))
+        .collect();
+    descendants.sort_unstable();
+    descendants.reverse();
+    for pid in descendants {
+        send_signal_to_pid(pid, signal);
+    }
+
+    send_signal_to_pid(root_pid, signal);
+}
+
+#[cfg(unix)]
+fn wait_for_process_tree_exit(
+    child: &mut Child,
+    known_descendants: &mut HashSet<i32>,
+    timeout: Duration,
+) -> bool {
+    let start = Instant::now();
+    let mut root_exited = false;
+    while start.elapsed() < timeout {
+        if !root_exited {
+            match child.try_wait() {
+                Ok(Some(_)) => root_exited = true,
+                Ok(None) => {}
+                Err(_) => root_exited = true,
+            }
+        }
+        known_descendants.retain(|pid| pid_alive(*pid as u32));
+        if root_exited && known_descendants.is_empty() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
@@ -1,7 +1,12 @@
+use serde::{Deserialize, Serialize};
+
 use crate::agent::AgentKind;
 use crate::model::Model;
 use crate::state::TaskStatus;
 
+pub const PROMPT_RECORD_FILE: &str = "prompt.json";
+pub const PROMPT_TEXT_FILE: &str = "prompt.md";
+
 pub struct PromptContext<'a> {
     pub repo_root: &'a str,
     pub task: Option<&'a str>,
@@ -11,6 +16,53 @@ pub struct PromptContext<'a> {
     pub review_finish_instructions: &'a str,
     pub parallelism_mode: &'a str,
     pub focus_section: &'a str,
+    pub change_summary: &'a str,
+}
+
+/// Inputs a stage prompt was rendered from, saved next to `session.json` so
+/// `mung replay` can re-render it against the current templates.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PromptRecord {
+    pub stage: String,
+    pub task: Option<String>,
+    pub session: String,
+    pub model: String,
+    pub repo_root: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_prompt: Option<String>,
+    #[serde(default)]
+    pub issues_header: String,
+    #[serde(default)]
+    pub issues_mode: String,
+    #[serde(default)]
+    pub review_finish_instructions: String,
+    #[serde(default)]
+    pub parallelism_mode: String,
+    #[serde(default)]
+    pub focus_section: String,
+    #[serde(default)]
+    pub change_summary: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub finish_marker: bool,
+    /// Note about a previous run that died mid-flight, placed above the prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handover: Option<String>,
+}
+
+impl PromptRecord {
+    pub fn context(&self) -> PromptContext<'_> {
+        PromptContext {
+            repo_root: &self.repo_root,
+            task: self.task.as_deref(),
+            session: Some(&self.session),
+            issues_header: &self.issues_header,
+            issues_mode: &self.issues_mode,
+            review_finish_instructions: &self.review_finish_instructions,
+            parallelism_mode: &self.parallelism_mode,
+            focus_section: &self.focus_section,
+            change_summary: &self.change_summary,
+        }
+    }
 }
 
 pub fn render_prompt(template: &str, context: &PromptContext<'_>) -> String {
@@ -33,6 +85,7 @@ pub fn render_prompt(template: &str, context: &PromptContext<'_>) -> String {
     );
     output = output.replace("{parallelism_mode}", context.parallelism_mode);
     output = output.replace("{focus_section}", context.focus_section);
+    output = output.replace("{change_summary}", context.change_summary);
     output
 }
 
@@ -52,11 +105,10 @@ pub fn issues_text(
         None => return (String::new(), String::new()),
     };
     let header = format!(
-        "0d. Review open issues first: `mung issues --task {task}`\n\n1. **PRIORITY: Issues** - Resolve all open issues before proceeding. After fixing an issue, mark it resolved:\n   `mung issue resolve <id> --resolution \"<brief explanation of the fix>\"`"
-    );
-    let mode = format!(
-        "99999999999999. **REVIEW ISSUES:** This task has open issues. Resolve them before finishing this phase."
+        "0d. Review open issues first: `mung issues --task {task}`\n\n1. **PRIORITY: Issues** - Resolve all open issues before proceeding. After fixing an issue, mark it resolved:\n   `mung issue resolve <id> --resolution \"<brief explanation of the fix>\" [--step T<n>]` (add `--step` for each plan step the fix touched)"
     );
+    let mode = "99999999999999. **REVIEW ISSUES:** This task has open issues. Resolve them before finishing this phase."
+        .to_string();
     (header, mode)
 }
 
@@ -67,3 +119,11 @@ pub fn parallelism_text(model: Model) -> String {
     "## Parallelism\n- Use subagents liberally for research before implementing\n- Codebase search: up to 100 subagents\n- File reading: up to 100 subagents\n- File writing: up to 10 subagents (independent files only)\n- Build/test: 1 subagent only\n- plan.md updates: 1 subagent"
         .to_string()
 }
+
+/// Appended to stage prompts when `[finish] marker` is on. The marker is quoted
+/// inline so an echoed prompt never contains it on a line of its own.
+pub fn finish_marker_text(stage: &str, session: &str) -> String {
+    format!(
+        "If you cannot run the finish command above, signal completion instead by ending your output with this line on its own (optionally add ` next=<stage>`): `MUNG_FINISH: stage={stage} session={session}`"
+    )
+}
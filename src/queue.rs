@@ -0,0 +1,398 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use crate::agent::AgentKind;
+use crate::config::{Fairness, QueueConfig, QueuePolicy};
+use crate::issues::IssuePriority;
+use crate::state::{TaskState, TaskStatus};
+
+/// Dependencies of `task` that are missing or not yet completed.
+pub fn unmet_dependencies<'a>(task: &'a TaskState, tasks: &[TaskState]) -> Vec<&'a str> {
+    task.depends_on
+        .iter()
+        .filter(|dependency| {
+            !tasks
+                .iter()
+                .any(|other| &other.task == *dependency && other.stage == "completed")
+        })
+        .map(String::as_str)
+        .collect()
+}
+
+/// A task's priority weight (`P0` = 0); tasks without one count as `P2`.
+pub fn task_priority(task: &TaskState) -> u8 {
+    task.priority
+        .as_deref()
+        .and_then(|priority| IssuePriority::from_str(priority).ok())
+        .unwrap_or(IssuePriority::P2)
+        .weight()
+}
+
+/// Picks the next task to run, skipping tasks whose dependencies are unfinished.
+/// Tasks in `urgent` (SLA breaches) go first, using the configured policy among
+/// themselves. Otherwise the highest task priority goes first, and the policy
+/// orders tasks of equal priority.
+pub fn next_eligible_task(
+    agent: AgentKind,
+    queue: &QueueConfig,
+    tasks: &[TaskState],
+    urgent: &HashSet<String>,
+) -> Option<TaskState> {
+    let ready: Vec<TaskState> = tasks
+        .iter()
+        .filter(|t| unmet_dependencies(t, tasks).is_empty())
+        .cloned()
+        .collect();
+    if queue.fairness == Fairness::None {
+        return pick_next_task(agent, queue.policy, &ready, urgent);
+    }
+    // SLA breaches still jump every group's turn.
+    let breached: Vec<TaskState> = ready
+        .iter()
+        .filter(|t| urgent.contains(&t.task))
+        .cloned()
+        .collect();
+    if let Some(task) = pick_next_task(agent, queue.policy, &breached, &HashSet::new()) {
+        return Some(task);
+    }
+    for group in groups_by_turn(queue.fairness, tasks) {
+        let members: Vec<TaskState> = ready
+            .iter()
+            .filter(|t| fairness_groups(queue.fairness, t).contains(&group))
+            .cloned()
+            .collect();
+        if let Some(task) = pick_next_task(agent, queue.policy, &members, &HashSet::new()) {
+            return Some(task);
+        }
+    }
+    None
+}
+
+/// The owner or labels a task is scheduled under; `-` when it has none.
+fn fairness_groups(fairness: Fairness, task: &TaskState) -> Vec<String> {
+    let groups = match fairness {
+        Fairness::None => Vec::new(),
+        Fairness::Owner => task.owner.iter().cloned().collect(),
+        Fairness::Label => task.labels.clone(),
+    };
+    if groups.is_empty() {
+        vec!["-".to_string()]
+    } else {
+        groups
+    }
+}
+
+/// Groups ordered by when one of their tasks last ran, least recent (or never) first.
+fn groups_by_turn(fairness: Fairness, tasks: &[TaskState]) -> Vec<String> {
+    let mut last_run: HashMap<String, Option<&str>> = HashMap::new();
+    for task in tasks {
+        for group in fairness_groups(fairness, task) {
+            let entry = last_run.entry(group).or_default();
+            *entry = (*entry).max(task.last_run_at.as_deref());
+        }
+    }
+    let mut groups: Vec<(String, Option<&str>)> = last_run.into_iter().collect();
+    groups.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+    groups.into_iter().map(|(group, _)| group).collect()
+}
+
+fn pick_next_task(
+    agent: AgentKind,
+    policy: QueuePolicy,
+    tasks: &[TaskState],
+    urgent: &HashSet<String>,
+) -> Option<TaskState> {
+    if !urgent.is_empty() {
+        let urgent_tasks: Vec<TaskState> = tasks
+            .iter()
+            .filter(|t| urgent.contains(&t.task))
+            .cloned()
+            .collect();
+        if let Some(task) = pick_next_task(agent, policy, &urgent_tasks, &HashSet::new()) {
+            return Some(task);
+        }
+    }
+    let queue_stages = agent.queue_stages();
+    let runnable = |t: &&TaskState| {
+        !t.held
+            && t.awaiting_approval.is_none()
+            && queue_stages.contains(&t.stage.as_str())
+            && matches!(
+                t.status,
+                TaskStatus::Pending | TaskStatus::Incomplete | TaskStatus::Issues
+            )
+    };
+    let top_priority = tasks.iter().filter(runnable).map(task_priority).min();
+    let eligible = |t: &&TaskState| runnable(t) && Some(task_priority(t)) == top_priority;
+    let by_rank = |a: &TaskState, b: &TaskState| {
+        let ar = a.queue_rank.unwrap_or(i64::MAX);
+        let br = b.queue_rank.unwrap_or(i64::MAX);
+        ar.cmp(&br).then_with(|| a.added_at.cmp(&b.added_at))
+    };
+    let stage_order: Vec<&str> = match policy {
+        QueuePolicy::StageOrder => queue_stages.to_vec(),
+        QueuePolicy::FinishFirst => queue_stages.iter().rev().copied().collect(),
+        QueuePolicy::OldestFirst => {
+            let mut candidates: Vec<&TaskState> = tasks.iter().filter(eligible).collect();
+            candidates.sort_by(|a, b| by_rank(a, b));
+            if let Some(task) = candidates.first() {
+                return Some((*task).clone());
+            }
+            Vec::new()
+        }
+    };
+    for stage in stage_order {
+        let mut stage_tasks: Vec<TaskState> = tasks
+            .iter()
+            .filter(eligible)
+            .filter(|t| t.stage == stage)
+            .cloned()
+            .collect();
+        if stage_tasks.is_empty() {
+            continue;
+        }
+        if stage == "build" {
+            stage_tasks.sort_by(by_rank);
+        } else {
+            stage_tasks.sort_by(|a, b| a.added_at.cmp(&b.added_at));
+        }
+        return stage_tasks.into_iter().next();
+    }
+    // Safety net: pick up completed tasks that still have Issues status
+    let mut issues_tasks: Vec<TaskState> = tasks
+        .iter()
+        .filter(|t| !t.held && t.stage == "completed" && t.status == TaskStatus::Issues)
+        .cloned()
+        .collect();
+    if !issues_tasks.is_empty() {
+        issues_tasks.sort_by(|a, b| a.added_at.cmp(&b.added_at));
+        // Override stage to build since completed has no prompt
+        return issues_tasks.into_iter().next().map(|mut t| {
+            t.stage = "build".to_string();
+            t
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::memory::MemoryStore;
+    use crate::store::StateStore;
+
+    fn add_task(store: &dyn StateStore, task: &str, stage: &str, added_at: &str) {
+        crate::state::create_task_state(store, "code", task, stage, added_at, false, None, None)
+            .unwrap();
+    }
+
+    #[test]
+    fn next_eligible_task_prefers_ranked_build_tasks() {
+        let store: &dyn StateStore = &MemoryStore::new();
+        add_task(store, "older", "build", "2026-01-01T00:00:00Z");
+        add_task(store, "ranked", "build", "2026-01-02T00:00:00Z");
+        add_task(store, "parked", "build", "2025-12-01T00:00:00Z");
+        store
+            .update_task("ranked", |task| {
+                task.queue_rank = Some(1);
+                Ok(())
+            })
+            .unwrap();
+        store
+            .update_task("parked", |task| {
+                task.held = true;
+                Ok(())
+            })
+            .unwrap();
+
+        let next = next_eligible_task(
+            AgentKind::Code,
+            &QueueConfig::default(),
+            &store.list_tasks(),
+            &HashSet::new(),
+        )
+        .unwrap();
+        assert_eq!(next.task, "ranked");
+    }
+
+    #[test]
+    fn next_eligible_task_runs_higher_priorities_first() {
+        let store: &dyn StateStore = &MemoryStore::new();
+        add_task(store, "ranked", "build", "2026-01-01T00:00:00Z");
+        add_task(store, "urgent", "review", "2026-01-03T00:00:00Z");
+        add_task(store, "minor", "build", "2026-01-02T00:00:00Z");
+        for (task, rank, priority) in [
+            ("ranked", Some(1), None),
+            ("urgent", None, Some("P1")),
+            ("minor", None, Some("P3")),
+        ] {
+            store
+                .update_task(task, |state| {
+                    state.queue_rank = rank;
+                    state.priority = priority.map(str::to_string);
+                    Ok(())
+                })
+                .unwrap();
+        }
+        let next = |tasks: &[TaskState]| {
+            next_eligible_task(
+                AgentKind::Code,
+                &QueueConfig::default(),
+                tasks,
+                &HashSet::new(),
+            )
+            .unwrap()
+            .task
+        };
+
+        // P1 in review beats an unprioritized (P2) ranked build task.
+        assert_eq!(next(&store.list_tasks()), "urgent");
+        let rest: Vec<TaskState> = store
+            .list_tasks()
+            .into_iter()
+            .filter(|task| task.task != "urgent")
+            .collect();
+        assert_eq!(next(&rest), "ranked");
+    }
+
+    #[test]
+    fn next_eligible_task_runs_sla_breaches_first() {
+        let store: &dyn StateStore = &MemoryStore::new();
+        add_task(
+            store,
+            "triage",
+            "spec-review-issues",
+            "2026-01-01T00:00:00Z",
+        );
+        add_task(store, "late", "build", "2026-01-02T00:00:00Z");
+        let urgent = HashSet::from(["late".to_string()]);
+
+        let next = next_eligible_task(
+            AgentKind::Code,
+            &QueueConfig::default(),
+            &store.list_tasks(),
+            &urgent,
+        )
+        .unwrap();
+        assert_eq!(next.task, "late");
+        let next = next_eligible_task(
+            AgentKind::Code,
+            &QueueConfig::default(),
+            &store.list_tasks(),
+            &HashSet::new(),
+        )
+        .unwrap();
+        assert_eq!(next.task, "triage");
+    }
+
+    #[test]
+    fn queue_policy_changes_stage_traversal() {
+        let store: &dyn StateStore = &MemoryStore::new();
+        add_task(store, "old-build", "build", "2026-01-01T00:00:00Z");
+        add_task(store, "review", "review", "2026-01-02T00:00:00Z");
+        add_task(
+            store,
+            "triage",
+            "spec-review-issues",
+            "2026-01-03T00:00:00Z",
+        );
+        let tasks = store.list_tasks();
+        let pick = |policy| {
+            let queue = QueueConfig {
+                policy,
+                ..QueueConfig::default()
+            };
+            next_eligible_task(AgentKind::Code, &queue, &tasks, &HashSet::new())
+                .unwrap()
+                .task
+        };
+
+        assert_eq!(pick(QueuePolicy::StageOrder), "triage");
+        assert_eq!(pick(QueuePolicy::FinishFirst), "review");
+        assert_eq!(pick(QueuePolicy::OldestFirst), "old-build");
+    }
+
+    #[test]
+    fn next_eligible_task_skips_unfinished_dependencies() {
+        let store: &dyn StateStore = &MemoryStore::new();
+        add_task(store, "schema", "review", "2026-01-02T00:00:00Z");
+        add_task(store, "api", "build", "2026-01-01T00:00:00Z");
+        store
+            .update_task("api", |task| {
+                task.depends_on = vec!["schema".to_string()];
+                Ok(())
+            })
+            .unwrap();
+        let pick = |tasks: &[TaskState]| {
+            next_eligible_task(
+                AgentKind::Code,
+                &QueueConfig {
+                    policy: QueuePolicy::OldestFirst,
+                    ..QueueConfig::default()
+                },
+                tasks,
+                &HashSet::from(["api".to_string()]),
+            )
+            .map(|task| task.task)
+        };
+
+        assert_eq!(pick(&store.list_tasks()).as_deref(), Some("schema"));
+        store
+            .update_task("schema", |task| {
+                task.stage = "completed".to_string();
+                task.status = TaskStatus::Completed;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(pick(&store.list_tasks()).as_deref(), Some("api"));
+    }
+
+    #[test]
+    fn owner_fairness_takes_turns_between_owners() {
+        let store: &dyn StateStore = &MemoryStore::new();
+        for (task, added_at) in [
+            ("epic-1", "2026-01-01T00:00:00Z"),
+            ("epic-2", "2026-01-01T00:00:01Z"),
+            ("epic-3", "2026-01-01T00:00:02Z"),
+            ("hotfix", "2026-01-05T00:00:00Z"),
+        ] {
+            add_task(store, task, "build", added_at);
+            let owner = if task == "hotfix" { "bo" } else { "ana" };
+            store
+                .update_task(task, |task| {
+                    task.owner = Some(owner.to_string());
+                    Ok(())
+                })
+                .unwrap();
+        }
+        let fair = QueueConfig {
+            fairness: Fairness::Owner,
+            ..QueueConfig::default()
+        };
+        let pick = |queue: &QueueConfig| {
+            next_eligible_task(AgentKind::Code, queue, &store.list_tasks(), &HashSet::new())
+                .map(|task| task.task)
+        };
+        assert_eq!(pick(&QueueConfig::default()).as_deref(), Some("epic-1"));
+        // Neither owner has run yet; ties go by name.
+        assert_eq!(pick(&fair).as_deref(), Some("epic-1"));
+
+        store
+            .update_task("epic-1", |task| {
+                task.stage = "review".to_string();
+                task.last_run_at = Some("2026-01-06T00:00:00Z".to_string());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(pick(&QueueConfig::default()).as_deref(), Some("epic-2"));
+        assert_eq!(pick(&fair).as_deref(), Some("hotfix"));
+
+        store
+            .update_task("hotfix", |task| {
+                task.last_run_at = Some("2026-01-07T00:00:00Z".to_string());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(pick(&fair).as_deref(), Some("epic-2"));
+    }
+}
@@ -0,0 +1,311 @@
+use anyhow::{bail, Context, Result};
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, TimeZone, Timelike,
+};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use crate::commands::{CommandContext, INTERRUPTED};
+
+/// A five-field cron expression (`minute hour day-of-month month day-of-week`),
+/// in local time. Fields take `*`, numbers, `a-b` ranges, `/step`, and comma
+/// lists; day-of-week 0 and 7 are both Sunday. As in cron, when both day fields
+/// are restricted a time matches if either does; a day field starting with `*`
+/// (so `*/2` too) doesn't count as restricted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let expanded = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields.as_slice() else {
+            bail!(
+                "Schedule '{}' needs five fields: minute hour day-of-month month day-of-week",
+                expr
+            );
+        };
+        let mut weekdays = parse_field(weekday, 0, 7, "day-of-week")?;
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+        weekdays.truncate(7);
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, "minute")?,
+            hours: parse_field(hour, 0, 23, "hour")?,
+            days: parse_field(day, 1, 31, "day-of-month")?,
+            months: parse_field(month, 1, 12, "month")?,
+            weekdays,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        })
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day = self.days[date.day() as usize];
+        let weekday = self.weekdays[date.weekday().num_days_from_sunday() as usize];
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        };
+        self.months[date.month() as usize] && day_matches
+    }
+
+    /// The first matching minute strictly after `after`. Days are checked first,
+    /// then the minutes of a matching day; the search spans eight years, the
+    /// longest gap between leap days, so `0 0 29 2 *` is always found.
+    pub fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let start = start.naive_local();
+        start
+            .date()
+            .iter_days()
+            .take(8 * 366 + 1)
+            .filter(|date| self.day_matches(*date))
+            .find_map(|date| {
+                (0..24 * 60).find_map(|minute| {
+                    let (hour, minute) = (minute / 60, minute % 60);
+                    if !self.hours[hour as usize] || !self.minutes[minute as usize] {
+                        return None;
+                    }
+                    let candidate = date.and_hms_opt(hour, minute, 0)?;
+                    if candidate < start {
+                        return None;
+                    }
+                    // Minutes skipped by a DST change don't exist locally.
+                    Local.from_local_datetime(&candidate).earliest()
+                })
+            })
+    }
+}
+
+/// Parses one field into a table indexed by value (`0..=max`).
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<Vec<bool>> {
+    let mut allowed = vec![false; max as usize + 1];
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .ok()
+                    .filter(|step| *step > 0)
+                    .with_context(|| format!("Invalid step in {} field '{}'", name, field))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let value = |text: &str| -> Result<u32> {
+            let value: u32 = text
+                .parse()
+                .with_context(|| format!("Invalid {} field '{}'", name, field))?;
+            if value < min || value > max {
+                bail!("{} {} is outside {}-{}", name, value, min, max);
+            }
+            Ok(value)
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                None if step > 1 => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        if start > end {
+            bail!("Invalid range in {} field '{}'", name, field);
+        }
+        for value in (start..=end).step_by(step as usize) {
+            allowed[value as usize] = true;
+        }
+    }
+    Ok(allowed)
+}
+
+/// `mung schedule <cron>`: stays in the foreground and runs `mung run-queue` (with
+/// `queue_args`) each time the schedule fires, writing each run's output to
+/// `.agents/<agent>/schedule/<time>.log`. A run still going when the next time
+/// comes around is not doubled up; the missed time is skipped. `now` also runs once
+/// at start; `runs` stops after that many runs.
+pub fn cmd_schedule(
+    ctx: &CommandContext,
+    expr: &str,
+    now: bool,
+    runs: Option<usize>,
+    queue_args: &[String],
+) -> Result<()> {
+    let schedule = CronSchedule::parse(expr)?;
+    let log_dir = ctx.agent_root.join("schedule");
+    let mut completed = 0usize;
+    let mut run_now = now;
+    loop {
+        if runs.is_some_and(|runs| completed >= runs) {
+            return Ok(());
+        }
+        if !run_now {
+            let Some(next) = schedule.next_after(Local::now()) else {
+                bail!("Schedule '{}' never fires", expr);
+            };
+            println!("Next run at {}", next.format("%Y-%m-%d %H:%M"));
+            std::io::stdout().flush().ok();
+            if !sleep_until(next) {
+                return Ok(());
+            }
+        }
+        run_now = false;
+        run_queue_logged(&log_dir, queue_args)?;
+        completed += 1;
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+    }
+}
+
+/// Runs `mung run-queue` with the same global flags this process got, output to a
+/// new log file under `log_dir`.
+fn run_queue_logged(log_dir: &std::path::Path, queue_args: &[String]) -> Result<()> {
+    fs::create_dir_all(log_dir)
+        .with_context(|| format!("Failed to create {}", log_dir.display()))?;
+    let log_path = log_dir.join(format!("{}.log", Local::now().format("%Y%m%d-%H%M%S")));
+    let log = File::create(&log_path)
+        .with_context(|| format!("Failed to create {}", log_path.display()))?;
+    let exe = std::env::current_exe().context("Failed to locate the mung binary")?;
+    // Flags before the subcommand (`--agent`, `--model`) apply to the queue run too.
+    let global: Vec<OsString> = std::env::args_os()
+        .skip(1)
+        .take_while(|arg| arg != "schedule")
+        .collect();
+    println!("Running queue, logging to {}", log_path.display());
+    std::io::stdout().flush().ok();
+    let status = Command::new(exe)
+        .args(global)
+        .arg("run-queue")
+        .args(queue_args)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        .status()
+        .context("Failed to start run-queue")?;
+    if status.success() {
+        println!("Queue run finished");
+    } else {
+        println!(
+            "Queue run exited with {} (see {})",
+            status,
+            log_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Sleeps until `deadline`; returns false as soon as Ctrl-C is pressed.
+fn sleep_until(deadline: DateTime<Local>) -> bool {
+    while Local::now() < deadline {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CronSchedule;
+    use chrono::{Local, TimeZone};
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> chrono::DateTime<Local> {
+        Local.with_ymd_and_hms(y, mo, d, h, mi, 0).single().unwrap()
+    }
+
+    #[test]
+    fn nightly_schedule_fires_at_ten_pm() {
+        let schedule = CronSchedule::parse("0 22 * * *").unwrap();
+        assert_eq!(
+            schedule.next_after(at(2026, 3, 10, 9, 30)),
+            Some(at(2026, 3, 10, 22, 0))
+        );
+        assert_eq!(
+            schedule.next_after(at(2026, 3, 10, 22, 0)),
+            Some(at(2026, 3, 11, 22, 0))
+        );
+    }
+
+    #[test]
+    fn steps_ranges_and_weekdays() {
+        let schedule = CronSchedule::parse("*/15 9-17 * * 1-5").unwrap();
+        // Saturday evening -> Monday 09:00.
+        assert_eq!(
+            schedule.next_after(at(2026, 3, 14, 18, 0)),
+            Some(at(2026, 3, 16, 9, 0))
+        );
+        assert_eq!(
+            schedule.next_after(at(2026, 3, 16, 9, 0)),
+            Some(at(2026, 3, 16, 9, 15))
+        );
+        let sundays = CronSchedule::parse("0 0 * * 7").unwrap();
+        assert_eq!(sundays, CronSchedule::parse("@weekly").unwrap());
+    }
+
+    #[test]
+    fn stepped_star_day_fields_are_unrestricted() {
+        // Every other day of the month, Mondays only: both must match.
+        let schedule = CronSchedule::parse("0 9 */2 * 1").unwrap();
+        // 2026-03-16 is a Monday but an even day; the next odd Monday is 03-23.
+        assert_eq!(
+            schedule.next_after(at(2026, 3, 15, 12, 0)),
+            Some(at(2026, 3, 23, 9, 0))
+        );
+        // Day 13 only, any weekday via */1: only the 13th matches.
+        let schedule = CronSchedule::parse("0 9 13 * */1").unwrap();
+        assert_eq!(
+            schedule.next_after(at(2026, 3, 14, 12, 0)),
+            Some(at(2026, 4, 13, 9, 0))
+        );
+    }
+
+    #[test]
+    fn leap_day_schedule_is_found_years_ahead() {
+        let schedule = CronSchedule::parse("0 0 29 2 *").unwrap();
+        assert_eq!(
+            schedule.next_after(at(2026, 3, 1, 0, 0)),
+            Some(at(2028, 2, 29, 0, 0))
+        );
+        // No Feb 29 in 2100: 2096 to 2104 is the longest gap.
+        assert_eq!(
+            schedule.next_after(at(2096, 3, 1, 0, 0)),
+            Some(at(2104, 2, 29, 0, 0))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        for expr in [
+            "0 22 * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "x * * * *",
+        ] {
+            assert!(CronSchedule::parse(expr).is_err(), "{expr}");
+        }
+    }
+}
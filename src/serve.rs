@@ -0,0 +1,658 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::commands::{
+    cmd_activate, cmd_finish, cmd_hold, cmd_task, CommandContext, TaskOptions, INTERRUPTED,
+};
+use crate::config::StateBackend;
+use crate::events::Event;
+use crate::issues::{parse_issue, render_issue};
+use crate::messages::msg;
+use crate::state::{
+    claim_expired, is_read_only, set_read_only, ClaimState, ReadOnlyError, SessionState, TaskState,
+};
+use crate::util::{env_var, now_iso, read_text, validate_task_name};
+
+const MAX_BODY_BYTES: usize = 1 << 20;
+
+/// How long a remote client may hold a record checked out before it lapses.
+const CHECKOUT_SECONDS: u64 = 30;
+
+struct Request {
+    method: String,
+    path: String,
+    token: Option<String>,
+    body: Vec<u8>,
+}
+
+/// A reply: HTTP status and JSON body.
+type Reply = (u16, Value);
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct NewTask {
+    name: String,
+    hold: bool,
+    description: Option<String>,
+    prompt: Option<String>,
+    depends_on: Vec<String>,
+    owner: Option<String>,
+    labels: Vec<String>,
+    tags: Vec<String>,
+    due: Option<String>,
+    estimate: Option<String>,
+    priority: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FinishRequest {
+    stage: Option<String>,
+    next: Option<String>,
+}
+
+/// Claims and checkouts handed to remote-backend clients. They live only in this
+/// process: restarting the server drops them.
+#[derive(Default)]
+struct Leases {
+    claims: BTreeMap<String, ClaimState>,
+    /// `tasks/<name>` or `sessions/<id>` -> (lock, taken at).
+    checkouts: BTreeMap<String, (String, Instant)>,
+    next_lock: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Record<T> {
+    record: T,
+    #[serde(default)]
+    lock: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Release {
+    lock: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Document {
+    document: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Claimant {
+    host: String,
+    pid: u32,
+    ttl_seconds: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Holder {
+    host: String,
+    pid: u32,
+}
+
+/// `mung serve`: a small JSON-over-HTTP API for the repo's tasks, issues, and
+/// sessions, with endpoints to enqueue, hold, activate, and finish. Each
+/// connection is read on its own thread, so a slow client can't stall the rest;
+/// requests are then handled one at a time. With a token (`MUNG_SERVE_TOKEN`, or
+/// the first line of `token_file`), every request must send `Authorization: Bearer
+/// <token>`. The `/state` endpoints back other machines' `[state] backend = "remote"`.
+/// `read_only` answers every write with `403`, for dashboards that only watch.
+pub fn cmd_serve(
+    ctx: &CommandContext,
+    addr: &str,
+    token_file: Option<&Path>,
+    read_only: bool,
+) -> Result<()> {
+    if ctx.config.state.backend == StateBackend::Remote {
+        bail!("mung serve needs local state; this repo uses [state] backend = \"remote\"");
+    }
+    let token = match token_file {
+        Some(path) => {
+            let token = read_text(path)?
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            if token.is_empty() {
+                bail!("{} holds no token", path.display());
+            }
+            Some(token)
+        }
+        None => env_var("MUNG_SERVE_TOKEN", "METAGENT_SERVE_TOKEN"),
+    };
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("Failed to listen on {}", addr))?;
+    let local = listener.local_addr()?;
+    if read_only {
+        set_read_only(true);
+    }
+    if token.is_none() && !local.ip().is_loopback() && !is_read_only() {
+        eprintln!("{}", msg("serve_no_token", &[("addr", &local)]));
+    }
+    listener.set_nonblocking(true)?;
+    let listening = if is_read_only() {
+        "serve_listening_read_only"
+    } else {
+        "serve_listening"
+    };
+    println!("{}", msg(listening, &[("addr", &local)]));
+    std::io::stdout().flush().ok();
+
+    let leases = Mutex::new(Leases::default());
+    thread::scope(|scope| {
+        while !INTERRUPTED.load(Ordering::SeqCst) {
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(50));
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let (leases, token) = (&leases, token.as_deref());
+            scope.spawn(move || handle_connection(ctx, leases, token, stream));
+        }
+        Ok(())
+    })
+}
+
+fn handle_connection(
+    ctx: &CommandContext,
+    leases: &Mutex<Leases>,
+    token: Option<&str>,
+    mut stream: TcpStream,
+) {
+    let request = stream
+        .set_nonblocking(false)
+        .and_then(|()| stream.set_read_timeout(Some(Duration::from_secs(10))))
+        .map_err(anyhow::Error::from)
+        .and_then(|()| read_request(&mut stream));
+    let (status, body) = match request {
+        Ok(request) if !authorized(&request, token) => error(401, "missing or wrong bearer token"),
+        Ok(request) => {
+            let mut leases = leases
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let (status, body) = route(ctx, &mut leases, &request).unwrap_or_else(|err| {
+                let status = if err.is::<ReadOnlyError>() { 403 } else { 400 };
+                error(status, &format!("{:#}", err))
+            });
+            eprintln!("{} {} -> {}", request.method, request.path, status);
+            (status, body)
+        }
+        Err(err) => error(400, &format!("{:#}", err)),
+    };
+    if let Err(err) = respond(&mut stream, status, &body) {
+        eprintln!("Warning: failed to send response: {:#}", err);
+    }
+}
+
+fn route(ctx: &CommandContext, leases: &mut Leases, request: &Request) -> Result<Reply> {
+    // Leases and claims live in memory here, so writes are refused up front
+    // rather than left to the state layer.
+    if is_read_only() && request.method != "GET" {
+        return Err(ReadOnlyError.into());
+    }
+    let path = request.path.trim_end_matches('/');
+    let segments: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        (method, ["state", rest @ ..]) => state_route(ctx, leases, method, rest, request),
+        ("GET", ["tasks"]) => {
+            let mut tasks = ctx.store.list_tasks();
+            tasks.sort_by(|a, b| a.added_at.cmp(&b.added_at));
+            Ok((200, json!(tasks)))
+        }
+        ("POST", ["tasks"]) => {
+            let new: NewTask = parse_body(request)?;
+            validate_task_name(&new.name)?;
+            if ctx.store.task_exists(&new.name) {
+                return Ok(error(409, &format!("Task '{}' already exists", new.name)));
+            }
+            cmd_task(
+                ctx,
+                &new.name,
+                TaskOptions {
+                    hold: new.hold,
+                    description: new.description,
+                    prompt: new.prompt,
+                    depends_on: new.depends_on,
+                    owner: new.owner,
+                    labels: new.labels,
+                    tags: new.tags,
+                    due: new.due,
+                    estimate: new.estimate,
+                    priority: new.priority,
+                },
+            )?;
+            Ok((201, json!(ctx.store.load_task(&new.name)?)))
+        }
+        ("GET", ["tasks", task]) => with_task(ctx, task, |task_state| Ok(json!(task_state))),
+        ("POST", ["tasks", task, "hold"]) => with_task(ctx, task, |_| {
+            cmd_hold(ctx, task, None, None, None)?;
+            Ok(json!(ctx.store.load_task(task)?))
+        }),
+        ("POST", ["tasks", task, "activate"]) => with_task(ctx, task, |_| {
+            cmd_activate(ctx, task)?;
+            Ok(json!(ctx.store.load_task(task)?))
+        }),
+        ("GET", ["issues"]) => Ok((200, json!(ctx.store.list_issues()?))),
+        ("GET", ["issues", id]) => {
+            validate_id(id)?;
+            if !ctx.store.issue_exists(id) {
+                return Ok(error(404, &format!("Issue '{}' not found", id)));
+            }
+            Ok((200, json!(ctx.store.load_issue(id)?)))
+        }
+        ("GET", ["sessions"]) => {
+            let mut sessions = ctx.store.list_sessions();
+            sessions.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+            Ok((200, json!(sessions)))
+        }
+        ("GET", ["sessions", id]) => {
+            validate_id(id)?;
+            match ctx.store.load_session(id) {
+                Ok(session) => Ok((200, json!(session))),
+                Err(_) => Ok(error(404, &format!("Session '{}' not found", id))),
+            }
+        }
+        ("POST", ["sessions", id, "finish"]) => {
+            validate_id(id)?;
+            let Ok(session) = ctx.store.load_session(id) else {
+                return Ok(error(404, &format!("Session '{}' not found", id)));
+            };
+            let finish: FinishRequest = parse_body(request)?;
+            let stage = finish.stage.unwrap_or_else(|| session.stage.clone());
+            cmd_finish(
+                ctx,
+                Some(stage),
+                finish.next,
+                Some(id.to_string()),
+                session.task.clone(),
+            )?;
+            Ok((200, json!(ctx.store.load_session(id)?)))
+        }
+        (_, ["tasks" | "issues" | "sessions", ..]) => Ok(error(405, "method not allowed")),
+        _ => Ok(error(404, "no such endpoint")),
+    }
+}
+
+/// Record-level access for `RemoteStore` clients. Writes to a record checked out
+/// by someone else are refused with 423 until its lock is returned or lapses.
+fn state_route(
+    ctx: &CommandContext,
+    leases: &mut Leases,
+    method: &str,
+    segments: &[&str],
+    request: &Request,
+) -> Result<Reply> {
+    let store = &ctx.store;
+    match (method, segments) {
+        ("GET", []) => Ok((200, json!({ "agent": ctx.agent.name() }))),
+        ("GET", ["tasks"]) => Ok((200, json!(store.list_tasks()))),
+        ("GET", ["tasks", task]) => {
+            validate_task_name(task)?;
+            if !store.task_exists(task) {
+                return Ok(error(404, &format!("Task '{}' not found", task)));
+            }
+            Ok((200, json!(store.load_task(task)?)))
+        }
+        ("PUT", ["tasks", task]) => {
+            validate_task_name(task)?;
+            let body: Record<TaskState> =
+                serde_json::from_slice(&request.body).context("Invalid JSON body")?;
+            if body.record.task != *task {
+                bail!("Record is for task '{}', not '{}'", body.record.task, task);
+            }
+            if let Some(reply) = leases.check_in(&format!("tasks/{}", task), body.lock) {
+                return Ok(reply);
+            }
+            let mut record = body.record;
+            record.record_history();
+            store.save_task(&record)?;
+            Ok((200, json!(record)))
+        }
+        ("DELETE", ["tasks", task]) => {
+            validate_task_name(task)?;
+            let body: Release = parse_body(request)?;
+            if let Some(reply) = leases.check_in(&format!("tasks/{}", task), body.lock) {
+                return Ok(reply);
+            }
+            store.delete_task(task)?;
+            Ok((200, json!({})))
+        }
+        ("POST", ["tasks", task, "checkout"]) => {
+            validate_task_name(task)?;
+            if !store.task_exists(task) {
+                return Ok(error(404, &format!("Task '{}' not found", task)));
+            }
+            let record = json!(store.load_task(task)?);
+            Ok(leases.check_out(&format!("tasks/{}", task), record))
+        }
+        ("POST", ["tasks", task, "release"]) => {
+            let body: Release = parse_body(request)?;
+            leases.check_in(&format!("tasks/{}", task), body.lock);
+            Ok((200, json!({})))
+        }
+        ("GET", ["sessions"]) => Ok((200, json!(store.list_sessions()))),
+        ("GET", ["sessions", id]) => {
+            validate_id(id)?;
+            match store.load_session(id) {
+                Ok(session) => Ok((200, json!(session))),
+                Err(_) => Ok(error(404, &format!("Session '{}' not found", id))),
+            }
+        }
+        ("PUT", ["sessions", id]) => {
+            validate_id(id)?;
+            let body: Record<SessionState> =
+                serde_json::from_slice(&request.body).context("Invalid JSON body")?;
+            if body.record.session_id != *id {
+                bail!(
+                    "Record is for session '{}', not '{}'",
+                    body.record.session_id,
+                    id
+                );
+            }
+            if let Some(reply) = leases.check_in(&format!("sessions/{}", id), body.lock) {
+                return Ok(reply);
+            }
+            store.save_session(&body.record)?;
+            Ok((200, json!(body.record)))
+        }
+        ("DELETE", ["sessions", id]) => {
+            validate_id(id)?;
+            let body: Release = parse_body(request)?;
+            if let Some(reply) = leases.check_in(&format!("sessions/{}", id), body.lock) {
+                return Ok(reply);
+            }
+            store.remove_session(id)?;
+            Ok((200, json!({})))
+        }
+        ("POST", ["sessions", id, "checkout"]) => {
+            validate_id(id)?;
+            let Ok(session) = store.load_session(id) else {
+                return Ok(error(404, &format!("Session '{}' not found", id)));
+            };
+            Ok(leases.check_out(&format!("sessions/{}", id), json!(session)))
+        }
+        ("POST", ["sessions", id, "release"]) => {
+            let body: Release = parse_body(request)?;
+            leases.check_in(&format!("sessions/{}", id), body.lock);
+            Ok((200, json!({})))
+        }
+        ("GET", ["issues"]) => {
+            let documents: Vec<String> = store.list_issues()?.iter().map(render_issue).collect();
+            Ok((200, json!(documents)))
+        }
+        ("GET", ["issues", id]) => {
+            validate_id(id)?;
+            if !store.issue_exists(id) {
+                return Ok(error(404, &format!("Issue '{}' not found", id)));
+            }
+            let document = render_issue(&store.load_issue(id)?);
+            Ok((200, json!({ "document": document })))
+        }
+        ("PUT", ["issues", id]) => {
+            validate_id(id)?;
+            let body: Document = parse_body(request)?;
+            let issue = parse_issue(&body.document)?;
+            if issue.id != *id {
+                bail!("Document is for issue '{}', not '{}'", issue.id, id);
+            }
+            store.save_issue(&issue)?;
+            Ok((200, json!({})))
+        }
+        ("POST", ["issues", id, "archive"]) => {
+            validate_id(id)?;
+            store.archive_issue(id)?;
+            Ok((200, json!({})))
+        }
+        ("GET", ["archived-issues"]) => {
+            let documents: Vec<String> = store
+                .list_archived_issues()?
+                .iter()
+                .map(render_issue)
+                .collect();
+            Ok((200, json!(documents)))
+        }
+        ("PUT", ["archived-issues", id]) => {
+            validate_id(id)?;
+            let body: Document = parse_body(request)?;
+            let issue = parse_issue(&body.document)?;
+            if issue.id != *id {
+                bail!("Document is for issue '{}', not '{}'", issue.id, id);
+            }
+            store.save_archived_issue(&issue)?;
+            Ok((200, json!({})))
+        }
+        ("GET", ["events"]) => Ok((200, json!(store.list_events()?))),
+        ("POST", ["events"]) => {
+            let event: Event =
+                serde_json::from_slice(&request.body).context("Invalid JSON body")?;
+            store.append_event(&event)?;
+            Ok((201, json!({})))
+        }
+        ("GET", ["claims"]) => Ok((200, json!(leases.claims.keys().collect::<Vec<_>>()))),
+        ("GET", ["claims", task]) => match leases.claims.get(*task) {
+            Some(claim) => Ok((200, json!(claim))),
+            None => Ok(error(404, &format!("No claim on '{}'", task))),
+        },
+        ("POST", ["claims", task]) => {
+            validate_task_name(task)?;
+            let claimant: Claimant = parse_body(request)?;
+            let stolen_from = match leases.claims.get(*task) {
+                Some(claim) if !claim_expired(claim) => {
+                    return Ok((409, json!({ "error": "claimed", "claim": claim })));
+                }
+                other => other.cloned(),
+            };
+            let claim = ClaimState {
+                task: task.to_string(),
+                agent: ctx.agent.name().to_string(),
+                pid: claimant.pid,
+                host: claimant.host,
+                started_at: now_iso(),
+                ttl_seconds: claimant.ttl_seconds,
+                heartbeat_at: None,
+            };
+            leases.claims.insert(task.to_string(), claim.clone());
+            Ok((201, json!({ "claim": claim, "stolen_from": stolen_from })))
+        }
+        ("POST", ["claims", task, "heartbeat"]) => {
+            let holder: Holder =
+                serde_json::from_slice(&request.body).context("Invalid JSON body")?;
+            match leases.claims.get_mut(*task) {
+                Some(claim) if claim.host == holder.host && claim.pid == holder.pid => {
+                    claim.heartbeat_at = Some(now_iso());
+                    Ok((200, json!(claim)))
+                }
+                Some(claim) => Ok((409, json!({ "error": "claimed", "claim": claim }))),
+                None => Ok(error(404, &format!("No claim on '{}'", task))),
+            }
+        }
+        ("DELETE", ["claims", task]) => {
+            let holder: Option<Holder> = if request.body.iter().all(u8::is_ascii_whitespace) {
+                None
+            } else {
+                Some(serde_json::from_slice(&request.body).context("Invalid JSON body")?)
+            };
+            let removable = match (leases.claims.get(*task), holder) {
+                (None, _) => true,
+                (Some(claim), Some(holder)) => claim.host == holder.host && claim.pid == holder.pid,
+                (Some(claim), None) => claim_expired(claim),
+            };
+            if removable {
+                leases.claims.remove(*task);
+            }
+            Ok((200, json!({ "removed": removable })))
+        }
+        _ => Ok(error(404, "no such endpoint")),
+    }
+}
+
+impl Leases {
+    fn check_out(&mut self, key: &str, record: Value) -> Reply {
+        if self.checked_out(key) {
+            return error(423, &format!("{} is being updated elsewhere", key));
+        }
+        self.next_lock += 1;
+        let lock = format!("{:x}-{:x}", std::process::id(), self.next_lock);
+        self.checkouts
+            .insert(key.to_string(), (lock.clone(), Instant::now()));
+        (200, json!({ "record": record, "lock": lock }))
+    }
+
+    /// Returns the checkout on `key` if `lock` holds it, or the refusal to send when
+    /// someone else does.
+    fn check_in(&mut self, key: &str, lock: Option<String>) -> Option<Reply> {
+        if !self.checked_out(key) {
+            self.checkouts.remove(key);
+            return None;
+        }
+        if self.checkouts.get(key).map(|(held, _)| held) == lock.as_ref() {
+            self.checkouts.remove(key);
+            return None;
+        }
+        Some(error(423, &format!("{} is being updated elsewhere", key)))
+    }
+
+    fn checked_out(&self, key: &str) -> bool {
+        self.checkouts
+            .get(key)
+            .is_some_and(|(_, at)| at.elapsed() < Duration::from_secs(CHECKOUT_SECONDS))
+    }
+}
+
+/// Session and issue ids end up in paths; keep them to one plain path segment.
+fn validate_id(id: &str) -> Result<()> {
+    if id.is_empty()
+        || id.starts_with('.')
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    {
+        bail!("Invalid id '{}'", id);
+    }
+    Ok(())
+}
+
+fn with_task(
+    ctx: &CommandContext,
+    task: &str,
+    handle: impl FnOnce(&TaskState) -> Result<Value>,
+) -> Result<Reply> {
+    validate_task_name(task)?;
+    if !ctx.store.task_exists(task) {
+        return Ok(error(404, &format!("Task '{}' not found", task)));
+    }
+    let task_state = ctx.store.load_task(task)?;
+    Ok((200, handle(&task_state)?))
+}
+
+fn parse_body<T: for<'de> Deserialize<'de> + Default>(request: &Request) -> Result<T> {
+    if request.body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(T::default());
+    }
+    serde_json::from_slice(&request.body).context("Invalid JSON body")
+}
+
+fn authorized(request: &Request, token: Option<&str>) -> bool {
+    match token {
+        None => true,
+        Some(token) => request.token.as_deref() == Some(token),
+    }
+}
+
+fn error(status: u16, message: &str) -> Reply {
+    (status, json!({ "error": message }))
+}
+
+fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        bail!("Malformed request line");
+    };
+    let path = target.split('?').next().unwrap_or(target).to_string();
+    let method = method.to_string();
+
+    let mut length = 0usize;
+    let mut token = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            break;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => {
+                length = value.parse().context("Invalid Content-Length")?;
+            }
+            "authorization" => {
+                token = value.strip_prefix("Bearer ").map(str::to_string);
+            }
+            _ => {}
+        }
+    }
+    if length > MAX_BODY_BYTES {
+        bail!("Request body is larger than {} bytes", MAX_BODY_BYTES);
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        path,
+        token,
+        body,
+    })
+}
+
+fn respond(stream: &mut TcpStream, status: u16, body: &Value) -> Result<()> {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        423 => "Locked",
+        _ => "Error",
+    };
+    let body = serde_json::to_string_pretty(body)?;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
@@ -0,0 +1,327 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::issues::Issue;
+use crate::state::TaskState;
+use crate::util::task_dir;
+
+/// Cosine similarity at or above which two tasks are reported as likely duplicates.
+pub const DUPLICATE_THRESHOLD: f64 = 0.4;
+
+/// Issues are short, so they need a closer match than tasks before being flagged.
+pub const ISSUE_DUPLICATE_THRESHOLD: f64 = 0.5;
+
+/// Added to the score of two issues that point at the same file.
+const SAME_FILE_BONUS: f64 = 0.2;
+
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "in", "into", "is", "it", "of",
+    "on", "or", "so", "that", "the", "this", "to", "when", "with", "should", "must", "we",
+    "overview", "types", "modules", "errors",
+];
+
+/// Text compared against other documents, keyed by a task name or issue id.
+pub struct Document {
+    pub id: String,
+    text: String,
+}
+
+impl Document {
+    pub fn new(id: &str, text: String) -> Self {
+        Self {
+            id: id.to_string(),
+            text,
+        }
+    }
+
+    /// A task's name, description, custom prompt, and spec files.
+    pub fn from_task(agent_root: &Path, task: &TaskState) -> Self {
+        let mut text = task.task.replace(['-', '_'], " ");
+        for part in [task.description.as_deref(), task.prompt.as_deref()]
+            .into_iter()
+            .flatten()
+        {
+            text.push('\n');
+            text.push_str(part);
+        }
+        let spec_dir = task_dir(agent_root, &task.task).join("spec");
+        if let Ok(entries) = fs::read_dir(spec_dir) {
+            let mut paths: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+            paths.sort();
+            for path in paths {
+                if path.extension().and_then(|ext| ext.to_str()) != Some("md") {
+                    continue;
+                }
+                if let Ok(content) = fs::read_to_string(&path) {
+                    text.push('\n');
+                    text.push_str(&content);
+                }
+            }
+        }
+        Self::new(&task.task, text)
+    }
+
+    /// An issue's title, counted twice since it names the problem, and its body.
+    /// Headings and unfilled `{placeholder}` lines are left out, so issues don't
+    /// look alike just for sharing an issue template.
+    pub fn from_issue(issue: &Issue) -> Self {
+        let mut text = format!("{}\n{}", issue.title, issue.title);
+        for line in issue.body.as_deref().unwrap_or_default().lines() {
+            let line = line.trim().trim_start_matches("- ");
+            if line.starts_with('#') || (line.starts_with('{') && line.ends_with('}')) {
+                continue;
+            }
+            text.push('\n');
+            text.push_str(line);
+        }
+        Self::new(&issue.id, text)
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.len() > 2)
+        .map(|word| word.to_lowercase())
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .collect()
+}
+
+type Vector = HashMap<String, f64>;
+
+fn tf_idf_vectors(documents: &[&str]) -> Vec<Vector> {
+    let token_lists: Vec<Vec<String>> = documents.iter().map(|doc| tokenize(doc)).collect();
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for tokens in &token_lists {
+        let unique: HashSet<&str> = tokens.iter().map(String::as_str).collect();
+        for token in unique {
+            *document_frequency.entry(token).or_default() += 1;
+        }
+    }
+    let total = documents.len() as f64;
+    token_lists
+        .iter()
+        .map(|tokens| {
+            let mut counts: HashMap<&str, f64> = HashMap::new();
+            for token in tokens {
+                *counts.entry(token.as_str()).or_default() += 1.0;
+            }
+            counts
+                .into_iter()
+                .map(|(token, count)| {
+                    // Smoothed IDF keeps terms shared by every document from vanishing
+                    // when only two tasks are compared.
+                    let idf = ((1.0 + total) / (1.0 + document_frequency[token] as f64)).ln() + 1.0;
+                    (token.to_string(), count * idf)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn cosine(a: &Vector, b: &Vector) -> f64 {
+    let dot: f64 = a
+        .iter()
+        .filter_map(|(token, weight)| b.get(token).map(|other| weight * other))
+        .sum();
+    let norm_a = a.values().map(|w| w * w).sum::<f64>().sqrt();
+    let norm_b = b.values().map(|w| w * w).sum::<f64>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Documents in `others` that look like duplicates of `candidate`, most similar first.
+pub fn similar_documents(
+    candidate: &Document,
+    others: &[Document],
+    threshold: f64,
+) -> Vec<(String, f64)> {
+    let mut texts: Vec<&str> = vec![candidate.text.as_str()];
+    texts.extend(others.iter().map(|doc| doc.text.as_str()));
+    let vectors = tf_idf_vectors(&texts);
+    let mut matches: Vec<(String, f64)> = others
+        .iter()
+        .zip(vectors.iter().skip(1))
+        .filter(|(doc, _)| doc.id != candidate.id)
+        .map(|(doc, vector)| (doc.id.clone(), cosine(&vectors[0], vector)))
+        .filter(|(_, score)| *score >= threshold)
+        .collect();
+    matches.sort_by(|a, b| b.1.total_cmp(&a.1));
+    matches
+}
+
+/// Every pair of documents at or above `threshold`, most similar first.
+pub fn duplicate_pairs(documents: &[Document], threshold: f64) -> Vec<(String, String, f64)> {
+    let texts: Vec<&str> = documents.iter().map(|doc| doc.text.as_str()).collect();
+    let vectors = tf_idf_vectors(&texts);
+    let mut pairs = Vec::new();
+    for i in 0..documents.len() {
+        for j in (i + 1)..documents.len() {
+            let score = cosine(&vectors[i], &vectors[j]);
+            if score >= threshold {
+                pairs.push((documents[i].id.clone(), documents[j].id.clone(), score));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.2.total_cmp(&a.2));
+    pairs
+}
+
+fn same_file(a: &Issue, b: &Issue) -> bool {
+    let normalize = |file: &str| file.trim().trim_start_matches("./").to_string();
+    match (a.file.as_deref(), b.file.as_deref()) {
+        (Some(a), Some(b)) => normalize(a) == normalize(b),
+        _ => false,
+    }
+}
+
+fn issue_score(a: &Issue, b: &Issue, vector_a: &Vector, vector_b: &Vector) -> f64 {
+    let bonus = if same_file(a, b) {
+        SAME_FILE_BONUS
+    } else {
+        0.0
+    };
+    (cosine(vector_a, vector_b) + bonus).min(1.0)
+}
+
+/// Issues in `others` on the same task as `candidate` whose title and body match
+/// it, with a boost for pointing at the same file. Most similar first.
+pub fn similar_issues(candidate: &Issue, others: &[Issue], threshold: f64) -> Vec<(String, f64)> {
+    let others: Vec<&Issue> = others
+        .iter()
+        .filter(|other| other.id != candidate.id && other.task == candidate.task)
+        .collect();
+    let documents: Vec<Document> = std::iter::once(candidate)
+        .chain(others.iter().copied())
+        .map(Document::from_issue)
+        .collect();
+    let texts: Vec<&str> = documents.iter().map(|doc| doc.text.as_str()).collect();
+    let vectors = tf_idf_vectors(&texts);
+    let mut matches: Vec<(String, f64)> = others
+        .iter()
+        .zip(vectors.iter().skip(1))
+        .map(|(other, vector)| {
+            let score = issue_score(candidate, other, &vectors[0], vector);
+            (other.id.clone(), score)
+        })
+        .filter(|(_, score)| *score >= threshold)
+        .collect();
+    matches.sort_by(|a, b| b.1.total_cmp(&a.1));
+    matches
+}
+
+/// Every pair of issues on the same task at or above `threshold`, most similar first.
+pub fn duplicate_issue_pairs(issues: &[Issue], threshold: f64) -> Vec<(String, String, f64)> {
+    let documents: Vec<Document> = issues.iter().map(Document::from_issue).collect();
+    let texts: Vec<&str> = documents.iter().map(|doc| doc.text.as_str()).collect();
+    let vectors = tf_idf_vectors(&texts);
+    let mut pairs = Vec::new();
+    for i in 0..issues.len() {
+        for j in (i + 1)..issues.len() {
+            if issues[i].task != issues[j].task {
+                continue;
+            }
+            let score = issue_score(&issues[i], &issues[j], &vectors[i], &vectors[j]);
+            if score >= threshold {
+                pairs.push((issues[i].id.clone(), issues[j].id.clone(), score));
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.2.total_cmp(&a.2));
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        duplicate_issue_pairs, duplicate_pairs, similar_documents, similar_issues, Document,
+        DUPLICATE_THRESHOLD, ISSUE_DUPLICATE_THRESHOLD,
+    };
+    use crate::issues::{new_issue, IssuePriority, IssueSource, IssueStatus, IssueType};
+
+    fn doc(id: &str, text: &str) -> Document {
+        Document::new(id, text.to_string())
+    }
+
+    #[test]
+    fn overlapping_descriptions_are_flagged() {
+        let others = vec![
+            doc(
+                "login-retry",
+                "Retry failed OAuth login requests with backoff",
+            ),
+            doc("csv-export", "Export the billing report as CSV"),
+            doc("dark-mode", "Add a dark theme toggle to settings"),
+        ];
+        let candidate = doc(
+            "oauth-backoff",
+            "Add backoff when OAuth login requests fail",
+        );
+        let matches = similar_documents(&candidate, &others, DUPLICATE_THRESHOLD);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].0, "login-retry");
+
+        let unrelated = doc("font-size", "Make the editor font size configurable");
+        assert!(similar_documents(&unrelated, &others, DUPLICATE_THRESHOLD).is_empty());
+    }
+
+    #[test]
+    fn duplicate_pairs_skip_unrelated_tasks() {
+        let docs = vec![
+            doc("a", "Cache parsed config files between runs"),
+            doc("b", "Cache the parsed config between runs"),
+            doc("c", "Render issue lists as a table"),
+        ];
+        let pairs = duplicate_pairs(&docs, DUPLICATE_THRESHOLD);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!((pairs[0].0.as_str(), pairs[0].1.as_str()), ("a", "b"));
+    }
+
+    fn issue(title: &str, task: &str, file: Option<&str>) -> crate::issues::Issue {
+        new_issue(
+            title.to_string(),
+            IssueStatus::Open,
+            IssuePriority::P2,
+            Some(task.to_string()),
+            IssueType::Build,
+            IssueSource::Review,
+            file.map(str::to_string),
+            None,
+        )
+    }
+
+    #[test]
+    fn issues_match_on_text_and_shared_files() {
+        let existing = vec![
+            issue("Token refresh races on concurrent requests", "auth", None),
+            issue("Missing index on sessions table", "auth", Some("src/db.rs")),
+            issue(
+                "Token refresh races on concurrent requests",
+                "billing",
+                None,
+            ),
+        ];
+        let again = issue("Concurrent requests race the token refresh", "auth", None);
+        let matches = similar_issues(&again, &existing, ISSUE_DUPLICATE_THRESHOLD);
+        assert_eq!(matches.len(), 1, "{matches:?}");
+        assert_eq!(matches[0].0, existing[0].id);
+
+        let loose = issue("Sessions query is slow", "auth", Some("./src/db.rs"));
+        let without_file = issue("Sessions query is slow", "auth", None);
+        let with_file_score = similar_issues(&loose, &existing, 0.0)
+            .into_iter()
+            .find(|(id, _)| id == &existing[1].id)
+            .expect("scored");
+        let text_score = similar_issues(&without_file, &existing, 0.0)
+            .into_iter()
+            .find(|(id, _)| id == &existing[1].id)
+            .expect("scored");
+        assert!(with_file_score.1 > text_score.1);
+
+        let pairs = duplicate_issue_pairs(&existing, ISSUE_DUPLICATE_THRESHOLD);
+        assert!(pairs.is_empty(), "different tasks never pair: {pairs:?}");
+    }
+}
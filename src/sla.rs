@@ -0,0 +1,114 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use crate::commands::CommandContext;
+use crate::config::SlaRule;
+use crate::issues::{Issue, IssuePriority, IssueStatus};
+use crate::state::is_read_only;
+use crate::util::now_iso;
+
+/// Open issue that has outlived its priority's SLA.
+pub struct SlaBreach {
+    pub issue: Issue,
+    pub age_hours: i64,
+    pub max_age_hours: u64,
+    pub notify: Option<String>,
+}
+
+fn issue_age_hours(issue: &Issue, now: DateTime<Utc>) -> Option<i64> {
+    let created = DateTime::parse_from_rfc3339(&issue.created_at).ok()?;
+    Some(now.signed_duration_since(created).num_hours())
+}
+
+pub fn find_breaches(rules: &[SlaRule], issues: &[Issue], now: DateTime<Utc>) -> Vec<SlaBreach> {
+    let mut breaches = Vec::new();
+    for issue in issues {
+        if issue.status != IssueStatus::Open {
+            continue;
+        }
+        let Some(rule) = rules.iter().find(|rule| {
+            IssuePriority::from_str(&rule.priority).ok().as_ref() == Some(&issue.priority)
+        }) else {
+            continue;
+        };
+        let Some(age_hours) = issue_age_hours(issue, now) else {
+            continue;
+        };
+        if age_hours >= 0 && age_hours as u64 > rule.max_age_hours {
+            breaches.push(SlaBreach {
+                issue: issue.clone(),
+                age_hours,
+                max_age_hours: rule.max_age_hours,
+                notify: rule.notify.clone(),
+            });
+        }
+    }
+    breaches
+}
+
+/// Finds SLA breaches and, for issues not yet escalated, stamps `escalated_at` and
+/// runs the rule's notify command. Read-only mode reports without escalating.
+pub fn check_issue_slas(ctx: &CommandContext) -> Result<Vec<SlaBreach>> {
+    if ctx.config.sla.is_empty() {
+        return Ok(Vec::new());
+    }
+    let issues = ctx.store.list_issues()?;
+    let breaches = find_breaches(&ctx.config.sla, &issues, Utc::now());
+    if is_read_only() {
+        return Ok(breaches);
+    }
+    for breach in &breaches {
+        if breach.issue.escalated_at.is_some() {
+            continue;
+        }
+        let mut issue = breach.issue.clone();
+        issue.escalated_at = Some(now_iso());
+        ctx.store.save_issue(&issue)?;
+        eprintln!(
+            "SLA breach: {} issue {} open {}h (limit {}h){}",
+            issue.priority,
+            issue.id,
+            breach.age_hours,
+            breach.max_age_hours,
+            issue
+                .task
+                .as_ref()
+                .map(|task| format!("; prioritizing '{task}'"))
+                .unwrap_or_default()
+        );
+        if let Some(command) = &breach.notify {
+            notify(ctx, command, breach);
+        }
+    }
+    Ok(breaches)
+}
+
+/// Tasks with an open, SLA-breaching issue; these run ahead of the normal queue order.
+pub fn breached_tasks(breaches: &[SlaBreach]) -> HashSet<String> {
+    breaches
+        .iter()
+        .filter_map(|breach| breach.issue.task.clone())
+        .collect()
+}
+
+fn notify(ctx: &CommandContext, command: &str, breach: &SlaBreach) {
+    let result = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(&ctx.repo_root)
+        .env("MUNG_SLA_ISSUE", &breach.issue.id)
+        .env("MUNG_SLA_TITLE", &breach.issue.title)
+        .env("MUNG_SLA_PRIORITY", breach.issue.priority.to_string())
+        .env("MUNG_SLA_TASK", breach.issue.task.as_deref().unwrap_or(""))
+        .env("MUNG_SLA_AGE_HOURS", breach.age_hours.to_string())
+        .stdin(Stdio::null())
+        .status();
+    match result {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("Warning: SLA notify command exited with {}", status),
+        Err(err) => eprintln!("Warning: failed to run SLA notify command: {}", err),
+    }
+}
@@ -0,0 +1,265 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::issues::Issue;
+use crate::util::copy_dir_all;
+
+/// Where finished stages leave copies of a task's `spec/`, as `<seq>-<stage>/`.
+pub const SPEC_HISTORY_DIR: &str = "spec-history";
+
+/// Content hash of every file under a task's `spec/` directory, keyed by path
+/// relative to it. Recorded when a spec review finishes.
+pub fn spec_hashes(spec_dir: &Path) -> BTreeMap<String, String> {
+    let mut hashes = BTreeMap::new();
+    collect_hashes(spec_dir, "", &mut hashes);
+    hashes
+}
+
+fn collect_hashes(dir: &Path, prefix: &str, hashes: &mut BTreeMap<String, String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let relative = format!("{prefix}{name}");
+        let path = entry.path();
+        if path.is_dir() {
+            collect_hashes(&path, &format!("{relative}/"), hashes);
+        } else if let Ok(content) = fs::read(&path) {
+            hashes.insert(relative, format!("{:016x}", fnv1a(&content)));
+        }
+    }
+}
+
+/// How much of one spec file has been written beyond the task template.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SpecFileProgress {
+    pub file: String,
+    pub filled: bool,
+    /// Headings below the title whose body is still empty or a placeholder.
+    pub empty_sections: Vec<String>,
+}
+
+/// Progress of every markdown file under `spec_dir`, sorted by path.
+pub fn spec_progress(spec_dir: &Path) -> Vec<SpecFileProgress> {
+    spec_hashes(spec_dir)
+        .into_keys()
+        .filter(|file| file.ends_with(".md"))
+        .filter_map(|file| {
+            let content = fs::read_to_string(spec_dir.join(&file)).ok()?;
+            Some(file_progress(file, &content))
+        })
+        .collect()
+}
+
+fn is_placeholder(line: &str) -> bool {
+    let line = line.trim().trim_start_matches(['-', '*']).trim();
+    let lower = line.to_lowercase();
+    line.is_empty()
+        || line.starts_with("<!--")
+        || lower == "todo"
+        || lower == "tbd"
+        || lower.starts_with("todo:")
+        || lower.starts_with("tbd:")
+        || (line.starts_with('(') && line.ends_with(')'))
+}
+
+fn file_progress(file: String, content: &str) -> SpecFileProgress {
+    let mut filled = false;
+    let mut empty_sections = Vec::new();
+    let mut section: Option<(String, bool)> = None;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("##") {
+            if let Some((heading, false)) = section.take() {
+                empty_sections.push(heading);
+            }
+            section = Some((trimmed.trim_start_matches('#').trim().to_string(), false));
+        } else if !trimmed.starts_with('#') && !is_placeholder(trimmed) {
+            filled = true;
+            if let Some((_, has_body)) = section.as_mut() {
+                *has_body = true;
+            }
+        }
+    }
+    if let Some((heading, false)) = section {
+        empty_sections.push(heading);
+    }
+    SpecFileProgress {
+        file,
+        filled,
+        empty_sections,
+    }
+}
+
+/// FNV-1a keeps the recorded hashes stable across toolchains without a digest crate.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// `(status, file)` for spec files added (`A`), modified (`M`), or deleted (`D`)
+/// since `reviewed` was recorded.
+pub fn changed_spec_files(
+    reviewed: &BTreeMap<String, String>,
+    current: &BTreeMap<String, String>,
+) -> Vec<(&'static str, String)> {
+    let names: BTreeSet<&String> = reviewed.keys().chain(current.keys()).collect();
+    names
+        .into_iter()
+        .filter_map(|name| match (reviewed.get(name), current.get(name)) {
+            (None, Some(_)) => Some(("A", name.clone())),
+            (Some(_), None) => Some(("D", name.clone())),
+            (Some(old), Some(new)) if old != new => Some(("M", name.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The spec file an issue points at, if its `file` names one of `files`.
+pub fn issue_spec_file<'a>(issue: &Issue, files: &'a [String]) -> Option<&'a String> {
+    let target = issue.file.as_deref()?.trim().trim_start_matches("./");
+    files.iter().find(|file| {
+        target == file.as_str()
+            || target.ends_with(&format!("spec/{file}"))
+            || target.ends_with(&format!("/{file}"))
+    })
+}
+
+/// A copy of a task's spec taken when `stage` finished.
+pub struct SpecSnapshot {
+    pub name: String,
+    pub stage: String,
+    pub path: PathBuf,
+}
+
+/// A task's spec snapshots, oldest first.
+pub fn spec_snapshots(task_dir: &Path) -> Vec<SpecSnapshot> {
+    let Ok(entries) = fs::read_dir(task_dir.join(SPEC_HISTORY_DIR)) else {
+        return Vec::new();
+    };
+    let mut snapshots: Vec<(u32, SpecSnapshot)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let (seq, stage) = name.split_once('-')?;
+            let seq = seq.parse().ok()?;
+            let snapshot = SpecSnapshot {
+                stage: stage.to_string(),
+                path: entry.path(),
+                name,
+            };
+            Some((seq, snapshot))
+        })
+        .collect();
+    snapshots.sort_by_key(|(seq, _)| *seq);
+    snapshots
+        .into_iter()
+        .map(|(_, snapshot)| snapshot)
+        .collect()
+}
+
+/// Copies the task's `spec/` into a new snapshot for `stage`, unless there is no
+/// spec or it matches the latest snapshot.
+pub fn snapshot_spec(task_dir: &Path, stage: &str) -> Result<Option<SpecSnapshot>> {
+    let spec_dir = task_dir.join("spec");
+    if !spec_dir.is_dir() {
+        return Ok(None);
+    }
+    let snapshots = spec_snapshots(task_dir);
+    let current = spec_hashes(&spec_dir);
+    if snapshots
+        .last()
+        .is_some_and(|latest| spec_hashes(&latest.path) == current)
+    {
+        return Ok(None);
+    }
+    let name = format!("{:03}-{}", snapshots.len() + 1, stage);
+    let path = task_dir.join(SPEC_HISTORY_DIR).join(&name);
+    copy_dir_all(&spec_dir, &path)?;
+    Ok(Some(SpecSnapshot {
+        name,
+        stage: stage.to_string(),
+        path,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_spec_files_reports_added_modified_and_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("api")).unwrap();
+        fs::write(dir.path().join("overview.md"), "one").unwrap();
+        fs::write(dir.path().join("api/routes.md"), "routes").unwrap();
+        fs::write(dir.path().join("storage.md"), "disk").unwrap();
+        let reviewed = spec_hashes(dir.path());
+        assert_eq!(
+            reviewed.keys().collect::<Vec<_>>(),
+            ["api/routes.md", "overview.md", "storage.md"]
+        );
+
+        fs::write(dir.path().join("overview.md"), "two").unwrap();
+        fs::remove_file(dir.path().join("storage.md")).unwrap();
+        fs::write(dir.path().join("errors.md"), "new").unwrap();
+        let current = spec_hashes(dir.path());
+        assert_eq!(
+            changed_spec_files(&reviewed, &current),
+            vec![
+                ("A", "errors.md".to_string()),
+                ("M", "overview.md".to_string()),
+                ("D", "storage.md".to_string()),
+            ]
+        );
+        assert!(changed_spec_files(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn spec_progress_separates_template_files_and_empty_sections() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("types.md"), "# Types\n\n").unwrap();
+        fs::write(
+            dir.path().join("overview.md"),
+            "# Overview\n\n## Goals\nShip it.\n\n## Non-goals\nTBD\n\n## Risks\n",
+        )
+        .unwrap();
+        assert_eq!(
+            spec_progress(dir.path()),
+            vec![
+                SpecFileProgress {
+                    file: "overview.md".to_string(),
+                    filled: true,
+                    empty_sections: vec!["Non-goals".to_string(), "Risks".to_string()],
+                },
+                SpecFileProgress {
+                    file: "types.md".to_string(),
+                    filled: false,
+                    empty_sections: Vec::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn snapshots_skip_unchanged_specs() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(snapshot_spec(dir.path(), "spec").unwrap().is_none());
+        fs::create_dir_all(dir.path().join("spec")).unwrap();
+        fs::write(dir.path().join("spec/overview.md"), "v1").unwrap();
+        let first = snapshot_spec(dir.path(), "spec").unwrap().unwrap();
+        assert_eq!(first.name, "001-spec");
+        assert!(snapshot_spec(dir.path(), "spec-review").unwrap().is_none());
+        fs::write(dir.path().join("spec/overview.md"), "v2").unwrap();
+        snapshot_spec(dir.path(), "spec-review").unwrap().unwrap();
+        let stages: Vec<String> = spec_snapshots(dir.path())
+            .into_iter()
+            .map(|snapshot| snapshot.stage)
+            .collect();
+        assert_eq!(stages, ["spec", "spec-review"]);
+    }
+}
@@ -2,12 +2,53 @@ use anyhow::{bail, Context, Result};
 use fs2::FileExt;
 use owo_colors::OwoColorize;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::{self, OpenOptions};
-use std::io::Write;
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::util::{claim_path, env_var, now_iso, session_state_path, task_state_path};
+use crate::events::{append_event, Actor, Event};
+use crate::process::pid_alive;
+use crate::store::StateStore;
+use crate::util::{claim_path, env_var, now_iso, session_dir};
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Observer mode: no locks, claims, or state writes are taken by this process.
+pub fn set_read_only(enabled: bool) {
+    READ_ONLY.store(enabled, Ordering::SeqCst);
+}
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::SeqCst)
+}
+
+/// The error `ensure_writable` fails with, so callers such as `mung serve` can
+/// tell a refused write from other failures.
+#[derive(Debug)]
+pub struct ReadOnlyError;
+
+impl std::fmt::Display for ReadOnlyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Read-only mode: refusing to modify workflow state")
+    }
+}
+
+impl std::error::Error for ReadOnlyError {}
+
+pub fn ensure_writable() -> Result<()> {
+    if is_read_only() {
+        return Err(ReadOnlyError.into());
+    }
+    Ok(())
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -18,6 +59,7 @@ pub enum TaskStatus {
     Failed,
     Completed,
     Issues,
+    Blocked,
 }
 
 impl TaskStatus {
@@ -29,18 +71,7 @@ impl TaskStatus {
             Self::Failed => "✗",
             Self::Completed => "✓",
             Self::Issues => "!",
-        }
-    }
-
-    pub fn from_str(value: &str) -> Result<Self> {
-        match value.trim().to_lowercase().as_str() {
-            "pending" => Ok(Self::Pending),
-            "running" => Ok(Self::Running),
-            "incomplete" => Ok(Self::Incomplete),
-            "failed" => Ok(Self::Failed),
-            "completed" => Ok(Self::Completed),
-            "issues" => Ok(Self::Issues),
-            other => bail!("Invalid task status: {}", other),
+            Self::Blocked => "⊘",
         }
     }
 
@@ -53,6 +84,24 @@ impl TaskStatus {
             Self::Failed => symbol.red().bold().to_string(),
             Self::Completed => symbol.green().to_string(),
             Self::Issues => symbol.magenta().bold().to_string(),
+            Self::Blocked => symbol.red().to_string(),
+        }
+    }
+}
+
+impl FromStr for TaskStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "pending" => Ok(Self::Pending),
+            "running" => Ok(Self::Running),
+            "incomplete" => Ok(Self::Incomplete),
+            "failed" => Ok(Self::Failed),
+            "completed" => Ok(Self::Completed),
+            "issues" => Ok(Self::Issues),
+            "blocked" => Ok(Self::Blocked),
+            other => bail!("Invalid task status: {}", other),
         }
     }
 }
@@ -66,6 +115,7 @@ impl std::fmt::Display for TaskStatus {
             Self::Failed => "failed",
             Self::Completed => "completed",
             Self::Issues => "issues",
+            Self::Blocked => "blocked",
         };
         write!(f, "{value}")
     }
@@ -81,14 +131,119 @@ pub struct TaskState {
     pub queue_rank: Option<i64>,
     #[serde(default)]
     pub held: bool,
+    /// When a held task goes back into the queue on its own (`hold --until/--for`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub held_until: Option<String>,
+    /// Why the task was held (`hold --reason`), shown in the queue's backlog.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub held_reason: Option<String>,
     #[serde(default)]
     pub description: Option<String>,
     #[serde(default)]
     pub prompt: Option<String>,
+    /// Tasks that must reach `completed` before this one is picked from the queue.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Who the task is for; `[queue] fairness = "owner"` takes turns between owners.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    /// Area or team tags; `queue --tag` and `run-queue --tag` only see matching tasks.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Date (`YYYY-MM-DD`) the task should be completed by; the queue flags it after.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<String>,
+    /// Effort: `S`, `M`, `L`, or hours such as `6h`; the queue totals it per stage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<String>,
+    /// `P0`-`P3`; the queue runs higher priorities first. Unset counts as `P2`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
     pub added_at: String,
     pub updated_at: String,
+    /// Most recently started session; older ones are linked by `previous_session`.
     pub last_session: Option<String>,
+    /// Sessions linked into the `last_session` chain.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub session_count: u32,
+    /// When `last_session` started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run_at: Option<String>,
     pub last_error: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub escalated_stages: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_commit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reviewed_commit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub awaiting_approval: Option<PendingApproval>,
+    /// Why the task can't proceed; set together with `TaskStatus::Blocked`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_reason: Option<String>,
+    /// Spec file hashes recorded when the last spec review finished.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub spec_hashes: BTreeMap<String, String>,
+    /// Note about a run that died mid-flight, shown once in the next session's prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handover: Option<String>,
+    /// Issues `mung issue stale --ping` flagged; listed once in the next review prompt.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pinged_issues: Vec<String>,
+    /// Tracker item the task was synced from (a Linear issue URL).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// The latest stage, status, and session changes (up to `TRANSITION_LIMIT`),
+    /// oldest first. Kept in the task so they outlive pruned sessions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transitions: Vec<StageTransition>,
+}
+
+/// Entries kept in `TaskState::transitions`; older ones are dropped. The event
+/// log has the full record.
+pub const TRANSITION_LIMIT: usize = 200;
+
+/// One entry of `TaskState::transitions`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StageTransition {
+    pub stage: String,
+    pub status: TaskStatus,
+    /// The task's `last_session` at the time; a new value marks a new run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    pub at: String,
+}
+
+impl TaskState {
+    /// Appends a transition if the stage, status, or session differs from the
+    /// latest one, dropping the oldest past `TRANSITION_LIMIT`.
+    pub fn record_history(&mut self) {
+        let changed = self.transitions.last().is_none_or(|last| {
+            last.stage != self.stage
+                || last.status != self.status
+                || last.session != self.last_session
+        });
+        if changed {
+            self.transitions.push(StageTransition {
+                stage: self.stage.clone(),
+                status: self.status.clone(),
+                session: self.last_session.clone(),
+                at: now_iso(),
+            });
+        }
+        let excess = self.transitions.len().saturating_sub(TRANSITION_LIMIT);
+        self.transitions.drain(..excess);
+    }
+}
+
+/// Stage transition held back until a human runs `mung approve`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingApproval {
+    pub stage: String,
+    pub status: TaskStatus,
+    pub requested_at: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
@@ -97,6 +252,20 @@ pub enum SessionStatus {
     Running,
     Finished,
     Failed,
+    /// Recorded by `mung skip`: the stage was passed over without running a model.
+    Skipped,
+}
+
+impl std::fmt::Display for SessionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = match self {
+            Self::Running => "running",
+            Self::Finished => "finished",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        };
+        write!(f, "{value}")
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -112,6 +281,15 @@ pub struct SessionState {
     pub pid: u32,
     pub host: String,
     pub repo_root: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// The task's session before this one, so its sessions can be walked from
+    /// `TaskState::last_session` without scanning every session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_session: Option<String>,
+    /// The model's own conversation id, which `mung resume` continues.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_session: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -122,25 +300,56 @@ pub struct ClaimState {
     pub host: String,
     pub started_at: String,
     pub ttl_seconds: u64,
+    /// Last refresh from the holder's running stage; staleness counts from here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat_at: Option<String>,
 }
 
 pub struct ClaimGuard {
-    path: PathBuf,
-    file: std::fs::File,
+    hold: ClaimHold,
+    /// The claim left behind by a holder that died without releasing it.
+    pub stolen_from: Option<ClaimState>,
+}
+
+enum ClaimHold {
+    File {
+        path: PathBuf,
+        file: std::fs::File,
+    },
+    /// Granted by a backend that keeps claims elsewhere; released by calling back.
+    Lease(Option<Box<dyn FnOnce() + Send>>),
 }
 
 impl ClaimGuard {
+    /// A claim held somewhere other than a local lock file; `release` runs on drop.
+    pub fn lease(stolen_from: Option<ClaimState>, release: impl FnOnce() + Send + 'static) -> Self {
+        Self {
+            hold: ClaimHold::Lease(Some(Box::new(release))),
+            stolen_from,
+        }
+    }
+
     #[allow(dead_code)]
     pub fn release(self) -> Result<()> {
-        fs::remove_file(&self.path).ok();
+        drop(self);
         Ok(())
     }
 }
 
 impl Drop for ClaimGuard {
     fn drop(&mut self) {
-        self.file.unlock().ok();
-        let _ = fs::remove_file(&self.path);
+        match &mut self.hold {
+            ClaimHold::File { path, file } => {
+                let _ = fs::remove_file(heartbeat_path(path));
+                file.unlock().ok();
+                let _ = fs::remove_file(path);
+            }
+            ClaimHold::Lease(release) => {
+                if let Some(release) = release.take() {
+                    release();
+                }
+            }
+        }
     }
 }
 
@@ -153,6 +362,7 @@ fn lock_path(path: &Path) -> PathBuf {
 }
 
 fn with_lock<T>(path: &Path, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    ensure_writable()?;
     let lock_path = lock_path(path);
     if let Some(parent) = lock_path.parent() {
         fs::create_dir_all(parent)
@@ -160,6 +370,7 @@ fn with_lock<T>(path: &Path, f: impl FnOnce() -> Result<T>) -> Result<T> {
     }
     let lock_file = OpenOptions::new()
         .create(true)
+        .truncate(false)
         .read(true)
         .write(true)
         .open(&lock_path)
@@ -202,11 +413,67 @@ pub fn save_task(path: &Path, task: &TaskState) -> Result<()> {
 }
 
 pub fn update_task(path: &Path, update: impl FnOnce(&mut TaskState) -> Result<()>) -> Result<()> {
-    with_lock(path, || {
+    let (before, after) = with_lock(path, || {
         let mut task = load_task(path)?;
+        let before = task.clone();
         update(&mut task)?;
-        write_json_atomic(path, &task)
-    })
+        write_json_atomic(path, &task)?;
+        Ok((before, task))
+    })?;
+    if let Some(agent_root) = path.parent().and_then(Path::parent).and_then(Path::parent) {
+        record_transitions(agent_root, &before, &after);
+    }
+    Ok(())
+}
+
+/// Logs stage and status changes to the event history. The state is already
+/// written, so a failure to log only warns.
+pub(crate) fn record_transitions(agent_root: &Path, before: &TaskState, after: &TaskState) {
+    for event in transition_events(agent_root, before, after) {
+        if let Err(err) = append_event(agent_root, &event) {
+            eprintln!("Warning: {:#}", err);
+        }
+    }
+}
+
+/// `stage_changed` / `status_changed` events for an update from `before` to `after`.
+pub(crate) fn transition_events(
+    agent_root: &Path,
+    before: &TaskState,
+    after: &TaskState,
+) -> Vec<Event> {
+    let mut changes = Vec::new();
+    if before.stage != after.stage {
+        changes.push(("stage_changed", before.stage.clone(), after.stage.clone()));
+    }
+    if before.status != after.status {
+        changes.push((
+            "status_changed",
+            before.status.to_string(),
+            after.status.to_string(),
+        ));
+    }
+    if changes.is_empty() {
+        return Vec::new();
+    }
+    let repo_root = agent_root
+        .parent()
+        .and_then(Path::parent)
+        .unwrap_or(agent_root);
+    let host = hostname::get()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let actor = Actor::current(repo_root, &host, None);
+    changes
+        .into_iter()
+        .map(|(kind, from, to)| {
+            let mut event = Event::new(&after.task, kind, actor.clone());
+            event.from = Some(from);
+            event.to = Some(to);
+            event
+        })
+        .collect()
 }
 
 pub fn load_session(path: &Path) -> Result<SessionState> {
@@ -257,15 +524,54 @@ pub fn list_tasks(agent_root: &Path) -> Vec<TaskState> {
     tasks
 }
 
-pub fn new_session_id() -> String {
+pub fn list_sessions(agent_root: &Path) -> Vec<SessionState> {
+    let sessions_dir = agent_root.join("sessions");
+    let mut sessions = Vec::new();
+    let entries = match fs::read_dir(&sessions_dir) {
+        Ok(entries) => entries,
+        Err(_) => return sessions,
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path().join("session.json");
+        if !path.exists() {
+            continue;
+        }
+        if let Ok(session) = load_session(&path) {
+            sessions.push(session);
+        }
+    }
+
+    sessions
+}
+
+/// Running session whose process is known to be gone (local host only).
+pub fn is_orphaned_session(session: &SessionState, local_host: &str) -> bool {
+    session.status == SessionStatus::Running
+        && session.host == local_host
+        && !pid_alive(session.pid)
+}
+
+/// `<epoch>-<pid>`, with a `-<n>` suffix when this process already started a
+/// session in the same second (a retried stage).
+pub fn new_session_id(agent_root: &Path) -> String {
     let epoch = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or(Duration::from_secs(0))
         .as_secs();
-    format!("{}-{}", epoch, std::process::id())
+    let base = format!("{}-{}", epoch, std::process::id());
+    let mut session_id = base.clone();
+    let mut suffix = 2;
+    while session_dir(agent_root, &session_id).exists() {
+        session_id = format!("{base}-{suffix}");
+        suffix += 1;
+    }
+    session_id
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_session(
+    store: &dyn StateStore,
     agent_root: &Path,
     session_id: &str,
     agent: &str,
@@ -273,7 +579,9 @@ pub fn create_session(
     task: Option<&str>,
     repo_root: &Path,
     host: &str,
+    model: &str,
 ) -> Result<SessionState> {
+    ensure_writable()?;
     let session = SessionState {
         session_id: session_id.to_string(),
         task: task.map(|t| t.to_string()),
@@ -286,18 +594,19 @@ pub fn create_session(
         pid: std::process::id(),
         host: host.to_string(),
         repo_root: repo_root.display().to_string(),
+        model: Some(model.to_string()),
+        previous_session: None,
+        model_session: None,
     };
 
-    let session_path = session_state_path(agent_root, session_id);
-    if let Some(parent) = session_path.parent() {
-        fs::create_dir_all(parent)
-            .with_context(|| format!("Failed to create {}", parent.display()))?;
-    }
-    save_session(&session_path, &session)?;
+    // Prompts and transcripts live next to the record whichever backend holds it.
+    let dir = session_dir(agent_root, session_id);
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    store.save_session(&session)?;
     Ok(session)
 }
 
-pub fn resolve_session_id(agent_root: &Path, explicit: Option<String>) -> Result<String> {
+pub fn resolve_session_id(store: &dyn StateStore, explicit: Option<String>) -> Result<String> {
     if let Some(session) = explicit {
         return Ok(session);
     }
@@ -305,36 +614,28 @@ pub fn resolve_session_id(agent_root: &Path, explicit: Option<String>) -> Result
         return Ok(session);
     }
 
-    let sessions_dir = agent_root.join("sessions");
-    let entries = match fs::read_dir(&sessions_dir) {
-        Ok(entries) => entries,
-        Err(_) => bail!("MUNG_SESSION (or METAGENT_SESSION) not set and no active session found"),
-    };
-
     let local_host = hostname::get()
         .unwrap_or_default()
         .to_string_lossy()
         .to_string();
     let mut running = Vec::new();
-    for entry in entries.flatten() {
-        let path = entry.path().join("session.json");
-        if !path.exists() {
+    for session in store.list_sessions() {
+        if session.status != SessionStatus::Running {
             continue;
         }
-        if let Ok(session) = load_session(&path) {
-            if session.status == SessionStatus::Running {
-                if session.host == local_host && !is_pid_alive(session.pid) {
-                    update_session(&path, |session_state| {
+        if session.host == local_host && !pid_alive(session.pid) {
+            if !is_read_only() {
+                store
+                    .update_session(&session.session_id, |session_state| {
                         session_state.status = SessionStatus::Failed;
                         session_state.finished_at = Some(now_iso());
                         Ok(())
                     })
                     .ok();
-                    continue;
-                }
-                running.push(session.session_id);
             }
+            continue;
         }
+        running.push(session.session_id);
     }
 
     if running.len() == 1 {
@@ -345,6 +646,7 @@ pub fn resolve_session_id(agent_root: &Path, explicit: Option<String>) -> Result
 }
 
 pub fn write_task_state(path: &Path, task: &TaskState) -> Result<()> {
+    ensure_writable()?;
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
             .with_context(|| format!("Failed to create {}", parent.display()))?;
@@ -352,8 +654,9 @@ pub fn write_task_state(path: &Path, task: &TaskState) -> Result<()> {
     save_task(path, task)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn create_task_state(
-    agent_root: &Path,
+    store: &dyn StateStore,
     agent: &str,
     task: &str,
     stage: &str,
@@ -369,16 +672,41 @@ pub fn create_task_state(
         status: TaskStatus::Pending,
         queue_rank: None,
         held,
+        held_until: None,
+        held_reason: None,
         description,
         prompt,
+        depends_on: Vec::new(),
+        owner: None,
+        labels: Vec::new(),
+        tags: Vec::new(),
+        due_at: None,
+        estimate: None,
+        priority: None,
         added_at: added_at.to_string(),
         updated_at: added_at.to_string(),
         last_session: None,
+        session_count: 0,
+        last_run_at: None,
         last_error: None,
+        escalated_stages: Vec::new(),
+        base_commit: None,
+        reviewed_commit: None,
+        awaiting_approval: None,
+        blocked_reason: None,
+        spec_hashes: BTreeMap::new(),
+        handover: None,
+        pinged_issues: Vec::new(),
+        origin: None,
+        transitions: vec![StageTransition {
+            stage: stage.to_string(),
+            status: TaskStatus::Pending,
+            session: None,
+            at: added_at.to_string(),
+        }],
     };
 
-    let task_path = task_state_path(agent_root, task);
-    write_task_state(&task_path, &task_state)?;
+    store.save_task(&task_state)?;
     Ok(task_state)
 }
 
@@ -388,6 +716,7 @@ pub fn claim_task(
     ttl_seconds: u64,
     host: &str,
 ) -> Result<Option<ClaimGuard>> {
+    ensure_writable()?;
     let path = claim_path(agent_root, task);
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
@@ -397,11 +726,17 @@ pub fn claim_task(
         .read(true)
         .write(true)
         .create(true)
+        .truncate(false)
         .open(&path)
         .with_context(|| format!("Failed to open claim {}", path.display()))?;
 
     match file.try_lock_exclusive() {
         Ok(()) => {
+            // A released claim removes its file, so leftover contents mean the
+            // previous holder died while it held the task.
+            let mut previous = String::new();
+            file.read_to_string(&mut previous).ok();
+            let stolen_from = serde_json::from_str::<ClaimState>(&previous).ok();
             let claim = ClaimState {
                 task: task.to_string(),
                 agent: agent_root
@@ -412,11 +747,18 @@ pub fn claim_task(
                 host: host.to_string(),
                 started_at: now_iso(),
                 ttl_seconds,
+                heartbeat_at: None,
             };
             let data = serde_json::to_string_pretty(&claim)?;
             file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
             file.write_all(data.as_bytes())?;
-            Ok(Some(ClaimGuard { path, file }))
+            // The previous holder's heartbeat must not keep the new claim looking fresh.
+            let _ = fs::remove_file(heartbeat_path(&path));
+            Ok(Some(ClaimGuard {
+                hold: ClaimHold::File { path, file },
+                stolen_from,
+            }))
         }
         Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
         Err(err) => Err(err.into()),
@@ -428,6 +770,9 @@ pub fn has_active_claim(agent_root: &Path, task: &str) -> Result<bool> {
     if !path.exists() {
         return Ok(false);
     }
+    if is_read_only() {
+        return Ok(claim_looks_alive(&path));
+    }
     let file = OpenOptions::new()
         .read(true)
         .write(true)
@@ -443,45 +788,169 @@ pub fn has_active_claim(agent_root: &Path, task: &str) -> Result<bool> {
     }
 }
 
-pub fn has_active_session(agent_root: &Path, task: &str) -> Result<bool> {
-    let sessions_dir = agent_root.join("sessions");
-    let entries = match fs::read_dir(&sessions_dir) {
+pub fn list_claims(agent_root: &Path) -> Vec<String> {
+    let claims_dir = agent_root.join("claims");
+    let mut claims = Vec::new();
+    let entries = match fs::read_dir(&claims_dir) {
         Ok(entries) => entries,
-        Err(_) => return Ok(false),
+        Err(_) => return claims,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("lock") {
+            continue;
+        }
+        if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+            claims.push(stem.to_string());
+        }
+    }
+    claims.sort();
+    claims
+}
+
+/// Parsed contents of a claim file, if it exists and is readable.
+pub fn read_claim(agent_root: &Path, task: &str) -> Option<ClaimState> {
+    read_claim_file(&claim_path(agent_root, task))
+}
+
+/// The claim at `path` with the latest heartbeat from its sidecar file applied.
+fn read_claim_file(path: &Path) -> Option<ClaimState> {
+    let mut claim: ClaimState = fs::read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())?;
+    if let Some(heartbeat) = fs::read_to_string(heartbeat_path(path))
+        .ok()
+        .and_then(|data| serde_json::from_str::<String>(&data).ok())
+    {
+        claim.heartbeat_at = Some(heartbeat);
+    }
+    Some(claim)
+}
+
+/// Heartbeats go next to the claim (`<task>.heartbeat`) rather than into it: the
+/// claim file can't be replaced without orphaning its lock, and rewriting it in
+/// place lets a concurrent reader see it empty.
+fn heartbeat_path(claim_path: &Path) -> PathBuf {
+    claim_path.with_extension("heartbeat")
+}
+
+/// Deletes the claim file even if its lock is held. The holder, if it is still
+/// running somewhere, no longer owns the task.
+pub fn release_claim(agent_root: &Path, task: &str) -> Result<()> {
+    ensure_writable()?;
+    let path = claim_path(agent_root, task);
+    let _ = fs::remove_file(heartbeat_path(&path));
+    if path.exists() {
+        fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove claim {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Refreshes the claim's heartbeat if this process holds it, by atomically
+/// replacing the claim's heartbeat file.
+pub fn heartbeat_claim(agent_root: &Path, task: &str) -> Result<()> {
+    ensure_writable()?;
+    let Some(claim) = read_claim(agent_root, task) else {
+        return Ok(());
     };
+    if claim.pid != std::process::id() {
+        return Ok(());
+    }
+    write_json_atomic(&heartbeat_path(&claim_path(agent_root, task)), &now_iso())
+}
+
+/// Removes a claim file nobody holds the lock on. Returns false if the claim is live.
+pub fn remove_stale_claim(agent_root: &Path, task: &str) -> Result<bool> {
+    ensure_writable()?;
+    let path = claim_path(agent_root, task);
+    if !path.exists() {
+        return Ok(true);
+    }
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open claim {}", path.display()))?;
+    match file.try_lock_exclusive() {
+        Ok(()) => {
+            let _ = fs::remove_file(heartbeat_path(&path));
+            let removed = fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove claim {}", path.display()));
+            file.unlock().ok();
+            removed?;
+            Ok(true)
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub fn has_active_session(store: &dyn StateStore, task: &str) -> Result<bool> {
     let local_host = hostname::get()
         .unwrap_or_default()
         .to_string_lossy()
         .to_string();
-    for entry in entries.flatten() {
-        let path = entry.path().join("session.json");
-        if !path.exists() {
+    for session in store.list_sessions() {
+        if session.status != SessionStatus::Running {
             continue;
         }
-        if let Ok(session) = load_session(&path) {
-            if session.status != SessionStatus::Running {
-                continue;
-            }
-            if session.task.as_deref() != Some(task) {
-                continue;
-            }
-            if session.host != local_host {
-                return Ok(true);
-            }
-            if is_pid_alive(session.pid) {
-                return Ok(true);
-            }
-            update_session(&path, |session_state| {
+        if session.task.as_deref() != Some(task) {
+            continue;
+        }
+        if session.host != local_host {
+            return Ok(true);
+        }
+        if pid_alive(session.pid) {
+            return Ok(true);
+        }
+        if is_read_only() {
+            continue;
+        }
+        store
+            .update_session(&session.session_id, |session_state| {
                 session_state.status = SessionStatus::Failed;
                 session_state.finished_at = Some(now_iso());
                 Ok(())
             })
             .ok();
-        }
     }
     Ok(false)
 }
 
-fn is_pid_alive(pid: u32) -> bool {
-    unsafe { libc::kill(pid as i32, 0) == 0 }
+/// Lock-free claim probe: trying the lock would race a concurrent `claim_task`.
+fn claim_looks_alive(path: &Path) -> bool {
+    let Some(claim) = read_claim_file(path) else {
+        // A fresh empty file is a claim being taken right now; its holder writes it
+        // next. An old one was left by a holder that died in between.
+        return fs::metadata(path).is_ok_and(|meta| {
+            meta.len() == 0
+                && meta
+                    .modified()
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok())
+                    .is_some_and(|age| age < Duration::from_secs(5))
+        });
+    };
+    let local_host = hostname::get()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    if claim.host == local_host {
+        return pid_alive(claim.pid);
+    }
+    !claim_expired(&claim)
+}
+
+/// Whether `ttl_seconds` have passed since the claim's last heartbeat (or since it
+/// was taken, before the first one). Another host's pid can't be checked, so this
+/// is all there is to go on for a holder elsewhere.
+pub fn claim_expired(claim: &ClaimState) -> bool {
+    let last_seen = claim.heartbeat_at.as_deref().unwrap_or(&claim.started_at);
+    chrono::DateTime::parse_from_rfc3339(last_seen)
+        .map(|started| {
+            let age = chrono::Utc::now().signed_duration_since(started);
+            age.num_seconds() >= claim.ttl_seconds as i64
+        })
+        .unwrap_or(true)
 }
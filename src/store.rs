@@ -0,0 +1,790 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::config::{StateBackend, StateConfig};
+use crate::events::{self, Event};
+use crate::issues::{self, archived_issue_path, issue_path, Issue};
+use crate::state::{self, ClaimGuard, ClaimState, SessionState, TaskState};
+use crate::util::{session_dir, session_state_path, task_state_path};
+
+/// Backend for task, session, and issue state. Commands go through this trait so the
+/// filesystem layout under `.agents/<agent>/` is one implementation among several.
+pub trait StateStore: Send + Sync + fmt::Debug {
+    fn task_exists(&self, task: &str) -> bool;
+    fn load_task(&self, task: &str) -> Result<TaskState>;
+    fn save_task(&self, task: &TaskState) -> Result<()>;
+    fn modify_task(
+        &self,
+        task: &str,
+        update: &mut dyn FnMut(&mut TaskState) -> Result<()>,
+    ) -> Result<()>;
+    fn list_tasks(&self) -> Vec<TaskState>;
+    /// Forgets the task's state. Its directory (spec, plan) is the caller's to move
+    /// or remove.
+    fn delete_task(&self, task: &str) -> Result<()>;
+
+    fn load_session(&self, session_id: &str) -> Result<SessionState>;
+    fn save_session(&self, session: &SessionState) -> Result<()>;
+    fn modify_session(
+        &self,
+        session_id: &str,
+        update: &mut dyn FnMut(&mut SessionState) -> Result<()>,
+    ) -> Result<()>;
+    fn list_sessions(&self) -> Vec<SessionState>;
+    /// Deletes the session and everything recorded with it (prompt, transcript).
+    fn remove_session(&self, session_id: &str) -> Result<()>;
+
+    fn issue_exists(&self, issue_id: &str) -> bool;
+    fn load_issue(&self, issue_id: &str) -> Result<Issue>;
+    fn save_issue(&self, issue: &Issue) -> Result<()>;
+    fn list_issues(&self) -> Result<Vec<Issue>>;
+    fn archive_issue(&self, issue_id: &str) -> Result<()>;
+    fn list_archived_issues(&self) -> Result<Vec<Issue>>;
+    fn save_archived_issue(&self, issue: &Issue) -> Result<()>;
+    /// The file an issue is kept in, for backends that keep issues as files.
+    fn issue_file(&self, _issue_id: &str) -> Option<PathBuf> {
+        None
+    }
+
+    fn append_event(&self, event: &Event) -> Result<()>;
+    fn list_events(&self) -> Result<Vec<Event>>;
+
+    /// Takes the task's claim for this process, or `None` while a live holder has it.
+    fn claim_task(&self, task: &str, ttl_seconds: u64, host: &str) -> Result<Option<ClaimGuard>>;
+    fn has_active_claim(&self, task: &str) -> Result<bool>;
+    fn list_claims(&self) -> Vec<String>;
+    fn read_claim(&self, task: &str) -> Option<ClaimState>;
+    /// Marks this process's claim on `task` as still alive. No-op if it isn't ours.
+    fn heartbeat_claim(&self, task: &str) -> Result<()>;
+    /// Removes a claim nobody live holds. Returns false if the claim is live.
+    fn remove_stale_claim(&self, task: &str) -> Result<bool>;
+    /// Removes the claim whoever holds it.
+    fn release_claim(&self, task: &str) -> Result<()>;
+}
+
+impl dyn StateStore + '_ {
+    pub fn update_task(
+        &self,
+        task: &str,
+        update: impl FnOnce(&mut TaskState) -> Result<()>,
+    ) -> Result<()> {
+        let mut update = Some(update);
+        self.modify_task(task, &mut |task_state| match update.take() {
+            Some(update) => {
+                update(task_state)?;
+                task_state.record_history();
+                Ok(())
+            }
+            None => Ok(()),
+        })
+    }
+
+    pub fn update_session(
+        &self,
+        session_id: &str,
+        update: impl FnOnce(&mut SessionState) -> Result<()>,
+    ) -> Result<()> {
+        let mut update = Some(update);
+        self.modify_session(session_id, &mut |session| match update.take() {
+            Some(update) => update(session),
+            None => Ok(()),
+        })
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+const SQLITE_UNAVAILABLE: &str = "[state] backend = \"sqlite\" needs mung built with the sqlite feature (cargo install --features sqlite)";
+
+/// The backend `[state]` in `.agents/mung.toml` selects for this agent.
+pub fn open_store(agent_root: &Path, config: &StateConfig) -> Result<Arc<dyn StateStore>> {
+    match config.backend {
+        StateBackend::Files => Ok(Arc::new(FsStore::new(agent_root.to_path_buf()))),
+        #[cfg(feature = "sqlite")]
+        StateBackend::Sqlite => {
+            let path = config.database(agent_root).unwrap_or_default();
+            Ok(Arc::new(sqlite::SqliteStore::open(agent_root, &path)?))
+        }
+        #[cfg(not(feature = "sqlite"))]
+        StateBackend::Sqlite => anyhow::bail!(SQLITE_UNAVAILABLE),
+        StateBackend::Remote => {
+            let Some(url) = config.url.as_deref() else {
+                anyhow::bail!("[state] backend = \"remote\" needs a url");
+            };
+            Ok(Arc::new(remote::RemoteStore::connect(agent_root, url)?))
+        }
+    }
+}
+
+/// Default backend: JSON and markdown files under `.agents/<agent>/`, guarded by
+/// file locks and atomic renames.
+#[derive(Debug, Clone)]
+pub struct FsStore {
+    agent_root: PathBuf,
+}
+
+impl FsStore {
+    pub fn new(agent_root: PathBuf) -> Self {
+        Self { agent_root }
+    }
+}
+
+impl StateStore for FsStore {
+    fn task_exists(&self, task: &str) -> bool {
+        task_state_path(&self.agent_root, task).exists()
+    }
+
+    fn load_task(&self, task: &str) -> Result<TaskState> {
+        state::load_task(&task_state_path(&self.agent_root, task))
+    }
+
+    fn save_task(&self, task: &TaskState) -> Result<()> {
+        state::write_task_state(&task_state_path(&self.agent_root, &task.task), task)
+    }
+
+    fn modify_task(
+        &self,
+        task: &str,
+        update: &mut dyn FnMut(&mut TaskState) -> Result<()>,
+    ) -> Result<()> {
+        state::update_task(&task_state_path(&self.agent_root, task), update)
+    }
+
+    fn list_tasks(&self) -> Vec<TaskState> {
+        state::list_tasks(&self.agent_root)
+    }
+
+    fn delete_task(&self, task: &str) -> Result<()> {
+        state::ensure_writable()?;
+        let path = task_state_path(&self.agent_root, task);
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    fn load_session(&self, session_id: &str) -> Result<SessionState> {
+        state::load_session(&session_state_path(&self.agent_root, session_id))
+    }
+
+    fn save_session(&self, session: &SessionState) -> Result<()> {
+        let path = session_state_path(&self.agent_root, &session.session_id);
+        state::save_session(&path, session)
+    }
+
+    fn modify_session(
+        &self,
+        session_id: &str,
+        update: &mut dyn FnMut(&mut SessionState) -> Result<()>,
+    ) -> Result<()> {
+        state::update_session(&session_state_path(&self.agent_root, session_id), update)
+    }
+
+    fn list_sessions(&self) -> Vec<SessionState> {
+        state::list_sessions(&self.agent_root)
+    }
+
+    fn remove_session(&self, session_id: &str) -> Result<()> {
+        state::ensure_writable()?;
+        let dir = session_dir(&self.agent_root, session_id);
+        fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))
+    }
+
+    fn issue_exists(&self, issue_id: &str) -> bool {
+        issue_path(&self.agent_root, issue_id).exists()
+    }
+
+    fn load_issue(&self, issue_id: &str) -> Result<Issue> {
+        issues::load_issue(&issue_path(&self.agent_root, issue_id))
+    }
+
+    fn save_issue(&self, issue: &Issue) -> Result<()> {
+        issues::save_issue(&issue_path(&self.agent_root, &issue.id), issue)
+    }
+
+    fn list_issues(&self) -> Result<Vec<Issue>> {
+        issues::list_issues(&self.agent_root)
+    }
+
+    fn archive_issue(&self, issue_id: &str) -> Result<()> {
+        state::ensure_writable()?;
+        issues::archive_issue(&self.agent_root, issue_id)
+    }
+
+    fn list_archived_issues(&self) -> Result<Vec<Issue>> {
+        issues::list_archived_issues(&self.agent_root)
+    }
+
+    fn save_archived_issue(&self, issue: &Issue) -> Result<()> {
+        issues::save_issue(&archived_issue_path(&self.agent_root, &issue.id), issue)
+    }
+
+    fn issue_file(&self, issue_id: &str) -> Option<PathBuf> {
+        let path = issue_path(&self.agent_root, issue_id);
+        if path.exists() {
+            return Some(path);
+        }
+        Some(archived_issue_path(&self.agent_root, issue_id))
+    }
+
+    fn append_event(&self, event: &Event) -> Result<()> {
+        state::ensure_writable()?;
+        events::append_event(&self.agent_root, event)
+    }
+
+    fn list_events(&self) -> Result<Vec<Event>> {
+        events::list_events(&self.agent_root)
+    }
+
+    fn claim_task(&self, task: &str, ttl_seconds: u64, host: &str) -> Result<Option<ClaimGuard>> {
+        state::claim_task(&self.agent_root, task, ttl_seconds, host)
+    }
+
+    fn has_active_claim(&self, task: &str) -> Result<bool> {
+        state::has_active_claim(&self.agent_root, task)
+    }
+
+    fn list_claims(&self) -> Vec<String> {
+        state::list_claims(&self.agent_root)
+    }
+
+    fn read_claim(&self, task: &str) -> Option<ClaimState> {
+        state::read_claim(&self.agent_root, task)
+    }
+
+    fn heartbeat_claim(&self, task: &str) -> Result<()> {
+        state::heartbeat_claim(&self.agent_root, task)
+    }
+
+    fn remove_stale_claim(&self, task: &str) -> Result<bool> {
+        state::remove_stale_claim(&self.agent_root, task)
+    }
+
+    fn release_claim(&self, task: &str) -> Result<()> {
+        state::release_claim(&self.agent_root, task)
+    }
+}
+
+pub mod remote;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+/// In-process backend for tests and for embedding without touching the filesystem.
+pub mod memory {
+    use anyhow::{bail, Result};
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    use super::StateStore;
+    use crate::events::Event;
+    use crate::issues::Issue;
+    use crate::state::{self, ClaimGuard, ClaimState, SessionState, TaskState};
+    use crate::util::now_iso;
+
+    /// Process-local backend with no persistence, used by the unit tests.
+    #[derive(Debug, Default)]
+    pub struct MemoryStore {
+        tasks: Mutex<BTreeMap<String, TaskState>>,
+        sessions: Mutex<BTreeMap<String, SessionState>>,
+        issues: Mutex<BTreeMap<String, Issue>>,
+        archived_issues: Mutex<BTreeMap<String, Issue>>,
+        events: Mutex<Vec<Event>>,
+        claims: Arc<Mutex<BTreeMap<String, ClaimState>>>,
+    }
+
+    impl MemoryStore {
+        pub fn new() -> Self {
+            Self::default()
+        }
+    }
+
+    impl StateStore for MemoryStore {
+        fn task_exists(&self, task: &str) -> bool {
+            self.tasks.lock().unwrap().contains_key(task)
+        }
+
+        fn load_task(&self, task: &str) -> Result<TaskState> {
+            match self.tasks.lock().unwrap().get(task) {
+                Some(task_state) => Ok(task_state.clone()),
+                None => bail!("Task '{}' not found", task),
+            }
+        }
+
+        fn save_task(&self, task: &TaskState) -> Result<()> {
+            state::ensure_writable()?;
+            self.tasks
+                .lock()
+                .unwrap()
+                .insert(task.task.clone(), task.clone());
+            Ok(())
+        }
+
+        fn modify_task(
+            &self,
+            task: &str,
+            update: &mut dyn FnMut(&mut TaskState) -> Result<()>,
+        ) -> Result<()> {
+            state::ensure_writable()?;
+            let mut tasks = self.tasks.lock().unwrap();
+            let Some(current) = tasks.get(task) else {
+                bail!("Task '{}' not found", task);
+            };
+            let mut updated = current.clone();
+            update(&mut updated)?;
+            tasks.insert(task.to_string(), updated);
+            Ok(())
+        }
+
+        fn list_tasks(&self) -> Vec<TaskState> {
+            self.tasks.lock().unwrap().values().cloned().collect()
+        }
+
+        fn delete_task(&self, task: &str) -> Result<()> {
+            state::ensure_writable()?;
+            self.tasks.lock().unwrap().remove(task);
+            Ok(())
+        }
+
+        fn load_session(&self, session_id: &str) -> Result<SessionState> {
+            match self.sessions.lock().unwrap().get(session_id) {
+                Some(session) => Ok(session.clone()),
+                None => bail!("Session '{}' not found", session_id),
+            }
+        }
+
+        fn save_session(&self, session: &SessionState) -> Result<()> {
+            state::ensure_writable()?;
+            self.sessions
+                .lock()
+                .unwrap()
+                .insert(session.session_id.clone(), session.clone());
+            Ok(())
+        }
+
+        fn modify_session(
+            &self,
+            session_id: &str,
+            update: &mut dyn FnMut(&mut SessionState) -> Result<()>,
+        ) -> Result<()> {
+            state::ensure_writable()?;
+            let mut sessions = self.sessions.lock().unwrap();
+            let Some(current) = sessions.get(session_id) else {
+                bail!("Session '{}' not found", session_id);
+            };
+            let mut updated = current.clone();
+            update(&mut updated)?;
+            sessions.insert(session_id.to_string(), updated);
+            Ok(())
+        }
+
+        fn list_sessions(&self) -> Vec<SessionState> {
+            self.sessions.lock().unwrap().values().cloned().collect()
+        }
+
+        fn remove_session(&self, session_id: &str) -> Result<()> {
+            state::ensure_writable()?;
+            if self.sessions.lock().unwrap().remove(session_id).is_none() {
+                bail!("Session '{}' not found", session_id);
+            }
+            Ok(())
+        }
+
+        fn issue_exists(&self, issue_id: &str) -> bool {
+            self.issues.lock().unwrap().contains_key(issue_id)
+        }
+
+        fn load_issue(&self, issue_id: &str) -> Result<Issue> {
+            match self.issues.lock().unwrap().get(issue_id) {
+                Some(issue) => Ok(issue.clone()),
+                None => bail!("Issue '{}' not found", issue_id),
+            }
+        }
+
+        fn save_issue(&self, issue: &Issue) -> Result<()> {
+            state::ensure_writable()?;
+            self.issues
+                .lock()
+                .unwrap()
+                .insert(issue.id.clone(), issue.clone());
+            Ok(())
+        }
+
+        fn list_issues(&self) -> Result<Vec<Issue>> {
+            Ok(self.issues.lock().unwrap().values().cloned().collect())
+        }
+
+        fn archive_issue(&self, issue_id: &str) -> Result<()> {
+            state::ensure_writable()?;
+            let Some(issue) = self.issues.lock().unwrap().remove(issue_id) else {
+                bail!("Issue '{}' not found", issue_id);
+            };
+            self.archived_issues
+                .lock()
+                .unwrap()
+                .insert(issue.id.clone(), issue);
+            Ok(())
+        }
+
+        fn list_archived_issues(&self) -> Result<Vec<Issue>> {
+            Ok(self
+                .archived_issues
+                .lock()
+                .unwrap()
+                .values()
+                .cloned()
+                .collect())
+        }
+
+        fn save_archived_issue(&self, issue: &Issue) -> Result<()> {
+            state::ensure_writable()?;
+            self.archived_issues
+                .lock()
+                .unwrap()
+                .insert(issue.id.clone(), issue.clone());
+            Ok(())
+        }
+
+        fn append_event(&self, event: &Event) -> Result<()> {
+            state::ensure_writable()?;
+            self.events.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+
+        fn list_events(&self) -> Result<Vec<Event>> {
+            Ok(self.events.lock().unwrap().clone())
+        }
+
+        fn claim_task(
+            &self,
+            task: &str,
+            ttl_seconds: u64,
+            host: &str,
+        ) -> Result<Option<ClaimGuard>> {
+            state::ensure_writable()?;
+            let mut claims = self.claims.lock().unwrap();
+            if claims.contains_key(task) {
+                return Ok(None);
+            }
+            let claim = ClaimState {
+                task: task.to_string(),
+                agent: String::new(),
+                pid: std::process::id(),
+                host: host.to_string(),
+                started_at: now_iso(),
+                ttl_seconds,
+                heartbeat_at: None,
+            };
+            claims.insert(task.to_string(), claim);
+            let held = Arc::clone(&self.claims);
+            let task = task.to_string();
+            Ok(Some(ClaimGuard::lease(None, move || {
+                held.lock().unwrap().remove(&task);
+            })))
+        }
+
+        fn has_active_claim(&self, task: &str) -> Result<bool> {
+            Ok(self.claims.lock().unwrap().contains_key(task))
+        }
+
+        fn list_claims(&self) -> Vec<String> {
+            self.claims.lock().unwrap().keys().cloned().collect()
+        }
+
+        fn read_claim(&self, task: &str) -> Option<ClaimState> {
+            self.claims.lock().unwrap().get(task).cloned()
+        }
+
+        fn heartbeat_claim(&self, task: &str) -> Result<()> {
+            if let Some(claim) = self.claims.lock().unwrap().get_mut(task) {
+                claim.heartbeat_at = Some(now_iso());
+            }
+            Ok(())
+        }
+
+        fn remove_stale_claim(&self, task: &str) -> Result<bool> {
+            Ok(!self.claims.lock().unwrap().contains_key(task))
+        }
+
+        fn release_claim(&self, task: &str) -> Result<()> {
+            state::ensure_writable()?;
+            self.claims.lock().unwrap().remove(task);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::memory::MemoryStore;
+    use super::{FsStore, StateStore};
+    use crate::issues::{new_issue, IssuePriority, IssueSource, IssueStatus, IssueType};
+    use crate::state::{
+        create_task_state, transition_events, SessionState, SessionStatus, TaskStatus,
+    };
+
+    fn exercise(store: &dyn StateStore) {
+        assert!(!store.task_exists("alpha"));
+        create_task_state(
+            store,
+            "code",
+            "alpha",
+            "spec",
+            "2026-01-01T00:00:00Z",
+            false,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(store.task_exists("alpha"));
+
+        store
+            .update_task("alpha", |task| {
+                task.stage = "build".to_string();
+                task.status = TaskStatus::Issues;
+                Ok(())
+            })
+            .unwrap();
+        let task = store.load_task("alpha").unwrap();
+        assert_eq!(task.stage, "build");
+        assert_eq!(task.status, TaskStatus::Issues);
+        assert_eq!(store.list_tasks().len(), 1);
+        assert!(store.update_task("missing", |_| Ok(())).is_err());
+
+        let issue = new_issue(
+            "Broken".to_string(),
+            IssueStatus::Open,
+            IssuePriority::P1,
+            Some("alpha".to_string()),
+            IssueType::Bug,
+            IssueSource::Manual,
+            None,
+            Some("details".to_string()),
+        );
+        store.save_issue(&issue).unwrap();
+        assert!(store.issue_exists(&issue.id));
+        let loaded = store.load_issue(&issue.id).unwrap();
+        assert_eq!(loaded.title, "Broken");
+        assert_eq!(loaded.task.as_deref(), Some("alpha"));
+        assert_eq!(store.list_issues().unwrap().len(), 1);
+
+        store.archive_issue(&issue.id).unwrap();
+        assert!(!store.issue_exists(&issue.id));
+        assert!(store.list_issues().unwrap().is_empty());
+        assert_eq!(store.list_archived_issues().unwrap()[0].id, issue.id);
+
+        let session = SessionState {
+            session_id: "20260101-000000".to_string(),
+            task: Some("alpha".to_string()),
+            agent: "code".to_string(),
+            stage: "build".to_string(),
+            status: SessionStatus::Finished,
+            started_at: "2026-01-01T00:00:00Z".to_string(),
+            finished_at: Some("2026-01-01T00:10:00Z".to_string()),
+            next_stage: None,
+            pid: 1,
+            host: "host".to_string(),
+            repo_root: "/repo".to_string(),
+            model: None,
+            previous_session: None,
+            model_session: None,
+        };
+        store.save_session(&session).unwrap();
+        assert_eq!(store.list_sessions().len(), 1);
+        store.remove_session(&session.session_id).unwrap();
+        assert!(store.list_sessions().is_empty());
+        assert!(store.remove_session(&session.session_id).is_err());
+    }
+
+    /// Updates from several threads at once must all land, none overwriting another.
+    fn concurrent_updates(store: &dyn StateStore) {
+        create_task_state(store, "code", "busy", "build", "now", false, None, None).unwrap();
+        std::thread::scope(|scope| {
+            for worker in 0..4 {
+                scope.spawn(move || {
+                    for round in 0..10 {
+                        store
+                            .update_task("busy", |task| {
+                                task.labels.push(format!("{worker}-{round}"));
+                                Ok(())
+                            })
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(store.load_task("busy").unwrap().labels.len(), 40);
+    }
+
+    #[test]
+    fn memory_store_round_trips_state() {
+        exercise(&MemoryStore::new());
+    }
+
+    #[test]
+    fn memory_store_serializes_concurrent_updates() {
+        concurrent_updates(&MemoryStore::new());
+    }
+
+    #[test]
+    fn fs_store_serializes_concurrent_updates() {
+        let dir = tempfile::TempDir::new().unwrap();
+        concurrent_updates(&FsStore::new(dir.path().join(".agents/code")));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_store_serializes_concurrent_updates() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let agent_root = dir.path().join(".agents/code");
+        let store = super::sqlite::SqliteStore::open(&agent_root, &agent_root.join("state.db"));
+        concurrent_updates(&store.unwrap());
+    }
+
+    #[test]
+    fn fs_store_round_trips_state() {
+        let dir = tempfile::TempDir::new().unwrap();
+        exercise(&FsStore::new(dir.path().join(".agents/code")));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_store_round_trips_state() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let agent_root = dir.path().join(".agents/code");
+        let store = super::sqlite::SqliteStore::open(&agent_root, &agent_root.join("state.db"));
+        exercise(&store.unwrap());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_store_starts_from_files_on_disk() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let agent_root = dir.path().join(".agents/code");
+        let files = FsStore::new(agent_root.clone());
+        create_task_state(&files, "code", "alpha", "plan", "now", false, None, None).unwrap();
+        let db = agent_root.join("state.db");
+
+        let store = super::sqlite::SqliteStore::open(&agent_root, &db).unwrap();
+        assert_eq!(store.load_task("alpha").unwrap().stage, "plan");
+        store.delete_task("alpha").unwrap();
+        drop(store);
+
+        // Only a new database imports; reopening keeps its own contents.
+        let store = super::sqlite::SqliteStore::open(&agent_root, &db).unwrap();
+        assert!(!store.task_exists("alpha"));
+    }
+
+    #[test]
+    fn update_task_error_leaves_state_unchanged() {
+        let store = MemoryStore::new();
+        let store: &dyn StateStore = &store;
+        create_task_state(store, "code", "beta", "spec", "now", false, None, None).unwrap();
+        let result = store.update_task("beta", |task| {
+            task.stage = "build".to_string();
+            anyhow::bail!("nope")
+        });
+        assert!(result.is_err());
+        assert_eq!(store.load_task("beta").unwrap().stage, "spec");
+    }
+
+    #[test]
+    fn claims_expire_from_their_last_heartbeat() {
+        let mut claim = crate::state::ClaimState {
+            task: "alpha".to_string(),
+            agent: "code".to_string(),
+            pid: 1,
+            host: "elsewhere".to_string(),
+            started_at: "2020-01-01T00:00:00Z".to_string(),
+            ttl_seconds: 60,
+            heartbeat_at: None,
+        };
+        assert!(crate::state::claim_expired(&claim));
+        claim.heartbeat_at = Some(crate::util::now_iso());
+        assert!(!crate::state::claim_expired(&claim));
+    }
+
+    #[test]
+    fn fs_claim_left_by_a_dead_holder_is_taken_over() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = FsStore::new(dir.path().join(".agents/code"));
+        let claim_file = crate::util::claim_path(&store.agent_root, "alpha");
+        std::fs::create_dir_all(claim_file.parent().unwrap()).unwrap();
+        let left = crate::state::ClaimState {
+            task: "alpha".to_string(),
+            agent: "code".to_string(),
+            pid: 1,
+            host: "elsewhere".to_string(),
+            started_at: "2020-01-01T00:00:00Z".to_string(),
+            ttl_seconds: 60,
+            heartbeat_at: None,
+        };
+        std::fs::write(&claim_file, serde_json::to_string(&left).unwrap()).unwrap();
+        // Nobody holds the lock, so the leftover file is not a live claim.
+        assert!(!store.has_active_claim("alpha").unwrap());
+
+        let guard = store.claim_task("alpha", 60, "host").unwrap().unwrap();
+        assert_eq!(guard.stolen_from.as_ref().unwrap().host, "elsewhere");
+        assert!(store.has_active_claim("alpha").unwrap());
+        assert!(store.claim_task("alpha", 60, "host").unwrap().is_none());
+        assert!(!store.remove_stale_claim("alpha").unwrap());
+
+        drop(guard);
+        assert!(!store.has_active_claim("alpha").unwrap());
+        let guard = store.claim_task("alpha", 60, "host").unwrap().unwrap();
+        assert!(guard.stolen_from.is_none());
+    }
+
+    #[test]
+    fn transition_events_name_stage_and_status_changes() {
+        let store = MemoryStore::new();
+        create_task_state(&store, "code", "alpha", "spec", "now", false, None, None).unwrap();
+        let before = store.load_task("alpha").unwrap();
+        let mut after = before.clone();
+        after.labels.push("unrelated".to_string());
+        let agent_root = std::path::Path::new("/repo/.agents/code");
+        assert!(transition_events(agent_root, &before, &after).is_empty());
+
+        after.stage = "build".to_string();
+        after.status = TaskStatus::Running;
+        let events: Vec<_> = transition_events(agent_root, &before, &after)
+            .into_iter()
+            .map(|event| (event.event, event.from, event.to, event.task))
+            .collect();
+        let change = |kind: &str, from: &str, to: &str| {
+            (
+                kind.to_string(),
+                Some(from.to_string()),
+                Some(to.to_string()),
+                Some("alpha".to_string()),
+            )
+        };
+        assert_eq!(
+            events,
+            vec![
+                change("stage_changed", "spec", "build"),
+                change("status_changed", "pending", "running"),
+            ]
+        );
+    }
+
+    #[test]
+    fn fs_heartbeat_leaves_the_claim_file_alone() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = FsStore::new(dir.path().join(".agents/code"));
+        let claim_file = crate::util::claim_path(&store.agent_root, "alpha");
+        let guard = store.claim_task("alpha", 60, "host").unwrap().unwrap();
+        let written = std::fs::read_to_string(&claim_file).unwrap();
+
+        store.heartbeat_claim("alpha").unwrap();
+        assert_eq!(std::fs::read_to_string(&claim_file).unwrap(), written);
+        assert!(store.read_claim("alpha").unwrap().heartbeat_at.is_some());
+        assert!(store.has_active_claim("alpha").unwrap());
+
+        drop(guard);
+        assert!(store.read_claim("alpha").is_none());
+        assert!(!claim_file.with_extension("heartbeat").exists());
+    }
+}
@@ -0,0 +1,492 @@
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::StateStore;
+use crate::events::Event;
+use crate::http::Request;
+use crate::issues::{parse_issue, render_issue, Issue};
+use crate::process::pid_alive;
+use crate::state::{self, claim_expired, ClaimGuard, ClaimState, SessionState, TaskState};
+use crate::util::{env_var, session_dir};
+
+/// How long `modify_task` waits for another machine's checkout of the same record.
+const CHECKOUT_WAIT: Duration = Duration::from_secs(30);
+
+/// State kept by a `mung serve` on another machine, so several hosts can pull
+/// from one queue. Updates check a record out, change it locally, and write it
+/// back; claims are leases the server hands out and expires after their TTL.
+/// Requests go through curl (see [`crate::http`]), with `MUNG_SERVE_TOKEN` as
+/// the bearer token.
+#[derive(Debug, Clone)]
+pub struct RemoteStore {
+    agent_root: PathBuf,
+    url: String,
+    token: Option<String>,
+    host: String,
+}
+
+impl RemoteStore {
+    /// Connects to the server at `url` and checks it serves the same agent.
+    pub fn connect(agent_root: &Path, url: &str) -> Result<Self> {
+        let store = Self {
+            agent_root: agent_root.to_path_buf(),
+            url: url.trim_end_matches('/').to_string(),
+            token: env_var("MUNG_SERVE_TOKEN", "METAGENT_SERVE_TOKEN"),
+            host: hostname::get()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+        };
+        let info = store
+            .call("GET", "/state", None)
+            .with_context(|| format!("Failed to reach state server {}", store.url))?;
+        let agent = agent_root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if info["agent"].as_str() != Some(agent.as_str()) {
+            bail!(
+                "State server {} serves agent '{}', not '{}'",
+                store.url,
+                info["agent"].as_str().unwrap_or("?"),
+                agent
+            );
+        }
+        Ok(store)
+    }
+
+    /// Sends one request and returns the status and JSON body.
+    fn request(&self, method: &str, path: &str, body: Option<&Value>) -> Result<(u16, Value)> {
+        let mut request = Request::new(method, format!("{}{}", self.url, path));
+        if let Some(token) = &self.token {
+            request = request.header(format!("Authorization: Bearer {token}"));
+        }
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request
+            .send()
+            .with_context(|| format!("{} {} failed", method, path))?;
+        let value = response
+            .json()
+            .with_context(|| format!("{} {}", method, path))?;
+        Ok((response.status, value))
+    }
+
+    /// Like `request`, failing with the server's message on anything but 2xx.
+    fn call(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value> {
+        let (status, value) = self.request(method, path, body)?;
+        if !(200..300).contains(&status) {
+            bail!(
+                "{}",
+                value["error"]
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("{} {} -> {}", method, path, status))
+            );
+        }
+        Ok(value)
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let value = self.call("GET", path, None)?;
+        serde_json::from_value(value).with_context(|| format!("Unexpected reply from {}", path))
+    }
+
+    /// Checks out `path` (a task or session), applies `update`, and writes it back.
+    /// Returns the record before and after.
+    fn modify<T>(&self, path: &str, update: &mut dyn FnMut(&mut T) -> Result<()>) -> Result<(T, T)>
+    where
+        T: DeserializeOwned + serde::Serialize + Clone,
+    {
+        state::ensure_writable()?;
+        let started = Instant::now();
+        let checkout = loop {
+            let (status, value) = self.request("POST", &format!("{}/checkout", path), None)?;
+            match status {
+                200 => break value,
+                423 if started.elapsed() < CHECKOUT_WAIT => {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                _ => bail!("{}", value["error"].as_str().unwrap_or("checkout failed")),
+            }
+        };
+        let lock = checkout["lock"].clone();
+        let before: T = serde_json::from_value(checkout["record"].clone())
+            .with_context(|| format!("Unexpected reply from {}", path))?;
+        let mut after = before.clone();
+        if let Err(err) = update(&mut after) {
+            self.request(
+                "POST",
+                &format!("{}/release", path),
+                Some(&json!({ "lock": lock })),
+            )
+            .ok();
+            return Err(err);
+        }
+        self.call("PUT", path, Some(&json!({ "record": after, "lock": lock })))?;
+        Ok((before, after))
+    }
+
+    fn claimant(&self) -> Value {
+        json!({ "host": self.host, "pid": std::process::id() })
+    }
+
+    /// A claim whose holder ran on this host and is gone, which the server can't see.
+    fn holder_died(&self, claim: &ClaimState) -> bool {
+        claim.host == self.host && !pid_alive(claim.pid)
+    }
+}
+
+fn parse_documents(value: Value) -> Result<Vec<Issue>> {
+    let documents: Vec<String> = serde_json::from_value(value)?;
+    documents
+        .iter()
+        .map(|document| parse_issue(document))
+        .collect()
+}
+
+impl StateStore for RemoteStore {
+    fn task_exists(&self, task: &str) -> bool {
+        matches!(
+            self.request("GET", &format!("/state/tasks/{}", task), None),
+            Ok((200, _))
+        )
+    }
+
+    fn load_task(&self, task: &str) -> Result<TaskState> {
+        self.get(&format!("/state/tasks/{}", task))
+    }
+
+    fn save_task(&self, task: &TaskState) -> Result<()> {
+        state::ensure_writable()?;
+        self.call(
+            "PUT",
+            &format!("/state/tasks/{}", task.task),
+            Some(&json!({ "record": task })),
+        )?;
+        Ok(())
+    }
+
+    fn modify_task(
+        &self,
+        task: &str,
+        update: &mut dyn FnMut(&mut TaskState) -> Result<()>,
+    ) -> Result<()> {
+        let (before, after) = self.modify(&format!("/state/tasks/{}", task), update)?;
+        for event in state::transition_events(&self.agent_root, &before, &after) {
+            if let Err(err) = self.append_event(&event) {
+                eprintln!("Warning: {:#}", err);
+            }
+        }
+        Ok(())
+    }
+
+    fn list_tasks(&self) -> Vec<TaskState> {
+        self.get("/state/tasks").unwrap_or_else(|err| {
+            eprintln!("Warning: failed to list tasks: {:#}", err);
+            Vec::new()
+        })
+    }
+
+    fn delete_task(&self, task: &str) -> Result<()> {
+        state::ensure_writable()?;
+        self.call("DELETE", &format!("/state/tasks/{}", task), None)?;
+        Ok(())
+    }
+
+    fn load_session(&self, session_id: &str) -> Result<SessionState> {
+        self.get(&format!("/state/sessions/{}", session_id))
+    }
+
+    fn save_session(&self, session: &SessionState) -> Result<()> {
+        state::ensure_writable()?;
+        self.call(
+            "PUT",
+            &format!("/state/sessions/{}", session.session_id),
+            Some(&json!({ "record": session })),
+        )?;
+        Ok(())
+    }
+
+    fn modify_session(
+        &self,
+        session_id: &str,
+        update: &mut dyn FnMut(&mut SessionState) -> Result<()>,
+    ) -> Result<()> {
+        self.modify(&format!("/state/sessions/{}", session_id), update)?;
+        Ok(())
+    }
+
+    fn list_sessions(&self) -> Vec<SessionState> {
+        self.get("/state/sessions").unwrap_or_else(|err| {
+            eprintln!("Warning: failed to list sessions: {:#}", err);
+            Vec::new()
+        })
+    }
+
+    fn remove_session(&self, session_id: &str) -> Result<()> {
+        state::ensure_writable()?;
+        self.call("DELETE", &format!("/state/sessions/{}", session_id), None)?;
+        // The prompt and transcript were written where the session ran.
+        let dir = session_dir(&self.agent_root, session_id);
+        if dir.exists() {
+            fs::remove_dir_all(&dir)
+                .with_context(|| format!("Failed to remove {}", dir.display()))?;
+        }
+        Ok(())
+    }
+
+    fn issue_exists(&self, issue_id: &str) -> bool {
+        matches!(
+            self.request("GET", &format!("/state/issues/{}", issue_id), None),
+            Ok((200, _))
+        )
+    }
+
+    fn load_issue(&self, issue_id: &str) -> Result<Issue> {
+        let value = self.call("GET", &format!("/state/issues/{}", issue_id), None)?;
+        parse_issue(value["document"].as_str().unwrap_or_default())
+    }
+
+    fn save_issue(&self, issue: &Issue) -> Result<()> {
+        state::ensure_writable()?;
+        self.call(
+            "PUT",
+            &format!("/state/issues/{}", issue.id),
+            Some(&json!({ "document": render_issue(issue) })),
+        )?;
+        Ok(())
+    }
+
+    fn list_issues(&self) -> Result<Vec<Issue>> {
+        parse_documents(self.call("GET", "/state/issues", None)?)
+    }
+
+    fn archive_issue(&self, issue_id: &str) -> Result<()> {
+        state::ensure_writable()?;
+        self.call("POST", &format!("/state/issues/{}/archive", issue_id), None)?;
+        Ok(())
+    }
+
+    fn list_archived_issues(&self) -> Result<Vec<Issue>> {
+        parse_documents(self.call("GET", "/state/archived-issues", None)?)
+    }
+
+    fn save_archived_issue(&self, issue: &Issue) -> Result<()> {
+        state::ensure_writable()?;
+        self.call(
+            "PUT",
+            &format!("/state/archived-issues/{}", issue.id),
+            Some(&json!({ "document": render_issue(issue) })),
+        )?;
+        Ok(())
+    }
+
+    fn append_event(&self, event: &Event) -> Result<()> {
+        state::ensure_writable()?;
+        self.call("POST", "/state/events", Some(&json!(event)))?;
+        Ok(())
+    }
+
+    fn list_events(&self) -> Result<Vec<Event>> {
+        self.get("/state/events")
+    }
+
+    fn claim_task(&self, task: &str, ttl_seconds: u64, _host: &str) -> Result<Option<ClaimGuard>> {
+        state::ensure_writable()?;
+        let path = format!("/state/claims/{}", task);
+        let mut request = self.claimant();
+        request["ttl_seconds"] = json!(ttl_seconds);
+        let mut stolen_from = None;
+        let granted = loop {
+            let (status, value) = self.request("POST", &path, Some(&request))?;
+            match status {
+                201 => break value,
+                409 => {
+                    let holder: Option<ClaimState> =
+                        serde_json::from_value(value["claim"].clone()).ok();
+                    match holder {
+                        Some(holder) if stolen_from.is_none() && self.holder_died(&holder) => {
+                            let identity = json!({ "host": holder.host, "pid": holder.pid });
+                            self.call("DELETE", &path, Some(&identity))?;
+                            stolen_from = Some(holder);
+                        }
+                        _ => return Ok(None),
+                    }
+                }
+                _ => bail!("{}", value["error"].as_str().unwrap_or("claim failed")),
+            }
+        };
+        let stolen_from =
+            stolen_from.or_else(|| serde_json::from_value(granted["stolen_from"].clone()).ok());
+        let store = self.clone();
+        let identity = self.claimant();
+        Ok(Some(ClaimGuard::lease(stolen_from, move || {
+            if let Err(err) = store.call("DELETE", &path, Some(&identity)) {
+                eprintln!("Warning: failed to release claim: {:#}", err);
+            }
+        })))
+    }
+
+    fn has_active_claim(&self, task: &str) -> Result<bool> {
+        Ok(match self.read_claim(task) {
+            Some(claim) => !claim_expired(&claim) && !self.holder_died(&claim),
+            None => false,
+        })
+    }
+
+    fn list_claims(&self) -> Vec<String> {
+        self.get("/state/claims").unwrap_or_else(|err| {
+            eprintln!("Warning: failed to list claims: {:#}", err);
+            Vec::new()
+        })
+    }
+
+    fn read_claim(&self, task: &str) -> Option<ClaimState> {
+        self.get(&format!("/state/claims/{}", task)).ok()
+    }
+
+    fn heartbeat_claim(&self, task: &str) -> Result<()> {
+        state::ensure_writable()?;
+        let path = format!("/state/claims/{}/heartbeat", task);
+        let (status, value) = self.request("POST", &path, Some(&self.claimant()))?;
+        match status {
+            200 | 404 | 409 => Ok(()),
+            _ => bail!("{}", value["error"].as_str().unwrap_or("heartbeat failed")),
+        }
+    }
+
+    fn remove_stale_claim(&self, task: &str) -> Result<bool> {
+        state::ensure_writable()?;
+        let Some(claim) = self.read_claim(task) else {
+            return Ok(true);
+        };
+        // Without the holder's identity the server only drops expired leases.
+        let body = self
+            .holder_died(&claim)
+            .then(|| json!({ "host": claim.host, "pid": claim.pid }));
+        let removed = self.call("DELETE", &format!("/state/claims/{}", task), body.as_ref())?;
+        Ok(removed["removed"].as_bool().unwrap_or(false))
+    }
+
+    fn release_claim(&self, task: &str) -> Result<()> {
+        state::ensure_writable()?;
+        let Some(claim) = self.read_claim(task) else {
+            return Ok(());
+        };
+        let holder = json!({ "host": claim.host, "pid": claim.pid });
+        self.call("DELETE", &format!("/state/claims/{}", task), Some(&holder))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RemoteStore;
+    use crate::state::{create_task_state, TaskState};
+    use crate::store::memory::MemoryStore;
+    use crate::store::StateStore;
+    use serde_json::{json, Value};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    /// A stand-in for `mung serve` holding one task; returns its address and
+    /// the requests it saw as "METHOD path body".
+    fn fake_server(task: TaskState) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&seen);
+        std::thread::spawn(move || {
+            let mut record = serde_json::to_value(&task).unwrap();
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut parts = line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let path = parts.next().unwrap_or_default().to_string();
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+                log.lock()
+                    .unwrap()
+                    .push(format!("{} {} {}", method, path, body));
+                let reply = match (method.as_str(), path.as_str()) {
+                    ("GET", "/state") => json!({ "agent": "code" }),
+                    ("POST", "/state/tasks/alpha/checkout") => {
+                        json!({ "record": record, "lock": "l1" })
+                    }
+                    ("PUT", "/state/tasks/alpha") => {
+                        record = body["record"].clone();
+                        json!({ "ok": true })
+                    }
+                    ("GET", "/state/tasks/alpha") => record.clone(),
+                    _ => json!({ "ok": true }),
+                };
+                let reply = reply.to_string();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    reply.len(),
+                    reply
+                )
+                .unwrap();
+            }
+        });
+        (addr, seen)
+    }
+
+    #[test]
+    fn update_checks_out_writes_back_and_posts_events() {
+        let local = MemoryStore::new();
+        create_task_state(&local, "code", "alpha", "spec", "now", false, None, None).unwrap();
+        let (url, seen) = fake_server(local.load_task("alpha").unwrap());
+
+        let remote = RemoteStore::connect(Path::new("/repo/.agents/code"), &url).unwrap();
+        let store: &dyn StateStore = &remote;
+        store
+            .update_task("alpha", |task| {
+                task.stage = "build".to_string();
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(store.load_task("alpha").unwrap().stage, "build");
+
+        let seen = seen.lock().unwrap();
+        let put = seen
+            .iter()
+            .find(|request| request.starts_with("PUT /state/tasks/alpha "))
+            .unwrap();
+        assert!(put.contains(r#""lock":"l1""#));
+        let event = seen
+            .iter()
+            .find(|request| request.starts_with("POST /state/events "))
+            .unwrap();
+        assert!(event.contains(r#""event":"stage_changed""#));
+        assert!(event.contains(r#""from":"spec""#));
+        assert!(event.contains(r#""to":"build""#));
+    }
+}
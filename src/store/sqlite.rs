@@ -0,0 +1,400 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::{FsStore, StateStore};
+use crate::events::{self, Event};
+use crate::issues::{parse_issue, render_issue, Issue};
+use crate::state::{self, ClaimGuard, ClaimState, SessionState, TaskState};
+use crate::util::session_dir;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS tasks (
+    name TEXT PRIMARY KEY,
+    state TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    task TEXT,
+    state TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS issues (
+    id TEXT PRIMARY KEY,
+    archived INTEGER NOT NULL DEFAULT 0,
+    document TEXT NOT NULL
+);
+";
+
+/// Task, session, and issue records in one SQLite database. Tasks and sessions
+/// are stored as the same JSON the files backend writes, issues as their markdown
+/// document, so switching backends loses nothing. Updates run in `BEGIN IMMEDIATE`
+/// transactions, which serialize writers across processes the way file locks do.
+#[derive(Debug)]
+pub struct SqliteStore {
+    agent_root: PathBuf,
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens (or creates) the database at `path`. A new database starts with a
+    /// copy of the state already on disk under `agent_root`.
+    pub fn open(agent_root: &Path, path: &Path) -> Result<Self> {
+        let created = !path.exists();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open state database {}", path.display()))?;
+        conn.busy_timeout(Duration::from_secs(10))?;
+        conn.execute_batch("PRAGMA journal_mode = WAL;")?;
+        conn.execute_batch(SCHEMA)
+            .with_context(|| format!("Failed to set up {}", path.display()))?;
+        let store = Self {
+            agent_root: agent_root.to_path_buf(),
+            conn: Mutex::new(conn),
+        };
+        if created {
+            store.import(&FsStore::new(agent_root.to_path_buf()))?;
+        }
+        Ok(store)
+    }
+
+    fn import(&self, files: &FsStore) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        for task in files.list_tasks() {
+            tx.execute(
+                "INSERT OR REPLACE INTO tasks (name, state) VALUES (?1, ?2)",
+                params![task.task, serde_json::to_string(&task)?],
+            )?;
+        }
+        for session in files.list_sessions() {
+            tx.execute(
+                "INSERT OR REPLACE INTO sessions (id, task, state) VALUES (?1, ?2, ?3)",
+                params![
+                    session.session_id,
+                    session.task,
+                    serde_json::to_string(&session)?
+                ],
+            )?;
+        }
+        for (archived, issues) in [
+            (false, files.list_issues()?),
+            (true, files.list_archived_issues()?),
+        ] {
+            for issue in issues {
+                tx.execute(
+                    "INSERT OR REPLACE INTO issues (id, archived, document) VALUES (?1, ?2, ?3)",
+                    params![issue.id, archived, render_issue(&issue)],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn write_issue(&self, issue: &Issue, archived: bool) -> Result<()> {
+        state::ensure_writable()?;
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO issues (id, archived, document) VALUES (?1, ?2, ?3)",
+            params![issue.id, archived, render_issue(issue)],
+        )?;
+        Ok(())
+    }
+
+    fn issues_where(&self, archived: bool) -> Result<Vec<Issue>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT id, document FROM issues WHERE archived = ?1")?;
+        let rows = stmt.query_map([archived], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut issues = Vec::new();
+        for row in rows {
+            let (id, document) = row?;
+            match parse_issue(&document) {
+                Ok(issue) => issues.push(issue),
+                Err(err) => eprintln!("Warning: issue {}: {:#} (skipping)", id, err),
+            }
+        }
+        Ok(issues)
+    }
+
+    /// Every row of `sql` (a single JSON column) that parses as `T`.
+    fn json_rows<T: serde::de::DeserializeOwned>(&self, sql: &str) -> Vec<T> {
+        let conn = self.conn.lock().unwrap();
+        let Ok(mut stmt) = conn.prepare(sql) else {
+            return Vec::new();
+        };
+        let Ok(rows) = stmt.query_map([], |row| row.get::<_, String>(0)) else {
+            return Vec::new();
+        };
+        rows.flatten()
+            .filter_map(|data| serde_json::from_str(&data).ok())
+            .collect()
+    }
+}
+
+impl StateStore for SqliteStore {
+    fn task_exists(&self, task: &str) -> bool {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT 1 FROM tasks WHERE name = ?1", [task], |_| Ok(()))
+            .optional()
+            .ok()
+            .flatten()
+            .is_some()
+    }
+
+    fn load_task(&self, task: &str) -> Result<TaskState> {
+        let data: Option<String> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row("SELECT state FROM tasks WHERE name = ?1", [task], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        let Some(data) = data else {
+            bail!("Task '{}' not found", task);
+        };
+        serde_json::from_str(&data).with_context(|| format!("Failed to parse task '{}'", task))
+    }
+
+    fn save_task(&self, task: &TaskState) -> Result<()> {
+        state::ensure_writable()?;
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO tasks (name, state) VALUES (?1, ?2)",
+            params![task.task, serde_json::to_string(task)?],
+        )?;
+        Ok(())
+    }
+
+    fn modify_task(
+        &self,
+        task: &str,
+        update: &mut dyn FnMut(&mut TaskState) -> Result<()>,
+    ) -> Result<()> {
+        state::ensure_writable()?;
+        let (before, after) = {
+            let mut conn = self.conn.lock().unwrap();
+            let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let data: Option<String> = tx
+                .query_row("SELECT state FROM tasks WHERE name = ?1", [task], |row| {
+                    row.get(0)
+                })
+                .optional()?;
+            let Some(data) = data else {
+                bail!("Task '{}' not found", task);
+            };
+            let mut task_state: TaskState = serde_json::from_str(&data)
+                .with_context(|| format!("Failed to parse task '{}'", task))?;
+            let before = task_state.clone();
+            update(&mut task_state)?;
+            tx.execute(
+                "UPDATE tasks SET state = ?2 WHERE name = ?1",
+                params![task, serde_json::to_string(&task_state)?],
+            )?;
+            tx.commit()?;
+            (before, task_state)
+        };
+        state::record_transitions(&self.agent_root, &before, &after);
+        Ok(())
+    }
+
+    fn list_tasks(&self) -> Vec<TaskState> {
+        self.json_rows("SELECT state FROM tasks ORDER BY name")
+    }
+
+    fn delete_task(&self, task: &str) -> Result<()> {
+        state::ensure_writable()?;
+        self.conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM tasks WHERE name = ?1", [task])?;
+        Ok(())
+    }
+
+    fn load_session(&self, session_id: &str) -> Result<SessionState> {
+        let data: Option<String> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT state FROM sessions WHERE id = ?1",
+                [session_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(data) = data else {
+            bail!("Session '{}' not found", session_id);
+        };
+        serde_json::from_str(&data)
+            .with_context(|| format!("Failed to parse session '{}'", session_id))
+    }
+
+    fn save_session(&self, session: &SessionState) -> Result<()> {
+        state::ensure_writable()?;
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO sessions (id, task, state) VALUES (?1, ?2, ?3)",
+            params![
+                session.session_id,
+                session.task,
+                serde_json::to_string(session)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn modify_session(
+        &self,
+        session_id: &str,
+        update: &mut dyn FnMut(&mut SessionState) -> Result<()>,
+    ) -> Result<()> {
+        state::ensure_writable()?;
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let data: Option<String> = tx
+            .query_row(
+                "SELECT state FROM sessions WHERE id = ?1",
+                [session_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(data) = data else {
+            bail!("Session '{}' not found", session_id);
+        };
+        let mut session: SessionState = serde_json::from_str(&data)
+            .with_context(|| format!("Failed to parse session '{}'", session_id))?;
+        update(&mut session)?;
+        tx.execute(
+            "UPDATE sessions SET task = ?2, state = ?3 WHERE id = ?1",
+            params![session_id, session.task, serde_json::to_string(&session)?],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn list_sessions(&self) -> Vec<SessionState> {
+        self.json_rows("SELECT state FROM sessions ORDER BY id")
+    }
+
+    fn remove_session(&self, session_id: &str) -> Result<()> {
+        state::ensure_writable()?;
+        let removed = self
+            .conn
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM sessions WHERE id = ?1", [session_id])?;
+        let dir = session_dir(&self.agent_root, session_id);
+        if dir.exists() {
+            fs::remove_dir_all(&dir)
+                .with_context(|| format!("Failed to remove {}", dir.display()))?;
+        } else if removed == 0 {
+            bail!("Session '{}' not found", session_id);
+        }
+        Ok(())
+    }
+
+    fn issue_exists(&self, issue_id: &str) -> bool {
+        self.conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT 1 FROM issues WHERE id = ?1 AND archived = 0",
+                [issue_id],
+                |_| Ok(()),
+            )
+            .optional()
+            .ok()
+            .flatten()
+            .is_some()
+    }
+
+    fn load_issue(&self, issue_id: &str) -> Result<Issue> {
+        let document: Option<String> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT document FROM issues WHERE id = ?1 AND archived = 0",
+                [issue_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(document) = document else {
+            bail!("Issue '{}' not found", issue_id);
+        };
+        parse_issue(&document).with_context(|| format!("Failed to parse issue '{}'", issue_id))
+    }
+
+    fn save_issue(&self, issue: &Issue) -> Result<()> {
+        self.write_issue(issue, false)
+    }
+
+    fn list_issues(&self) -> Result<Vec<Issue>> {
+        self.issues_where(false)
+    }
+
+    fn archive_issue(&self, issue_id: &str) -> Result<()> {
+        state::ensure_writable()?;
+        let changed = self.conn.lock().unwrap().execute(
+            "UPDATE issues SET archived = 1 WHERE id = ?1 AND archived = 0",
+            [issue_id],
+        )?;
+        if changed == 0 {
+            bail!("Issue '{}' not found", issue_id);
+        }
+        Ok(())
+    }
+
+    fn list_archived_issues(&self) -> Result<Vec<Issue>> {
+        self.issues_where(true)
+    }
+
+    fn save_archived_issue(&self, issue: &Issue) -> Result<()> {
+        self.write_issue(issue, true)
+    }
+
+    fn append_event(&self, event: &Event) -> Result<()> {
+        state::ensure_writable()?;
+        events::append_event(&self.agent_root, event)
+    }
+
+    fn list_events(&self) -> Result<Vec<Event>> {
+        events::list_events(&self.agent_root)
+    }
+
+    // Claims stay lock files: a held lock dies with its process, which a row can't.
+    fn claim_task(&self, task: &str, ttl_seconds: u64, host: &str) -> Result<Option<ClaimGuard>> {
+        state::claim_task(&self.agent_root, task, ttl_seconds, host)
+    }
+
+    fn has_active_claim(&self, task: &str) -> Result<bool> {
+        state::has_active_claim(&self.agent_root, task)
+    }
+
+    fn list_claims(&self) -> Vec<String> {
+        state::list_claims(&self.agent_root)
+    }
+
+    fn read_claim(&self, task: &str) -> Option<ClaimState> {
+        state::read_claim(&self.agent_root, task)
+    }
+
+    fn heartbeat_claim(&self, task: &str) -> Result<()> {
+        state::heartbeat_claim(&self.agent_root, task)
+    }
+
+    fn remove_stale_claim(&self, task: &str) -> Result<bool> {
+        state::remove_stale_claim(&self.agent_root, task)
+    }
+
+    fn release_claim(&self, task: &str) -> Result<()> {
+        state::release_claim(&self.agent_root, task)
+    }
+}
@@ -0,0 +1,247 @@
+use anyhow::{bail, Context, Result};
+use owo_colors::OwoColorize;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::agent::AgentKind;
+use crate::commands::{
+    cmd_init, cmd_issue, cmd_run_next, cmd_task, CommandContext, IssueCommands, ModelChoice,
+    TaskOptions, PROMPT_HOME_DIR,
+};
+use crate::model::Model;
+use crate::util::{find_on_path, home_dir};
+
+const TOUR_TASK: &str = "hello-tour";
+const TOUR_ISSUE: &str = "Greeting is missing punctuation";
+
+/// `mung tour`: walks through a throwaway task in a scratch repo with the fake
+/// model, checking the environment first. The scratch repo is removed unless
+/// `keep` is set.
+pub fn cmd_tour(keep: bool, yes: bool) -> Result<()> {
+    let interactive = !yes && io::stdin().is_terminal();
+    let steps = 6;
+
+    step(
+        1,
+        steps,
+        "Check your environment",
+        "mung drives model CLIs inside a git repo. Real runs need at least one model CLI; this tour only needs git.",
+    );
+    check_environment()?;
+    if !pause(interactive)? {
+        return Ok(());
+    }
+
+    let scratch = env::temp_dir().join(format!("mung-tour-{}", std::process::id()));
+    let result = run_tour(&scratch, steps, interactive);
+    if keep {
+        println!("Kept the tour repo at {}", scratch.display());
+    } else if scratch.exists() {
+        fs::remove_dir_all(&scratch)
+            .with_context(|| format!("Failed to remove {}", scratch.display()))?;
+    }
+    result
+}
+
+fn run_tour(scratch: &Path, steps: usize, interactive: bool) -> Result<()> {
+    fs::create_dir_all(scratch)
+        .with_context(|| format!("Failed to create {}", scratch.display()))?;
+    let status = Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(scratch)
+        .status()
+        .context("Failed to run git init")?;
+    if !status.success() {
+        bail!("git init failed in {}", scratch.display());
+    }
+    // The fake model replaces claude/codex, so every stage runs offline and instantly.
+    let model_choice = ModelChoice {
+        model: Model::Fake,
+        explicit: true,
+        force_model: true,
+        model_args: Vec::new(),
+        headless: false,
+    };
+
+    step(
+        2,
+        steps,
+        "Initialize a repo",
+        "`mung init` writes templates and state under .agents/code/. Here it runs in a scratch repo, so nothing in your projects changes.",
+    );
+    cmd_init(
+        AgentKind::Code,
+        Some(scratch.to_path_buf()),
+        model_choice.clone(),
+    )?;
+    let ctx = CommandContext::new(AgentKind::Code, model_choice, fs::canonicalize(scratch)?)?;
+    if !pause(interactive)? {
+        return Ok(());
+    }
+
+    step(
+        3,
+        steps,
+        "Create a task",
+        "`mung task <name>` creates a task in the spec stage. Each stage is one model session.",
+    );
+    cmd_task(
+        &ctx,
+        TOUR_TASK,
+        TaskOptions {
+            description: Some("Print a friendly greeting".to_string()),
+            ..TaskOptions::default()
+        },
+    )?;
+    if !pause(interactive)? {
+        return Ok(());
+    }
+
+    step(
+        4,
+        steps,
+        "Run a stage",
+        "`mung run-next <task>` starts a session for the task's current stage. The model calls `mung finish` when done, which advances the stage.",
+    );
+    cmd_run_next(&ctx, Some(TOUR_TASK))?;
+    println!(
+        "Stage is now {}",
+        ctx.store.load_task(TOUR_TASK)?.stage.bold()
+    );
+    if !pause(interactive)? {
+        return Ok(());
+    }
+
+    step(
+        5,
+        steps,
+        "File and resolve an issue",
+        "Reviews file issues against a task. Open issues keep it out of `completed` and send it back to build until they are resolved.",
+    );
+    cmd_issue(
+        &ctx,
+        IssueCommands::Add {
+            title: TOUR_ISSUE.to_string(),
+            task: Some(TOUR_TASK.to_string()),
+            priority: Some("P2".to_string()),
+            issue_type: Some("bug".to_string()),
+            source: Some("review".to_string()),
+            file: None,
+            stage: None,
+            body: Some("The greeting should end with an exclamation mark.".to_string()),
+            stdin_body: false,
+            labels: Vec::new(),
+            attachments: Vec::new(),
+        },
+    )?;
+    println!("Task status: {}", ctx.store.load_task(TOUR_TASK)?.status);
+    let issue = ctx
+        .store
+        .list_issues()?
+        .into_iter()
+        .find(|issue| issue.title == TOUR_ISSUE)
+        .context("Tour issue was not created")?;
+    cmd_issue(
+        &ctx,
+        IssueCommands::Resolve {
+            id: issue.id,
+            resolution: Some("Added the exclamation mark".to_string()),
+            steps: Vec::new(),
+        },
+    )?;
+    println!("Task status: {}", ctx.store.load_task(TOUR_TASK)?.status);
+    if !pause(interactive)? {
+        return Ok(());
+    }
+
+    step(
+        6,
+        steps,
+        "Finish the pipeline",
+        "`mung run-queue` repeats this for every queued task. Here each remaining stage runs once until the task completes.",
+    );
+    let limit = ctx.agent.stages().len();
+    for _ in 0..limit {
+        if ctx.store.load_task(TOUR_TASK)?.stage == "completed" {
+            break;
+        }
+        cmd_run_next(&ctx, Some(TOUR_TASK))?;
+    }
+    let task = ctx.store.load_task(TOUR_TASK)?;
+    if task.stage != "completed" {
+        bail!(
+            "Tour task stopped in stage '{}'; rerun with --keep to inspect it",
+            task.stage
+        );
+    }
+    println!(
+        "{} '{}' completed after {} sessions.",
+        "Done:".green(),
+        TOUR_TASK,
+        task.session_count
+    );
+    println!("Next: run 'mung install' once, then 'mung init' in a real repo.");
+    Ok(())
+}
+
+fn step(number: usize, total: usize, title: &str, text: &str) {
+    println!();
+    println!("{}", format!("Step {number}/{total}: {title}").bold());
+    println!("{}", text);
+    println!();
+}
+
+/// Waits for Enter; `q` ends the tour early. Returns immediately when not interactive.
+fn pause(interactive: bool) -> Result<bool> {
+    if !interactive {
+        return Ok(true);
+    }
+    print!("{}", "Press Enter to continue (q to quit) ".dimmed());
+    io::stdout().flush().ok();
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(!matches!(input.trim(), "q" | "Q"))
+}
+
+fn check_environment() -> Result<()> {
+    let git = Command::new("git")
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+    report(git, "git", "required; install git and rerun the tour");
+    if !git {
+        bail!("git is required for the tour");
+    }
+    report(
+        find_on_path("mung").is_some(),
+        "mung on PATH",
+        "run 'mung install' to copy it to ~/.local/bin",
+    );
+    report(
+        home_dir()?.join(PROMPT_HOME_DIR).is_dir(),
+        "prompts installed",
+        "run 'mung install' to install prompts and slash commands",
+    );
+    for cli in ["claude", "codex"] {
+        report(
+            find_on_path(cli).is_some(),
+            cli,
+            "not found; needed for real runs, not for this tour",
+        );
+    }
+    Ok(())
+}
+
+fn report(ok: bool, name: &str, hint: &str) {
+    if ok {
+        println!("  {}  {}", "ok".green(), name);
+    } else {
+        println!("  {}  {} ({})", "--".yellow(), name, hint);
+    }
+}
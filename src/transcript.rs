@@ -0,0 +1,222 @@
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::config::RepoConfig;
+use crate::util::env_var;
+
+pub const TRANSCRIPT_FILE: &str = "transcript.log";
+pub const FINISH_MARKER: &str = "MUNG_FINISH:";
+const LEGACY_FINISH_MARKER: &str = "METAGENT_FINISH:";
+
+/// `MUNG_TRANSCRIPT=1|0` overrides `[transcripts] capture` from the repo config.
+pub fn capture_enabled(config: &RepoConfig) -> bool {
+    match env_var("MUNG_TRANSCRIPT", "METAGENT_TRANSCRIPT") {
+        Some(value) => matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"),
+        None => config.transcripts.capture,
+    }
+}
+
+/// Copies a child's piped stdout/stderr to the terminal and to a transcript file.
+pub struct Transcript {
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl Transcript {
+    /// Takes the child's piped output streams; spawn the child with `Stdio::piped()`
+    /// for stdout and stderr first.
+    pub fn attach(child: &mut Child, path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open transcript {}", path.display()))?;
+        let file = Arc::new(Mutex::new(file));
+        let mut handles = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            handles.push(tee(stdout, io::stdout(), Arc::clone(&file)));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            handles.push(tee(stderr, io::stderr(), Arc::clone(&file)));
+        }
+        Ok(Self { handles })
+    }
+
+    /// Waits briefly for the copy threads to drain. A grandchild that keeps the pipe
+    /// open must not hang the stage, so stragglers are left detached.
+    pub fn finish(self) {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        for handle in self.handles {
+            while !handle.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(20));
+            }
+            if handle.is_finished() {
+                handle.join().ok();
+            }
+        }
+    }
+}
+
+fn tee(
+    mut source: impl Read + Send + 'static,
+    mut terminal: impl Write + Send + 'static,
+    file: Arc<Mutex<File>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut buffer = [0u8; 8192];
+        loop {
+            let read = match source.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read,
+            };
+            terminal.write_all(&buffer[..read]).ok();
+            terminal.flush().ok();
+            if let Ok(mut file) = file.lock() {
+                file.write_all(&buffer[..read]).ok();
+            }
+        }
+    })
+}
+
+/// A `MUNG_FINISH: stage=<stage> [next=<stage>] [session=<id>]` line printed by a
+/// model that could not run `mung finish` itself.
+#[derive(Debug, PartialEq, Eq)]
+pub struct FinishMarker {
+    pub stage: String,
+    pub next: Option<String>,
+    pub session: Option<String>,
+}
+
+/// Parses a marker that stands alone on its line; markdown emphasis or code ticks
+/// around it are ignored, so an instruction quoting the marker mid-sentence is not
+/// mistaken for one.
+pub fn parse_finish_marker(line: &str) -> Option<FinishMarker> {
+    let line = line.trim().trim_matches(|c| c == '`' || c == '*').trim();
+    let rest = line
+        .strip_prefix(FINISH_MARKER)
+        .or_else(|| line.strip_prefix(LEGACY_FINISH_MARKER))?;
+    let mut marker = FinishMarker {
+        stage: String::new(),
+        next: None,
+        session: None,
+    };
+    for field in rest.split_whitespace() {
+        let (key, value) = field.split_once('=')?;
+        match key {
+            "stage" => marker.stage = value.to_string(),
+            "next" => marker.next = Some(value.to_string()),
+            "session" => marker.session = Some(value.to_string()),
+            _ => return None,
+        }
+    }
+    if marker.stage.is_empty() {
+        return None;
+    }
+    Some(marker)
+}
+
+/// The model's conversation id from a `session id: <id>` line (as codex prints
+/// in its header), if the transcript has one.
+pub fn find_model_session(path: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(path).ok()?;
+    content.lines().find_map(parse_model_session)
+}
+
+fn parse_model_session(line: &str) -> Option<String> {
+    let line = line.trim();
+    let (label, value) = line.split_once(':')?;
+    if !label.trim().eq_ignore_ascii_case("session id") {
+        return None;
+    }
+    let id = value.trim();
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| id.to_string())
+}
+
+/// Follows a growing transcript file and reports the first finish marker.
+pub struct MarkerScanner {
+    path: PathBuf,
+    offset: u64,
+    partial: String,
+}
+
+impl MarkerScanner {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            offset: 0,
+            partial: String::new(),
+        }
+    }
+
+    /// Reads output appended since the last call. A trailing line without a newline
+    /// is only checked once `finished` is set, when no more output will follow.
+    pub fn poll(&mut self, finished: bool) -> Option<FinishMarker> {
+        if let Ok(mut file) = File::open(&self.path) {
+            let mut appended = Vec::new();
+            if file.seek(SeekFrom::Start(self.offset)).is_ok()
+                && file.read_to_end(&mut appended).is_ok()
+            {
+                self.offset += appended.len() as u64;
+                self.partial.push_str(&String::from_utf8_lossy(&appended));
+            }
+        }
+        let complete: String = match self.partial.rfind('\n') {
+            Some(end) => self.partial.drain(..=end).collect(),
+            None => String::new(),
+        };
+        let marker = complete.lines().find_map(parse_finish_marker);
+        if marker.is_some() || !finished {
+            return marker;
+        }
+        parse_finish_marker(&std::mem::take(&mut self.partial))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finish_marker_must_stand_alone() {
+        assert_eq!(
+            parse_finish_marker("  `MUNG_FINISH: stage=build next=review`"),
+            Some(FinishMarker {
+                stage: "build".to_string(),
+                next: Some("review".to_string()),
+                session: None,
+            })
+        );
+        assert_eq!(
+            parse_finish_marker("METAGENT_FINISH: stage=spec session=abc")
+                .and_then(|marker| marker.session),
+            Some("abc".to_string())
+        );
+        assert!(parse_finish_marker("MUNG_FINISH: next=review").is_none());
+        assert!(parse_finish_marker("MUNG_FINISH: stage=build because done").is_none());
+        assert!(parse_finish_marker("Print `MUNG_FINISH: stage=build` when done").is_none());
+    }
+
+    #[test]
+    fn model_session_line_is_recognized() {
+        assert_eq!(
+            parse_model_session("session id: 0199a2b4-7c1e-7d40-9f3a-1b2c3d4e5f60"),
+            Some("0199a2b4-7c1e-7d40-9f3a-1b2c3d4e5f60".to_string())
+        );
+        assert_eq!(
+            parse_model_session("  Session ID: abc_123 "),
+            Some("abc_123".to_string())
+        );
+        assert!(parse_model_session("session id:").is_none());
+        assert!(parse_model_session("the session id: is below").is_none());
+        assert!(parse_model_session("workdir: /repo").is_none());
+    }
+}
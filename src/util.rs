@@ -5,11 +5,53 @@ use std::ffi::OsString;
 use std::fs;
 use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Global `--json`: listing commands print structured JSON instead of styled text.
+pub fn set_json_output(enabled: bool) {
+    JSON_OUTPUT.store(enabled, Ordering::SeqCst);
+}
+
+pub fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::SeqCst)
+}
+
+pub fn print_json<T: serde::Serialize + ?Sized>(value: &T) -> Result<()> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).context("Failed to serialize JSON output")?
+    );
+    Ok(())
+}
 
 pub fn now_iso() -> String {
     Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
 }
 
+/// A random (version 4) UUID, from `/dev/urandom` or, failing that, the clock and pid.
+pub fn new_uuid() -> String {
+    let mut bytes = [0u8; 16];
+    let read = fs::File::open("/dev/urandom").and_then(|mut file| file.read_exact(&mut bytes));
+    if read.is_err() {
+        let nanos = Utc::now().timestamp_nanos_opt().unwrap_or_default() as u128;
+        let seed = nanos ^ ((std::process::id() as u128) << 64);
+        bytes = seed.to_le_bytes();
+    }
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
 pub fn today_date() -> String {
     Utc::now().format("%Y-%m-%d").to_string()
 }
@@ -18,6 +60,106 @@ pub fn home_dir() -> Result<PathBuf> {
     dirs::home_dir().context("Failed to resolve home directory")
 }
 
+/// First executable file named `program` in a `$PATH` directory. On Windows each
+/// `%PATHEXT%` extension is tried too, so `codex` finds `codex.cmd`.
+pub fn find_on_path(program: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .flat_map(|dir| with_extensions(dir.join(program)))
+        .find(|path| is_executable_file(path))
+}
+
+/// Splits `input` into words the way a shell would: whitespace separates them,
+/// single quotes keep everything literal, double quotes allow `\"` and `\\`, and
+/// a backslash outside quotes escapes the next character.
+pub fn split_words(input: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = input.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\'' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(ch) => current.push(ch),
+                        None => bail!("Unterminated ' in \"{}\"", input),
+                    }
+                }
+            }
+            '"' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(ch @ ('"' | '\\')) => current.push(ch),
+                            Some(ch) => {
+                                current.push('\\');
+                                current.push(ch);
+                            }
+                            None => bail!("Unterminated \" in \"{}\"", input),
+                        },
+                        Some(ch) => current.push(ch),
+                        None => bail!("Unterminated \" in \"{}\"", input),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(ch) => word.get_or_insert_with(String::new).push(ch),
+                None => bail!("Trailing \\ in \"{}\"", input),
+            },
+            ch if ch.is_whitespace() => words.extend(word.take()),
+            ch => word.get_or_insert_with(String::new).push(ch),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Whether `program` (a path, or a name looked up on `$PATH`) is an executable file.
+pub fn is_executable(program: &str) -> bool {
+    if program.contains('/') || (cfg!(windows) && program.contains('\\')) {
+        with_extensions(PathBuf::from(program))
+            .iter()
+            .any(|path| is_executable_file(path))
+    } else {
+        find_on_path(program).is_some()
+    }
+}
+
+/// `path` followed by `path` plus each `%PATHEXT%` extension on Windows.
+fn with_extensions(path: PathBuf) -> Vec<PathBuf> {
+    let mut candidates = vec![path.clone()];
+    if cfg!(windows) {
+        let extensions = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+        candidates.extend(
+            extensions
+                .split(';')
+                .filter(|extension| !extension.is_empty())
+                .map(|extension| {
+                    let mut name = path.clone().into_os_string();
+                    name.push(extension);
+                    PathBuf::from(name)
+                }),
+        );
+    }
+    candidates
+}
+
+fn is_executable_file(path: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path)
+            .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        path.is_file()
+    }
+}
+
 pub fn env_var(primary: &str, legacy: &str) -> Option<String> {
     env::var(primary)
         .ok()
@@ -25,6 +167,7 @@ pub fn env_var(primary: &str, legacy: &str) -> Option<String> {
         .or_else(|| env::var(legacy).ok().filter(|value| !value.is_empty()))
 }
 
+#[allow(dead_code)]
 pub fn env_var_os(primary: &str, legacy: &str) -> Option<OsString> {
     env::var_os(primary)
         .filter(|value| !value.is_empty())
@@ -84,6 +227,32 @@ pub fn read_text(path: &Path) -> Result<String> {
     Ok(buf)
 }
 
+pub fn copy_dir_all(source: &Path, dest: &Path) -> Result<()> {
+    ensure_dir(dest)?;
+    let entries =
+        fs::read_dir(source).with_context(|| format!("Failed to read {}", source.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let target = dest.join(entry.file_name());
+        if path.is_dir() {
+            copy_dir_all(&path, &target)?;
+        } else {
+            fs::copy(&path, &target)
+                .with_context(|| format!("Failed to copy {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn prompt_line(prompt: &str) -> Result<String> {
+    print!("{prompt}");
+    io::stdout().flush().ok();
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
 pub fn confirm(prompt: &str) -> Result<bool> {
     print!("{prompt}");
     io::stdout().flush().ok();
@@ -116,6 +285,10 @@ pub fn task_dir(agent_root: &Path, task: &str) -> PathBuf {
     agent_root.join("tasks").join(task)
 }
 
+pub fn archive_dir(agent_root: &Path, task: &str) -> PathBuf {
+    agent_root.join("archive").join(task)
+}
+
 pub fn task_state_path(agent_root: &Path, task: &str) -> PathBuf {
     task_dir(agent_root, task).join("task.json")
 }
@@ -189,3 +362,25 @@ impl TerminalGuard {
         TerminalGuard
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::split_words;
+
+    #[test]
+    fn split_words_honours_quotes_and_escapes() {
+        let words = split_words(r#"--append-system-prompt "be brief, \"please\"" -c 'a b' x\ y"#);
+        assert_eq!(
+            words.unwrap(),
+            [
+                "--append-system-prompt",
+                "be brief, \"please\"",
+                "-c",
+                "a b",
+                "x y"
+            ]
+        );
+        assert_eq!(split_words("  ''  ").unwrap(), [""]);
+        assert!(split_words("--flag 'open").is_err());
+    }
+}
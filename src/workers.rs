@@ -0,0 +1,106 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::env;
+use std::io::{self, Read};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use crate::commands::CommandContext;
+use crate::state::TaskState;
+
+/// `tasks` with those another process holds a live claim on marked held, so the
+/// queue passes them over while they still count for dependencies. Checked on
+/// every pass, so a task comes back as soon as its claim is released.
+pub fn passed_over(ctx: &CommandContext, mut tasks: Vec<TaskState>) -> Vec<TaskState> {
+    let claimed: HashSet<String> = ctx
+        .store
+        .list_claims()
+        .into_iter()
+        .filter(|task| ctx.store.has_active_claim(task).unwrap_or(false))
+        .collect();
+    for task in tasks.iter_mut() {
+        if claimed.contains(&task.task) {
+            task.held = true;
+        }
+    }
+    tasks
+}
+
+/// Worker processes for `run-queue` / `run-next`: `flag` (`--jobs`), else the
+/// `[concurrency]` setting. A worker never starts workers of its own.
+pub fn worker_count(flag: Option<usize>, configured: usize) -> usize {
+    if env::var_os("MUNG_WORKER").is_some() {
+        return 1;
+    }
+    flag.unwrap_or(configured).max(1)
+}
+
+/// Re-runs this command as `jobs` worker processes sharing the queue through
+/// claims, prefixing each line of their output with the worker number. Fails if
+/// any worker does. Workers have no terminal, so they always run headless.
+pub fn run_workers(jobs: usize) -> Result<()> {
+    let exe = env::current_exe().context("Failed to locate the mung binary")?;
+    let mut args = Vec::new();
+    let mut skip_value = false;
+    for arg in env::args_os().skip(1) {
+        if skip_value {
+            skip_value = false;
+            continue;
+        }
+        match arg.to_str() {
+            Some("--jobs" | "-j") => skip_value = true,
+            Some(flag) if flag.starts_with("--jobs=") => {}
+            _ => args.push(arg),
+        }
+    }
+
+    println!("Starting {} workers", jobs);
+    let mut workers = Vec::new();
+    for worker in 1..=jobs {
+        let mut child = Command::new(&exe)
+            .args(&args)
+            .env("MUNG_WORKER", worker.to_string())
+            .env("MUNG_HEADLESS", "1")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to start worker {}", worker))?;
+        let mut relays = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            relays.push(thread::spawn(move || relay_lines(stdout, worker, false)));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            relays.push(thread::spawn(move || relay_lines(stderr, worker, true)));
+        }
+        workers.push((worker, child, relays));
+        // Stagger starts so workers don't all reach for the same task at once.
+        thread::sleep(Duration::from_millis(200));
+    }
+
+    let mut failed = Vec::new();
+    for (worker, mut child, relays) in workers {
+        let status = child.wait()?;
+        for relay in relays {
+            relay.join().ok();
+        }
+        if !status.success() {
+            failed.push(worker.to_string());
+        }
+    }
+    if !failed.is_empty() {
+        bail!("Worker(s) {} failed", failed.join(", "));
+    }
+    Ok(())
+}
+
+fn relay_lines(stream: impl Read, worker: usize, stderr: bool) {
+    for line in io::BufRead::lines(io::BufReader::new(stream)).map_while(Result::ok) {
+        if stderr {
+            eprintln!("[worker {}] {}", worker, line);
+        } else {
+            println!("[worker {}] {}", worker, line);
+        }
+    }
+}